@@ -1,28 +1,67 @@
 use super::{
-    config::{Config, ResourceType},
+    config::{Config, EvictionPolicy, ResourceType},
+    filter::{GuildFilter, MessageFilter},
     InMemoryCache,
 };
+use std::time::Duration;
+use twilight_model::id::ChannelId;
 
 /// Builder to configure and construct an [`InMemoryCache`].
-#[derive(Clone, Debug, Default, Eq, PartialEq)]
-pub struct InMemoryCacheBuilder(Config);
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryCacheBuilder {
+    config: Config,
+    guild_filter: Option<GuildFilter>,
+    message_filter: Option<MessageFilter>,
+}
 
 impl InMemoryCacheBuilder {
     /// Creates a builder to configure and construct an [`InMemoryCache`].
     pub const fn new() -> Self {
-        Self(Config::new())
+        Self {
+            config: Config::new(),
+            guild_filter: None,
+            message_filter: None,
+        }
     }
 
     /// Consume the builder, returning a configured cache.
     pub fn build(self) -> InMemoryCache {
-        InMemoryCache::new_with_config(self.0)
+        InMemoryCache::new_with_config(self.config, self.guild_filter, self.message_filter)
+    }
+
+    /// Sets a filter deciding which guilds resources are cached for.
+    ///
+    /// The filter can be replaced later with
+    /// [`InMemoryCache::set_guild_filter`].
+    ///
+    /// Defaults to caching resources for all guilds.
+    pub fn guild_filter(mut self, filter: GuildFilter) -> Self {
+        self.guild_filter.replace(filter);
+
+        self
+    }
+
+    /// Sets a predicate deciding which channels messages are cached for.
+    ///
+    /// The predicate is called with the ID of the channel for every message
+    /// that would be cached. It can be replaced later with
+    /// [`InMemoryCache::set_message_filter`].
+    ///
+    /// Defaults to caching messages for all channels.
+    pub fn message_filter(
+        mut self,
+        predicate: impl Fn(ChannelId) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.message_filter.replace(MessageFilter::new(predicate));
+
+        self
     }
 
     /// Sets the list of resource types for the cache to handle.
     ///
     /// Defaults to all types.
     pub const fn resource_types(mut self, resource_types: ResourceType) -> Self {
-        self.0.resource_types = resource_types;
+        self.config.resource_types = resource_types;
 
         self
     }
@@ -31,17 +70,75 @@ impl InMemoryCacheBuilder {
     ///
     /// Defaults to 100.
     pub const fn message_cache_size(mut self, message_cache_size: usize) -> Self {
-        self.0.message_cache_size = message_cache_size;
+        self.config.message_cache_size = message_cache_size;
+
+        self
+    }
+
+    /// Sets the window in which a member must have sent a message to be kept
+    /// in the cache.
+    ///
+    /// Refer to [`Config::member_activity_window`] for more information.
+    ///
+    /// Defaults to keeping all members.
+    pub const fn member_activity_window(mut self, window: Duration) -> Self {
+        self.config.member_activity_window = Some(window);
+
+        self
+    }
+
+    /// Sets the eviction policy for members.
+    ///
+    /// Evicted members are also removed from their guild's set of members.
+    ///
+    /// Defaults to never evicting members.
+    pub const fn member_eviction(mut self, policy: EvictionPolicy) -> Self {
+        self.config.member_eviction = policy;
+
+        self
+    }
+
+    /// Sets the eviction policy for presences.
+    ///
+    /// Evicted presences are also removed from their guild's set of presences.
+    ///
+    /// Defaults to never evicting presences.
+    pub const fn presence_eviction(mut self, policy: EvictionPolicy) -> Self {
+        self.config.presence_eviction = policy;
+
+        self
+    }
+
+    /// Sets the eviction policy for users.
+    ///
+    /// Defaults to never evicting users.
+    pub const fn user_eviction(mut self, policy: EvictionPolicy) -> Self {
+        self.config.user_eviction = policy;
 
         self
     }
 }
 
+impl Eq for InMemoryCacheBuilder {}
+
+/// Builders are equal if they have the same configuration and set the same
+/// filters.
+///
+/// Filters may be predicates, which can't be compared, so only whether each
+/// filter is set is taken into account.
+impl PartialEq for InMemoryCacheBuilder {
+    fn eq(&self, other: &Self) -> bool {
+        self.config == other.config
+            && self.guild_filter.is_some() == other.guild_filter.is_some()
+            && self.message_filter.is_some() == other.message_filter.is_some()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::InMemoryCacheBuilder;
     use static_assertions::assert_impl_all;
     use std::fmt::Debug;
 
-    assert_impl_all!(InMemoryCacheBuilder: Clone, Debug, Default, Send, Sync);
+    assert_impl_all!(InMemoryCacheBuilder: Clone, Debug, Default, Eq, PartialEq, Send, Sync);
 }
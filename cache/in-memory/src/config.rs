@@ -1,4 +1,5 @@
 use bitflags::bitflags;
+use std::time::Duration;
 
 bitflags! {
     /// A set of bitflags which can be used to specify what resource to process
@@ -24,6 +25,62 @@ bitflags! {
     }
 }
 
+/// Policy for evicting entries of a resource from the cache.
+///
+/// Entries are tracked by when they were last seen in an event. An entry is
+/// evicted once it hasn't been seen for longer than the [time to live], or
+/// when the number of entries exceeds the [maximum count], in which case the
+/// least recently seen entries are evicted first.
+///
+/// The default policy never evicts anything.
+///
+/// [maximum count]: Self::max_count
+/// [time to live]: Self::ttl
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct EvictionPolicy {
+    pub(super) max_count: Option<usize>,
+    pub(super) ttl: Option<Duration>,
+}
+
+impl EvictionPolicy {
+    /// Create a new eviction policy.
+    ///
+    /// Passing `None` for both values creates a policy that never evicts.
+    pub const fn new(max_count: Option<usize>, ttl: Option<Duration>) -> Self {
+        Self { max_count, ttl }
+    }
+
+    /// Returns the maximum number of entries to keep, if any.
+    ///
+    /// Defaults to `None`.
+    pub const fn max_count(&self) -> Option<usize> {
+        self.max_count
+    }
+
+    /// Returns a mutable reference to the maximum number of entries to keep.
+    pub fn max_count_mut(&mut self) -> &mut Option<usize> {
+        &mut self.max_count
+    }
+
+    /// Returns how long an entry is kept after it was last seen, if any.
+    ///
+    /// Defaults to `None`.
+    pub const fn ttl(&self) -> Option<Duration> {
+        self.ttl
+    }
+
+    /// Returns a mutable reference to how long an entry is kept after it was
+    /// last seen.
+    pub fn ttl_mut(&mut self) -> &mut Option<Duration> {
+        &mut self.ttl
+    }
+
+    /// Whether the policy would ever evict an entry.
+    pub const fn is_enabled(&self) -> bool {
+        self.max_count.is_some() || self.ttl.is_some()
+    }
+}
+
 /// Configuration for an [`InMemoryCache`].
 ///
 /// [`InMemoryCache`]: crate::InMemoryCache
@@ -31,6 +88,10 @@ bitflags! {
 pub struct Config {
     pub(super) resource_types: ResourceType,
     pub(super) message_cache_size: usize,
+    pub(super) member_eviction: EvictionPolicy,
+    pub(super) member_activity_window: Option<Duration>,
+    pub(super) presence_eviction: EvictionPolicy,
+    pub(super) user_eviction: EvictionPolicy,
 }
 
 impl Config {
@@ -41,9 +102,47 @@ impl Config {
         Self {
             resource_types: ResourceType::all(),
             message_cache_size: 100,
+            member_eviction: EvictionPolicy::new(None, None),
+            member_activity_window: None,
+            presence_eviction: EvictionPolicy::new(None, None),
+            user_eviction: EvictionPolicy::new(None, None),
         }
     }
 
+    /// Returns the eviction policy for members.
+    ///
+    /// Defaults to never evicting members.
+    pub const fn member_eviction(&self) -> EvictionPolicy {
+        self.member_eviction
+    }
+
+    /// Returns a mutable reference to the eviction policy for members.
+    pub fn member_eviction_mut(&mut self) -> &mut EvictionPolicy {
+        &mut self.member_eviction
+    }
+
+    /// Returns the window in which a member must have sent a message to be
+    /// kept in the cache, if any.
+    ///
+    /// When set, members are only cached once they have sent a message and
+    /// are evicted once they haven't sent one within the window. Other events
+    /// referencing the member don't count as the member being seen. This
+    /// replaces the [`ttl`] of the [member eviction policy], while its maximum
+    /// count still applies.
+    ///
+    /// Defaults to `None`.
+    ///
+    /// [`ttl`]: EvictionPolicy::ttl
+    /// [member eviction policy]: Self::member_eviction
+    pub const fn member_activity_window(&self) -> Option<Duration> {
+        self.member_activity_window
+    }
+
+    /// Returns a mutable reference to the member activity window.
+    pub fn member_activity_window_mut(&mut self) -> &mut Option<Duration> {
+        &mut self.member_activity_window
+    }
+
     /// Returns an immutable reference to the message cache size.
     ///
     /// Defaults to 100.
@@ -55,6 +154,19 @@ impl Config {
     pub fn message_cache_size_mut(&mut self) -> &mut usize {
         &mut self.message_cache_size
     }
+
+    /// Returns the eviction policy for presences.
+    ///
+    /// Defaults to never evicting presences.
+    pub const fn presence_eviction(&self) -> EvictionPolicy {
+        self.presence_eviction
+    }
+
+    /// Returns a mutable reference to the eviction policy for presences.
+    pub fn presence_eviction_mut(&mut self) -> &mut EvictionPolicy {
+        &mut self.presence_eviction
+    }
+
     /// Returns an immutable reference to the resource types enabled.
     ///
     /// Defaults to all resource types.
@@ -66,6 +178,18 @@ impl Config {
     pub fn resource_types_mut(&mut self) -> &mut ResourceType {
         &mut self.resource_types
     }
+
+    /// Returns the eviction policy for users.
+    ///
+    /// Defaults to never evicting users.
+    pub const fn user_eviction(&self) -> EvictionPolicy {
+        self.user_eviction
+    }
+
+    /// Returns a mutable reference to the eviction policy for users.
+    pub fn user_eviction_mut(&mut self) -> &mut EvictionPolicy {
+        &mut self.user_eviction
+    }
 }
 
 impl Default for Config {
@@ -76,10 +200,18 @@ impl Default for Config {
 
 #[cfg(test)]
 mod tests {
-    use super::{Config, ResourceType};
+    use super::{Config, EvictionPolicy, ResourceType};
     use static_assertions::assert_fields;
 
-    assert_fields!(Config: resource_types, message_cache_size);
+    assert_fields!(
+        Config: resource_types,
+        message_cache_size,
+        member_eviction,
+        member_activity_window,
+        presence_eviction,
+        user_eviction
+    );
+    assert_fields!(EvictionPolicy: max_count, ttl);
 
     #[test]
     #[allow(clippy::cognitive_complexity)]
@@ -103,9 +235,18 @@ mod tests {
         let conf = Config {
             resource_types: ResourceType::all(),
             message_cache_size: 100,
+            member_eviction: EvictionPolicy::default(),
+            member_activity_window: None,
+            presence_eviction: EvictionPolicy::default(),
+            user_eviction: EvictionPolicy::default(),
         };
         let default = Config::default();
         assert_eq!(conf.resource_types, default.resource_types);
         assert_eq!(conf.message_cache_size, default.message_cache_size);
+        assert_eq!(conf.member_eviction, default.member_eviction);
+        assert_eq!(conf.member_activity_window, default.member_activity_window);
+        assert_eq!(conf.presence_eviction, default.presence_eviction);
+        assert_eq!(conf.user_eviction, default.user_eviction);
+        assert!(!default.member_eviction.is_enabled());
     }
 }
@@ -0,0 +1,177 @@
+use crate::config::EvictionPolicy;
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Tracker of when entries of a resource were last seen.
+///
+/// Entries are ordered by recency so that both the least recently seen and
+/// expired entries can be popped from the front.
+#[derive(Debug)]
+pub(crate) struct Tracker<K> {
+    evicted: AtomicU64,
+    // So long as the lock isn't held across await or panic points this is fine.
+    inner: Mutex<TrackerInner<K>>,
+}
+
+#[derive(Debug)]
+struct TrackerInner<K> {
+    entries: HashMap<K, (u64, Instant)>,
+    next_tick: u64,
+    order: BTreeMap<u64, K>,
+}
+
+impl<K: Copy + Eq + Hash> Tracker<K> {
+    /// Whether the entry is being tracked.
+    pub fn contains(&self, key: &K) -> bool {
+        self.lock().entries.contains_key(key)
+    }
+
+    /// Number of entries that have been evicted over the tracker's lifetime.
+    pub fn evicted(&self) -> u64 {
+        self.evicted.load(Ordering::Relaxed)
+    }
+
+    /// Mark an entry as having been seen now.
+    pub fn touch(&self, key: K) {
+        self.touch_at(key, Instant::now());
+    }
+
+    /// Mark an entry as having been seen at the given time.
+    pub fn touch_at(&self, key: K, at: Instant) {
+        let mut inner = self.lock();
+        let tick = inner.next_tick;
+        inner.next_tick += 1;
+
+        if let Some((old_tick, _)) = inner.entries.insert(key, (tick, at)) {
+            inner.order.remove(&old_tick);
+        }
+
+        inner.order.insert(tick, key);
+    }
+
+    /// Stop tracking an entry, such as when it was removed from the cache.
+    pub fn remove(&self, key: &K) {
+        let mut inner = self.lock();
+
+        if let Some((tick, _)) = inner.entries.remove(key) {
+            inner.order.remove(&tick);
+        }
+    }
+
+    /// Stop tracking all entries.
+    pub fn clear(&self) {
+        let mut inner = self.lock();
+        inner.entries.clear();
+        inner.order.clear();
+    }
+
+    /// Pop all entries that should be evicted according to a policy.
+    ///
+    /// `ttl` overrides the policy's own time to live. The returned entries are
+    /// no longer tracked and must be removed from the cache by the caller.
+    pub fn evict(&self, policy: EvictionPolicy, ttl: Option<Duration>, now: Instant) -> Vec<K> {
+        let ttl = ttl.or(policy.ttl);
+        let mut evicted = Vec::new();
+        let mut inner = self.lock();
+
+        while let Some((&tick, &key)) = inner.order.iter().next() {
+            let over_capacity = policy
+                .max_count
+                .map_or(false, |max| inner.entries.len() > max);
+            let expired = ttl.map_or(false, |ttl| {
+                inner.entries.get(&key).map_or(true, |(_, seen)| {
+                    now.saturating_duration_since(*seen) >= ttl
+                })
+            });
+
+            if !over_capacity && !expired {
+                break;
+            }
+
+            inner.order.remove(&tick);
+            inner.entries.remove(&key);
+            evicted.push(key);
+        }
+
+        drop(inner);
+
+        self.evicted
+            .fetch_add(evicted.len() as u64, Ordering::Relaxed);
+
+        evicted
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, TrackerInner<K>> {
+        self.inner.lock().expect("eviction tracker poisoned")
+    }
+}
+
+impl<K> Default for Tracker<K> {
+    fn default() -> Self {
+        Self {
+            evicted: AtomicU64::new(0),
+            inner: Mutex::new(TrackerInner {
+                entries: HashMap::new(),
+                next_tick: 0,
+                order: BTreeMap::new(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Tracker;
+    use crate::config::EvictionPolicy;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_evicts_least_recently_seen() {
+        let tracker = Tracker::default();
+        tracker.touch(1);
+        tracker.touch(2);
+        tracker.touch(3);
+        tracker.touch(1);
+
+        let policy = EvictionPolicy::new(Some(2), None);
+        assert_eq!(vec![2], tracker.evict(policy, None, Instant::now()));
+        assert!(tracker.contains(&1));
+        assert!(tracker.contains(&3));
+        assert_eq!(1, tracker.evicted());
+    }
+
+    #[test]
+    fn test_evicts_expired() {
+        let tracker = Tracker::default();
+        let start = Instant::now();
+        tracker.touch_at(1, start);
+        tracker.touch_at(2, start + Duration::from_secs(10));
+
+        let policy = EvictionPolicy::new(None, Some(Duration::from_secs(30)));
+        let now = start + Duration::from_secs(35);
+        assert_eq!(vec![1], tracker.evict(policy, None, now));
+        assert!(tracker.evict(policy, None, now).is_empty());
+
+        let window = Some(Duration::from_secs(5));
+        assert_eq!(vec![2], tracker.evict(policy, window, now));
+        assert_eq!(2, tracker.evicted());
+    }
+
+    #[test]
+    fn test_disabled_policy_evicts_nothing() {
+        let tracker = Tracker::default();
+        tracker.touch(1);
+
+        assert!(tracker
+            .evict(EvictionPolicy::default(), None, Instant::now())
+            .is_empty());
+        assert_eq!(0, tracker.evicted());
+    }
+}
@@ -0,0 +1,150 @@
+use crate::config::ResourceType;
+use std::{
+    collections::HashSet,
+    fmt::{Debug, Formatter, Result as FmtResult},
+    sync::Arc,
+};
+use twilight_model::id::{ChannelId, GuildId};
+
+#[derive(Clone)]
+enum GuildFilterKind {
+    AllowList(HashSet<GuildId>),
+    Predicate(Arc<dyn Fn(GuildId) -> bool + Send + Sync>),
+}
+
+/// Filter deciding which guilds resources are cached for.
+///
+/// A filter applies to a set of [`ResourceType`]s. Resources of those types
+/// that belong to a guild that isn't allowed by the filter are skipped, while
+/// resources of other types are cached for all guilds. This can, for example,
+/// be used to only cache members and presences for a handful of guilds.
+///
+/// # Examples
+///
+/// Only cache members and presences for two guilds:
+///
+/// ```
+/// use twilight_cache_inmemory::{GuildFilter, InMemoryCache, ResourceType};
+/// use twilight_model::id::GuildId;
+///
+/// let filter = GuildFilter::allow_list(
+///     ResourceType::MEMBER | ResourceType::PRESENCE,
+///     vec![GuildId(1), GuildId(2)],
+/// );
+///
+/// let cache = InMemoryCache::builder().guild_filter(filter).build();
+/// ```
+#[derive(Clone)]
+pub struct GuildFilter {
+    kind: GuildFilterKind,
+    resource_types: ResourceType,
+}
+
+impl GuildFilter {
+    /// Create a filter allowing only the given guilds.
+    pub fn allow_list(
+        resource_types: ResourceType,
+        guild_ids: impl IntoIterator<Item = GuildId>,
+    ) -> Self {
+        Self {
+            kind: GuildFilterKind::AllowList(guild_ids.into_iter().collect()),
+            resource_types,
+        }
+    }
+
+    /// Create a filter allowing the guilds for which a predicate returns
+    /// `true`.
+    ///
+    /// The predicate is called for every event that would cache a filtered
+    /// resource, so it should be cheap.
+    pub fn predicate(
+        resource_types: ResourceType,
+        predicate: impl Fn(GuildId) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            kind: GuildFilterKind::Predicate(Arc::new(predicate)),
+            resource_types,
+        }
+    }
+
+    /// Returns the resource types the filter applies to.
+    pub const fn resource_types(&self) -> ResourceType {
+        self.resource_types
+    }
+
+    /// Whether resources of a type belonging to a guild may be cached.
+    ///
+    /// This is always `true` for resource types the filter doesn't apply to.
+    pub fn allows(&self, resource_type: ResourceType, guild_id: GuildId) -> bool {
+        if !self.resource_types.intersects(resource_type) {
+            return true;
+        }
+
+        match &self.kind {
+            GuildFilterKind::AllowList(guild_ids) => guild_ids.contains(&guild_id),
+            GuildFilterKind::Predicate(predicate) => predicate(guild_id),
+        }
+    }
+}
+
+impl Debug for GuildFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let mut debug = f.debug_struct("GuildFilter");
+
+        match &self.kind {
+            GuildFilterKind::AllowList(guild_ids) => debug.field("allow_list", guild_ids),
+            GuildFilterKind::Predicate(_) => debug.field("predicate", &"<fn>"),
+        };
+
+        debug.field("resource_types", &self.resource_types).finish()
+    }
+}
+
+/// Predicate deciding which channels messages are cached for.
+#[derive(Clone)]
+pub(crate) struct MessageFilter(Arc<dyn Fn(ChannelId) -> bool + Send + Sync>);
+
+impl MessageFilter {
+    pub fn new(predicate: impl Fn(ChannelId) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(predicate))
+    }
+
+    pub fn allows(&self, channel_id: ChannelId) -> bool {
+        (self.0)(channel_id)
+    }
+}
+
+impl Debug for MessageFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_tuple("MessageFilter").field(&"<fn>").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GuildFilter;
+    use crate::config::ResourceType;
+    use static_assertions::assert_impl_all;
+    use std::fmt::Debug;
+    use twilight_model::id::GuildId;
+
+    assert_impl_all!(GuildFilter: Clone, Debug, Send, Sync);
+
+    #[test]
+    fn test_allow_list() {
+        let filter = GuildFilter::allow_list(ResourceType::MEMBER, vec![GuildId(1)]);
+
+        assert!(filter.allows(ResourceType::MEMBER, GuildId(1)));
+        assert!(!filter.allows(ResourceType::MEMBER, GuildId(2)));
+        // Resource types the filter doesn't apply to are always allowed.
+        assert!(filter.allows(ResourceType::ROLE, GuildId(2)));
+    }
+
+    #[test]
+    fn test_predicate() {
+        let filter = GuildFilter::predicate(ResourceType::PRESENCE, |id| id.0 % 2 == 0);
+
+        assert!(filter.allows(ResourceType::PRESENCE, GuildId(2)));
+        assert!(!filter.allows(ResourceType::PRESENCE, GuildId(3)));
+    }
+}
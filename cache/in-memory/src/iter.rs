@@ -0,0 +1,262 @@
+//! Iterators over the resources in the cache.
+//!
+//! The iterators borrow from the cache's underlying maps instead of cloning
+//! them. Refer to [`InMemoryCacheIter`] for more information.
+
+use crate::{
+    model::{CachedEmoji, CachedGuild, CachedMember, CachedMessage, CachedPresence},
+    GuildItem, InMemoryCache,
+};
+use dashmap::{iter::Iter, mapref::multiple::RefMulti};
+use std::{
+    collections::{BTreeSet, VecDeque},
+    fmt::{Debug, Formatter, Result as FmtResult},
+    hash::Hash,
+    ops::Deref,
+};
+use twilight_model::{
+    channel::{Group, GuildChannel, PrivateChannel, StageInstance},
+    guild::{GuildIntegration, Role},
+    id::{ChannelId, EmojiId, GuildId, IntegrationId, RoleId, StageId, UserId},
+    user::User,
+    voice::VoiceState,
+};
+
+/// Reference to a resource value being iterated over.
+///
+/// The reference holds a read lock on a shard of the underlying map, so it
+/// should be dropped as soon as possible; holding it while updating the cache
+/// may deadlock.
+pub struct IterReference<'a, K, V> {
+    inner: RefMulti<'a, K, V>,
+}
+
+impl<'a, K: Eq + Hash, V> IterReference<'a, K, V> {
+    /// Immutable reference to the resource's key.
+    pub fn key(&self) -> &K {
+        self.inner.key()
+    }
+
+    /// Immutable reference to the resource's value.
+    pub fn value(&self) -> &V {
+        self.inner.value()
+    }
+}
+
+impl<K: Debug + Eq + Hash, V: Debug> Debug for IterReference<'_, K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("IterReference")
+            .field("key", self.key())
+            .field("value", self.value())
+            .finish()
+    }
+}
+
+impl<K: Eq + Hash, V> Deref for IterReference<'_, K, V> {
+    type Target = V;
+
+    fn deref(&self) -> &Self::Target {
+        self.value()
+    }
+}
+
+/// Iterator over the resources of a type in the cache.
+///
+/// Created by the methods of [`InMemoryCacheIter`].
+pub struct ResourceIter<'a, K, V> {
+    iter: Iter<'a, K, V>,
+}
+
+impl<K, V> Debug for ResourceIter<'_, K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("ResourceIter").finish()
+    }
+}
+
+impl<'a, K: Eq + Hash, V> Iterator for ResourceIter<'a, K, V> {
+    type Item = IterReference<'a, K, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|inner| IterReference { inner })
+    }
+}
+
+/// Interface to create iterators over the resources in the cache.
+///
+/// Iterators are backed by the cache's underlying concurrent maps, so no
+/// resources are cloned. Iteration doesn't provide a snapshot: resources
+/// inserted or removed while iterating may or may not be yielded.
+///
+/// # Examples
+///
+/// Count the number of users in the cache whose username starts with "twi":
+///
+/// ```
+/// use twilight_cache_inmemory::InMemoryCache;
+///
+/// let cache = InMemoryCache::new();
+///
+/// // later in the application...
+/// let count = cache
+///     .iter()
+///     .users()
+///     .filter(|user| user.0.name.starts_with("twi"))
+///     .count();
+///
+/// println!("user count: {}", count);
+/// ```
+#[derive(Clone, Debug)]
+pub struct InMemoryCacheIter<'a>(&'a InMemoryCache);
+
+impl<'a> InMemoryCacheIter<'a> {
+    pub(super) const fn new(cache: &'a InMemoryCache) -> Self {
+        Self(cache)
+    }
+
+    /// Immutable reference to the underlying cache.
+    pub const fn cache_ref(&'a self) -> &'a InMemoryCache {
+        self.0
+    }
+
+    /// Create an iterator over the emojis in the cache.
+    pub fn emojis(&self) -> ResourceIter<'a, EmojiId, GuildItem<CachedEmoji>> {
+        ResourceIter {
+            iter: self.0 .0.emojis.iter(),
+        }
+    }
+
+    /// Create an iterator over the groups in the cache.
+    pub fn groups(&self) -> ResourceIter<'a, ChannelId, Group> {
+        ResourceIter {
+            iter: self.0 .0.groups.iter(),
+        }
+    }
+
+    /// Create an iterator over the guild channels in the cache.
+    pub fn guild_channels(&self) -> ResourceIter<'a, ChannelId, GuildItem<GuildChannel>> {
+        ResourceIter {
+            iter: self.0 .0.channels_guild.iter(),
+        }
+    }
+
+    /// Create an iterator over the guilds in the cache.
+    pub fn guilds(&self) -> ResourceIter<'a, GuildId, CachedGuild> {
+        ResourceIter {
+            iter: self.0 .0.guilds.iter(),
+        }
+    }
+
+    /// Create an iterator over the integrations in the cache.
+    pub fn integrations(
+        &self,
+    ) -> ResourceIter<'a, (GuildId, IntegrationId), GuildItem<GuildIntegration>> {
+        ResourceIter {
+            iter: self.0 .0.integrations.iter(),
+        }
+    }
+
+    /// Create an iterator over the members across all guilds in the cache.
+    pub fn members(&self) -> ResourceIter<'a, (GuildId, UserId), CachedMember> {
+        ResourceIter {
+            iter: self.0 .0.members.iter(),
+        }
+    }
+
+    /// Create an iterator over the cached messages of each channel, ordered
+    /// from newest to oldest.
+    pub fn messages(&self) -> ResourceIter<'a, ChannelId, VecDeque<CachedMessage>> {
+        ResourceIter {
+            iter: self.0 .0.messages.iter(),
+        }
+    }
+
+    /// Create an iterator over the presences across all guilds in the cache.
+    pub fn presences(&self) -> ResourceIter<'a, (GuildId, UserId), CachedPresence> {
+        ResourceIter {
+            iter: self.0 .0.presences.iter(),
+        }
+    }
+
+    /// Create an iterator over the private channels in the cache.
+    pub fn private_channels(&self) -> ResourceIter<'a, ChannelId, PrivateChannel> {
+        ResourceIter {
+            iter: self.0 .0.channels_private.iter(),
+        }
+    }
+
+    /// Create an iterator over the roles in the cache.
+    pub fn roles(&self) -> ResourceIter<'a, RoleId, GuildItem<Role>> {
+        ResourceIter {
+            iter: self.0 .0.roles.iter(),
+        }
+    }
+
+    /// Create an iterator over the stage instances in the cache.
+    pub fn stage_instances(&self) -> ResourceIter<'a, StageId, GuildItem<StageInstance>> {
+        ResourceIter {
+            iter: self.0 .0.stage_instances.iter(),
+        }
+    }
+
+    /// Create an iterator over the users in the cache, along with the IDs of
+    /// the guilds they're known to be in.
+    pub fn users(&self) -> ResourceIter<'a, UserId, (User, BTreeSet<GuildId>)> {
+        ResourceIter {
+            iter: self.0 .0.users.iter(),
+        }
+    }
+
+    /// Create an iterator over the voice states in the cache.
+    pub fn voice_states(&self) -> ResourceIter<'a, (GuildId, UserId), VoiceState> {
+        ResourceIter {
+            iter: self.0 .0.voice_states.iter(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InMemoryCacheIter, IterReference, ResourceIter};
+    use crate::{model::CachedMember, InMemoryCache};
+    use static_assertions::assert_impl_all;
+    use std::{borrow::Cow, fmt::Debug};
+    use twilight_model::{
+        id::{GuildId, UserId},
+        user::User,
+    };
+
+    assert_impl_all!(InMemoryCacheIter<'_>: Clone, Debug, Send, Sync);
+    assert_impl_all!(IterReference<'_, (GuildId, UserId), CachedMember>: Debug, Send, Sync);
+    assert_impl_all!(ResourceIter<'_, (GuildId, UserId), CachedMember>: Debug, Iterator, Send, Sync);
+
+    fn user(id: UserId) -> User {
+        User {
+            avatar: None,
+            bot: false,
+            discriminator: "0001".to_owned(),
+            email: None,
+            flags: None,
+            id,
+            locale: None,
+            mfa_enabled: None,
+            name: "user".to_owned(),
+            premium_type: None,
+            public_flags: None,
+            system: None,
+            verified: None,
+        }
+    }
+
+    #[test]
+    fn test_iter_users() {
+        let cache = InMemoryCache::new();
+        cache.cache_user(Cow::Owned(user(UserId(1))), Some(GuildId(1)));
+        cache.cache_user(Cow::Owned(user(UserId(2))), Some(GuildId(1)));
+
+        let mut ids = cache.iter().users().map(|r| *r.key()).collect::<Vec<_>>();
+        ids.sort();
+
+        assert_eq!(vec![UserId(1), UserId(2)], ids);
+        assert!(cache.iter().users().all(|r| r.1.contains(&GuildId(1))));
+    }
+}
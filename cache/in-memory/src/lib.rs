@@ -31,6 +31,14 @@
 //! # Ok(()) }
 //! ```
 //!
+//! ## Features
+//!
+//! ### `http`
+//!
+//! The `http` feature enables the `read_through` module, which combines the
+//! cache with a [`twilight-http`] client to fetch resources missing from the
+//! cache. It is not enabled by default.
+//!
 //! ## License
 //!
 //! All first-party crates are licensed under [ISC][LICENSE.md]
@@ -44,6 +52,7 @@
 //! [license badge]: https://img.shields.io/badge/license-ISC-blue.svg?style=for-the-badge&logo=pastebin
 //! [license link]: https://github.com/twilight-rs/twilight/blob/main/LICENSE.md
 //! [rust badge]: https://img.shields.io/badge/rust-1.49+-93450a.svg?style=for-the-badge&logo=rust
+//! [`twilight-http`]: https://docs.rs/twilight-http
 
 #![deny(
     clippy::missing_const_for_fn,
@@ -53,21 +62,28 @@
     warnings
 )]
 
+pub mod iter;
 pub mod model;
 
+#[cfg(feature = "http")]
+pub mod read_through;
+
 mod builder;
 mod config;
+mod eviction;
+mod filter;
 mod stats;
 mod updates;
 
 pub use self::{
     builder::InMemoryCacheBuilder,
-    config::{Config, ResourceType},
+    config::{Config, EvictionPolicy, ResourceType},
+    filter::GuildFilter,
     stats::InMemoryCacheStats,
-    updates::UpdateCache,
+    updates::{PreviousValue, UpdateCache},
 };
 
-use self::model::*;
+use self::{eviction::Tracker, filter::MessageFilter, iter::InMemoryCacheIter, model::*};
 use dashmap::{
     mapref::{entry::Entry, one::Ref},
     DashMap, DashSet,
@@ -76,7 +92,9 @@ use std::{
     borrow::Cow,
     collections::{BTreeSet, HashSet, VecDeque},
     hash::Hash,
-    sync::{Arc, Mutex},
+    ops::Deref,
+    sync::{Arc, Mutex, RwLock},
+    time::Instant,
 };
 use twilight_model::{
     application::interaction::application_command::InteractionMember,
@@ -88,37 +106,68 @@ use twilight_model::{
     voice::VoiceState,
 };
 
+/// Resource associated with a guild.
+///
+/// This is used for resources whose models don't always include the ID of the
+/// guild they belong to, such as roles. It dereferences to the resource.
 #[derive(Debug)]
-struct GuildItem<T> {
+pub struct GuildItem<T> {
     data: T,
     guild_id: GuildId,
 }
 
+impl<T> GuildItem<T> {
+    /// Immutable reference to the resource.
+    pub const fn data(&self) -> &T {
+        &self.data
+    }
+
+    /// ID of the guild the resource belongs to.
+    pub const fn guild_id(&self) -> GuildId {
+        self.guild_id
+    }
+}
+
+impl<T> Deref for GuildItem<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+/// Insert an item belonging to a guild, returning the item it replaced.
 fn upsert_guild_item<K: Eq + Hash, V: PartialEq>(
     map: &DashMap<K, GuildItem<V>>,
     guild_id: GuildId,
     key: K,
     value: V,
-) {
+) -> Option<V> {
     match map.entry(key) {
-        Entry::Occupied(entry) if entry.get().data == value => {}
-        Entry::Occupied(mut entry) => {
-            entry.insert(GuildItem {
-                data: value,
-                guild_id,
-            });
-        }
+        // The replaced item is equal to the new one, so there's no need to
+        // perform the insert.
+        Entry::Occupied(entry) if entry.get().data == value => None,
+        Entry::Occupied(mut entry) => Some(
+            entry
+                .insert(GuildItem {
+                    data: value,
+                    guild_id,
+                })
+                .data,
+        ),
         Entry::Vacant(entry) => {
             entry.insert(GuildItem {
                 data: value,
                 guild_id,
             });
+
+            None
         }
     }
 }
 
-fn upsert_item<K: Eq + Hash, V: PartialEq>(map: &DashMap<K, V>, k: K, v: V) {
-    map.insert(k, v);
+fn upsert_item<K: Eq + Hash, V: PartialEq>(map: &DashMap<K, V>, k: K, v: V) -> Option<V> {
+    map.insert(k, v)
 }
 
 // When adding a field here, be sure to add it to `InMemoryCache::clear` if
@@ -135,6 +184,8 @@ struct InMemoryCacheRef {
     guilds: DashMap<GuildId, CachedGuild>,
     guild_channels: DashMap<GuildId, HashSet<ChannelId>>,
     guild_emojis: DashMap<GuildId, HashSet<EmojiId>>,
+    /// Filter deciding which guilds resources are cached for.
+    guild_filter: RwLock<Option<Arc<GuildFilter>>>,
     guild_integrations: DashMap<GuildId, HashSet<IntegrationId>>,
     guild_members: DashMap<GuildId, HashSet<UserId>>,
     guild_presences: DashMap<GuildId, HashSet<UserId>>,
@@ -142,12 +193,20 @@ struct InMemoryCacheRef {
     guild_stage_instances: DashMap<GuildId, HashSet<StageId>>,
     integrations: DashMap<(GuildId, IntegrationId), GuildItem<GuildIntegration>>,
     members: DashMap<(GuildId, UserId), CachedMember>,
+    /// When members were last seen, for eviction.
+    member_tracker: Tracker<(GuildId, UserId)>,
+    /// Filter deciding which channels messages are cached for.
+    message_filter: RwLock<Option<MessageFilter>>,
     messages: DashMap<ChannelId, VecDeque<CachedMessage>>,
     presences: DashMap<(GuildId, UserId), CachedPresence>,
+    /// When presences were last seen, for eviction.
+    presence_tracker: Tracker<(GuildId, UserId)>,
     roles: DashMap<RoleId, GuildItem<Role>>,
     stage_instances: DashMap<StageId, GuildItem<StageInstance>>,
     unavailable_guilds: DashSet<GuildId>,
     users: DashMap<UserId, (User, BTreeSet<GuildId>)>,
+    /// When users were last seen, for eviction.
+    user_tracker: Tracker<UserId>,
     /// Mapping of channels and the users currently connected.
     voice_state_channels: DashMap<ChannelId, HashSet<(GuildId, UserId)>>,
     /// Mapping of guilds and users currently connected to its voice channels.
@@ -215,9 +274,15 @@ impl InMemoryCache {
         Self::default()
     }
 
-    fn new_with_config(config: Config) -> Self {
+    fn new_with_config(
+        config: Config,
+        guild_filter: Option<GuildFilter>,
+        message_filter: Option<MessageFilter>,
+    ) -> Self {
         Self(Arc::new(InMemoryCacheRef {
             config,
+            guild_filter: RwLock::new(guild_filter.map(Arc::new)),
+            message_filter: RwLock::new(message_filter),
             ..Default::default()
         }))
     }
@@ -232,6 +297,94 @@ impl InMemoryCache {
         self.0.config.clone()
     }
 
+    /// Replace the filter deciding which guilds resources are cached for.
+    ///
+    /// Resources of guilds that aren't allowed by the new filter are removed
+    /// from the cache.
+    ///
+    /// # Examples
+    ///
+    /// Stop caching members for a guild that is no longer premium:
+    ///
+    /// ```
+    /// use twilight_cache_inmemory::{GuildFilter, InMemoryCache, ResourceType};
+    /// use twilight_model::id::GuildId;
+    ///
+    /// let cache = InMemoryCache::builder()
+    ///     .guild_filter(GuildFilter::allow_list(
+    ///         ResourceType::MEMBER,
+    ///         vec![GuildId(1), GuildId(2)],
+    ///     ))
+    ///     .build();
+    ///
+    /// // later on...
+    /// cache.set_guild_filter(GuildFilter::allow_list(
+    ///     ResourceType::MEMBER,
+    ///     vec![GuildId(1)],
+    /// ));
+    /// ```
+    pub fn set_guild_filter(&self, filter: GuildFilter) {
+        let filter = Arc::new(filter);
+
+        self.0
+            .guild_filter
+            .write()
+            .expect("guild filter poisoned")
+            .replace(Arc::clone(&filter));
+
+        self.purge_filtered_guilds(&filter);
+    }
+
+    /// Remove the guild filter, caching resources for all guilds.
+    pub fn clear_guild_filter(&self) {
+        self.0
+            .guild_filter
+            .write()
+            .expect("guild filter poisoned")
+            .take();
+    }
+
+    /// Replace the predicate deciding which channels messages are cached for.
+    ///
+    /// Messages of channels that aren't allowed by the new predicate are
+    /// removed from the cache.
+    pub fn set_message_filter(
+        &self,
+        predicate: impl Fn(ChannelId) -> bool + Send + Sync + 'static,
+    ) {
+        let filter = MessageFilter::new(predicate);
+
+        self.0
+            .message_filter
+            .write()
+            .expect("message filter poisoned")
+            .replace(filter.clone());
+
+        // The predicate is user code, so it mustn't run while a shard of the
+        // map is locked.
+        let channel_ids = self
+            .0
+            .messages
+            .iter()
+            .map(|entry| *entry.key())
+            .collect::<Vec<_>>();
+
+        for channel_id in channel_ids {
+            if !filter.allows(channel_id) {
+                self.0.messages.remove(&channel_id);
+            }
+        }
+    }
+
+    /// Remove the message filter, caching messages for all channels.
+    pub fn clear_message_filter(&self) {
+        self.0
+            .message_filter
+            .write()
+            .expect("message filter poisoned")
+            .take();
+    }
+
     /// Create an interface for retrieving statistics about the cache.
     ///
     /// # Examples
@@ -251,9 +404,93 @@ impl InMemoryCache {
         InMemoryCacheStats::new(self)
     }
 
+    /// Create an interface for iterating over the resources in the cache.
+    ///
+    /// # Examples
+    ///
+    /// Print the names of all guilds in the cache:
+    ///
+    /// ```
+    /// use twilight_cache_inmemory::InMemoryCache;
+    ///
+    /// let cache = InMemoryCache::new();
+    ///
+    /// // later on...
+    /// for guild in cache.iter().guilds() {
+    ///     println!("{}", guild.name);
+    /// }
+    /// ```
+    pub const fn iter(&self) -> InMemoryCacheIter<'_> {
+        InMemoryCacheIter::new(self)
+    }
+
     /// Update the cache with an event from the gateway.
-    pub fn update(&self, value: &impl UpdateCache) {
-        value.update(self);
+    ///
+    /// Returns the cached state the event replaced or removed, if any. Refer
+    /// to [`PreviousValue`] for more information.
+    ///
+    /// Members, presences, and users that should be evicted according to the
+    /// configured eviction policies are removed afterwards.
+    ///
+    /// # Examples
+    ///
+    /// Log the content of a message before it was edited:
+    ///
+    /// ```
+    /// use twilight_cache_inmemory::{InMemoryCache, PreviousValue};
+    /// use twilight_model::gateway::payload::MessageUpdate;
+    ///
+    /// fn handle(cache: &InMemoryCache, update: &MessageUpdate) {
+    ///     if let Some(PreviousValue::Message(old)) = cache.update(update) {
+    ///         println!("message edited from {:?} to {:?}", old.content, update.content);
+    ///     }
+    /// }
+    /// ```
+    pub fn update(&self, value: &impl UpdateCache) -> Option<PreviousValue> {
+        let previous = value.update(self);
+        self.evict();
+
+        previous
+    }
+
+    /// Gets the IDs of the channels within a category, sorted by their
+    /// position.
+    ///
+    /// Returns `None` if the category isn't cached or the channel isn't a
+    /// category.
+    ///
+    /// This is a O(c log c) operation, where c is the amount of channels in the
+    /// category's guild. This requires the [`GUILDS`] intent.
+    ///
+    /// [`GUILDS`]: ::twilight_model::gateway::Intents::GUILDS
+    pub fn category_channels(&self, category_id: ChannelId) -> Option<Vec<ChannelId>> {
+        let guild_id = {
+            let category = self.0.channels_guild.get(&category_id)?;
+
+            if !matches!(category.data, GuildChannel::Category(_)) {
+                return None;
+            }
+
+            category.guild_id
+        };
+        let channel_ids = self.0.guild_channels.get(&guild_id)?;
+
+        let mut channels = channel_ids
+            .iter()
+            .filter_map(|id| {
+                let channel = self.0.channels_guild.get(id)?;
+
+                if channel.data.parent_id() == Some(category_id) {
+                    Some((channel.data.position(), *id))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        channels.sort_unstable();
+
+        Some(channels.into_iter().map(|(_, id)| id).collect())
     }
 
     /// Gets a channel by ID.
@@ -359,6 +596,31 @@ impl InMemoryCache {
         self.0.guild_roles.get(&guild_id).map(|r| r.clone())
     }
 
+    /// Gets the IDs of the members in a guild that have a role.
+    ///
+    /// This list may be incomplete if not all members have been cached.
+    ///
+    /// This is a O(m) operation, where m is the amount of members in the guild.
+    /// This requires the [`GUILD_MEMBERS`] intent.
+    ///
+    /// [`GUILD_MEMBERS`]: ::twilight_model::gateway::Intents::GUILD_MEMBERS
+    pub fn guild_role_members(&self, guild_id: GuildId, role_id: RoleId) -> Option<Vec<UserId>> {
+        let user_ids = self.0.guild_members.get(&guild_id)?;
+
+        Some(
+            user_ids
+                .iter()
+                .copied()
+                .filter(|user_id| {
+                    self.0
+                        .members
+                        .get(&(guild_id, *user_id))
+                        .map_or(false, |member| member.roles.contains(&role_id))
+                })
+                .collect(),
+        )
+    }
+
     /// Gets the set of stage instances in a guild.
     ///
     /// This is a O(m) operation, where m is the amount of stage instances in
@@ -454,6 +716,20 @@ impl InMemoryCache {
         self.0.users.get(&user_id)
     }
 
+    /// Gets the IDs of the users connected to a voice channel.
+    ///
+    /// Returns `None` if nobody is connected to the channel.
+    ///
+    /// This requires both the [`GUILDS`] and [`GUILD_VOICE_STATES`] intents.
+    ///
+    /// [`GUILDS`]: ::twilight_model::gateway::Intents::GUILDS
+    /// [`GUILD_VOICE_STATES`]: ::twilight_model::gateway::Intents::GUILD_VOICE_STATES
+    pub fn voice_channel_members(&self, channel_id: ChannelId) -> Option<Vec<UserId>> {
+        let user_ids = self.0.voice_state_channels.get(&channel_id)?;
+
+        Some(user_ids.iter().map(|(_, user_id)| *user_id).collect())
+    }
+
     /// Gets the voice states within a voice channel.
     ///
     /// This requires both the [`GUILDS`] and [`GUILD_VOICE_STATES`] intents.
@@ -508,22 +784,25 @@ impl InMemoryCache {
         self.0.guild_stage_instances.clear();
         self.0.integrations.clear();
         self.0.members.clear();
+        self.0.member_tracker.clear();
         self.0.messages.clear();
         self.0.presences.clear();
+        self.0.presence_tracker.clear();
         self.0.roles.clear();
         self.0.unavailable_guilds.clear();
         self.0.users.clear();
+        self.0.user_tracker.clear();
         self.0.voice_state_channels.clear();
         self.0.voice_state_guilds.clear();
         self.0.voice_states.clear();
     }
 
-    fn cache_current_user(&self, current_user: CurrentUser) {
+    fn cache_current_user(&self, current_user: CurrentUser) -> Option<CurrentUser> {
         self.0
             .current_user
             .lock()
             .expect("current user poisoned")
-            .replace(current_user);
+            .replace(current_user)
     }
 
     fn cache_guild_channels(
@@ -536,7 +815,15 @@ impl InMemoryCache {
         }
     }
 
-    fn cache_guild_channel(&self, guild_id: GuildId, mut channel: GuildChannel) {
+    fn cache_guild_channel(
+        &self,
+        guild_id: GuildId,
+        mut channel: GuildChannel,
+    ) -> Option<GuildChannel> {
+        if !self.allows_guild(ResourceType::CHANNEL, guild_id) {
+            return None;
+        }
+
         match channel {
             GuildChannel::Category(ref mut c) => {
                 c.guild_id.replace(guild_id);
@@ -559,7 +846,7 @@ impl InMemoryCache {
             .or_default()
             .insert(id);
 
-        upsert_guild_item(&self.0.channels_guild, guild_id, id, channel);
+        upsert_guild_item(&self.0.channels_guild, guild_id, id, channel)
     }
 
     fn cache_emoji(&self, guild_id: GuildId, emoji: Emoji) {
@@ -600,7 +887,23 @@ impl InMemoryCache {
             .insert(emoji.id);
     }
 
-    fn cache_emojis(&self, guild_id: GuildId, emojis: Vec<Emoji>) {
+    /// Replace the emojis of a guild, returning the previously cached emojis.
+    fn cache_emojis(&self, guild_id: GuildId, emojis: Vec<Emoji>) -> Vec<CachedEmoji> {
+        if !self.allows_guild(ResourceType::EMOJI, guild_id) {
+            return Vec::new();
+        }
+
+        let previous = self
+            .0
+            .guild_emojis
+            .get(&guild_id)
+            .map(|ids| {
+                ids.iter()
+                    .filter_map(|id| self.0.emojis.get(id).map(|emoji| emoji.data.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
         if let Some(mut guild_emojis) = self.0.guild_emojis.get_mut(&guild_id) {
             let incoming: Vec<EmojiId> = emojis.iter().map(|e| e.id).collect();
 
@@ -622,31 +925,33 @@ impl InMemoryCache {
         for emoji in emojis {
             self.cache_emoji(guild_id, emoji);
         }
+
+        previous
     }
 
-    fn cache_group(&self, group: Group) {
+    fn cache_group(&self, group: Group) -> Option<Group> {
         upsert_item(&self.0.groups, group.id, group)
     }
 
-    fn cache_guild(&self, guild: Guild) {
+    fn cache_guild(&self, guild: Guild) -> Option<CachedGuild> {
         // The map and set creation needs to occur first, so caching states and
         // objects always has a place to put them.
-        if self.wants(ResourceType::CHANNEL) {
+        if self.wants_guild(ResourceType::CHANNEL, guild.id) {
             self.0.guild_channels.insert(guild.id, HashSet::new());
             self.cache_guild_channels(guild.id, guild.channels);
         }
 
-        if self.wants(ResourceType::EMOJI) {
+        if self.wants_guild(ResourceType::EMOJI, guild.id) {
             self.0.guild_emojis.insert(guild.id, HashSet::new());
             self.cache_emojis(guild.id, guild.emojis);
         }
 
-        if self.wants(ResourceType::MEMBER) {
+        if self.wants_guild(ResourceType::MEMBER, guild.id) {
             self.0.guild_members.insert(guild.id, HashSet::new());
             self.cache_members(guild.id, guild.members);
         }
 
-        if self.wants(ResourceType::PRESENCE) {
+        if self.wants_guild(ResourceType::PRESENCE, guild.id) {
             self.0.guild_presences.insert(guild.id, HashSet::new());
             self.cache_presences(
                 guild.id,
@@ -654,17 +959,17 @@ impl InMemoryCache {
             );
         }
 
-        if self.wants(ResourceType::ROLE) {
+        if self.wants_guild(ResourceType::ROLE, guild.id) {
             self.0.guild_roles.insert(guild.id, HashSet::new());
             self.cache_roles(guild.id, guild.roles);
         }
 
-        if self.wants(ResourceType::VOICE_STATE) {
+        if self.wants_guild(ResourceType::VOICE_STATE, guild.id) {
             self.0.voice_state_guilds.insert(guild.id, HashSet::new());
             self.cache_voice_states(guild.voice_states);
         }
 
-        if self.wants(ResourceType::STAGE_INSTANCE) {
+        if self.wants_guild(ResourceType::STAGE_INSTANCE, guild.id) {
             self.0
                 .guild_stage_instances
                 .insert(guild.id, HashSet::new());
@@ -709,10 +1014,23 @@ impl InMemoryCache {
         };
 
         self.0.unavailable_guilds.remove(&guild.id);
-        self.0.guilds.insert(guild.id, guild);
+
+        if !self.allows_guild(ResourceType::GUILD, guild.id) {
+            return None;
+        }
+
+        self.0.guilds.insert(guild.id, guild)
     }
 
-    fn cache_integration(&self, guild_id: GuildId, integration: GuildIntegration) {
+    fn cache_integration(
+        &self,
+        guild_id: GuildId,
+        integration: GuildIntegration,
+    ) -> Option<GuildIntegration> {
+        if !self.allows_guild(ResourceType::INTEGRATION, guild_id) {
+            return None;
+        }
+
         self.0
             .guild_integrations
             .entry(guild_id)
@@ -724,39 +1042,34 @@ impl InMemoryCache {
             guild_id,
             (guild_id, integration.id),
             integration,
-        );
+        )
     }
 
-    fn cache_member(&self, guild_id: GuildId, member: Member) {
+    fn cache_member(&self, guild_id: GuildId, member: Member) -> Option<CachedMember> {
         let member_id = member.user.id;
         let id = (guild_id, member_id);
 
+        if !self.admits_member(id) {
+            return None;
+        }
+
+        self.member_seen(id);
+
         if let Some(m) = self.0.members.get(&id) {
             if *m == member {
-                return;
+                return None;
             }
         }
 
-        let user_id = member.user.id;
-
-        self.cache_user(Cow::Owned(member.user), Some(guild_id));
-        let cached = CachedMember {
-            deaf: Some(member.deaf),
-            guild_id,
-            joined_at: member.joined_at,
-            mute: Some(member.mute),
-            nick: member.nick,
-            pending: member.pending,
-            premium_since: member.premium_since,
-            roles: member.roles,
-            user_id,
-        };
-        self.0.members.insert(id, cached);
+        self.cache_user(Cow::Borrowed(&member.user), Some(guild_id));
+        let cached = CachedMember::from_member(guild_id, member);
         self.0
             .guild_members
             .entry(guild_id)
             .or_default()
             .insert(member_id);
+
+        self.0.members.insert(id, cached)
     }
 
     fn cache_borrowed_partial_member(
@@ -764,12 +1077,18 @@ impl InMemoryCache {
         guild_id: GuildId,
         member: &PartialMember,
         user_id: UserId,
-    ) {
+    ) -> Option<CachedMember> {
         let id = (guild_id, user_id);
 
+        if !self.admits_member(id) {
+            return None;
+        }
+
+        self.member_seen(id);
+
         if let Some(m) = self.0.members.get(&id) {
             if *m == member {
-                return;
+                return Some(m.clone());
             }
         }
 
@@ -790,14 +1109,25 @@ impl InMemoryCache {
             roles: member.roles.to_owned(),
             user_id,
         };
-        self.0.members.insert(id, cached);
+
+        self.0.members.insert(id, cached)
     }
 
-    fn cache_borrowed_interaction_member(&self, guild_id: GuildId, member: &InteractionMember) {
+    fn cache_borrowed_interaction_member(
+        &self,
+        guild_id: GuildId,
+        member: &InteractionMember,
+    ) -> Option<CachedMember> {
         let id = (guild_id, member.id);
 
+        if !self.admits_member(id) {
+            return None;
+        }
+
+        self.member_seen(id);
+
         let (deaf, mute) = match self.0.members.get(&id) {
-            Some(m) if *m == member => return,
+            Some(m) if *m == member => return Some(m.clone()),
             Some(m) => (m.deaf, m.mute),
             None => (None, None),
         };
@@ -820,13 +1150,19 @@ impl InMemoryCache {
             user_id: member.id,
         };
 
-        self.0.members.insert(id, cached);
+        self.0.members.insert(id, cached)
     }
 
-    fn cache_members(&self, guild_id: GuildId, members: impl IntoIterator<Item = Member>) {
-        for member in members {
-            self.cache_member(guild_id, member);
-        }
+    /// Cache members of a guild, returning the members they replaced.
+    fn cache_members(
+        &self,
+        guild_id: GuildId,
+        members: impl IntoIterator<Item = Member>,
+    ) -> Vec<CachedMember> {
+        members
+            .into_iter()
+            .filter_map(|member| self.cache_member(guild_id, member))
+            .collect()
     }
 
     fn cache_presences(
@@ -839,16 +1175,34 @@ impl InMemoryCache {
         }
     }
 
-    fn cache_presence(&self, guild_id: GuildId, presence: CachedPresence) {
+    fn cache_presence(
+        &self,
+        guild_id: GuildId,
+        presence: CachedPresence,
+    ) -> Option<CachedPresence> {
+        if !self.allows_guild(ResourceType::PRESENCE, guild_id) {
+            return None;
+        }
+
+        let user_id = presence.user_id;
+
+        if self.0.config.presence_eviction.is_enabled() {
+            self.0.presence_tracker.touch((guild_id, user_id));
+        }
+
         self.0
-            .presences
-            .insert((guild_id, presence.user_id), presence);
+            .guild_presences
+            .entry(guild_id)
+            .or_default()
+            .insert(user_id);
+
+        self.0.presences.insert((guild_id, user_id), presence)
     }
 
-    fn cache_private_channel(&self, private_channel: PrivateChannel) {
+    fn cache_private_channel(&self, private_channel: PrivateChannel) -> Option<PrivateChannel> {
         self.0
             .channels_private
-            .insert(private_channel.id, private_channel);
+            .insert(private_channel.id, private_channel)
     }
 
     fn cache_roles(&self, guild_id: GuildId, roles: impl IntoIterator<Item = Role>) {
@@ -857,7 +1211,11 @@ impl InMemoryCache {
         }
     }
 
-    fn cache_role(&self, guild_id: GuildId, role: Role) {
+    fn cache_role(&self, guild_id: GuildId, role: Role) -> Option<Role> {
+        if !self.allows_guild(ResourceType::ROLE, guild_id) {
+            return None;
+        }
+
         // Insert the role into the guild_roles map
         self.0
             .guild_roles
@@ -866,7 +1224,7 @@ impl InMemoryCache {
             .insert(role.id);
 
         // Insert the role into the all roles map
-        upsert_guild_item(&self.0.roles, guild_id, role.id, role);
+        upsert_guild_item(&self.0.roles, guild_id, role.id, role)
     }
 
     fn cache_stage_instances(
@@ -879,7 +1237,15 @@ impl InMemoryCache {
         }
     }
 
-    fn cache_stage_instance(&self, guild_id: GuildId, stage_instance: StageInstance) {
+    fn cache_stage_instance(
+        &self,
+        guild_id: GuildId,
+        stage_instance: StageInstance,
+    ) -> Option<StageInstance> {
+        if !self.allows_guild(ResourceType::STAGE_INSTANCE, guild_id) {
+            return None;
+        }
+
         self.0
             .guild_stage_instances
             .entry(guild_id)
@@ -891,16 +1257,25 @@ impl InMemoryCache {
             guild_id,
             stage_instance.id,
             stage_instance,
-        );
+        )
     }
 
     fn cache_user(&self, user: Cow<'_, User>, guild_id: Option<GuildId>) {
+        // Users of guilds that are filtered out are only updated if they're
+        // already known from another guild.
+        let guild_id = guild_id.filter(|id| self.allows_guild(ResourceType::USER, *id));
+        let tracked = self.0.config.user_eviction.is_enabled();
+
         match self.0.users.get_mut(&user.id) {
             Some(mut u) if u.0 == *user => {
                 if let Some(guild_id) = guild_id {
                     u.1.insert(guild_id);
                 }
 
+                if tracked {
+                    self.0.user_tracker.touch(user.id);
+                }
+
                 return;
             }
             Some(_) | None => {}
@@ -908,6 +1283,10 @@ impl InMemoryCache {
         let user = user.into_owned();
 
         if let Some(guild_id) = guild_id {
+            if tracked {
+                self.0.user_tracker.touch(user.id);
+            }
+
             let mut guild_id_set = BTreeSet::new();
             guild_id_set.insert(guild_id);
             self.0.users.insert(user.id, (user, guild_id_set));
@@ -920,13 +1299,17 @@ impl InMemoryCache {
         }
     }
 
-    fn cache_voice_state(&self, voice_state: VoiceState) {
+    fn cache_voice_state(&self, voice_state: VoiceState) -> Option<VoiceState> {
         // This should always exist, but just incase use a match
         let guild_id = match voice_state.guild_id {
             Some(id) => id,
-            None => return,
+            None => return None,
         };
 
+        if !self.allows_guild(ResourceType::VOICE_STATE, guild_id) {
+            return None;
+        }
+
         let user_id = voice_state.user_id;
 
         // Check if the user is switching channels in the same guild (ie. they already have a voice state entry)
@@ -968,13 +1351,15 @@ impl InMemoryCache {
                 }
             }
 
-            self.0.voice_states.remove(&(guild_id, user_id));
-
-            return;
+            return self
+                .0
+                .voice_states
+                .remove(&(guild_id, user_id))
+                .map(|(_, voice_state)| voice_state);
         }
 
         let maybe_channel_id = voice_state.channel_id;
-        self.0.voice_states.insert((guild_id, user_id), voice_state);
+        let previous = self.0.voice_states.insert((guild_id, user_id), voice_state);
 
         self.0
             .voice_state_guilds
@@ -989,58 +1374,275 @@ impl InMemoryCache {
                 .or_default()
                 .insert((guild_id, user_id));
         }
+
+        previous
     }
 
-    fn delete_group(&self, channel_id: ChannelId) {
-        self.0.groups.remove(&channel_id);
+    fn delete_group(&self, channel_id: ChannelId) -> Option<Group> {
+        self.0.groups.remove(&channel_id).map(|(_, group)| group)
     }
 
-    fn unavailable_guild(&self, guild_id: GuildId) {
+    /// Delete a member from the cache.
+    ///
+    /// The member is removed from its guild's list of members, and the user is
+    /// removed if it's no longer in any cached guild.
+    fn delete_member(&self, guild_id: GuildId, user_id: UserId) -> Option<CachedMember> {
+        let removed = self
+            .0
+            .members
+            .remove(&(guild_id, user_id))
+            .map(|(_, member)| member);
+        self.0.member_tracker.remove(&(guild_id, user_id));
+
+        if let Some(mut members) = self.0.guild_members.get_mut(&guild_id) {
+            members.remove(&user_id);
+        }
+
+        // Avoid a deadlock by mutating the user, dropping the lock to the map,
+        // and then maybe conditionally removing the user later.
+        let mut maybe_remove_user = false;
+
+        if let Some(mut user_tuple) = self.0.users.get_mut(&user_id) {
+            user_tuple.1.remove(&guild_id);
+
+            maybe_remove_user = true;
+        }
+
+        if maybe_remove_user
+            && self
+                .0
+                .users
+                .remove_if(&user_id, |_, guild_set| guild_set.1.is_empty())
+                .is_some()
+        {
+            self.0.user_tracker.remove(&user_id);
+        }
+
+        removed
+    }
+
+    /// Delete a presence from the cache, including its entry in its guild's
+    /// list of presences.
+    fn delete_presence(&self, guild_id: GuildId, user_id: UserId) -> Option<CachedPresence> {
+        self.0.presence_tracker.remove(&(guild_id, user_id));
+
+        if let Some(mut presences) = self.0.guild_presences.get_mut(&guild_id) {
+            presences.remove(&user_id);
+        }
+
+        self.0
+            .presences
+            .remove(&(guild_id, user_id))
+            .map(|(_, presence)| presence)
+    }
+
+    /// Delete the resources of the given types belonging to a guild.
+    ///
+    /// Returns the removed guild if [`ResourceType::GUILD`] is included.
+    fn delete_guild_resources(
+        &self,
+        guild_id: GuildId,
+        resource_types: ResourceType,
+    ) -> Option<CachedGuild> {
+        fn remove_ids<T: Eq + Hash, U>(
+            guild_map: &DashMap<GuildId, HashSet<T>>,
+            container: &DashMap<T, U>,
+            guild_id: GuildId,
+        ) {
+            if let Some((_, ids)) = guild_map.remove(&guild_id) {
+                for id in ids {
+                    container.remove(&id);
+                }
+            }
+        }
+
+        // Messages are looked up through the guild's channels, so they need to
+        // be removed before the channels are.
+        if resource_types.contains(ResourceType::MESSAGE) {
+            if let Some(channel_ids) = self.0.guild_channels.get(&guild_id) {
+                for channel_id in channel_ids.iter() {
+                    self.0.messages.remove(channel_id);
+                }
+            }
+        }
+
+        if resource_types.contains(ResourceType::CHANNEL) {
+            remove_ids(&self.0.guild_channels, &self.0.channels_guild, guild_id);
+        }
+
+        if resource_types.contains(ResourceType::EMOJI) {
+            remove_ids(&self.0.guild_emojis, &self.0.emojis, guild_id);
+        }
+
+        if resource_types.contains(ResourceType::INTEGRATION) {
+            if let Some((_, ids)) = self.0.guild_integrations.remove(&guild_id) {
+                for integration_id in ids {
+                    self.0.integrations.remove(&(guild_id, integration_id));
+                }
+            }
+        }
+
+        if resource_types.contains(ResourceType::ROLE) {
+            remove_ids(&self.0.guild_roles, &self.0.roles, guild_id);
+        }
+
+        if resource_types.contains(ResourceType::STAGE_INSTANCE) {
+            remove_ids(
+                &self.0.guild_stage_instances,
+                &self.0.stage_instances,
+                guild_id,
+            );
+        }
+
+        if resource_types.contains(ResourceType::VOICE_STATE) {
+            // Clear out a guilds voice states when a guild leaves
+            if let Some((_, user_ids)) = self.0.voice_state_guilds.remove(&guild_id) {
+                for user_id in user_ids {
+                    let channel_id = self
+                        .0
+                        .voice_states
+                        .remove(&(guild_id, user_id))
+                        .and_then(|(_, voice_state)| voice_state.channel_id);
+
+                    if let Some(channel_id) = channel_id {
+                        let remove_channel_mapping = self
+                            .0
+                            .voice_state_channels
+                            .get_mut(&channel_id)
+                            .map(|mut channel_voice_states| {
+                                channel_voice_states.remove(&(guild_id, user_id));
+
+                                channel_voice_states.is_empty()
+                            })
+                            .unwrap_or_default();
+
+                        if remove_channel_mapping {
+                            self.0.voice_state_channels.remove(&channel_id);
+                        }
+                    }
+                }
+            }
+        }
+
+        if resource_types.contains(ResourceType::MEMBER) {
+            if let Some((_, user_ids)) = self.0.guild_members.remove(&guild_id) {
+                for user_id in user_ids {
+                    self.delete_member(guild_id, user_id);
+                }
+            }
+        }
+
+        if resource_types.contains(ResourceType::PRESENCE) {
+            if let Some((_, user_ids)) = self.0.guild_presences.remove(&guild_id) {
+                for user_id in user_ids {
+                    self.0.presences.remove(&(guild_id, user_id));
+                    self.0.presence_tracker.remove(&(guild_id, user_id));
+                }
+            }
+        }
+
+        if resource_types.contains(ResourceType::USER) {
+            let mut removed = Vec::new();
+
+            self.0.users.retain(|user_id, (_, guild_ids)| {
+                if !guild_ids.remove(&guild_id) || !guild_ids.is_empty() {
+                    return true;
+                }
+
+                removed.push(*user_id);
+
+                false
+            });
+
+            for user_id in removed {
+                self.0.user_tracker.remove(&user_id);
+            }
+        }
+
+        if resource_types.contains(ResourceType::GUILD) {
+            self.0.guilds.remove(&guild_id).map(|(_, guild)| guild)
+        } else {
+            None
+        }
+    }
+
+    /// Delete the resources of guilds that aren't allowed by a guild filter.
+    fn purge_filtered_guilds(&self, filter: &GuildFilter) {
+        let resource_types = filter.resource_types() & self.0.config.resource_types();
+
+        if resource_types.is_empty() {
+            return;
+        }
+
+        let mut guild_ids = HashSet::new();
+        guild_ids.extend(self.0.guilds.iter().map(|r| *r.key()));
+        guild_ids.extend(self.0.guild_channels.iter().map(|r| *r.key()));
+        guild_ids.extend(self.0.guild_emojis.iter().map(|r| *r.key()));
+        guild_ids.extend(self.0.guild_integrations.iter().map(|r| *r.key()));
+        guild_ids.extend(self.0.guild_members.iter().map(|r| *r.key()));
+        guild_ids.extend(self.0.guild_presences.iter().map(|r| *r.key()));
+        guild_ids.extend(self.0.guild_roles.iter().map(|r| *r.key()));
+        guild_ids.extend(self.0.guild_stage_instances.iter().map(|r| *r.key()));
+        guild_ids.extend(self.0.voice_state_guilds.iter().map(|r| *r.key()));
+
+        for guild_id in guild_ids {
+            if !filter.allows(resource_types, guild_id) {
+                self.delete_guild_resources(guild_id, resource_types);
+            }
+        }
+    }
+
+    fn unavailable_guild(&self, guild_id: GuildId) -> Option<CachedGuild> {
         self.0.unavailable_guilds.insert(guild_id);
-        self.0.guilds.remove(&guild_id);
+        self.0.guilds.remove(&guild_id).map(|(_, guild)| guild)
     }
 
     /// Delete a guild channel from the cache.
     ///
     /// The guild channel data itself and the channel entry in its guild's list
     /// of channels will be deleted.
-    fn delete_guild_channel(&self, channel_id: ChannelId) {
-        if let Some((_, item)) = self.0.channels_guild.remove(&channel_id) {
-            if let Some(mut guild_channels) = self.0.guild_channels.get_mut(&item.guild_id) {
-                guild_channels.remove(&channel_id);
-            }
+    fn delete_guild_channel(&self, channel_id: ChannelId) -> Option<GuildChannel> {
+        let (_, item) = self.0.channels_guild.remove(&channel_id)?;
+
+        if let Some(mut guild_channels) = self.0.guild_channels.get_mut(&item.guild_id) {
+            guild_channels.remove(&channel_id);
         }
+
+        Some(item.data)
     }
 
-    fn delete_integration(&self, guild_id: GuildId, integration_id: IntegrationId) {
-        if self
-            .0
-            .integrations
-            .remove(&(guild_id, integration_id))
-            .is_some()
-        {
-            if let Some(mut integrations) = self.0.guild_integrations.get_mut(&guild_id) {
-                integrations.remove(&integration_id);
-            }
+    fn delete_integration(
+        &self,
+        guild_id: GuildId,
+        integration_id: IntegrationId,
+    ) -> Option<GuildIntegration> {
+        let (_, item) = self.0.integrations.remove(&(guild_id, integration_id))?;
+
+        if let Some(mut integrations) = self.0.guild_integrations.get_mut(&guild_id) {
+            integrations.remove(&integration_id);
         }
+
+        Some(item.data)
     }
 
-    fn delete_role(&self, role_id: RoleId) {
-        if let Some((_, role)) = self.0.roles.remove(&role_id) {
-            if let Some(mut roles) = self.0.guild_roles.get_mut(&role.guild_id) {
-                roles.remove(&role_id);
-            }
+    fn delete_role(&self, role_id: RoleId) -> Option<Role> {
+        let (_, role) = self.0.roles.remove(&role_id)?;
+
+        if let Some(mut roles) = self.0.guild_roles.get_mut(&role.guild_id) {
+            roles.remove(&role_id);
         }
+
+        Some(role.data)
     }
 
-    fn delete_stage_instance(&self, stage_id: StageId) {
-        if let Some((_, data)) = self.0.stage_instances.remove(&stage_id) {
-            let guild_id = data.guild_id;
+    fn delete_stage_instance(&self, stage_id: StageId) -> Option<StageInstance> {
+        let (_, item) = self.0.stage_instances.remove(&stage_id)?;
 
-            if let Some(mut stage_instances) = self.0.guild_stage_instances.get_mut(&guild_id) {
-                stage_instances.remove(&stage_id);
-            }
+        if let Some(mut stage_instances) = self.0.guild_stage_instances.get_mut(&item.guild_id) {
+            stage_instances.remove(&stage_id);
         }
+
+        Some(item.data)
     }
 
     /// Determine whether the configured cache wants a specific resource to be
@@ -1048,6 +1650,103 @@ impl InMemoryCache {
     fn wants(&self, resource_type: ResourceType) -> bool {
         self.0.config.resource_types().contains(resource_type)
     }
+
+    /// Determine whether the configured cache wants a specific resource of a
+    /// guild to be processed, taking the guild filter into account.
+    fn wants_guild(&self, resource_type: ResourceType, guild_id: GuildId) -> bool {
+        self.wants(resource_type) && self.allows_guild(resource_type, guild_id)
+    }
+
+    /// Determine whether the guild filter allows a specific resource of a guild
+    /// to be cached.
+    fn allows_guild(&self, resource_type: ResourceType, guild_id: GuildId) -> bool {
+        // Clone the filter out of the lock so that it's not held while calling
+        // a user-provided predicate.
+        let filter = self
+            .0
+            .guild_filter
+            .read()
+            .expect("guild filter poisoned")
+            .clone();
+
+        filter.map_or(true, |filter| filter.allows(resource_type, guild_id))
+    }
+
+    /// Determine whether the message filter allows messages of a channel to be
+    /// cached.
+    fn allows_message(&self, channel_id: ChannelId) -> bool {
+        let filter = self
+            .0
+            .message_filter
+            .read()
+            .expect("message filter poisoned")
+            .clone();
+
+        filter.map_or(true, |filter| filter.allows(channel_id))
+    }
+
+    /// Determine whether a member may be cached.
+    ///
+    /// Members of guilds that are filtered out aren't cached. When a member
+    /// activity window is configured only members that have recently sent a
+    /// message are cached.
+    fn admits_member(&self, id: (GuildId, UserId)) -> bool {
+        self.allows_guild(ResourceType::MEMBER, id.0)
+            && (self.0.config.member_activity_window.is_none()
+                || self.0.member_tracker.contains(&id))
+    }
+
+    /// Mark a member as seen for the purposes of eviction.
+    fn member_seen(&self, id: (GuildId, UserId)) {
+        let config = &self.0.config;
+
+        if config.member_activity_window.is_none() && config.member_eviction.is_enabled() {
+            self.0.member_tracker.touch(id);
+        }
+    }
+
+    /// Mark a member as having sent a message for the purposes of eviction.
+    fn member_spoke(&self, id: (GuildId, UserId)) {
+        if self.0.config.member_activity_window.is_some() {
+            self.0.member_tracker.touch(id);
+        }
+    }
+
+    /// Remove members, presences, and users that should be evicted according
+    /// to the configured eviction policies.
+    fn evict(&self) {
+        let config = &self.0.config;
+        let now = Instant::now();
+
+        if config.member_eviction.is_enabled() || config.member_activity_window.is_some() {
+            let evicted = self.0.member_tracker.evict(
+                config.member_eviction,
+                config.member_activity_window,
+                now,
+            );
+
+            for (guild_id, user_id) in evicted {
+                self.delete_member(guild_id, user_id);
+            }
+        }
+
+        if config.presence_eviction.is_enabled() {
+            let evicted = self
+                .0
+                .presence_tracker
+                .evict(config.presence_eviction, None, now);
+
+            for (guild_id, user_id) in evicted {
+                self.delete_presence(guild_id, user_id);
+            }
+        }
+
+        if config.user_eviction.is_enabled() {
+            for user_id in self.0.user_tracker.evict(config.user_eviction, None, now) {
+                self.0.users.remove(&user_id);
+            }
+        }
+    }
 }
 
 const fn presence_user_id(user_or_id: &UserOrId) -> UserId {
@@ -1059,21 +1758,23 @@ const fn presence_user_id(user_or_id: &UserOrId) -> UserId {
 
 #[cfg(test)]
 mod tests {
-    use crate::InMemoryCache;
-    use std::borrow::Cow;
+    use crate::{EvictionPolicy, GuildFilter, InMemoryCache, PreviousValue, ResourceType};
+    use std::{borrow::Cow, time::Duration};
     use twilight_model::{
         channel::{
-            stage_instance::PrivacyLevel, ChannelType, GuildChannel, StageInstance, TextChannel,
+            stage_instance::PrivacyLevel, CategoryChannel, ChannelType, GuildChannel,
+            StageInstance, TextChannel,
         },
         gateway::payload::{
-            GuildEmojisUpdate, MemberRemove, RoleDelete, StageInstanceCreate, StageInstanceDelete,
+            GuildDelete, GuildEmojisUpdate, MemberAdd, MemberChunk, MemberRemove,
+            MessageDeleteBulk, RoleCreate, RoleDelete, StageInstanceCreate, StageInstanceDelete,
             StageInstanceUpdate,
         },
         guild::{
             DefaultMessageNotificationLevel, Emoji, ExplicitContentFilter, Guild, Member, MfaLevel,
             NSFWLevel, Permissions, PremiumTier, Role, SystemChannelFlags, VerificationLevel,
         },
-        id::{ChannelId, EmojiId, GuildId, RoleId, StageId, UserId},
+        id::{ChannelId, EmojiId, GuildId, MessageId, RoleId, StageId, UserId},
         user::{CurrentUser, User},
         voice::VoiceState,
     };
@@ -1524,6 +2225,78 @@ mod tests {
 
         // Returns None if the channel does not exist.
         assert!(cache.voice_channel_states(ChannelId(0)).is_none());
+
+        let mut members = cache.voice_channel_members(ChannelId(2)).unwrap();
+        members.sort();
+        assert_eq!(vec![UserId(3), UserId(4)], members);
+    }
+
+    #[test]
+    fn test_category_channels() {
+        fn text_channel(id: u64, parent_id: Option<ChannelId>, position: i64) -> GuildChannel {
+            GuildChannel::Text(TextChannel {
+                id: ChannelId(id),
+                guild_id: None,
+                kind: ChannelType::GuildText,
+                last_message_id: None,
+                last_pin_timestamp: None,
+                name: "test".to_owned(),
+                nsfw: false,
+                permission_overwrites: Vec::new(),
+                parent_id,
+                position,
+                rate_limit_per_user: None,
+                topic: None,
+            })
+        }
+
+        let cache = InMemoryCache::new();
+        let guild_id = GuildId(1);
+        let category_id = ChannelId(10);
+
+        cache.cache_guild_channels(
+            guild_id,
+            vec![
+                GuildChannel::Category(CategoryChannel {
+                    guild_id: None,
+                    id: category_id,
+                    kind: ChannelType::GuildCategory,
+                    name: "category".to_owned(),
+                    permission_overwrites: Vec::new(),
+                    position: 0,
+                }),
+                text_channel(11, Some(category_id), 2),
+                text_channel(12, Some(category_id), 0),
+                text_channel(13, None, 1),
+                text_channel(14, Some(category_id), 1),
+            ],
+        );
+
+        assert_eq!(
+            vec![ChannelId(12), ChannelId(14), ChannelId(11)],
+            cache.category_channels(category_id).unwrap()
+        );
+        assert!(cache.category_channels(ChannelId(99)).is_none());
+        // Channels that aren't categories have no children.
+        assert!(cache.category_channels(ChannelId(11)).is_none());
+    }
+
+    #[test]
+    fn test_guild_role_members() {
+        let cache = InMemoryCache::new();
+        let guild_id = GuildId(1);
+
+        let mut with_role = member(UserId(2), guild_id);
+        with_role.roles.push(RoleId(3));
+        cache.cache_member(guild_id, with_role);
+        cache.cache_member(guild_id, member(UserId(4), guild_id));
+
+        assert_eq!(
+            vec![UserId(2)],
+            cache.guild_role_members(guild_id, RoleId(3)).unwrap()
+        );
+        assert!(cache.guild_role_members(GuildId(5), RoleId(3)).is_none());
+        assert_eq!(2, cache.iter().members().count());
     }
 
     #[test]
@@ -1769,4 +2542,170 @@ mod tests {
         assert!(cache.0.emojis.is_empty());
         assert!(cache.0.guild_emojis.get(&guild_id).unwrap().is_empty());
     }
+
+    #[test]
+    fn test_guild_delete_keeps_users() {
+        let cache = InMemoryCache::new();
+        cache.update(&MemberAdd(member(UserId(1), GuildId(1))));
+
+        cache.update(&GuildDelete {
+            id: GuildId(1),
+            unavailable: false,
+        });
+
+        assert!(cache.member(GuildId(1), UserId(1)).is_none());
+        assert!(cache.user(UserId(1)).is_some());
+    }
+
+    #[test]
+    fn test_previous_uncached() {
+        let cache = InMemoryCache::new();
+        let guild_id = GuildId(1);
+
+        assert!(cache
+            .update(&GuildEmojisUpdate {
+                emojis: vec![emoji(EmojiId(1), None)],
+                guild_id,
+            })
+            .is_none());
+
+        let chunk = MemberChunk {
+            chunk_count: 1,
+            chunk_index: 0,
+            guild_id,
+            members: vec![member(UserId(1), guild_id)],
+            nonce: None,
+            not_found: Vec::new(),
+            presences: Vec::new(),
+        };
+        assert!(cache.update(&chunk).is_none());
+        // Members equal to the cached ones aren't replaced.
+        assert!(cache.update(&chunk).is_none());
+        assert!(cache
+            .update(&MemberAdd(member(UserId(1), guild_id)))
+            .is_none());
+
+        assert!(cache
+            .update(&MessageDeleteBulk {
+                channel_id: ChannelId(2),
+                guild_id: Some(guild_id),
+                ids: vec![MessageId(3)],
+            })
+            .is_none());
+
+        let mut updated = member(UserId(1), guild_id);
+        updated.nick = Some("nick".to_owned());
+        assert!(matches!(
+            cache.update(&MemberAdd(updated)),
+            Some(PreviousValue::Member(previous)) if previous.nick.is_none()
+        ));
+    }
+
+    #[test]
+    fn test_guild_filter() {
+        let filter = GuildFilter::allow_list(ResourceType::MEMBER, vec![GuildId(1)]);
+        let cache = InMemoryCache::builder().guild_filter(filter).build();
+
+        cache.update(&MemberAdd(member(UserId(1), GuildId(1))));
+        cache.update(&MemberAdd(member(UserId(2), GuildId(2))));
+        cache.update(&RoleCreate {
+            guild_id: GuildId(2),
+            role: role(RoleId(3)),
+        });
+
+        assert!(cache.member(GuildId(1), UserId(1)).is_some());
+        assert!(cache.member(GuildId(2), UserId(2)).is_none());
+        assert!(cache.guild_members(GuildId(2)).is_none());
+        // The filter only applies to members.
+        assert!(cache.role(RoleId(3)).is_some());
+    }
+
+    #[test]
+    fn test_set_guild_filter_purges() {
+        let cache = InMemoryCache::new();
+
+        cache.update(&MemberAdd(member(UserId(1), GuildId(1))));
+        cache.update(&MemberAdd(member(UserId(2), GuildId(2))));
+        cache.update(&RoleCreate {
+            guild_id: GuildId(2),
+            role: role(RoleId(3)),
+        });
+
+        cache.set_guild_filter(GuildFilter::predicate(
+            ResourceType::MEMBER | ResourceType::USER,
+            |guild_id| guild_id == GuildId(1),
+        ));
+
+        assert!(cache.member(GuildId(1), UserId(1)).is_some());
+        assert!(cache.member(GuildId(2), UserId(2)).is_none());
+        assert!(cache.user(UserId(2)).is_none());
+        assert!(cache.role(RoleId(3)).is_some());
+
+        // Once the filter is cleared members of all guilds are cached again.
+        cache.clear_guild_filter();
+        cache.update(&MemberAdd(member(UserId(2), GuildId(2))));
+        assert!(cache.member(GuildId(2), UserId(2)).is_some());
+    }
+
+    #[test]
+    fn test_member_eviction() {
+        let cache = InMemoryCache::builder()
+            .member_eviction(EvictionPolicy::new(Some(2), None))
+            .build();
+        let guild_id = GuildId(1);
+
+        for id in 1..=3 {
+            cache.update(&MemberAdd(member(UserId(id), guild_id)));
+        }
+
+        let members = cache.guild_members(guild_id).unwrap();
+        assert_eq!(2, members.len());
+        assert!(!members.contains(&UserId(1)));
+        assert!(cache.member(guild_id, UserId(1)).is_none());
+        // The user was only in the one guild, so it's removed as well.
+        assert!(cache.user(UserId(1)).is_none());
+        assert_eq!(1, cache.stats().evicted_members());
+
+        // Seeing a member again makes it the most recently seen one.
+        cache.update(&MemberAdd(member(UserId(2), guild_id)));
+        cache.update(&MemberAdd(member(UserId(4), guild_id)));
+
+        let members = cache.guild_members(guild_id).unwrap();
+        assert!(members.contains(&UserId(2)));
+        assert!(members.contains(&UserId(4)));
+        assert_eq!(2, cache.stats().evicted_members());
+    }
+
+    #[test]
+    fn test_member_activity_window() {
+        let cache = InMemoryCache::builder()
+            .member_activity_window(Duration::from_secs(60))
+            .build();
+        let guild_id = GuildId(1);
+
+        // Members that haven't sent a message aren't cached.
+        cache.update(&MemberAdd(member(UserId(1), guild_id)));
+        assert!(cache.member(guild_id, UserId(1)).is_none());
+
+        cache.member_spoke((guild_id, UserId(1)));
+        cache.update(&MemberAdd(member(UserId(1), guild_id)));
+        assert!(cache.member(guild_id, UserId(1)).is_some());
+        assert!(cache.guild_members(guild_id).unwrap().contains(&UserId(1)));
+    }
+
+    #[test]
+    fn test_user_eviction() {
+        let cache = InMemoryCache::builder()
+            .user_eviction(EvictionPolicy::new(Some(1), None))
+            .build();
+
+        cache.update(&MemberAdd(member(UserId(1), GuildId(1))));
+        cache.update(&MemberAdd(member(UserId(2), GuildId(1))));
+
+        assert!(cache.user(UserId(1)).is_none());
+        assert!(cache.user(UserId(2)).is_some());
+        // Members are kept when only users are evicted.
+        assert_eq!(2, cache.guild_members(GuildId(1)).unwrap().len());
+        assert_eq!(1, cache.stats().evicted_users());
+    }
 }
@@ -19,6 +19,23 @@ pub struct CachedMember {
     pub user_id: UserId,
 }
 
+impl CachedMember {
+    /// Create a cached member from a member of a guild.
+    pub(crate) fn from_member(guild_id: GuildId, member: Member) -> Self {
+        Self {
+            deaf: Some(member.deaf),
+            guild_id,
+            joined_at: member.joined_at,
+            mute: Some(member.mute),
+            nick: member.nick,
+            pending: member.pending,
+            premium_since: member.premium_since,
+            roles: member.roles,
+            user_id: member.user.id,
+        }
+    }
+}
+
 impl PartialEq<Member> for CachedMember {
     fn eq(&self, other: &Member) -> bool {
         (
@@ -0,0 +1,388 @@
+//! Read-through layer fetching resources missing from the cache over HTTP.
+//!
+//! Requires the `http` feature. Refer to [`ReadThroughCache`] for more
+//! information.
+
+use crate::{model::CachedMember, InMemoryCache};
+use dashmap::{mapref::entry::Entry, DashMap};
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    future::Future,
+    hash::Hash,
+    sync::Arc,
+};
+use tokio::sync::watch::{self, Receiver, Sender};
+use twilight_http::Client;
+use twilight_model::{
+    channel::{Channel, GuildChannel},
+    id::{ChannelId, GuildId, UserId},
+};
+
+/// Fetching a resource over HTTP failed.
+#[derive(Debug)]
+pub struct FetchError {
+    kind: FetchErrorType,
+    source: Option<Box<dyn Error + Send + Sync>>,
+}
+
+impl FetchError {
+    /// Immutable reference to the type of error that occurred.
+    #[must_use = "retrieving the type has no effect if left unused"]
+    pub const fn kind(&self) -> &FetchErrorType {
+        &self.kind
+    }
+
+    /// Consume the error, returning the source error if there is any.
+    #[must_use = "consuming the error and retrieving the source has no effect if left unused"]
+    pub fn into_source(self) -> Option<Box<dyn Error + Send + Sync>> {
+        self.source
+    }
+
+    /// Consume the error, returning the owned error type and the source error.
+    #[must_use = "consuming the error into its parts has no effect if left unused"]
+    pub fn into_parts(self) -> (FetchErrorType, Option<Box<dyn Error + Send + Sync>>) {
+        (self.kind, self.source)
+    }
+
+    fn request(source: twilight_http::Error) -> Self {
+        Self {
+            kind: FetchErrorType::Request,
+            source: Some(Box::new(source)),
+        }
+    }
+}
+
+impl Display for FetchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self.kind {
+            FetchErrorType::Request => f.write_str("fetching the resource over http failed"),
+        }
+    }
+}
+
+impl Error for FetchError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| &**source as &(dyn Error + 'static))
+    }
+}
+
+/// Type of [`FetchError`] that occurred.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FetchErrorType {
+    /// Sending the request or deserializing its response failed.
+    ///
+    /// The source is the [`twilight_http::Error`].
+    Request,
+}
+
+/// Outcome of a fetch, shared with the callers waiting on it.
+#[derive(Clone, Debug)]
+enum Fetched<T> {
+    /// The resource exists.
+    Found(T),
+    /// The resource doesn't exist.
+    Missing,
+    /// The request failed, waiting callers should try again.
+    Failed,
+}
+
+/// Fetches that are currently in flight, keyed by the resource they fetch.
+type InFlight<K, T> = DashMap<K, Receiver<Option<Fetched<T>>>>;
+
+/// Removes the in-flight entry of a fetch once it is completed or cancelled.
+///
+/// The entry is removed before the sender is dropped, so waiting callers that
+/// retry never observe a stale entry.
+struct InFlightGuard<'a, K: Eq + Hash, T> {
+    key: K,
+    map: &'a InFlight<K, T>,
+    tx: Sender<Option<Fetched<T>>>,
+}
+
+impl<K: Eq + Hash, T> Drop for InFlightGuard<'_, K, T> {
+    fn drop(&mut self) {
+        self.map.remove(&self.key);
+    }
+}
+
+#[derive(Debug, Default)]
+struct ReadThroughCacheRef {
+    channels: InFlight<ChannelId, GuildChannel>,
+    members: InFlight<(GuildId, UserId), CachedMember>,
+}
+
+/// Cache fetching resources it doesn't have over HTTP.
+///
+/// This combines an [`InMemoryCache`] with a [`twilight_http::Client`]. When a
+/// requested resource isn't in the cache it's fetched, inserted into the
+/// cache, and returned. Concurrent requests for the same resource are
+/// deduplicated so that only one HTTP request is made.
+///
+/// Fetched resources are cached according to the cache's configuration, so
+/// a resource whose type isn't cached or whose guild is filtered out is still
+/// returned but will be fetched again next time.
+///
+/// This is cheap to clone.
+///
+/// # Examples
+///
+/// Get a member, fetching it if it's not cached:
+///
+/// ```no_run
+/// use twilight_cache_inmemory::{read_through::ReadThroughCache, InMemoryCache};
+/// use twilight_http::Client;
+/// use twilight_model::id::{GuildId, UserId};
+///
+/// # #[tokio::main] async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let cache = InMemoryCache::new();
+/// let http = Client::new("my token");
+/// let read_through = ReadThroughCache::new(cache, http);
+///
+/// if let Some(member) = read_through.member(GuildId(1), UserId(2)).await? {
+///     println!("member nick: {:?}", member.nick);
+/// }
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ReadThroughCache {
+    cache: InMemoryCache,
+    http: Client,
+    in_flight: Arc<ReadThroughCacheRef>,
+}
+
+impl ReadThroughCache {
+    /// Create a new read-through cache from a cache and an HTTP client.
+    pub fn new(cache: InMemoryCache, http: Client) -> Self {
+        Self {
+            cache,
+            http,
+            in_flight: Arc::default(),
+        }
+    }
+
+    /// Immutable reference to the underlying cache.
+    pub const fn cache(&self) -> &InMemoryCache {
+        &self.cache
+    }
+
+    /// Immutable reference to the HTTP client used to fetch resources.
+    pub const fn http(&self) -> &Client {
+        &self.http
+    }
+
+    /// Gets a guild channel by ID, fetching it if it's not cached.
+    ///
+    /// Returns `None` if the channel doesn't exist or isn't a guild channel.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`FetchErrorType::Request`] error type if fetching the
+    /// channel failed.
+    pub async fn guild_channel(
+        &self,
+        channel_id: ChannelId,
+    ) -> Result<Option<GuildChannel>, FetchError> {
+        fetch_deduplicated(
+            &self.in_flight.channels,
+            channel_id,
+            || self.cache.guild_channel(channel_id),
+            || async move {
+                let channel = self
+                    .http
+                    .channel(channel_id)
+                    .await
+                    .map_err(FetchError::request)?;
+
+                let channel = match channel {
+                    Some(Channel::Guild(channel)) => channel,
+                    _ => return Ok(None),
+                };
+
+                if let Some(guild_id) = channel.guild_id() {
+                    self.cache.cache_guild_channel(guild_id, channel.clone());
+                }
+
+                Ok(Some(channel))
+            },
+        )
+        .await
+    }
+
+    /// Gets a member of a guild, fetching it if it's not cached.
+    ///
+    /// Returns `None` if the user isn't a member of the guild.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`FetchErrorType::Request`] error type if fetching the
+    /// member failed.
+    pub async fn member(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> Result<Option<CachedMember>, FetchError> {
+        fetch_deduplicated(
+            &self.in_flight.members,
+            (guild_id, user_id),
+            || self.cache.member(guild_id, user_id),
+            || async move {
+                let member = match self
+                    .http
+                    .guild_member(guild_id, user_id)
+                    .await
+                    .map_err(FetchError::request)?
+                {
+                    Some(member) => member,
+                    None => return Ok(None),
+                };
+
+                self.cache.cache_member(guild_id, member.clone());
+
+                Ok(Some(CachedMember::from_member(guild_id, member)))
+            },
+        )
+        .await
+    }
+}
+
+/// Look up a resource, fetching it if it's missing.
+///
+/// Only one fetch per key is in flight at a time; concurrent callers wait for
+/// it and share its outcome. If it fails they retry, so that every caller
+/// either receives the resource or its own error.
+async fn fetch_deduplicated<K, T, F, Fut>(
+    in_flight: &InFlight<K, T>,
+    key: K,
+    lookup: impl Fn() -> Option<T>,
+    fetch: F,
+) -> Result<Option<T>, FetchError>
+where
+    K: Clone + Eq + Hash,
+    T: Clone,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<Option<T>, FetchError>>,
+{
+    loop {
+        if let Some(value) = lookup() {
+            return Ok(Some(value));
+        }
+
+        let mut rx = match in_flight.entry(key.clone()) {
+            Entry::Occupied(entry) => entry.get().clone(),
+            Entry::Vacant(entry) => {
+                let (tx, rx) = watch::channel(None);
+                entry.insert(rx);
+
+                let guard = InFlightGuard {
+                    key,
+                    map: in_flight,
+                    tx,
+                };
+
+                let result = fetch().await;
+
+                let fetched = match &result {
+                    Ok(Some(value)) => Fetched::Found(value.clone()),
+                    Ok(None) => Fetched::Missing,
+                    Err(_) => Fetched::Failed,
+                };
+
+                // The guard holds a receiver in the map until it's dropped, so
+                // sending can't fail.
+                let _ = guard.tx.send(Some(fetched));
+
+                return result;
+            }
+        };
+
+        let fetched = loop {
+            if let Some(fetched) = rx.borrow().clone() {
+                break fetched;
+            }
+
+            // The fetch was cancelled before completing.
+            if rx.changed().await.is_err() {
+                break rx.borrow().clone().unwrap_or(Fetched::Failed);
+            }
+        };
+
+        match fetched {
+            Fetched::Found(value) => return Ok(Some(value)),
+            Fetched::Missing => return Ok(None),
+            Fetched::Failed => continue,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fetch_deduplicated, FetchError, InFlight, ReadThroughCache};
+    use static_assertions::assert_impl_all;
+    use std::{
+        error::Error,
+        fmt::{Debug, Display},
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+    use tokio::sync::oneshot;
+
+    assert_impl_all!(FetchError: Debug, Display, Error, Send, Sync);
+    assert_impl_all!(ReadThroughCache: Clone, Debug, Send, Sync);
+
+    #[tokio::test]
+    async fn test_concurrent_fetches_are_deduplicated() {
+        let in_flight = InFlight::<u64, String>::default();
+        let fetches = AtomicUsize::new(0);
+        let (tx, rx) = oneshot::channel::<()>();
+
+        let first = fetch_deduplicated(
+            &in_flight,
+            1,
+            || None,
+            || async {
+                fetches.fetch_add(1, Ordering::SeqCst);
+                rx.await.unwrap();
+
+                Ok(Some("value".to_owned()))
+            },
+        );
+        let second = fetch_deduplicated(
+            &in_flight,
+            1,
+            || None,
+            || async {
+                fetches.fetch_add(1, Ordering::SeqCst);
+
+                Ok(None)
+            },
+        );
+        let release = async {
+            tx.send(()).unwrap();
+        };
+
+        let (first, second, _) = tokio::join!(first, second, release);
+
+        assert_eq!(Some("value".to_owned()), first.unwrap());
+        assert_eq!(Some("value".to_owned()), second.unwrap());
+        assert_eq!(1, fetches.load(Ordering::SeqCst));
+        assert!(in_flight.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cached_value_skips_fetch() {
+        let in_flight = InFlight::<u64, String>::default();
+
+        let value = fetch_deduplicated(
+            &in_flight,
+            1,
+            || Some("cached".to_owned()),
+            || async { panic!("fetched a cached value") },
+        )
+        .await;
+
+        assert_eq!(Some("cached".to_owned()), value.unwrap());
+    }
+}
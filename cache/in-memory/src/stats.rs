@@ -45,6 +45,33 @@ impl<'a> InMemoryCacheStats<'a> {
         Some(channel.len())
     }
 
+    /// Number of members that have been evicted over the cache's lifetime.
+    ///
+    /// Refer to [`Config::member_eviction`] for more information.
+    ///
+    /// [`Config::member_eviction`]: crate::Config::member_eviction
+    pub fn evicted_members(&self) -> u64 {
+        self.0 .0.member_tracker.evicted()
+    }
+
+    /// Number of presences that have been evicted over the cache's lifetime.
+    ///
+    /// Refer to [`Config::presence_eviction`] for more information.
+    ///
+    /// [`Config::presence_eviction`]: crate::Config::presence_eviction
+    pub fn evicted_presences(&self) -> u64 {
+        self.0 .0.presence_tracker.evicted()
+    }
+
+    /// Number of users that have been evicted over the cache's lifetime.
+    ///
+    /// Refer to [`Config::user_eviction`] for more information.
+    ///
+    /// [`Config::user_eviction`]: crate::Config::user_eviction
+    pub fn evicted_users(&self) -> u64 {
+        self.0 .0.user_tracker.evicted()
+    }
+
     /// Number of emojis in the cache.
     pub fn emojis(&self) -> usize {
         self.0 .0.emojis.len()
@@ -1,39 +1,92 @@
-use crate::model::{CachedMessage, CachedPresence};
+use crate::model::{CachedEmoji, CachedGuild, CachedMember, CachedMessage, CachedPresence};
 
 use super::{config::ResourceType, InMemoryCache};
 use dashmap::DashMap;
 use std::{borrow::Cow, collections::HashSet, hash::Hash, ops::Deref};
 use twilight_model::{
     application::interaction::Interaction,
-    channel::{message::MessageReaction, Channel, GuildChannel, ReactionType},
+    channel::{
+        message::MessageReaction, Channel, Group, GuildChannel, PrivateChannel, ReactionType,
+        StageInstance,
+    },
     gateway::{event::Event, payload::*},
+    guild::{GuildIntegration, Role},
     id::GuildId,
+    user::CurrentUser,
+    voice::VoiceState,
 };
 
+/// Cached state that was replaced or removed while processing an event.
+///
+/// This is the state of a resource before the event was applied, such as a
+/// message before it was edited, a role before it was deleted, or the messages
+/// removed by a bulk delete. It's returned by [`InMemoryCache::update`].
+///
+/// Only state that was actually replaced or removed is returned; for example,
+/// updating a message that isn't in the cache, or with a value equal to the
+/// cached one, returns `None`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum PreviousValue {
+    /// Current user replaced by a [`Ready`] or [`UserUpdate`] event.
+    CurrentUser(CurrentUser),
+    /// Emojis of a guild replaced by a [`GuildEmojisUpdate`] event.
+    Emojis(Vec<CachedEmoji>),
+    /// Group replaced or removed by a channel event.
+    Group(Group),
+    /// Guild replaced or removed by a guild event.
+    Guild(CachedGuild),
+    /// Guild channel replaced or removed by a channel event.
+    GuildChannel(GuildChannel),
+    /// Integration replaced or removed by an integration event.
+    Integration(GuildIntegration),
+    /// Member replaced or removed by a member, interaction, or message event.
+    Member(CachedMember),
+    /// Members replaced by a [`MemberChunk`] event.
+    Members(Vec<CachedMember>),
+    /// Message replaced or removed by a message or reaction event.
+    Message(CachedMessage),
+    /// Messages removed by a [`MessageDeleteBulk`] event.
+    Messages(Vec<CachedMessage>),
+    /// Presence replaced by a [`PresenceUpdate`] event.
+    Presence(CachedPresence),
+    /// Private channel replaced or removed by a channel event.
+    PrivateChannel(PrivateChannel),
+    /// Role replaced or removed by a role event.
+    Role(Role),
+    /// Stage instance replaced or removed by a stage instance event.
+    StageInstance(StageInstance),
+    /// Voice state replaced or removed by a [`VoiceStateUpdate`] event.
+    VoiceState(VoiceState),
+}
+
 pub trait UpdateCache {
     // Allow this for presentation purposes in documentation.
     #[allow(unused_variables)]
-    fn update(&self, cache: &InMemoryCache) {}
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
+        None
+    }
 }
 
 impl UpdateCache for Event {
     #[allow(clippy::cognitive_complexity)]
-    fn update(&self, c: &InMemoryCache) {
+    fn update(&self, c: &InMemoryCache) -> Option<PreviousValue> {
         use Event::*;
 
         match self {
-            BanAdd(_) => {}
-            BanRemove(_) => {}
+            BanAdd(_) => None,
+            BanRemove(_) => None,
             ChannelCreate(v) => c.update(v),
             ChannelDelete(v) => c.update(v),
             ChannelPinsUpdate(v) => c.update(v),
             ChannelUpdate(v) => c.update(v),
-            GatewayHeartbeat(_) => {}
-            GatewayHeartbeatAck => {}
-            GatewayHello(_) => {}
-            GatewayInvalidateSession(_v) => {}
-            GatewayReconnect => {}
-            GiftCodeUpdate => {}
+            ClusterReady(_) => None,
+            GatewayHeartbeat(_) => None,
+            GatewayHeartbeatAck => None,
+            GatewayHello(_) => None,
+            GatewayInvalidateSession(_v) => None,
+            GatewayReconnect => None,
+            GiftCodeUpdate => None,
             GuildCreate(v) => c.update(v.deref()),
             GuildDelete(v) => c.update(v.deref()),
             GuildEmojisUpdate(v) => c.update(v),
@@ -43,8 +96,8 @@ impl UpdateCache for Event {
             IntegrationDelete(v) => c.update(v.deref()),
             IntegrationUpdate(v) => c.update(v.deref()),
             InteractionCreate(v) => c.update(v.deref()),
-            InviteCreate(_) => {}
-            InviteDelete(_) => {}
+            InviteCreate(_) => None,
+            InviteDelete(_) => None,
             MemberAdd(v) => c.update(v.deref()),
             MemberRemove(v) => c.update(v),
             MemberUpdate(v) => c.update(v.deref()),
@@ -54,23 +107,25 @@ impl UpdateCache for Event {
             MessageDeleteBulk(v) => c.update(v),
             MessageUpdate(v) => c.update(v.deref()),
             PresenceUpdate(v) => c.update(v.deref()),
-            PresencesReplace => {}
+            PresencesReplace => None,
             ReactionAdd(v) => c.update(v.deref()),
             ReactionRemove(v) => c.update(v.deref()),
             ReactionRemoveAll(v) => c.update(v),
             ReactionRemoveEmoji(v) => c.update(v),
             Ready(v) => c.update(v.deref()),
-            Resumed => {}
+            Resumed => None,
             RoleCreate(v) => c.update(v),
             RoleDelete(v) => c.update(v),
             RoleUpdate(v) => c.update(v),
-            ShardConnected(_) => {}
-            ShardConnecting(_) => {}
-            ShardDisconnected(_) => {}
-            ShardIdentifying(_) => {}
-            ShardReconnecting(_) => {}
-            ShardPayload(_) => {}
-            ShardResuming(_) => {}
+            ShardConnected(_) => None,
+            ShardConnecting(_) => None,
+            ShardDisconnected(_) => None,
+            ShardFullyReady(_) => None,
+            ShardIdentifying(_) => None,
+            ShardReconnecting(_) => None,
+            ShardPayload(_) => None,
+            ShardResuming(_) => None,
+            ShardUnhealthy(_) => None,
             StageInstanceCreate(v) => c.update(v),
             StageInstanceDelete(v) => c.update(v),
             StageInstanceUpdate(v) => c.update(v),
@@ -89,109 +144,114 @@ impl UpdateCache for BanAdd {}
 impl UpdateCache for BanRemove {}
 
 impl UpdateCache for ChannelCreate {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::CHANNEL) {
-            return;
+            return None;
         }
 
         match &self.0 {
             Channel::Group(c) => {
-                super::upsert_item(&cache.0.groups, c.id, c.clone());
-            }
-            Channel::Guild(c) => {
-                if let Some(gid) = c.guild_id() {
-                    cache.cache_guild_channel(gid, c.clone());
-                }
-            }
-            Channel::Private(c) => {
-                cache.cache_private_channel(c.clone());
+                super::upsert_item(&cache.0.groups, c.id, c.clone()).map(PreviousValue::Group)
             }
+            Channel::Guild(c) => c.guild_id().and_then(|gid| {
+                cache
+                    .cache_guild_channel(gid, c.clone())
+                    .map(PreviousValue::GuildChannel)
+            }),
+            Channel::Private(c) => cache
+                .cache_private_channel(c.clone())
+                .map(PreviousValue::PrivateChannel),
         }
     }
 }
 
 impl UpdateCache for ChannelDelete {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::CHANNEL) {
-            return;
+            return None;
         }
 
         match self.0 {
-            Channel::Group(ref c) => {
-                cache.delete_group(c.id);
-            }
-            Channel::Guild(ref c) => {
-                cache.delete_guild_channel(c.id());
-            }
-            Channel::Private(ref c) => {
-                cache.0.channels_private.remove(&c.id);
-            }
+            Channel::Group(ref c) => cache.delete_group(c.id).map(PreviousValue::Group),
+            Channel::Guild(ref c) => cache
+                .delete_guild_channel(c.id())
+                .map(PreviousValue::GuildChannel),
+            Channel::Private(ref c) => cache
+                .0
+                .channels_private
+                .remove(&c.id)
+                .map(|(_, channel)| PreviousValue::PrivateChannel(channel)),
         }
     }
 }
 
 impl UpdateCache for ChannelPinsUpdate {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::CHANNEL) {
-            return;
+            return None;
         }
 
         if let Some(mut r) = cache.0.channels_guild.get_mut(&self.channel_id) {
             let value = r.value_mut();
+            let previous = value.data.clone();
 
             if let GuildChannel::Text(ref mut text) = value.data {
                 text.last_pin_timestamp = self.last_pin_timestamp.clone();
             }
 
-            return;
+            return Some(PreviousValue::GuildChannel(previous));
         }
 
         if let Some(mut channel) = cache.0.channels_private.get_mut(&self.channel_id) {
+            let previous = channel.clone();
             channel.last_pin_timestamp = self.last_pin_timestamp.clone();
 
-            return;
+            return Some(PreviousValue::PrivateChannel(previous));
         }
 
         if let Some(mut group) = cache.0.groups.get_mut(&self.channel_id) {
+            let previous = group.clone();
             group.last_pin_timestamp = self.last_pin_timestamp.clone();
+
+            return Some(PreviousValue::Group(previous));
         }
+
+        None
     }
 }
 
 impl UpdateCache for ChannelUpdate {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::CHANNEL) {
-            return;
+            return None;
         }
 
         match self.0.clone() {
-            Channel::Group(c) => {
-                cache.cache_group(c);
-            }
-            Channel::Guild(c) => {
-                if let Some(gid) = c.guild_id() {
-                    cache.cache_guild_channel(gid, c);
-                }
-            }
-            Channel::Private(c) => {
-                cache.cache_private_channel(c);
-            }
+            Channel::Group(c) => cache.cache_group(c).map(PreviousValue::Group),
+            Channel::Guild(c) => c.guild_id().and_then(|gid| {
+                cache
+                    .cache_guild_channel(gid, c)
+                    .map(PreviousValue::GuildChannel)
+            }),
+            Channel::Private(c) => cache
+                .cache_private_channel(c)
+                .map(PreviousValue::PrivateChannel),
         }
     }
 }
 
 impl UpdateCache for GuildCreate {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::GUILD) {
-            return;
+            return None;
         }
 
-        cache.cache_guild(self.0.clone());
+        cache.cache_guild(self.0.clone()).map(PreviousValue::Guild)
     }
 }
 
 impl UpdateCache for GuildDelete {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         fn remove_ids<T: Eq + Hash, U>(
             guild_map: &DashMap<GuildId, HashSet<T>>,
             container: &DashMap<T, U>,
@@ -205,12 +265,12 @@ impl UpdateCache for GuildDelete {
         }
 
         if !cache.wants(ResourceType::GUILD) {
-            return;
+            return None;
         }
 
         let id = self.id;
 
-        cache.0.guilds.remove(&id);
+        let previous = cache.0.guilds.remove(&id).map(|(_, guild)| guild);
 
         if cache.wants(ResourceType::CHANNEL) {
             remove_ids(&cache.0.guild_channels, &cache.0.channels_guild, id);
@@ -233,6 +293,7 @@ impl UpdateCache for GuildDelete {
             if let Some((_, ids)) = cache.0.guild_members.remove(&id) {
                 for user_id in ids {
                     cache.0.members.remove(&(id, user_id));
+                    cache.0.member_tracker.remove(&(id, user_id));
                 }
             }
         }
@@ -241,102 +302,118 @@ impl UpdateCache for GuildDelete {
             if let Some((_, ids)) = cache.0.guild_presences.remove(&id) {
                 for user_id in ids {
                     cache.0.presences.remove(&(id, user_id));
+                    cache.0.presence_tracker.remove(&(id, user_id));
                 }
             }
         }
+
+        previous.map(PreviousValue::Guild)
     }
 }
 
 impl UpdateCache for GuildEmojisUpdate {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::EMOJI) {
-            return;
+            return None;
         }
 
-        cache.cache_emojis(self.guild_id, self.emojis.clone());
+        let previous = cache.cache_emojis(self.guild_id, self.emojis.clone());
+
+        if previous.is_empty() {
+            return None;
+        }
+
+        Some(PreviousValue::Emojis(previous))
     }
 }
 
 impl UpdateCache for GuildIntegrationsUpdate {}
 
 impl UpdateCache for GuildUpdate {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::GUILD) {
-            return;
-        }
-
-        if let Some(mut guild) = cache.0.guilds.get_mut(&self.0.id) {
-            guild.afk_channel_id = self.afk_channel_id;
-            guild.afk_timeout = self.afk_timeout;
-            guild.banner = self.banner.clone();
-            guild.default_message_notifications = self.default_message_notifications;
-            guild.description = self.description.clone();
-            guild.features = self.features.clone();
-            guild.icon = self.icon.clone();
-            guild.max_members = self.max_members;
-            guild.max_presences = Some(self.max_presences.unwrap_or(25000));
-            guild.mfa_level = self.mfa_level;
-            guild.name = self.name.clone();
-            guild.nsfw_level = self.nsfw_level;
-            guild.owner = self.owner;
-            guild.owner_id = self.owner_id;
-            guild.permissions = self.permissions;
-            guild.preferred_locale = self.preferred_locale.clone();
-            guild.premium_tier = self.premium_tier;
-            guild
-                .premium_subscription_count
-                .replace(self.premium_subscription_count.unwrap_or_default());
-            guild.splash = self.splash.clone();
-            guild.system_channel_id = self.system_channel_id;
-            guild.verification_level = self.verification_level;
-            guild.vanity_url_code = self.vanity_url_code.clone();
-            guild.widget_channel_id = self.widget_channel_id;
-            guild.widget_enabled = self.widget_enabled;
-        };
+            return None;
+        }
+
+        let mut guild = cache.0.guilds.get_mut(&self.0.id)?;
+        let previous = guild.clone();
+
+        guild.afk_channel_id = self.afk_channel_id;
+        guild.afk_timeout = self.afk_timeout;
+        guild.banner = self.banner.clone();
+        guild.default_message_notifications = self.default_message_notifications;
+        guild.description = self.description.clone();
+        guild.features = self.features.clone();
+        guild.icon = self.icon.clone();
+        guild.max_members = self.max_members;
+        guild.max_presences = Some(self.max_presences.unwrap_or(25000));
+        guild.mfa_level = self.mfa_level;
+        guild.name = self.name.clone();
+        guild.nsfw_level = self.nsfw_level;
+        guild.owner = self.owner;
+        guild.owner_id = self.owner_id;
+        guild.permissions = self.permissions;
+        guild.preferred_locale = self.preferred_locale.clone();
+        guild.premium_tier = self.premium_tier;
+        guild
+            .premium_subscription_count
+            .replace(self.premium_subscription_count.unwrap_or_default());
+        guild.splash = self.splash.clone();
+        guild.system_channel_id = self.system_channel_id;
+        guild.verification_level = self.verification_level;
+        guild.vanity_url_code = self.vanity_url_code.clone();
+        guild.widget_channel_id = self.widget_channel_id;
+        guild.widget_enabled = self.widget_enabled;
+
+        Some(PreviousValue::Guild(previous))
     }
 }
 
 impl UpdateCache for IntegrationCreate {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::INTEGRATION) {
-            return;
+            return None;
         }
 
-        if let Some(guild_id) = self.guild_id {
-            super::upsert_guild_item(
-                &cache.0.integrations,
-                guild_id,
-                (guild_id, self.id),
-                self.0.clone(),
-            );
-        }
+        let guild_id = self.guild_id?;
+
+        cache
+            .cache_integration(guild_id, self.0.clone())
+            .map(PreviousValue::Integration)
     }
 }
 
 impl UpdateCache for IntegrationDelete {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::INTEGRATION) {
-            return;
+            return None;
         }
 
-        cache.delete_integration(self.guild_id, self.id);
+        cache
+            .delete_integration(self.guild_id, self.id)
+            .map(PreviousValue::Integration)
     }
 }
 
 impl UpdateCache for IntegrationUpdate {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::INTEGRATION) {
-            return;
+            return None;
         }
 
-        if let Some(guild_id) = self.guild_id {
-            cache.cache_integration(guild_id, self.0.clone());
-        }
+        let guild_id = self.guild_id?;
+
+        cache
+            .cache_integration(guild_id, self.0.clone())
+            .map(PreviousValue::Integration)
     }
 }
 
 impl UpdateCache for InteractionCreate {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
+        // The member invoking the command is the one returned as replaced.
+        let mut previous = None;
+
         #[allow(clippy::single_match)]
         match &self.0 {
             Interaction::ApplicationCommand(command) => {
@@ -345,11 +422,13 @@ impl UpdateCache for InteractionCreate {
                         if let Some(user) = &member.user {
                             cache.cache_user(Cow::Borrowed(user), command.guild_id);
 
-                            cache.cache_borrowed_partial_member(
-                                command.guild_id.unwrap(),
-                                &member,
-                                user.id,
-                            );
+                            previous = cache
+                                .cache_borrowed_partial_member(
+                                    command.guild_id.unwrap(),
+                                    &member,
+                                    user.id,
+                                )
+                                .map(PreviousValue::Member);
                         }
                     }
                 }
@@ -384,83 +463,67 @@ impl UpdateCache for InteractionCreate {
             }
             _ => {}
         };
+
+        previous
     }
 }
 
 impl UpdateCache for MemberAdd {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::MEMBER) {
-            return;
+            return None;
         }
 
-        cache.cache_member(self.guild_id, self.0.clone());
-
         cache
-            .0
-            .guild_members
-            .entry(self.guild_id)
-            .or_default()
-            .insert(self.0.user.id);
+            .cache_member(self.guild_id, self.0.clone())
+            .map(PreviousValue::Member)
     }
 }
 
 impl UpdateCache for MemberChunk {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::MEMBER) {
-            return;
+            return None;
         }
 
         if self.members.is_empty() {
-            return;
+            return None;
         }
 
-        cache.cache_members(self.guild_id, self.members.clone());
-        let mut guild = cache.0.guild_members.entry(self.guild_id).or_default();
-        guild.extend(self.members.iter().map(|member| member.user.id));
+        let previous = cache.cache_members(self.guild_id, self.members.clone());
+
+        if previous.is_empty() {
+            return None;
+        }
+
+        Some(PreviousValue::Members(previous))
     }
 }
 
 impl UpdateCache for MemberRemove {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::MEMBER) {
-            return;
-        }
-
-        cache.0.members.remove(&(self.guild_id, self.user.id));
-
-        if let Some(mut members) = cache.0.guild_members.get_mut(&self.guild_id) {
-            members.remove(&self.user.id);
-        }
-
-        // Avoid a deadlock by mutating the user, dropping the lock to the map,
-        // and then maybe conditionally removing the user later.
-        let mut maybe_remove_user = false;
-
-        if let Some(mut user_tuple) = cache.0.users.get_mut(&self.user.id) {
-            user_tuple.1.remove(&self.guild_id);
-
-            maybe_remove_user = true;
+            return None;
         }
 
-        if maybe_remove_user {
-            cache
-                .0
-                .users
-                .remove_if(&self.user.id, |_, guild_set| guild_set.1.is_empty());
-        }
+        cache
+            .delete_member(self.guild_id, self.user.id)
+            .map(PreviousValue::Member)
     }
 }
 
 impl UpdateCache for MemberUpdate {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::MEMBER) {
-            return;
+            return None;
         }
 
-        let mut member = match cache.0.members.get_mut(&(self.guild_id, self.user.id)) {
-            Some(member) => member,
-            None => return,
-        };
+        let id = (self.guild_id, self.user.id);
+
+        let mut member = cache.0.members.get_mut(&id)?;
+        let previous = member.clone();
+
+        cache.member_seen(id);
 
         member.deaf = self.deaf.or(member.deaf);
         member.mute = self.mute.or(member.mute);
@@ -468,25 +531,38 @@ impl UpdateCache for MemberUpdate {
         member.roles = self.roles.clone();
         member.joined_at.replace(self.joined_at.clone());
         member.pending = self.pending;
+
+        Some(PreviousValue::Member(previous))
     }
 }
 
 impl UpdateCache for MessageCreate {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if cache.wants(ResourceType::USER) {
             cache.cache_user(Cow::Borrowed(&self.author), self.guild_id);
         }
 
-        if let (Some(member), Some(guild_id), true) = (
+        let previous = if let (Some(member), Some(guild_id), true) = (
             &self.member,
             self.guild_id,
             cache.wants(ResourceType::MEMBER),
         ) {
-            cache.cache_borrowed_partial_member(guild_id, member, self.author.id)
+            cache.member_spoke((guild_id, self.author.id));
+            cache
+                .cache_borrowed_partial_member(guild_id, member, self.author.id)
+                .map(PreviousValue::Member)
+        } else {
+            None
+        };
+
+        if !cache.wants(ResourceType::MESSAGE) || !cache.allows_message(self.0.channel_id) {
+            return previous;
         }
 
-        if !cache.wants(ResourceType::MESSAGE) {
-            return;
+        if let Some(guild_id) = self.guild_id {
+            if !cache.allows_guild(ResourceType::MESSAGE, guild_id) {
+                return previous;
+            }
         }
 
         let mut channel = cache.0.messages.entry(self.0.channel_id).or_default();
@@ -496,95 +572,108 @@ impl UpdateCache for MessageCreate {
         }
 
         channel.push_front(CachedMessage::from(self.0.clone()));
+
+        previous
     }
 }
 
 impl UpdateCache for MessageDelete {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::MESSAGE) {
-            return;
+            return None;
         }
 
         let mut channel = cache.0.messages.entry(self.channel_id).or_default();
 
-        if let Some(idx) = channel.iter().position(|msg| msg.id == self.id) {
-            channel.remove(idx);
-        }
+        let idx = channel.iter().position(|msg| msg.id == self.id)?;
+
+        channel.remove(idx).map(PreviousValue::Message)
     }
 }
 
 impl UpdateCache for MessageDeleteBulk {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::MESSAGE) {
-            return;
+            return None;
         }
 
         let mut channel = cache.0.messages.entry(self.channel_id).or_default();
 
+        let mut removed = Vec::new();
+
         for id in &self.ids {
             if let Some(idx) = channel.iter().position(|msg| &msg.id == id) {
-                channel.remove(idx);
+                removed.extend(channel.remove(idx));
             }
         }
+
+        if removed.is_empty() {
+            return None;
+        }
+
+        Some(PreviousValue::Messages(removed))
     }
 }
 
 impl UpdateCache for MessageUpdate {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::MESSAGE) {
-            return;
+            return None;
         }
 
         let mut channel = cache.0.messages.entry(self.channel_id).or_default();
 
-        if let Some(mut message) = channel.iter_mut().find(|msg| msg.id == self.id) {
-            if let Some(attachments) = &self.attachments {
-                message.attachments = attachments.clone();
-            }
+        let message = channel.iter_mut().find(|msg| msg.id == self.id)?;
+        let previous = message.clone();
 
-            if let Some(content) = &self.content {
-                message.content = content.clone();
-            }
+        if let Some(attachments) = &self.attachments {
+            message.attachments = attachments.clone();
+        }
 
-            if let Some(edited_timestamp) = &self.edited_timestamp {
-                message.edited_timestamp.replace(edited_timestamp.clone());
-            }
+        if let Some(content) = &self.content {
+            message.content = content.clone();
+        }
 
-            if let Some(embeds) = &self.embeds {
-                message.embeds = embeds.clone();
-            }
+        if let Some(edited_timestamp) = &self.edited_timestamp {
+            message.edited_timestamp.replace(edited_timestamp.clone());
+        }
 
-            if let Some(mention_everyone) = self.mention_everyone {
-                message.mention_everyone = mention_everyone;
-            }
+        if let Some(embeds) = &self.embeds {
+            message.embeds = embeds.clone();
+        }
 
-            if let Some(mention_roles) = &self.mention_roles {
-                message.mention_roles = mention_roles.clone();
-            }
+        if let Some(mention_everyone) = self.mention_everyone {
+            message.mention_everyone = mention_everyone;
+        }
 
-            if let Some(mentions) = &self.mentions {
-                message.mentions = mentions.iter().map(|x| x.id).collect::<Vec<_>>();
-            }
+        if let Some(mention_roles) = &self.mention_roles {
+            message.mention_roles = mention_roles.clone();
+        }
 
-            if let Some(pinned) = self.pinned {
-                message.pinned = pinned;
-            }
+        if let Some(mentions) = &self.mentions {
+            message.mentions = mentions.iter().map(|x| x.id).collect::<Vec<_>>();
+        }
 
-            if let Some(timestamp) = &self.timestamp {
-                message.timestamp = timestamp.clone();
-            }
+        if let Some(pinned) = self.pinned {
+            message.pinned = pinned;
+        }
 
-            if let Some(tts) = self.tts {
-                message.tts = tts;
-            }
+        if let Some(timestamp) = &self.timestamp {
+            message.timestamp = timestamp.clone();
         }
+
+        if let Some(tts) = self.tts {
+            message.tts = tts;
+        }
+
+        Some(PreviousValue::Message(previous))
     }
 }
 
 impl UpdateCache for PresenceUpdate {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::PRESENCE) {
-            return;
+            return None;
         }
 
         let presence = CachedPresence {
@@ -595,22 +684,22 @@ impl UpdateCache for PresenceUpdate {
             user_id: crate::presence_user_id(&self.user),
         };
 
-        cache.cache_presence(self.guild_id, presence);
+        cache
+            .cache_presence(self.guild_id, presence)
+            .map(PreviousValue::Presence)
     }
 }
 
 impl UpdateCache for ReactionAdd {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::REACTION) {
-            return;
+            return None;
         }
 
         let mut channel = cache.0.messages.entry(self.0.channel_id).or_default();
 
-        let message = match channel.iter_mut().find(|msg| msg.id == self.0.message_id) {
-            Some(message) => message,
-            None => return,
-        };
+        let message = channel.iter_mut().find(|msg| msg.id == self.0.message_id)?;
+        let previous = PreviousValue::Message(message.clone());
 
         if let Some(reaction) = message
             .reactions
@@ -638,21 +727,21 @@ impl UpdateCache for ReactionAdd {
                 me,
             });
         }
+
+        Some(previous)
     }
 }
 
 impl UpdateCache for ReactionRemove {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::REACTION) {
-            return;
+            return None;
         }
 
         let mut channel = cache.0.messages.entry(self.0.channel_id).or_default();
 
-        let message = match channel.iter_mut().find(|msg| msg.id == self.0.message_id) {
-            Some(message) => message,
-            None => return,
-        };
+        let message = channel.iter_mut().find(|msg| msg.id == self.0.message_id)?;
+        let previous = PreviousValue::Message(message.clone());
 
         if let Some(reaction) = message
             .reactions
@@ -673,38 +762,38 @@ impl UpdateCache for ReactionRemove {
                 message.reactions.retain(|e| !(e.emoji == self.0.emoji));
             }
         }
+
+        Some(previous)
     }
 }
 
 impl UpdateCache for ReactionRemoveAll {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::REACTION) {
-            return;
+            return None;
         }
 
         let mut channel = cache.0.messages.entry(self.channel_id).or_default();
 
-        let message = match channel.iter_mut().find(|msg| msg.id == self.message_id) {
-            Some(message) => message,
-            None => return,
-        };
+        let message = channel.iter_mut().find(|msg| msg.id == self.message_id)?;
+        let previous = PreviousValue::Message(message.clone());
 
         message.reactions.clear();
+
+        Some(previous)
     }
 }
 
 impl UpdateCache for ReactionRemoveEmoji {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::REACTION) {
-            return;
+            return None;
         }
 
         let mut channel = cache.0.messages.entry(self.channel_id).or_default();
 
-        let message = match channel.iter_mut().find(|msg| msg.id == self.message_id) {
-            Some(message) => message,
-            None => return,
-        };
+        let message = channel.iter_mut().find(|msg| msg.id == self.message_id)?;
+        let previous = PreviousValue::Message(message.clone());
 
         let maybe_index = message.reactions.iter().position(|r| {
             matches!(&r.emoji,
@@ -717,126 +806,142 @@ impl UpdateCache for ReactionRemoveEmoji {
         if let Some(index) = maybe_index {
             message.reactions.remove(index);
         }
+
+        Some(previous)
     }
 }
 
 impl UpdateCache for Ready {
-    fn update(&self, cache: &InMemoryCache) {
-        if cache.wants(ResourceType::USER_CURRENT) {
-            cache.cache_current_user(self.user.clone());
-        }
-
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if cache.wants(ResourceType::GUILD) {
             for guild in &self.guilds {
                 cache.unavailable_guild(guild.id);
             }
         }
+
+        if !cache.wants(ResourceType::USER_CURRENT) {
+            return None;
+        }
+
+        cache
+            .cache_current_user(self.user.clone())
+            .map(PreviousValue::CurrentUser)
     }
 }
 
 impl UpdateCache for RoleCreate {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::ROLE) {
-            return;
+            return None;
         }
 
-        super::upsert_guild_item(
-            &cache.0.roles,
-            self.guild_id,
-            self.role.id,
-            self.role.clone(),
-        );
+        cache
+            .cache_role(self.guild_id, self.role.clone())
+            .map(PreviousValue::Role)
     }
 }
 
 impl UpdateCache for RoleDelete {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::ROLE) {
-            return;
+            return None;
         }
 
-        cache.delete_role(self.role_id);
+        cache.delete_role(self.role_id).map(PreviousValue::Role)
     }
 }
 
 impl UpdateCache for RoleUpdate {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::ROLE) {
-            return;
+            return None;
         }
 
-        cache.cache_role(self.guild_id, self.role.clone());
+        cache
+            .cache_role(self.guild_id, self.role.clone())
+            .map(PreviousValue::Role)
     }
 }
 
 impl UpdateCache for StageInstanceCreate {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::STAGE_INSTANCE) {
-            return;
+            return None;
         }
 
-        cache.cache_stage_instance(self.guild_id, self.0.clone());
+        cache
+            .cache_stage_instance(self.guild_id, self.0.clone())
+            .map(PreviousValue::StageInstance)
     }
 }
 
 impl UpdateCache for StageInstanceDelete {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::STAGE_INSTANCE) {
-            return;
+            return None;
         }
 
-        cache.delete_stage_instance(self.id);
+        cache
+            .delete_stage_instance(self.id)
+            .map(PreviousValue::StageInstance)
     }
 }
 
 impl UpdateCache for StageInstanceUpdate {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::STAGE_INSTANCE) {
-            return;
+            return None;
         }
 
-        cache.cache_stage_instance(self.guild_id, self.0.clone());
+        cache
+            .cache_stage_instance(self.guild_id, self.0.clone())
+            .map(PreviousValue::StageInstance)
     }
 }
 
 impl UpdateCache for TypingStart {}
 
 impl UpdateCache for UnavailableGuild {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::GUILD) {
-            return;
+            return None;
         }
 
-        cache.0.guilds.remove(&self.id);
-        cache.0.unavailable_guilds.insert(self.id);
+        cache.unavailable_guild(self.id).map(PreviousValue::Guild)
     }
 }
 
 impl UpdateCache for UserUpdate {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::USER_CURRENT) {
-            return;
+            return None;
         }
 
-        cache.cache_current_user(self.0.clone());
+        cache
+            .cache_current_user(self.0.clone())
+            .map(PreviousValue::CurrentUser)
     }
 }
 
 impl UpdateCache for VoiceServerUpdate {
-    fn update(&self, _: &InMemoryCache) {}
+    fn update(&self, _: &InMemoryCache) -> Option<PreviousValue> {
+        None
+    }
 }
 
 impl UpdateCache for VoiceStateUpdate {
-    fn update(&self, cache: &InMemoryCache) {
+    fn update(&self, cache: &InMemoryCache) -> Option<PreviousValue> {
         if !cache.wants(ResourceType::VOICE_STATE) {
-            return;
+            return None;
         }
 
-        cache.cache_voice_state(self.0.clone());
+        let previous = cache.cache_voice_state(self.0.clone());
 
         if let (Some(guild_id), Some(member)) = (self.0.guild_id, &self.0.member) {
             cache.cache_member(guild_id, member.clone());
         }
+
+        previous.map(PreviousValue::VoiceState)
     }
 }
 
@@ -1112,8 +1217,9 @@ mod tests {
             widget_enabled: guild.widget_enabled,
         };
 
-        cache.update(&GuildUpdate(mutation.clone()));
+        let previous = cache.update(&GuildUpdate(mutation.clone()));
 
+        assert!(matches!(previous, Some(PreviousValue::Guild(g)) if g.name == guild.name));
         assert_eq!(cache.guild(guild.id).unwrap().name, mutation.name);
         assert_eq!(cache.guild(guild.id).unwrap().owner_id, mutation.owner_id);
         assert_eq!(cache.guild(guild.id).unwrap().id, mutation.id);
@@ -1133,7 +1239,8 @@ mod tests {
             .unwrap()
             .contains(&channel_id));
 
-        cache.update(&ChannelDelete(Channel::Guild(channel)));
+        let previous = cache.update(&ChannelDelete(Channel::Guild(channel.clone())));
+        assert_eq!(Some(PreviousValue::GuildChannel(channel)), previous);
         assert!(cache.0.channels_guild.is_empty());
         assert!(cache.0.guild_channels.get(&guild_id).unwrap().is_empty());
     }
@@ -1311,6 +1418,88 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_message_update_previous() {
+        let cache = cache_with_message_and_reactions();
+
+        let previous = cache.update(&MessageUpdate {
+            attachments: None,
+            author: None,
+            channel_id: ChannelId(2),
+            content: Some("pong".to_owned()),
+            edited_timestamp: None,
+            embeds: None,
+            guild_id: Some(GuildId(1)),
+            id: MessageId(4),
+            kind: None,
+            mention_everyone: None,
+            mention_roles: None,
+            mentions: None,
+            pinned: None,
+            timestamp: None,
+            tts: None,
+        });
+
+        match previous {
+            Some(PreviousValue::Message(message)) => assert_eq!("ping", message.content),
+            other => panic!("{:?}", other),
+        }
+
+        let msg = cache.message(ChannelId(2), MessageId(4)).unwrap();
+        assert_eq!("pong", msg.content);
+    }
+
+    #[test]
+    fn test_message_delete_bulk_previous() {
+        let cache = cache_with_message_and_reactions();
+
+        let previous = cache.update(&MessageDeleteBulk {
+            channel_id: ChannelId(2),
+            guild_id: Some(GuildId(1)),
+            ids: vec![MessageId(4), MessageId(5)],
+        });
+
+        match previous {
+            Some(PreviousValue::Messages(messages)) => {
+                assert_eq!(1, messages.len());
+                assert_eq!(MessageId(4), messages[0].id);
+            }
+            other => panic!("{:?}", other),
+        }
+
+        assert!(cache.message(ChannelId(2), MessageId(4)).is_none());
+    }
+
+    #[test]
+    fn test_message_filter() {
+        let cache = cache_with_message_and_reactions();
+        assert!(cache.message(ChannelId(2), MessageId(4)).is_some());
+
+        // Setting a filter purges messages of channels it doesn't allow.
+        cache.set_message_filter(|channel_id| channel_id != ChannelId(2));
+        assert!(cache.message(ChannelId(2), MessageId(4)).is_none());
+
+        let previous = cache.update(&MessageUpdate {
+            attachments: None,
+            author: None,
+            channel_id: ChannelId(2),
+            content: Some("pong".to_owned()),
+            edited_timestamp: None,
+            embeds: None,
+            guild_id: Some(GuildId(1)),
+            id: MessageId(4),
+            kind: None,
+            mention_everyone: None,
+            mention_roles: None,
+            mentions: None,
+            pinned: None,
+            timestamp: None,
+            tts: None,
+        });
+        assert!(previous.is_none());
+        assert!(cache.message(ChannelId(2), MessageId(4)).is_none());
+    }
+
     #[test]
     fn test_reaction_add() {
         let cache = cache_with_message_and_reactions();
@@ -0,0 +1,90 @@
+use serde::Deserialize;
+use serde_json::Value;
+use twilight_model::gateway::{
+    payload::{identify::IdentifyInfo, resume::ResumeInfo},
+    OpCode,
+};
+
+/// Payload sent by a shard to the gateway.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Command {
+    /// Heartbeat with the last sequence the shard received.
+    ///
+    /// Only yielded if heartbeats aren't acknowledged automatically.
+    Heartbeat(Option<u64>),
+    /// Identify to start a new session.
+    Identify(IdentifyInfo),
+    /// Resume a previous session.
+    Resume(ResumeInfo),
+    /// Any other payload, such as requesting guild members or updating the
+    /// presence.
+    Other {
+        /// Opcode of the payload.
+        op: u8,
+        /// Data of the payload.
+        d: Value,
+    },
+}
+
+#[derive(Deserialize)]
+struct RawCommand {
+    op: u8,
+    #[serde(default)]
+    d: Value,
+}
+
+impl Command {
+    /// Parse a JSON payload sent by a shard.
+    pub(crate) fn from_json(json: &[u8]) -> Result<Self, serde_json::Error> {
+        let RawCommand { op, d } = serde_json::from_slice(json)?;
+
+        Ok(if op == OpCode::Heartbeat as u8 {
+            Self::Heartbeat(serde_json::from_value(d)?)
+        } else if op == OpCode::Identify as u8 {
+            Self::Identify(serde_json::from_value(d)?)
+        } else if op == OpCode::Resume as u8 {
+            Self::Resume(serde_json::from_value(d)?)
+        } else {
+            Self::Other { op, d }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Command;
+    use serde_json::json;
+    use static_assertions::assert_impl_all;
+    use std::fmt::Debug;
+    use twilight_model::gateway::payload::resume::ResumeInfo;
+
+    assert_impl_all!(Command: Clone, Debug, Eq, PartialEq, Send, Sync);
+
+    #[test]
+    fn test_from_json() {
+        assert_eq!(
+            Command::Heartbeat(Some(3)),
+            Command::from_json(br#"{"op":1,"d":3}"#).unwrap()
+        );
+        assert_eq!(
+            Command::Heartbeat(None),
+            Command::from_json(br#"{"op":1,"d":null}"#).unwrap()
+        );
+        assert_eq!(
+            Command::Resume(ResumeInfo::new(5, "session", "Bot token")),
+            Command::from_json(
+                br#"{"op":6,"d":{"seq":5,"session_id":"session","token":"Bot token"}}"#
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            Command::Other {
+                op: 8,
+                d: json!({"guild_id": "1"}),
+            },
+            Command::from_json(br#"{"op":8,"d":{"guild_id":"1"}}"#).unwrap()
+        );
+        assert!(Command::from_json(b"{}").is_err());
+    }
+}
@@ -0,0 +1,524 @@
+use super::command::Command;
+use flate2::{Compress, Compression, FlushCompress};
+use futures_util::{
+    sink::SinkExt,
+    stream::{SplitSink, SplitStream, StreamExt},
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{
+    borrow::Cow,
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+use tokio::{
+    net::TcpStream,
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+};
+use tokio_tungstenite::{
+    tungstenite::{
+        handshake::server::{Request, Response},
+        protocol::{frame::coding::CloseCode, CloseFrame},
+        Error as WsError, Message,
+    },
+    WebSocketStream,
+};
+use twilight_model::{
+    gateway::{payload::Ready, OpCode},
+    guild::UnavailableGuild,
+    id::{ApplicationId, GuildId, UserId},
+    oauth::PartialApplication,
+    user::{CurrentUser, UserFlags},
+};
+
+/// Sending a payload to a shard failed.
+#[derive(Debug)]
+pub struct SendError {
+    kind: SendErrorType,
+    source: Option<Box<dyn Error + Send + Sync>>,
+}
+
+impl SendError {
+    /// Immutable reference to the type of error that occurred.
+    #[must_use = "retrieving the type has no effect if left unused"]
+    pub const fn kind(&self) -> &SendErrorType {
+        &self.kind
+    }
+
+    /// Consume the error, returning the source error if there is any.
+    #[must_use = "consuming the error and retrieving the source has no effect if left unused"]
+    pub fn into_source(self) -> Option<Box<dyn Error + Send + Sync>> {
+        self.source
+    }
+
+    /// Consume the error, returning the owned error type and the source error.
+    #[must_use = "consuming the error into its parts has no effect if left unused"]
+    pub fn into_parts(self) -> (SendErrorType, Option<Box<dyn Error + Send + Sync>>) {
+        (self.kind, self.source)
+    }
+}
+
+impl Display for SendError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match &self.kind {
+            SendErrorType::Closed => f.write_str("connection is closed"),
+            SendErrorType::Serializing => f.write_str("serializing the payload failed"),
+        }
+    }
+}
+
+impl Error for SendError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| &**source as &(dyn Error + 'static))
+    }
+}
+
+/// Type of [`SendError`] that occurred.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SendErrorType {
+    /// Connection is closed.
+    Closed,
+    /// Serializing the payload failed.
+    Serializing,
+}
+
+/// Message to send over the connection.
+enum Outgoing {
+    Close(CloseFrame<'static>),
+    Payload(String),
+}
+
+/// Connection opened by a shard to the [`MockGateway`].
+///
+/// Dropping the connection drops the WebSocket connection without closing it,
+/// like a network failure would. Use [`close`] to close it with a close code.
+///
+/// [`MockGateway`]: crate::MockGateway
+/// [`close`]: Self::close
+#[derive(Debug)]
+pub struct Connection {
+    commands: UnboundedReceiver<Command>,
+    compressed: bool,
+    heartbeat_ack: Arc<AtomicBool>,
+    outgoing: UnboundedSender<Outgoing>,
+    query: Box<str>,
+    seq: u64,
+}
+
+impl Connection {
+    /// Perform the WebSocket handshake and start forwarding messages.
+    pub(crate) async fn accept(stream: TcpStream) -> Result<Self, WsError> {
+        let query = Arc::new(Mutex::new(None::<String>));
+        let query_handshake = Arc::clone(&query);
+
+        let stream = tokio_tungstenite::accept_hdr_async(
+            stream,
+            move |request: &Request, response: Response| {
+                *query_handshake.lock().expect("query poisoned") =
+                    request.uri().query().map(ToOwned::to_owned);
+
+                Ok(response)
+            },
+        )
+        .await?;
+
+        let query = query
+            .lock()
+            .expect("query poisoned")
+            .take()
+            .unwrap_or_default();
+        let compressed = query
+            .split('&')
+            .any(|parameter| parameter == "compress=zlib-stream");
+
+        let (sink, stream) = stream.split();
+        let (commands_tx, commands) = mpsc::unbounded_channel();
+        let (outgoing, outgoing_rx) = mpsc::unbounded_channel();
+        let heartbeat_ack = Arc::new(AtomicBool::new(true));
+
+        let reader = tokio::spawn(read(
+            stream,
+            commands_tx,
+            Arc::clone(&heartbeat_ack),
+            outgoing.clone(),
+        ));
+        tokio::spawn(async move {
+            write(sink, outgoing_rx, compressed).await;
+
+            // Drop the whole connection once nothing is sent anymore.
+            reader.abort();
+        });
+
+        Ok(Self {
+            commands,
+            compressed,
+            heartbeat_ack,
+            outgoing,
+            query: query.into_boxed_str(),
+            seq: 0,
+        })
+    }
+
+    /// Query of the URL the shard connected with, such as
+    /// `v=8&encoding=json&compress=zlib-stream`.
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Whether payloads are compressed as a single zlib stream.
+    pub const fn is_compressed(&self) -> bool {
+        self.compressed
+    }
+
+    /// Sequence of the last dispatched event.
+    pub const fn sequence(&self) -> u64 {
+        self.seq
+    }
+
+    /// Set the sequence of the last dispatched event.
+    ///
+    /// The sequence is set automatically when the shard resumes a session.
+    pub fn set_sequence(&mut self, seq: u64) {
+        self.seq = seq;
+    }
+
+    /// Set whether heartbeats are acknowledged automatically.
+    ///
+    /// Heartbeats that aren't acknowledged automatically are yielded by
+    /// [`next_command`] instead.
+    ///
+    /// Defaults to `true`.
+    ///
+    /// [`next_command`]: Self::next_command
+    pub fn set_heartbeat_ack(&self, enabled: bool) {
+        self.heartbeat_ack.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Wait for the next payload sent by the shard.
+    ///
+    /// If the shard resumes a session then the sequence of dispatched events
+    /// continues from the resumed sequence.
+    ///
+    /// Returns `None` if the connection was closed.
+    pub async fn next_command(&mut self) -> Option<Command> {
+        let command = self.commands.recv().await?;
+
+        if let Command::Resume(resume) = &command {
+            self.seq = resume.seq;
+        }
+
+        Some(command)
+    }
+
+    /// Send a hello, which the shard responds to by identifying or resuming.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SendErrorType::Closed`] error type if the connection is
+    /// closed.
+    pub fn hello(&self, heartbeat_interval: u64) -> Result<(), SendError> {
+        self.send(&payload(
+            OpCode::Hello,
+            json!({ "heartbeat_interval": heartbeat_interval }),
+        ))
+    }
+
+    /// Request the shard to send a heartbeat, including the sequence of the
+    /// last dispatched event like the gateway does.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SendErrorType::Closed`] error type if the connection is
+    /// closed.
+    pub fn heartbeat(&self) -> Result<(), SendError> {
+        self.send(&payload(OpCode::Heartbeat, Value::from(self.seq)))
+    }
+
+    /// Acknowledge a heartbeat.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SendErrorType::Closed`] error type if the connection is
+    /// closed.
+    pub fn heartbeat_ack(&self) -> Result<(), SendError> {
+        self.send(&payload(OpCode::HeartbeatAck, Value::Null))
+    }
+
+    /// Invalidate the shard's session.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SendErrorType::Closed`] error type if the connection is
+    /// closed.
+    pub fn invalid_session(&self, resumable: bool) -> Result<(), SendError> {
+        self.send(&payload(OpCode::InvalidSession, Value::Bool(resumable)))
+    }
+
+    /// Request the shard to reconnect and resume.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SendErrorType::Closed`] error type if the connection is
+    /// closed.
+    pub fn reconnect(&self) -> Result<(), SendError> {
+        self.send(&payload(OpCode::Reconnect, Value::Null))
+    }
+
+    /// Dispatch an event, returning its sequence.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SendErrorType::Closed`] error type if the connection is
+    /// closed.
+    ///
+    /// Returns a [`SendErrorType::Serializing`] error type if the data
+    /// couldn't be serialized.
+    pub fn dispatch(&mut self, event_type: &str, data: &impl Serialize) -> Result<u64, SendError> {
+        let data = serde_json::to_value(data).map_err(|source| SendError {
+            kind: SendErrorType::Serializing,
+            source: Some(Box::new(source)),
+        })?;
+
+        self.seq += 1;
+
+        self.send(&json!({
+            "d": data,
+            "op": OpCode::Event as u8,
+            "s": self.seq,
+            "t": event_type,
+        }))?;
+
+        Ok(self.seq)
+    }
+
+    /// Dispatch a ready event for a new session with unavailable guilds,
+    /// returning its sequence.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SendErrorType::Closed`] error type if the connection is
+    /// closed.
+    pub fn ready(
+        &mut self,
+        session_id: &str,
+        guilds: impl IntoIterator<Item = GuildId>,
+    ) -> Result<u64, SendError> {
+        let ready = Ready {
+            application: PartialApplication {
+                flags: UserFlags::empty(),
+                id: ApplicationId(1),
+            },
+            guilds: guilds
+                .into_iter()
+                .map(|id| UnavailableGuild {
+                    id,
+                    unavailable: true,
+                })
+                .collect(),
+            session_id: session_id.to_owned(),
+            shard: None,
+            user: CurrentUser {
+                avatar: None,
+                bot: true,
+                discriminator: "0001".to_owned(),
+                email: None,
+                flags: None,
+                id: UserId(1),
+                locale: None,
+                mfa_enabled: false,
+                name: "mock".to_owned(),
+                premium_type: None,
+                public_flags: None,
+                verified: None,
+            },
+            version: 8,
+        };
+
+        self.seq = 0;
+
+        self.dispatch("READY", &ready)
+    }
+
+    /// Dispatch the event acknowledging that a session was resumed, returning
+    /// its sequence.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SendErrorType::Closed`] error type if the connection is
+    /// closed.
+    pub fn resumed(&mut self) -> Result<u64, SendError> {
+        self.dispatch("RESUMED", &json!({}))
+    }
+
+    /// Send a raw payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SendErrorType::Closed`] error type if the connection is
+    /// closed.
+    ///
+    /// Returns a [`SendErrorType::Serializing`] error type if the payload
+    /// couldn't be serialized.
+    pub fn send(&self, payload: &impl Serialize) -> Result<(), SendError> {
+        let json = serde_json::to_string(payload).map_err(|source| SendError {
+            kind: SendErrorType::Serializing,
+            source: Some(Box::new(source)),
+        })?;
+
+        self.outgoing
+            .send(Outgoing::Payload(json))
+            .map_err(|_| SendError {
+                kind: SendErrorType::Closed,
+                source: None,
+            })
+    }
+
+    /// Close the connection with a close code, such as `4004` for an invalid
+    /// token.
+    pub fn close(self, code: u16, reason: impl Into<Cow<'static, str>>) {
+        let _res = self.outgoing.send(Outgoing::Close(CloseFrame {
+            code: CloseCode::from(code),
+            reason: reason.into(),
+        }));
+    }
+}
+
+/// Payload that isn't a dispatch event.
+fn payload(op: OpCode, d: Value) -> Value {
+    let mut payload = json!({
+        "op": op as u8,
+        "s": null,
+        "t": null,
+    });
+    payload["d"] = d;
+
+    payload
+}
+
+/// Forward the payloads sent by the shard, acknowledging heartbeats if
+/// enabled.
+async fn read(
+    mut stream: SplitStream<WebSocketStream<TcpStream>>,
+    commands: UnboundedSender<Command>,
+    heartbeat_ack: Arc<AtomicBool>,
+    outgoing: UnboundedSender<Outgoing>,
+) {
+    while let Some(Ok(message)) = stream.next().await {
+        let json = match message {
+            Message::Binary(bytes) => bytes,
+            Message::Text(text) => text.into_bytes(),
+            Message::Close(_) => break,
+            Message::Ping(_) | Message::Pong(_) => continue,
+        };
+
+        let command = match Command::from_json(&json) {
+            Ok(command) => command,
+            Err(source) => {
+                tracing::warn!("received invalid payload: {}", source);
+
+                continue;
+            }
+        };
+
+        if matches!(command, Command::Heartbeat(_)) && heartbeat_ack.load(Ordering::Relaxed) {
+            let ack = payload(OpCode::HeartbeatAck, Value::Null).to_string();
+            let _res = outgoing.send(Outgoing::Payload(ack));
+
+            continue;
+        }
+
+        if commands.send(command).is_err() {
+            break;
+        }
+    }
+}
+
+/// Send the scripted messages, compressing them if enabled, until the
+/// connection is closed or dropped.
+async fn write(
+    mut sink: SplitSink<WebSocketStream<TcpStream>, Message>,
+    mut outgoing: UnboundedReceiver<Outgoing>,
+    compressed: bool,
+) {
+    let mut compress = Compress::new(Compression::default(), true);
+
+    while let Some(message) = outgoing.recv().await {
+        let message = match message {
+            Outgoing::Close(frame) => {
+                let _res = sink.send(Message::Close(Some(frame))).await;
+
+                break;
+            }
+            Outgoing::Payload(json) if compressed => {
+                Message::Binary(compress_message(&mut compress, json.as_bytes()))
+            }
+            Outgoing::Payload(json) => Message::Text(json),
+        };
+
+        if sink.send(message).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Compress a message as part of the connection's zlib stream, flushing the
+/// stream at the end of the message.
+fn compress_message(compress: &mut Compress, input: &[u8]) -> Vec<u8> {
+    let start = compress.total_in();
+    let mut output = Vec::with_capacity(input.len() + 64);
+
+    loop {
+        // The consumed length is bounded by the input's length.
+        #[allow(clippy::cast_possible_truncation)]
+        let consumed = (compress.total_in() - start) as usize;
+
+        compress
+            .compress_vec(&input[consumed..], &mut output, FlushCompress::Sync)
+            .expect("compressing into a vector can't fail");
+
+        // The stream is only flushed if there was capacity left over.
+        if compress.total_in() - start == input.len() as u64 && output.len() < output.capacity() {
+            return output;
+        }
+
+        output.reserve(1024);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compress_message, Connection, SendError, SendErrorType};
+    use flate2::{Compress, Compression, Decompress, FlushDecompress};
+    use static_assertions::assert_impl_all;
+    use std::{error::Error, fmt::Debug};
+
+    assert_impl_all!(Connection: Debug, Send, Sync);
+    assert_impl_all!(SendErrorType: Debug, Send, Sync);
+    assert_impl_all!(SendError: Error, Send, Sync);
+
+    #[test]
+    fn test_compress_stream() {
+        let messages: &[&[u8]] = &[br#"{"op":11,"d":null}"#, &[b'a'; 10_000]];
+        let mut compress = Compress::new(Compression::default(), true);
+        let mut decompress = Decompress::new(true);
+
+        for message in messages {
+            let compressed = compress_message(&mut compress, message);
+            assert!(compressed.ends_with(&[0x00, 0x00, 0xff, 0xff]));
+
+            let mut output = Vec::with_capacity(message.len() + 64);
+            decompress
+                .decompress_vec(&compressed, &mut output, FlushDecompress::Sync)
+                .unwrap();
+
+            assert_eq!(*message, output.as_slice());
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! In-process mock of the Discord Gateway for testing shards and clusters.
+//!
+//! A [`MockGateway`] runs a local WebSocket server speaking the gateway
+//! protocol. Point a shard at it with `ShardBuilder::gateway_url` and script
+//! the session from the test through the [`Connection`]s the shard opens:
+//! send a hello, wait for the shard to identify or resume, dispatch events,
+//! invalidate the session, or close the connection with a close code.
+//!
+//! Heartbeats sent by the shard are acknowledged automatically, which can be
+//! turned off per connection to simulate a connection that stopped
+//! responding. If the shard requests `zlib-stream` compression then every
+//! payload is compressed as part of a single zlib stream, like the gateway
+//! does. Only the JSON encoding is supported.
+//!
+//! A [`MockProxy`] accepts HTTP CONNECT and SOCKS5 connections, optionally
+//! requiring a username and password, to test connecting through a proxy.
+//!
+//! # Examples
+//!
+//! Start a shard and identify it with a new session:
+//!
+//! ```no_run
+//! use futures_util::StreamExt;
+//! use twilight_gateway::{Event, Intents, Shard};
+//! use twilight_gateway_mock::{Command, MockGateway};
+//! use twilight_model::id::GuildId;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+//! let mut gateway = MockGateway::bind().await?;
+//!
+//! let (shard, mut events) = Shard::builder("token", Intents::GUILDS)
+//!     .gateway_url(Some(gateway.url()))
+//!     .build();
+//! shard.start().await?;
+//!
+//! let mut connection = gateway.next_connection().await.expect("shard connected");
+//! connection.hello(41_250)?;
+//! assert!(matches!(
+//!     connection.next_command().await,
+//!     Some(Command::Identify(_))
+//! ));
+//! connection.ready("session", vec![GuildId(1)])?;
+//!
+//! while let Some(event) = events.next().await {
+//!     if matches!(event, Event::Ready(_)) {
+//!         break;
+//!     }
+//! }
+//! # Ok(()) }
+//! ```
+
+#![deny(
+    clippy::all,
+    clippy::missing_const_for_fn,
+    clippy::pedantic,
+    future_incompatible,
+    missing_docs,
+    nonstandard_style,
+    rust_2018_idioms,
+    broken_intra_doc_links,
+    unused,
+    warnings
+)]
+#![allow(clippy::module_name_repetitions, clippy::must_use_candidate)]
+
+mod command;
+mod connection;
+mod proxy;
+
+pub use self::{
+    command::Command,
+    connection::{Connection, SendError, SendErrorType},
+    proxy::{MockProxy, Tunnel, TunnelKind},
+};
+
+use std::{io::Error as IoError, net::SocketAddr, time::Duration};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, UnboundedReceiver},
+    task::JoinHandle,
+    time,
+};
+
+/// Initial wait before accepting connections again after accepting failed.
+const ACCEPT_BACKOFF_MIN: Duration = Duration::from_millis(10);
+
+/// Maximum wait before accepting connections again after accepting failed.
+const ACCEPT_BACKOFF_MAX: Duration = Duration::from_secs(1);
+
+/// Local WebSocket server speaking the gateway protocol.
+///
+/// Every WebSocket connection opened to the server is yielded by
+/// [`next_connection`] to be scripted by the test. The server stops accepting
+/// connections when it's dropped.
+///
+/// [`next_connection`]: Self::next_connection
+#[derive(Debug)]
+pub struct MockGateway {
+    addr: SocketAddr,
+    connections: UnboundedReceiver<Connection>,
+    task: JoinHandle<()>,
+}
+
+impl MockGateway {
+    /// Bind the server to a random local port and start accepting
+    /// connections.
+    ///
+    /// # Errors
+    ///
+    /// Returns an IO error if binding to a local port failed.
+    pub async fn bind() -> Result<Self, IoError> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let addr = listener.local_addr()?;
+        let (tx, connections) = mpsc::unbounded_channel();
+
+        let task = tokio::spawn(async move {
+            loop {
+                let stream = accept(&listener).await;
+                let tx = tx.clone();
+
+                tokio::spawn(async move {
+                    match Connection::accept(stream).await {
+                        Ok(connection) => {
+                            let _res = tx.send(connection);
+                        }
+                        Err(source) => {
+                            tracing::warn!("websocket handshake failed: {}", source);
+                        }
+                    }
+                });
+            }
+        });
+
+        Ok(Self {
+            addr,
+            connections,
+            task,
+        })
+    }
+
+    /// Address the server is listening on.
+    pub const fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// URL to connect to the server, for use as a shard's gateway URL.
+    pub fn url(&self) -> String {
+        format!("ws://{}", self.addr)
+    }
+
+    /// Wait for the next connection to be opened.
+    ///
+    /// Returns `None` if the server stopped accepting connections.
+    pub async fn next_connection(&mut self) -> Option<Connection> {
+        self.connections.recv().await
+    }
+}
+
+impl Drop for MockGateway {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Accept the next connection, backing off while accepting fails, such as when
+/// the process has run out of file descriptors.
+async fn accept(listener: &TcpListener) -> TcpStream {
+    let mut backoff = ACCEPT_BACKOFF_MIN;
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => return stream,
+            Err(source) => {
+                tracing::warn!(
+                    "accepting connection failed, retrying in {:?}: {}",
+                    backoff,
+                    source
+                );
+
+                time::sleep(backoff).await;
+                backoff = (backoff * 2).min(ACCEPT_BACKOFF_MAX);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MockGateway;
+    use static_assertions::assert_impl_all;
+    use std::fmt::Debug;
+
+    assert_impl_all!(MockGateway: Debug, Send, Sync);
+
+    #[tokio::test]
+    async fn test_url() {
+        let gateway = MockGateway::bind().await.unwrap();
+
+        assert_eq!(
+            format!("ws://127.0.0.1:{}", gateway.addr().port()),
+            gateway.url()
+        );
+    }
+}
@@ -0,0 +1,311 @@
+use std::{io::Error as IoError, net::SocketAddr, str, sync::Arc};
+use tokio::{
+    io::{self, AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    task::JoinHandle,
+};
+
+/// Protocol a [`Tunnel`] was requested with.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TunnelKind {
+    /// Tunnel requested with an HTTP CONNECT request.
+    Http,
+    /// Tunnel requested with a SOCKS5 CONNECT command.
+    Socks5,
+}
+
+/// Tunnel opened through a [`MockProxy`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Tunnel {
+    kind: TunnelKind,
+    target: String,
+    username: Option<String>,
+}
+
+impl Tunnel {
+    /// Protocol the tunnel was requested with.
+    pub const fn kind(&self) -> TunnelKind {
+        self.kind
+    }
+
+    /// Host and port the tunnel was opened to.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Username the client authenticated with, if any.
+    pub fn username(&self) -> Option<&str> {
+        self.username.as_deref()
+    }
+}
+
+/// Local proxy accepting both HTTP CONNECT requests and SOCKS5 connections.
+///
+/// Every tunnel opened through the proxy is reported by [`next_tunnel`] once
+/// it's established, after which bytes are forwarded between the client and
+/// the target. Clients failing to authenticate are rejected and not reported.
+/// The proxy stops accepting connections when it's dropped.
+///
+/// [`next_tunnel`]: Self::next_tunnel
+#[derive(Debug)]
+pub struct MockProxy {
+    addr: SocketAddr,
+    task: JoinHandle<()>,
+    tunnels: UnboundedReceiver<Tunnel>,
+}
+
+impl MockProxy {
+    /// Bind the proxy to a random local port and start accepting connections
+    /// without requiring authentication.
+    ///
+    /// # Errors
+    ///
+    /// Returns an IO error if binding to a local port failed.
+    pub async fn bind() -> Result<Self, IoError> {
+        Self::_bind(None).await
+    }
+
+    /// Bind the proxy to a random local port and start accepting connections
+    /// authenticating with the username and password.
+    ///
+    /// # Errors
+    ///
+    /// Returns an IO error if binding to a local port failed.
+    pub async fn bind_with_auth(
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Result<Self, IoError> {
+        Self::_bind(Some((username.into(), password.into()))).await
+    }
+
+    async fn _bind(auth: Option<(String, String)>) -> Result<Self, IoError> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let addr = listener.local_addr()?;
+        let auth = Arc::new(auth);
+        let (tx, tunnels) = mpsc::unbounded_channel();
+
+        let task = tokio::spawn(async move {
+            loop {
+                let stream = super::accept(&listener).await;
+                let auth = Arc::clone(&auth);
+                let tx = tx.clone();
+
+                tokio::spawn(async move {
+                    if let Err(source) = handle(stream, (*auth).as_ref(), &tx).await {
+                        tracing::warn!("proxying connection failed: {}", source);
+                    }
+                });
+            }
+        });
+
+        Ok(Self {
+            addr,
+            task,
+            tunnels,
+        })
+    }
+
+    /// Address the proxy is listening on, as a host and port.
+    pub fn address(&self) -> String {
+        self.addr.to_string()
+    }
+
+    /// Wait for the next tunnel to be established.
+    ///
+    /// Returns `None` if the proxy stopped accepting connections.
+    pub async fn next_tunnel(&mut self) -> Option<Tunnel> {
+        self.tunnels.recv().await
+    }
+}
+
+impl Drop for MockProxy {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn handle(
+    mut stream: TcpStream,
+    auth: Option<&(String, String)>,
+    tx: &UnboundedSender<Tunnel>,
+) -> Result<(), IoError> {
+    // SOCKS5 clients start with the protocol version, HTTP clients with the
+    // request method.
+    let first = stream.read_u8().await?;
+
+    let tunnel = if first == 5 {
+        socks5(&mut stream, auth).await?
+    } else {
+        http(&mut stream, first, auth).await?
+    };
+
+    let (tunnel, mut target) = match tunnel {
+        Some(tunnel) => tunnel,
+        None => return Ok(()),
+    };
+
+    let _res = tx.send(tunnel);
+    io::copy_bidirectional(&mut stream, &mut target).await?;
+
+    Ok(())
+}
+
+async fn http(
+    stream: &mut TcpStream,
+    first: u8,
+    auth: Option<&(String, String)>,
+) -> Result<Option<(Tunnel, TcpStream)>, IoError> {
+    let mut request = vec![first];
+
+    while !request.ends_with(b"\r\n\r\n") {
+        request.push(stream.read_u8().await?);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut lines = request.lines();
+    let target = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .unwrap_or_default()
+        .to_owned();
+    let credentials = lines
+        .filter_map(|line| line.strip_prefix("Proxy-Authorization: Basic "))
+        .find_map(|encoded| base64::decode(encoded).ok())
+        .and_then(|decoded| String::from_utf8(decoded).ok());
+
+    let username = match (auth, credentials) {
+        (None, _) => None,
+        (Some((username, password)), Some(credentials))
+            if credentials == format!("{}:{}", username, password) =>
+        {
+            Some(username.clone())
+        }
+        (Some(_), _) => {
+            stream
+                .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n")
+                .await?;
+
+            return Ok(None);
+        }
+    };
+
+    let target_stream = if let Ok(target_stream) = TcpStream::connect(&target).await {
+        target_stream
+    } else {
+        stream
+            .write_all(b"HTTP/1.1 502 Bad Gateway\r\n\r\n")
+            .await?;
+
+        return Ok(None);
+    };
+
+    stream
+        .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+        .await?;
+
+    let tunnel = Tunnel {
+        kind: TunnelKind::Http,
+        target,
+        username,
+    };
+
+    Ok(Some((tunnel, target_stream)))
+}
+
+async fn socks5(
+    stream: &mut TcpStream,
+    auth: Option<&(String, String)>,
+) -> Result<Option<(Tunnel, TcpStream)>, IoError> {
+    let method_count = stream.read_u8().await?;
+    let mut methods = vec![0; method_count.into()];
+    stream.read_exact(&mut methods).await?;
+
+    let method = if auth.is_some() { 2 } else { 0 };
+
+    if !methods.contains(&method) {
+        stream.write_all(&[5, 0xff]).await?;
+
+        return Ok(None);
+    }
+
+    stream.write_all(&[5, method]).await?;
+
+    let username = if let Some((username, password)) = auth {
+        let _version = stream.read_u8().await?;
+        let received_username = read_string(stream).await?;
+        let received_password = read_string(stream).await?;
+
+        if received_username != *username || received_password != *password {
+            stream.write_all(&[1, 1]).await?;
+
+            return Ok(None);
+        }
+
+        stream.write_all(&[1, 0]).await?;
+
+        Some(received_username)
+    } else {
+        None
+    };
+
+    let mut request = [0; 4];
+    stream.read_exact(&mut request).await?;
+
+    let host = match request[3] {
+        1 => {
+            let mut octets = [0; 4];
+            stream.read_exact(&mut octets).await?;
+
+            std::net::Ipv4Addr::from(octets).to_string()
+        }
+        3 => read_string(stream).await?,
+        _ => {
+            // Address type not supported.
+            stream.write_all(&[5, 8, 0, 1, 0, 0, 0, 0, 0, 0]).await?;
+
+            return Ok(None);
+        }
+    };
+    let port = stream.read_u16().await?;
+    let target = format!("{}:{}", host, port);
+
+    let target_stream = if let Ok(target_stream) = TcpStream::connect(&target).await {
+        target_stream
+    } else {
+        // Connection refused.
+        stream.write_all(&[5, 5, 0, 1, 0, 0, 0, 0, 0, 0]).await?;
+
+        return Ok(None);
+    };
+
+    stream.write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0, 0]).await?;
+
+    let tunnel = Tunnel {
+        kind: TunnelKind::Socks5,
+        target,
+        username,
+    };
+
+    Ok(Some((tunnel, target_stream)))
+}
+
+/// Read a string prefixed by its length in a single byte.
+async fn read_string(stream: &mut TcpStream) -> Result<String, IoError> {
+    let len = stream.read_u8().await?;
+    let mut bytes = vec![0; len.into()];
+    stream.read_exact(&mut bytes).await?;
+
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MockProxy, Tunnel, TunnelKind};
+    use static_assertions::assert_impl_all;
+    use std::{fmt::Debug, hash::Hash};
+
+    assert_impl_all!(MockProxy: Debug, Send, Sync);
+    assert_impl_all!(Tunnel: Clone, Debug, Eq, PartialEq, Send, Sync);
+    assert_impl_all!(TunnelKind: Clone, Copy, Debug, Eq, Hash, PartialEq, Send, Sync);
+}
@@ -1,110 +1,263 @@
-use std::{
-    error::Error,
-    fmt::{Display, Formatter, Result as FmtResult},
-    time::Duration,
-};
+use super::LargeBotQueueEvent;
+use std::time::Duration;
 use tokio::{
-    sync::Mutex,
+    sync::{mpsc::UnboundedSender, Mutex, Notify},
     time::{self, Instant},
 };
+use twilight_http::Client;
+use twilight_model::gateway::SessionStartLimit;
 
-/// Creating a day limiter queue failed.
-#[derive(Debug)]
-pub struct DayLimiterError {
-    kind: DayLimiterErrorType,
-    source: Option<Box<dyn Error + Send + Sync>>,
-}
+/// Duration the session start limit is assumed to reset after if the reset
+/// can't be retrieved.
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
 
-impl Display for DayLimiterError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        match &self.kind {
-            DayLimiterErrorType::RetrievingSessionAvailability { .. } => {
-                f.write_str("retrieving the bot's gateway session availability failed")
-            }
-        }
-    }
+/// Session start limits of a bot.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SessionLimits {
+    pub remaining: u64,
+    pub reset_after: Duration,
+    pub total: u64,
 }
 
-impl Error for DayLimiterError {
-    fn source(&self) -> Option<&(dyn Error + 'static)> {
-        self.source
-            .as_ref()
-            .map(|source| &**source as &(dyn Error + 'static))
+impl From<&SessionStartLimit> for SessionLimits {
+    fn from(session_start_limit: &SessionStartLimit) -> Self {
+        Self {
+            remaining: session_start_limit.remaining,
+            reset_after: Duration::from_millis(session_start_limit.reset_after),
+            total: session_start_limit.total,
+        }
     }
 }
 
-/// Type of [`DayLimiterError`] that occurred.
 #[derive(Debug)]
-#[non_exhaustive]
-pub enum DayLimiterErrorType {
-    /// Retrieving the bot's available gateway session initiation information
-    /// via the HTTP API failed.
-    RetrievingSessionAvailability,
+pub(crate) struct DayLimiter {
+    /// Sender to notify of the remaining identifies dropping below the
+    /// threshold.
+    events: UnboundedSender<LargeBotQueueEvent>,
+    /// HTTP client to refresh the limits with once they reset.
+    http: Option<Client>,
+    pub(crate) inner: Mutex<DayLimiterInner>,
+    /// Number of remaining identifies below which to warn.
+    threshold: u64,
+    /// Notified when the limits are updated from another source.
+    updated: Notify,
 }
 
-#[derive(Debug)]
-pub(crate) struct DayLimiter(pub(crate) Mutex<DayLimiterInner>);
-
 #[derive(Debug)]
 pub(crate) struct DayLimiterInner {
-    pub http: twilight_http::Client,
     pub last_check: Instant,
     pub next_reset: Duration,
     pub total: u64,
     pub current: u64,
+    /// Whether the remaining identifies dropping below the threshold has been
+    /// warned about since they were last above it.
+    warned: bool,
 }
 
 impl DayLimiter {
-    pub async fn new(http: &twilight_http::Client) -> Result<Self, DayLimiterError> {
-        let info = http
-            .gateway()
-            .authed()
-            .await
-            .map_err(|source| DayLimiterError {
-                kind: DayLimiterErrorType::RetrievingSessionAvailability,
-                source: Some(Box::new(source)),
-            })?;
-
-        let last_check = Instant::now();
-
-        let next_reset = Duration::from_millis(info.session_start_limit.reset_after);
-        let total = info.session_start_limit.total;
-        let remaining = info.session_start_limit.remaining;
-        debug_assert!(total >= remaining);
-        let current = total - remaining;
-        Ok(DayLimiter(Mutex::new(DayLimiterInner {
-            http: http.clone(),
-            last_check,
-            next_reset,
-            total: info.session_start_limit.total,
-            current,
-        })))
+    pub fn new(
+        limits: SessionLimits,
+        http: Option<Client>,
+        threshold: u64,
+        events: UnboundedSender<LargeBotQueueEvent>,
+    ) -> Self {
+        debug_assert!(limits.total >= limits.remaining);
+
+        Self {
+            events,
+            http,
+            inner: Mutex::new(DayLimiterInner {
+                last_check: Instant::now(),
+                next_reset: limits.reset_after,
+                total: limits.total,
+                current: limits.total.saturating_sub(limits.remaining),
+                warned: false,
+            }),
+            threshold,
+            updated: Notify::new(),
+        }
     }
 
     pub async fn get(&self) {
-        let mut lock = self.0.lock().await;
-        if lock.current < lock.total {
-            lock.current += 1;
-        } else {
-            let wait = lock.last_check + lock.next_reset;
-            time::sleep_until(wait).await;
-            if let Ok(info) = lock.http.gateway().authed().await {
-                let last_check = Instant::now();
-                let next_reset = Duration::from_millis(info.session_start_limit.remaining);
-                tracing::info!("next session start limit reset in: {:.2?}", next_reset);
-                let total = info.session_start_limit.total;
-                let remaining = info.session_start_limit.remaining;
-                assert!(total >= remaining);
-                let current = total - remaining;
-                lock.last_check = last_check;
-                lock.next_reset = next_reset;
-                lock.total = total;
-                lock.current = current + 1;
-            } else {
-                tracing::warn!(
-                    "unable to get new session limits, skipping (this may cause bad things)"
-                )
+        loop {
+            let (reset, updated) = {
+                let mut lock = self.inner.lock().await;
+
+                if lock.current < lock.total {
+                    lock.current += 1;
+                    self.check(&mut lock);
+
+                    return;
+                }
+
+                (lock.last_check + lock.next_reset, self.updated.notified())
+            };
+
+            // Don't hold the lock while waiting for the reset so that the
+            // limits can still be updated in the meantime, which may make
+            // identifies available earlier.
+            let _res = time::timeout_at(reset, updated).await;
+
+            let mut lock = self.inner.lock().await;
+
+            // Another request may have already refreshed the limits, or they
+            // may have been updated while waiting.
+            if lock.current < lock.total || lock.last_check + lock.next_reset > Instant::now() {
+                continue;
             }
+
+            let limits = match self.http.as_ref() {
+                Some(http) => match http.gateway().authed().await {
+                    Ok(info) => Some(SessionLimits::from(&info.session_start_limit)),
+                    Err(source) => {
+                        tracing::warn!(
+                            "unable to get new session limits, assuming they reset: {}",
+                            source
+                        );
+
+                        None
+                    }
+                },
+                None => None,
+            };
+
+            let limits = limits.unwrap_or(SessionLimits {
+                remaining: lock.total,
+                reset_after: DAY,
+                total: lock.total,
+            });
+
+            tracing::info!(
+                "next session start limit reset in: {:.2?}",
+                limits.reset_after
+            );
+            self.apply(&mut lock, limits);
         }
     }
+
+    /// Update the limits with ones retrieved from another source.
+    pub async fn update(&self, limits: SessionLimits) {
+        let mut lock = self.inner.lock().await;
+        self.apply(&mut lock, limits);
+        self.updated.notify_waiters();
+    }
+
+    fn apply(&self, lock: &mut DayLimiterInner, limits: SessionLimits) {
+        debug_assert!(limits.total >= limits.remaining);
+
+        lock.last_check = Instant::now();
+        lock.next_reset = limits.reset_after;
+        lock.total = limits.total;
+        lock.current = limits.total.saturating_sub(limits.remaining);
+        self.check(lock);
+    }
+
+    /// Warn if the remaining identifies dropped below the threshold.
+    fn check(&self, lock: &mut DayLimiterInner) {
+        let remaining = lock.total.saturating_sub(lock.current);
+
+        if remaining >= self.threshold {
+            lock.warned = false;
+
+            return;
+        }
+
+        if lock.warned {
+            return;
+        }
+
+        lock.warned = true;
+
+        let reset_after =
+            (lock.last_check + lock.next_reset).saturating_duration_since(Instant::now());
+        tracing::warn!(
+            "{}/{} identifies remaining before next reset in {:.2?}",
+            remaining,
+            lock.total,
+            reset_after
+        );
+
+        // The receiver may have been dropped if the events aren't of interest.
+        let _res = self.events.send(LargeBotQueueEvent::RemainingLow {
+            remaining,
+            reset_after,
+            total: lock.total,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DayLimiter, SessionLimits};
+    use crate::LargeBotQueueEvent;
+    use std::time::Duration;
+    use tokio::{sync::mpsc, time};
+
+    #[tokio::test]
+    async fn test_remaining_low() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let limits = SessionLimits {
+            remaining: 3,
+            reset_after: Duration::from_secs(3600),
+            total: 5,
+        };
+        let limiter = DayLimiter::new(limits, None, 2, tx);
+
+        limiter.get().await;
+        assert!(rx.try_recv().is_err());
+
+        limiter.get().await;
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(LargeBotQueueEvent::RemainingLow {
+                remaining: 1,
+                total: 5,
+                ..
+            })
+        ));
+
+        // Only warn once until the remaining identifies are above the
+        // threshold again.
+        limiter.get().await;
+        assert!(rx.try_recv().is_err());
+
+        limiter.update(limits).await;
+        limiter.get().await;
+        limiter.get().await;
+        assert!(rx.try_recv().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_update_while_waiting() {
+        time::pause();
+
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let limits = SessionLimits {
+            remaining: 0,
+            reset_after: Duration::from_secs(3600),
+            total: 1,
+        };
+        let limiter = DayLimiter::new(limits, None, 0, tx);
+
+        let get = limiter.get();
+        tokio::pin!(get);
+        assert!(time::timeout(Duration::from_secs(60), &mut get)
+            .await
+            .is_err());
+
+        // Updating isn't blocked by the request waiting for the reset, and
+        // wakes the request up if identifies became available.
+        let limits = SessionLimits {
+            remaining: 1,
+            ..limits
+        };
+        assert!(
+            time::timeout(Duration::from_secs(1), limiter.update(limits))
+                .await
+                .is_ok()
+        );
+        assert!(time::timeout(Duration::from_secs(1), &mut get)
+            .await
+            .is_ok());
+    }
 }
@@ -0,0 +1,312 @@
+use super::Queue;
+use hyper::{client::HttpConnector, http::uri::InvalidUri, Client, StatusCode, Uri};
+use std::{
+    error::Error,
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+    future::Future,
+    pin::Pin,
+    time::Duration,
+};
+use tokio::time::sleep;
+
+/// Creating an [`HttpQueue`] failed.
+#[derive(Debug)]
+pub struct HttpQueueError {
+    kind: HttpQueueErrorType,
+    source: Option<Box<dyn Error + Send + Sync>>,
+}
+
+impl HttpQueueError {
+    /// Immutable reference to the type of error that occurred.
+    #[must_use = "retrieving the type has no effect if left unused"]
+    pub const fn kind(&self) -> &HttpQueueErrorType {
+        &self.kind
+    }
+
+    /// Consume the error, returning the source error if there is any.
+    #[must_use = "consuming the error and retrieving the source has no effect if left unused"]
+    pub fn into_source(self) -> Option<Box<dyn Error + Send + Sync>> {
+        self.source
+    }
+
+    /// Consume the error, returning the owned error type and the source error.
+    #[must_use = "consuming the error into its parts has no effect if left unused"]
+    pub fn into_parts(self) -> (HttpQueueErrorType, Option<Box<dyn Error + Send + Sync>>) {
+        (self.kind, self.source)
+    }
+}
+
+impl Display for HttpQueueError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match &self.kind {
+            HttpQueueErrorType::SchemeUnsupported { url } => f.write_fmt(format_args!(
+                "url {} doesn't use the only supported scheme, http",
+                url
+            )),
+            HttpQueueErrorType::UrlInvalid { url } => {
+                f.write_fmt(format_args!("url {} is invalid", url))
+            }
+        }
+    }
+}
+
+impl Error for HttpQueueError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| &**source as &(dyn Error + 'static))
+    }
+}
+
+/// Type of [`HttpQueueError`] that occurred.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum HttpQueueErrorType {
+    /// URL doesn't use the `http` scheme.
+    ///
+    /// HTTPS isn't supported; use a proxy terminating TLS if the server isn't
+    /// reachable over a private network.
+    SchemeUnsupported {
+        /// URL of the server.
+        url: String,
+    },
+    /// URL isn't a valid absolute URL.
+    UrlInvalid {
+        /// URL of the server.
+        url: String,
+    },
+}
+
+/// Queue requesting allowance to identify from a [`QueueServer`] over HTTP.
+///
+/// Shards in multiple processes can share their identify ratelimits by
+/// pointing their queues to the same server. The queue can also request
+/// allowance from the [`gateway-queue`] broker.
+///
+/// If the server can't be reached or doesn't grant allowance, such as when it
+/// rejects the request, the request is retried, waiting longer between each
+/// attempt, so that shards don't identify without allowance.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::{env, sync::Arc};
+/// use twilight_gateway_queue::{HttpQueue, Queue};
+///
+/// # #[tokio::main] async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// let queue: Arc<Box<dyn Queue>> = Arc::new(Box::new(HttpQueue::new("http://queue.internal:8000")?));
+///
+/// // Wait for the allowance of shard 3 out of 16 to identify.
+/// queue.request([3, 16]).await;
+/// # Ok(()) }
+/// ```
+///
+/// [`QueueServer`]: crate::QueueServer
+/// [`gateway-queue`]: https://github.com/twilight-rs/gateway-queue
+#[derive(Clone, Debug)]
+pub struct HttpQueue {
+    client: Client<HttpConnector>,
+    url: Box<str>,
+}
+
+impl HttpQueue {
+    /// Maximum time to wait between attempts to reach the server.
+    const MAX_RETRY_WAIT: Duration = Duration::from_secs(32);
+
+    /// Create a new queue requesting allowance from the server at a URL, such
+    /// as `http://queue.internal:8000`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`HttpQueueErrorType::UrlInvalid`] error type if the URL
+    /// isn't a valid absolute URL.
+    ///
+    /// Returns an [`HttpQueueErrorType::SchemeUnsupported`] error type if the
+    /// URL doesn't use the `http` scheme.
+    pub fn new(url: impl Into<String>) -> Result<Self, HttpQueueError> {
+        let mut url = url.into();
+
+        if url.ends_with('/') {
+            url.pop();
+        }
+
+        // Validate the URL requests are made to, so that requesting allowance
+        // can't fail to build it later on.
+        let uri = match Self::uri(&url, [0, 1]) {
+            Ok(uri) => uri,
+            Err(source) => {
+                return Err(HttpQueueError {
+                    kind: HttpQueueErrorType::UrlInvalid { url },
+                    source: Some(Box::new(source)),
+                })
+            }
+        };
+
+        if uri.authority().is_none() {
+            return Err(HttpQueueError {
+                kind: HttpQueueErrorType::UrlInvalid { url },
+                source: None,
+            });
+        }
+
+        if uri.scheme_str() != Some("http") {
+            return Err(HttpQueueError {
+                kind: HttpQueueErrorType::SchemeUnsupported { url },
+                source: None,
+            });
+        }
+
+        Ok(Self {
+            client: Client::new(),
+            url: url.into_boxed_str(),
+        })
+    }
+
+    /// URL of the server.
+    pub const fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// URL to request allowance for a shard from.
+    fn uri(url: &str, [id, total]: [u64; 2]) -> Result<Uri, InvalidUri> {
+        format!("{}/?shard={}&total={}", url, id, total).parse()
+    }
+
+    async fn try_request(&self, uri: Uri) -> Result<(), String> {
+        let response = self
+            .client
+            .get(uri)
+            .await
+            .map_err(|source| source.to_string())?;
+
+        match response.status() {
+            StatusCode::OK => Ok(()),
+            status @ StatusCode::REQUEST_TIMEOUT | status @ StatusCode::TOO_MANY_REQUESTS => {
+                Err(format!("server responded with status {}", status))
+            }
+            // Retrying won't help until the server or URL is fixed.
+            status if status.is_client_error() => Err(format!(
+                "server rejected the request with status {}, is {} a queue server?",
+                status, self.url
+            )),
+            status => Err(format!("server responded with status {}", status)),
+        }
+    }
+}
+
+impl Queue for HttpQueue {
+    /// Request to be able to identify with the gateway. The returned future
+    /// resolves once the server responds with the allowance.
+    fn request(&'_ self, [id, total]: [u64; 2]) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+        Box::pin(async move {
+            // Only the numbers differ from the URL validated on creation.
+            let uri = Self::uri(&self.url, [id, total]).expect("url is valid");
+
+            tracing::info!("shard {}/{} waiting for allowance", id, total);
+
+            let mut wait = Duration::from_secs(1);
+
+            while let Err(why) = self.try_request(uri.clone()).await {
+                tracing::warn!(
+                    "requesting allowance for shard {} failed, retrying in {:?}: {}",
+                    id,
+                    wait,
+                    why
+                );
+
+                sleep(wait).await;
+
+                if wait < Self::MAX_RETRY_WAIT {
+                    wait *= 2;
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HttpQueue, HttpQueueError, HttpQueueErrorType, Queue};
+    use hyper::{
+        server::conn::AddrStream,
+        service::{make_service_fn, service_fn},
+        Body, Response, Server, StatusCode,
+    };
+    use static_assertions::assert_impl_all;
+    use std::{
+        convert::Infallible,
+        error::Error,
+        fmt::Debug,
+        net::SocketAddr,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+    use tokio::time;
+
+    assert_impl_all!(HttpQueue: Clone, Debug, Queue, Send, Sync);
+    assert_impl_all!(HttpQueueError: Error, Send, Sync);
+    assert_impl_all!(HttpQueueErrorType: Debug, Send, Sync);
+
+    #[test]
+    fn test_url() {
+        assert_eq!(
+            "http://localhost:8000",
+            HttpQueue::new("http://localhost:8000/").unwrap().url()
+        );
+    }
+
+    #[test]
+    fn test_url_invalid() {
+        assert!(matches!(
+            HttpQueue::new("queue.internal:8000").unwrap_err().kind(),
+            HttpQueueErrorType::UrlInvalid { .. }
+        ));
+        assert!(matches!(
+            HttpQueue::new("http://queue internal").unwrap_err().kind(),
+            HttpQueueErrorType::UrlInvalid { .. }
+        ));
+        assert!(matches!(
+            HttpQueue::new("https://queue.internal").unwrap_err().kind(),
+            HttpQueueErrorType::SchemeUnsupported { .. }
+        ));
+    }
+
+    /// Requests the server rejects are retried instead of granting allowance.
+    #[tokio::test]
+    async fn test_rejected() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let service_requests = Arc::clone(&requests);
+
+        // Reject the first request, granting allowance to the next one.
+        let service = make_service_fn(move |_: &AddrStream| {
+            let requests = Arc::clone(&service_requests);
+
+            async move {
+                Ok::<_, Infallible>(service_fn(move |_| {
+                    let status = if requests.fetch_add(1, Ordering::SeqCst) == 0 {
+                        StatusCode::NOT_FOUND
+                    } else {
+                        StatusCode::OK
+                    };
+
+                    let mut response = Response::new(Body::empty());
+                    *response.status_mut() = status;
+
+                    async move { Ok::<_, Infallible>(response) }
+                }))
+            }
+        });
+
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(service);
+        let queue = HttpQueue::new(format!("http://{}", server.local_addr())).unwrap();
+        tokio::spawn(server);
+
+        assert!(time::timeout(Duration::from_secs(5), queue.request([0, 1]))
+            .await
+            .is_ok());
+        assert_eq!(2, requests.load(Ordering::SeqCst));
+    }
+}
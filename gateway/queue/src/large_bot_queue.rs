@@ -1,12 +1,239 @@
-use super::{DayLimiter, Queue};
-use std::{fmt::Debug, future::Future, pin::Pin, time::Duration};
+use super::{
+    day_limiter::{DayLimiter, SessionLimits},
+    Queue,
+};
+use std::{
+    error::Error,
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 use tokio::{
     sync::{
         mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
         oneshot::{self, Sender},
+        watch,
     },
-    time::sleep,
+    task::JoinHandle,
+    time::{interval_at, sleep, Instant},
 };
+use twilight_http::Client;
+
+/// Creating a [`LargeBotQueue`] failed.
+#[derive(Debug)]
+pub struct LargeBotQueueError {
+    kind: LargeBotQueueErrorType,
+    source: Option<Box<dyn Error + Send + Sync>>,
+}
+
+impl LargeBotQueueError {
+    /// Immutable reference to the type of error that occurred.
+    #[must_use = "retrieving the type has no effect if left unused"]
+    pub const fn kind(&self) -> &LargeBotQueueErrorType {
+        &self.kind
+    }
+
+    /// Consume the error, returning the source error if there is any.
+    #[must_use = "consuming the error and retrieving the source has no effect if left unused"]
+    pub fn into_source(self) -> Option<Box<dyn Error + Send + Sync>> {
+        self.source
+    }
+
+    /// Consume the error, returning the owned error type and the source error.
+    #[must_use = "consuming the error into its parts has no effect if left unused"]
+    pub fn into_parts(self) -> (LargeBotQueueErrorType, Option<Box<dyn Error + Send + Sync>>) {
+        (self.kind, self.source)
+    }
+}
+
+impl Display for LargeBotQueueError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match &self.kind {
+            LargeBotQueueErrorType::RetrievingSessionAvailability => {
+                f.write_str("retrieving the bot's gateway session availability failed")
+            }
+        }
+    }
+}
+
+impl Error for LargeBotQueueError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| &**source as &(dyn Error + 'static))
+    }
+}
+
+/// Type of [`LargeBotQueueError`] that occurred.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum LargeBotQueueErrorType {
+    /// Retrieving the bot's available gateway session initiation information
+    /// via the HTTP API failed.
+    RetrievingSessionAvailability,
+}
+
+/// Event emitted by a [`LargeBotQueue`] about the bot's session start limit.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum LargeBotQueueEvent {
+    /// Number of remaining identifies before the next reset dropped below the
+    /// configured threshold.
+    ///
+    /// Emitted once each time the remaining identifies drop below the
+    /// threshold.
+    ///
+    /// Refer to [`LargeBotQueueBuilder::remaining_threshold`] to configure
+    /// the threshold.
+    RemainingLow {
+        /// Number of identifies remaining.
+        remaining: u64,
+        /// Time until the number of remaining identifies resets to the total.
+        reset_after: Duration,
+        /// Total number of identifies available per reset.
+        total: u64,
+    },
+}
+
+/// Builder to configure and construct a [`LargeBotQueue`].
+///
+/// # Examples
+///
+/// Create a queue warning when less than 50 identifies remain:
+///
+/// ```no_run
+/// use std::env;
+/// use twilight_gateway_queue::LargeBotQueue;
+/// use twilight_http::Client;
+///
+/// # #[tokio::main] async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// let http = Client::new(env::var("DISCORD_TOKEN")?);
+///
+/// let (queue, mut events) = LargeBotQueue::builder(http)
+///     .remaining_threshold(50)
+///     .build()
+///     .await?;
+///
+/// tokio::spawn(async move {
+///     while let Some(event) = events.recv().await {
+///         println!("{:?}", event);
+///     }
+/// });
+/// # Ok(()) }
+/// ```
+#[derive(Debug)]
+pub struct LargeBotQueueBuilder {
+    buckets: Option<usize>,
+    http: Client,
+    refresh_interval: Option<Duration>,
+    remaining_threshold: Option<u64>,
+}
+
+impl LargeBotQueueBuilder {
+    /// Default interval to refresh the session start limit at.
+    const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+    /// Create a new builder to configure and construct a [`LargeBotQueue`].
+    pub const fn new(http: Client) -> Self {
+        Self {
+            buckets: None,
+            http,
+            refresh_interval: Some(Self::DEFAULT_REFRESH_INTERVAL),
+            remaining_threshold: None,
+        }
+    }
+
+    /// Consume the builder, retrieving the bot's session start limit and
+    /// creating the queue.
+    ///
+    /// Returns the queue and a receiver of [`LargeBotQueueEvent`]s. Events
+    /// aren't buffered if the receiver is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`LargeBotQueueErrorType::RetrievingSessionAvailability`]
+    /// error type if retrieving the bot's session start limit failed.
+    pub async fn build(
+        self,
+    ) -> Result<(LargeBotQueue, UnboundedReceiver<LargeBotQueueEvent>), LargeBotQueueError> {
+        let info = self
+            .http
+            .gateway()
+            .authed()
+            .await
+            .map_err(|source| LargeBotQueueError {
+                kind: LargeBotQueueErrorType::RetrievingSessionAvailability,
+                source: Some(Box::new(source)),
+            })?;
+
+        let limits = SessionLimits::from(&info.session_start_limit);
+        // Warn when less than a tenth of the identifies remain by default.
+        let threshold = self.remaining_threshold.unwrap_or(limits.total / 10);
+        #[allow(clippy::cast_possible_truncation)]
+        let buckets = self
+            .buckets
+            .unwrap_or(info.session_start_limit.max_concurrency as usize);
+
+        let (tx, rx) = unbounded_channel();
+        let limiter = DayLimiter::new(limits, Some(self.http.clone()), threshold, tx);
+
+        tracing::info!(
+            "{}/{} identifies used before next reset in {:.2?}",
+            limits.total - limits.remaining,
+            limits.total,
+            limits.reset_after
+        );
+
+        let shared = Arc::new(Shared {
+            buckets: Mutex::new(Buckets::spawn(buckets, None)),
+            limiter,
+        });
+
+        let refresher = self.refresh_interval.map(|period| {
+            tokio::spawn(refresh(
+                Arc::clone(&shared),
+                self.http,
+                period,
+                self.buckets.is_none(),
+            ))
+        });
+
+        Ok((LargeBotQueue { refresher, shared }, rx))
+    }
+
+    /// Set the number of buckets shards identify in.
+    ///
+    /// Defaults to the `max_concurrency` of the bot's session start limit,
+    /// which is kept up to date when the limit is refreshed. Setting the
+    /// number of buckets fixes it.
+    pub const fn buckets(mut self, buckets: usize) -> Self {
+        self.buckets = Some(buckets);
+
+        self
+    }
+
+    /// Set the interval to refresh the bot's session start limit at through
+    /// the HTTP API, or `None` to only refresh it once it resets.
+    ///
+    /// Defaults to an hour.
+    pub const fn refresh_interval(mut self, refresh_interval: Option<Duration>) -> Self {
+        self.refresh_interval = refresh_interval;
+
+        self
+    }
+
+    /// Set the number of remaining identifies below which to emit a
+    /// [`LargeBotQueueEvent::RemainingLow`] event and log a warning.
+    ///
+    /// Defaults to a tenth of the total identifies available per reset.
+    pub const fn remaining_threshold(mut self, remaining_threshold: u64) -> Self {
+        self.remaining_threshold = Some(remaining_threshold);
+
+        self
+    }
+}
 
 /// Queue built for single-process clusters that require identifying via
 /// [Sharding for Very Large Bots].
@@ -19,53 +246,175 @@ use tokio::{
 /// [module-level]: crate
 #[derive(Debug)]
 pub struct LargeBotQueue {
-    buckets: Vec<UnboundedSender<Sender<()>>>,
+    refresher: Option<JoinHandle<()>>,
+    shared: Arc<Shared>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    buckets: Mutex<Buckets>,
     limiter: DayLimiter,
 }
 
+/// Waiters of the buckets shards identify in.
+#[derive(Debug)]
+struct Buckets {
+    /// Receiver notified once every waiter of the buckets has stopped.
+    stopped: watch::Receiver<()>,
+    /// Senders to queue requests in each bucket.
+    senders: Vec<UnboundedSender<Sender<()>>>,
+}
+
+impl Buckets {
+    /// Spawn a waiter for each bucket.
+    ///
+    /// If the buckets replace previous ones, the waiters only start granting
+    /// allowance once the previous waiters have stopped, so that no more
+    /// shards than there are buckets identify at once while switching.
+    fn spawn(buckets: usize, previous: Option<&Self>) -> Self {
+        let (stopped_tx, stopped) = watch::channel(());
+        // Waiters hold on to the sender, so it's dropped once they all stop.
+        let stopped_tx = Arc::new(stopped_tx);
+
+        let senders = (0..buckets.max(1))
+            .map(|_| {
+                let (tx, rx) = unbounded_channel();
+
+                tokio::spawn(waiter(
+                    rx,
+                    previous.map(|previous| previous.stopped.clone()),
+                    Arc::clone(&stopped_tx),
+                ));
+
+                tx
+            })
+            .collect();
+
+        Self { stopped, senders }
+    }
+
+    fn len(&self) -> usize {
+        self.senders.len()
+    }
+}
+
 impl LargeBotQueue {
     /// Create a new large bot queue.
     ///
     /// You must provide the number of buckets Discord requires your bot to
-    /// connect with.
-    pub async fn new(buckets: usize, http: &twilight_http::Client) -> Self {
-        let mut queues = Vec::with_capacity(buckets);
-        for _ in 0..buckets {
-            let (tx, rx) = unbounded_channel();
+    /// connect with. Use the [`builder`] to derive the number of buckets from
+    /// the bot's session start limit and to configure the queue further.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`LargeBotQueueErrorType::RetrievingSessionAvailability`]
+    /// error type if retrieving the bot's session start limit failed.
+    ///
+    /// [`builder`]: Self::builder
+    pub async fn new(buckets: usize, http: &Client) -> Result<Self, LargeBotQueueError> {
+        let (queue, _) = Self::builder(http.clone()).buckets(buckets).build().await?;
+
+        Ok(queue)
+    }
+
+    /// Create a builder to configure and construct a large bot queue.
+    pub const fn builder(http: Client) -> LargeBotQueueBuilder {
+        LargeBotQueueBuilder::new(http)
+    }
 
-            tokio::spawn(waiter(rx));
+    /// Create a new large bot queue from known session start limits.
+    ///
+    /// Unlike [`new`], the limits aren't retrieved or refreshed through the
+    /// HTTP API: once the daily limit resets, `total` identifies are assumed
+    /// to be available again for another day. This is useful when the limits
+    /// are provided by another source, or for testing.
+    ///
+    /// [`new`]: Self::new
+    pub fn with_limits(buckets: usize, total: u64, remaining: u64, reset_after: Duration) -> Self {
+        let limits = SessionLimits {
+            remaining,
+            reset_after,
+            total,
+        };
+        let (tx, _) = unbounded_channel();
 
-            queues.push(tx)
+        Self {
+            refresher: None,
+            shared: Arc::new(Shared {
+                buckets: Mutex::new(Buckets::spawn(buckets, None)),
+                limiter: DayLimiter::new(limits, None, 0, tx),
+            }),
         }
+    }
 
-        let limiter = DayLimiter::new(http).await.expect(
-            "Getting the first session limits failed, \
-             Is network connection available?",
-        );
+    /// Number of buckets shards identify in.
+    pub fn buckets(&self) -> usize {
+        self.shared.buckets.lock().expect("buckets poisoned").len()
+    }
+}
+
+impl Drop for LargeBotQueue {
+    fn drop(&mut self) {
+        if let Some(refresher) = self.refresher.take() {
+            refresher.abort();
+        }
+    }
+}
+
+/// Periodically refresh the session start limit, updating the number of
+/// buckets to its `max_concurrency` if `update_buckets` is set.
+async fn refresh(shared: Arc<Shared>, http: Client, period: Duration, update_buckets: bool) {
+    let mut interval = interval_at(Instant::now() + period, period);
+
+    loop {
+        interval.tick().await;
 
-        // The level_enabled macro does not turn off with the dynamic
-        // tracing levels. It is made for the static_max_level_xxx features
-        // And will return false if you do not use those features of if
-        // You use the feature but then dynamically set a lower feature.
-        if tracing::level_enabled!(tracing::Level::INFO) {
-            let lock = limiter.0.lock().await;
+        let info = match http.gateway().authed().await {
+            Ok(info) => info,
+            Err(source) => {
+                tracing::warn!("refreshing session start limit failed: {}", source);
+
+                continue;
+            }
+        };
+
+        shared
+            .limiter
+            .update(SessionLimits::from(&info.session_start_limit))
+            .await;
+
+        #[allow(clippy::cast_possible_truncation)]
+        let max_concurrency = info.session_start_limit.max_concurrency as usize;
+        let mut buckets = shared.buckets.lock().expect("buckets poisoned");
+
+        if update_buckets && buckets.len() != max_concurrency.max(1) {
             tracing::info!(
-                "{}/{} identifies used before next reset in {:.2?}",
-                lock.current,
-                lock.total,
-                lock.next_reset
+                "max concurrency changed from {} to {}",
+                buckets.len(),
+                max_concurrency
             );
-        }
 
-        Self {
-            buckets: queues,
-            limiter,
+            // Dropping the previous senders stops their waiters once they've
+            // granted the requests already queued in them.
+            *buckets = Buckets::spawn(max_concurrency, Some(&buckets));
         }
     }
 }
 
-async fn waiter(mut rx: UnboundedReceiver<Sender<()>>) {
+/// Grant the requests queued in a bucket allowance one at a time, starting
+/// once the waiters of the previous buckets have stopped.
+async fn waiter(
+    mut rx: UnboundedReceiver<Sender<()>>,
+    previous: Option<watch::Receiver<()>>,
+    _stopped: Arc<watch::Sender<()>>,
+) {
     const DUR: Duration = Duration::from_secs(6);
+
+    if let Some(mut previous) = previous {
+        // Errors once the previous waiters dropped their sender.
+        while previous.changed().await.is_ok() {}
+    }
+
     while let Some(req) = rx.recv().await {
         if let Err(err) = req.send(()) {
             tracing::warn!("skipping, send failed with: {:?}", err);
@@ -79,13 +428,21 @@ impl Queue for LargeBotQueue {
     /// request behind all other requests, and the returned future will resolve
     /// once the request has been completed.
     fn request(&'_ self, shard_id: [u64; 2]) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
-        #[allow(clippy::cast_possible_truncation)]
-        let bucket = (shard_id[0] % (self.buckets.len() as u64)) as usize;
         let (tx, rx) = oneshot::channel();
 
         Box::pin(async move {
-            self.limiter.get().await;
-            if let Err(err) = self.buckets[bucket].clone().send(tx) {
+            self.shared.limiter.get().await;
+
+            let bucket = {
+                let buckets = self.shared.buckets.lock().expect("buckets poisoned");
+
+                #[allow(clippy::cast_possible_truncation)]
+                let index = (shard_id[0] % (buckets.len() as u64)) as usize;
+
+                buckets.senders[index].clone()
+            };
+
+            if let Err(err) = bucket.send(tx) {
                 tracing::warn!("skipping, send failed with: {:?}", err);
                 return;
             }
@@ -99,9 +456,39 @@ impl Queue for LargeBotQueue {
 
 #[cfg(test)]
 mod tests {
-    use super::{LargeBotQueue, Queue};
+    use super::{
+        Buckets, LargeBotQueue, LargeBotQueueBuilder, LargeBotQueueError, LargeBotQueueErrorType,
+        LargeBotQueueEvent, Queue,
+    };
     use static_assertions::assert_impl_all;
-    use std::fmt::Debug;
+    use std::{error::Error, fmt::Debug, time::Duration};
+    use tokio::{sync::oneshot, time};
 
     assert_impl_all!(LargeBotQueue: Debug, Queue, Send, Sync);
+    assert_impl_all!(LargeBotQueueBuilder: Debug, Send, Sync);
+    assert_impl_all!(LargeBotQueueError: Error, Send, Sync);
+    assert_impl_all!(LargeBotQueueErrorType: Debug, Send, Sync);
+    assert_impl_all!(LargeBotQueueEvent: Clone, Debug, Eq, PartialEq, Send, Sync);
+
+    #[tokio::test]
+    async fn test_buckets_switch() {
+        time::pause();
+
+        let previous = Buckets::spawn(1, None);
+        let (tx, rx) = oneshot::channel();
+        previous.senders[0].send(tx).unwrap();
+        rx.await.unwrap();
+
+        let buckets = Buckets::spawn(2, Some(&previous));
+        drop(previous);
+
+        // The new buckets wait for the previous bucket's six seconds to pass.
+        let (tx, mut rx) = oneshot::channel();
+        buckets.senders[1].send(tx).unwrap();
+        time::sleep(Duration::from_secs(5)).await;
+        assert!(rx.try_recv().is_err());
+
+        time::sleep(Duration::from_secs(2)).await;
+        assert!(rx.try_recv().is_ok());
+    }
 }
@@ -30,14 +30,31 @@
 //! all so a [`Queue`] trait is provided that shards can use to make requests to
 //! create sessions.
 //!
+//! With the opt-in `http-queue` feature, the `QueueServer` is such a broker:
+//! it serves any queue, such as the [`LargeBotQueue`], over HTTP. Shards in
+//! every process can then use an `HttpQueue` pointing to the server to share
+//! its ratelimits.
+//!
 //! [Sharding for Very Large Bots]: https://discord.com/developers/docs/topics/gateway#sharding-for-very-large-bots
 
 mod day_limiter;
+#[cfg(feature = "http-queue")]
+mod http_queue;
 mod large_bot_queue;
+#[cfg(feature = "http-queue")]
+mod server;
+
+pub use self::large_bot_queue::{
+    LargeBotQueue, LargeBotQueueBuilder, LargeBotQueueError, LargeBotQueueErrorType,
+    LargeBotQueueEvent,
+};
 
-pub use large_bot_queue::LargeBotQueue;
+#[cfg(feature = "http-queue")]
+pub use self::{
+    http_queue::{HttpQueue, HttpQueueError, HttpQueueErrorType},
+    server::{QueueServer, QueueServerError, QueueServerErrorType},
+};
 
-use day_limiter::DayLimiter;
 use std::{fmt::Debug, future::Future, pin::Pin, time::Duration};
 use tokio::{
     sync::{
@@ -86,7 +103,8 @@ pub trait Queue: Debug + Send + Sync {
 /// [`LargeBotQueue`] can be used.
 ///
 /// If you can't use this, look into an alternative implementation of the
-/// [`Queue`], such as the [`gateway-queue`] broker.
+/// [`Queue`], such as the [`HttpQueue`] paired with a [`QueueServer`] or the
+/// [`gateway-queue`] broker.
 ///
 /// [`gateway-queue`]: https://github.com/twilight-rs/gateway-queue
 #[derive(Clone, Debug)]
@@ -0,0 +1,280 @@
+use super::Queue;
+use hyper::{
+    server::conn::AddrStream,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use std::{
+    convert::Infallible,
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    net::SocketAddr,
+    sync::Arc,
+};
+use tokio::task::JoinHandle;
+
+/// Starting a [`QueueServer`] failed.
+#[derive(Debug)]
+pub struct QueueServerError {
+    kind: QueueServerErrorType,
+    source: Option<Box<dyn Error + Send + Sync>>,
+}
+
+impl QueueServerError {
+    /// Immutable reference to the type of error that occurred.
+    #[must_use = "retrieving the type has no effect if left unused"]
+    pub const fn kind(&self) -> &QueueServerErrorType {
+        &self.kind
+    }
+
+    /// Consume the error, returning the source error if there is any.
+    #[must_use = "consuming the error and retrieving the source has no effect if left unused"]
+    pub fn into_source(self) -> Option<Box<dyn Error + Send + Sync>> {
+        self.source
+    }
+
+    /// Consume the error, returning the owned error type and the source error.
+    #[must_use = "consuming the error into its parts has no effect if left unused"]
+    pub fn into_parts(self) -> (QueueServerErrorType, Option<Box<dyn Error + Send + Sync>>) {
+        (self.kind, self.source)
+    }
+}
+
+impl Display for QueueServerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match &self.kind {
+            QueueServerErrorType::Binding { addr } => {
+                f.write_fmt(format_args!("binding the server to {} failed", addr))
+            }
+        }
+    }
+}
+
+impl Error for QueueServerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| &**source as &(dyn Error + 'static))
+    }
+}
+
+/// Type of [`QueueServerError`] that occurred.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum QueueServerErrorType {
+    /// Binding the server to the address failed.
+    Binding {
+        /// Address the server was bound to.
+        addr: SocketAddr,
+    },
+}
+
+/// HTTP server granting shards in multiple processes allowance to identify
+/// from a single [`Queue`].
+///
+/// Shards request allowance with an [`HttpQueue`] pointing to the server. The
+/// server receives requests in the form of `GET /?shard={id}&total={total}`
+/// and responds with an empty `200 OK` response once the queue grants the
+/// shard allowance to identify. The total number of shards is optional.
+///
+/// The server enforces the ratelimits of the queue it's given: with a
+/// [`LargeBotQueue`] it enforces the `max_concurrency` buckets and the daily
+/// session start limit of the bot.
+///
+/// The server stops when it's dropped.
+///
+/// # Examples
+///
+/// Serve a queue with 16 buckets on port 8000:
+///
+/// ```no_run
+/// use std::{env, net::SocketAddr, sync::Arc};
+/// use twilight_gateway_queue::{LargeBotQueue, QueueServer};
+/// use twilight_http::Client;
+///
+/// # #[tokio::main] async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// let http = Client::new(env::var("DISCORD_TOKEN")?);
+/// let queue = LargeBotQueue::new(16, &http).await?;
+///
+/// let addr = SocketAddr::from(([0, 0, 0, 0], 8000));
+/// let server = QueueServer::bind(addr, Arc::new(Box::new(queue))).await?;
+/// println!("serving the queue on {}", server.addr());
+///
+/// // Keep the server running.
+/// std::future::pending::<()>().await;
+/// # Ok(()) }
+/// ```
+///
+/// [`HttpQueue`]: crate::HttpQueue
+/// [`LargeBotQueue`]: crate::LargeBotQueue
+#[derive(Debug)]
+pub struct QueueServer {
+    addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl QueueServer {
+    /// Bind the server to an address and start serving requests.
+    ///
+    /// Use port 0 to bind to a random available port, which is then available
+    /// through [`addr`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`QueueServerErrorType::Binding`] error type if binding to
+    /// the address failed.
+    ///
+    /// [`addr`]: Self::addr
+    pub async fn bind(
+        addr: SocketAddr,
+        queue: Arc<Box<dyn Queue>>,
+    ) -> Result<Self, QueueServerError> {
+        let builder = Server::try_bind(&addr).map_err(|source| QueueServerError {
+            kind: QueueServerErrorType::Binding { addr },
+            source: Some(Box::new(source)),
+        })?;
+
+        let service = make_service_fn(move |_: &AddrStream| {
+            let queue = Arc::clone(&queue);
+
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    handle(Arc::clone(&queue), request)
+                }))
+            }
+        });
+
+        let server = builder.serve(service);
+        let addr = server.local_addr();
+
+        let task = tokio::spawn(async move {
+            if let Err(source) = server.await {
+                tracing::error!("queue server stopped: {}", source);
+            }
+        });
+
+        tracing::info!("queue server listening on {}", addr);
+
+        Ok(Self { addr, task })
+    }
+
+    /// Address the server is listening on.
+    pub const fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for QueueServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Wait for the queue to grant the shard of the request allowance to identify.
+async fn handle(
+    queue: Arc<Box<dyn Queue>>,
+    request: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    if request.method() != Method::GET {
+        return Ok(status(StatusCode::METHOD_NOT_ALLOWED));
+    }
+
+    let mut shard = None;
+    let mut total = None;
+
+    for pair in request.uri().query().unwrap_or_default().split('&') {
+        let mut parts = pair.splitn(2, '=');
+
+        match (parts.next(), parts.next().map(str::parse::<u64>)) {
+            (Some("shard"), Some(Ok(id))) => shard = Some(id),
+            (Some("total"), Some(Ok(value))) => total = Some(value),
+            _ => {}
+        }
+    }
+
+    let id = if let Some(id) = shard {
+        id
+    } else {
+        return Ok(status(StatusCode::BAD_REQUEST));
+    };
+
+    // The total is only informational, so requests without it are still
+    // served.
+    let total = total.unwrap_or(id + 1);
+
+    tracing::debug!("shard {}/{} requested allowance", id, total);
+    queue.request([id, total]).await;
+    tracing::debug!("shard {}/{} granted allowance", id, total);
+
+    Ok(status(StatusCode::OK))
+}
+
+fn status(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QueueServer, QueueServerError, QueueServerErrorType};
+    use crate::{HttpQueue, LargeBotQueue, Queue};
+    use hyper::{Client, StatusCode};
+    use static_assertions::assert_impl_all;
+    use std::{error::Error, fmt::Debug, net::SocketAddr, sync::Arc, time::Duration};
+    use tokio::time;
+
+    assert_impl_all!(QueueServer: Debug, Send, Sync);
+    assert_impl_all!(QueueServerError: Error, Send, Sync);
+    assert_impl_all!(QueueServerErrorType: Debug, Send, Sync);
+
+    async fn server(queue: LargeBotQueue) -> (QueueServer, HttpQueue) {
+        let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+        let server = QueueServer::bind(addr, Arc::new(Box::new(queue)))
+            .await
+            .unwrap();
+        let client = HttpQueue::new(format!("http://{}", server.addr())).unwrap();
+
+        (server, client)
+    }
+
+    /// Whether the queue grants the shard allowance within a second.
+    async fn granted(queue: &HttpQueue, shard: [u64; 2]) -> bool {
+        time::timeout(Duration::from_secs(1), queue.request(shard))
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn test_buckets() {
+        let queue = LargeBotQueue::with_limits(2, 1000, 1000, Duration::from_secs(3600));
+        let (_server, client) = server(queue).await;
+
+        assert!(granted(&client, [0, 4]).await);
+        assert!(granted(&client, [1, 4]).await);
+        // Shard 2 is in the same bucket as shard 0.
+        assert!(!granted(&client, [2, 4]).await);
+    }
+
+    #[tokio::test]
+    async fn test_session_start_limit() {
+        let queue = LargeBotQueue::with_limits(2, 1000, 1, Duration::from_secs(3600));
+        let (_server, client) = server(queue).await;
+
+        assert!(granted(&client, [0, 2]).await);
+        assert!(!granted(&client, [1, 2]).await);
+    }
+
+    #[tokio::test]
+    async fn test_shard_missing() {
+        let queue = LargeBotQueue::with_limits(1, 1000, 1000, Duration::from_secs(3600));
+        let (server, _) = server(queue).await;
+
+        let uri = format!("http://{}/?total=2", server.addr())
+            .parse()
+            .unwrap();
+        let response = Client::new().get(uri).await.unwrap();
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    }
+}
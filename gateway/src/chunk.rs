@@ -0,0 +1,571 @@
+//! Request guild members and collect the member chunks sent in response.
+//!
+//! Discord responds to a [`RequestGuildMembers`] command with one or more
+//! [`MemberChunk`] events, which are identified by the request's nonce. The
+//! [`MemberChunker`] generates the nonces, collects the chunks of each request
+//! as its events are [processed], and resolves a [`MemberChunkFuture`] with
+//! all of the requested members once every chunk has been received.
+//!
+//! It can also automatically request the members of every large guild the
+//! shards are in, so that caches are populated with their full member lists.
+//!
+//! [processed]: MemberChunker::process
+
+use crate::{
+    pending::{self, PendingRequests, Registration},
+    shard::{CommandError, Shard},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use twilight_model::{
+    gateway::{
+        event::Event,
+        payload::{MemberChunk, RequestGuildMembers},
+        presence::Presence,
+    },
+    guild::{Guild, Member},
+    id::{GuildId, UserId},
+};
+
+/// Collecting the member chunks of a request failed.
+///
+/// Returned by awaiting a [`MemberChunkFuture`].
+#[derive(Debug)]
+pub struct MemberChunkError {
+    kind: MemberChunkErrorType,
+    source: Option<Box<dyn Error + Send + Sync>>,
+}
+
+impl MemberChunkError {
+    /// Immutable reference to the type of error that occurred.
+    #[must_use = "retrieving the type has no effect if left unused"]
+    pub const fn kind(&self) -> &MemberChunkErrorType {
+        &self.kind
+    }
+
+    /// Consume the error, returning the source error if there is any.
+    #[must_use = "consuming the error and retrieving the source has no effect if left unused"]
+    pub fn into_source(self) -> Option<Box<dyn Error + Send + Sync>> {
+        self.source
+    }
+
+    /// Consume the error, returning the owned error type and the source error.
+    #[must_use = "consuming the error into its parts has no effect if left unused"]
+    pub fn into_parts(self) -> (MemberChunkErrorType, Option<Box<dyn Error + Send + Sync>>) {
+        (self.kind, self.source)
+    }
+}
+
+impl Display for MemberChunkError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match &self.kind {
+            MemberChunkErrorType::Cancelled => {
+                f.write_str("the member chunker was dropped before all chunks were received")
+            }
+            MemberChunkErrorType::SessionInvalidated => {
+                f.write_str("the shard started a new session before all chunks were received")
+            }
+        }
+    }
+}
+
+impl Error for MemberChunkError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| &**source as &(dyn Error + 'static))
+    }
+}
+
+/// Type of [`MemberChunkError`] that occurred.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum MemberChunkErrorType {
+    /// Member chunker was dropped before all chunks were received.
+    Cancelled,
+    /// Shard started a new session before all chunks were received, so the
+    /// remaining chunks will never be sent.
+    SessionInvalidated,
+}
+
+/// Members, presences, and user IDs not found, collected from all of the
+/// member chunks sent in response to a request.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GuildMembers {
+    /// ID of the guild the members are in.
+    pub guild_id: GuildId,
+    /// Members that were found.
+    pub members: Vec<Member>,
+    /// IDs of requested users that aren't members of the guild.
+    pub not_found: Vec<UserId>,
+    /// Presences of the members, if they were requested.
+    pub presences: Vec<Presence>,
+}
+
+/// Future resolving once all of the member chunks of a request have been
+/// received.
+///
+/// Returned by [`MemberChunker::request`]. The request is forgotten by the
+/// chunker when the future is dropped, such as when awaiting it timed out.
+#[derive(Debug)]
+pub struct MemberChunkFuture(Registration<String, Chunks, Result<GuildMembers, MemberChunkError>>);
+
+impl MemberChunkFuture {
+    /// Nonce the request was sent with.
+    pub fn nonce(&self) -> &str {
+        self.0.key()
+    }
+}
+
+impl Future for MemberChunkFuture {
+    type Output = Result<GuildMembers, MemberChunkError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.0.poll_recv(cx).map(|result| {
+            // The chunker was dropped.
+            result.unwrap_or(Err(MemberChunkError {
+                kind: MemberChunkErrorType::Cancelled,
+                source: None,
+            }))
+        })
+    }
+}
+
+/// Builder to configure and construct a [`MemberChunker`].
+#[derive(Debug, Default)]
+#[must_use = "has no effect if not built"]
+pub struct MemberChunkerBuilder {
+    chunk_large_guilds: bool,
+    presences: bool,
+}
+
+impl MemberChunkerBuilder {
+    /// Create a new builder to configure and construct a member chunker.
+    pub const fn new() -> Self {
+        Self {
+            chunk_large_guilds: false,
+            presences: false,
+        }
+    }
+
+    /// Consume the builder, constructing a member chunker.
+    pub fn build(self) -> MemberChunker {
+        MemberChunker(Arc::new(MemberChunkerRef {
+            chunk_large_guilds: self.chunk_large_guilds,
+            nonce: AtomicU64::new(0),
+            pending: Arc::new(PendingRequests::new()),
+            presences: self.presences,
+            queues: Mutex::new(HashMap::new()),
+        }))
+    }
+
+    /// Set whether to automatically request the members of every large guild.
+    ///
+    /// Large guilds only include online members when they're sent, so when
+    /// enabled the members of each large guild are requested once its
+    /// [`GuildCreate`] event is processed. Requests are sent one at a time
+    /// per shard and wait for the shard's [command ratelimit], leaving the
+    /// reserve for heartbeats untouched. The members are received as regular
+    /// [`MemberChunk`] events.
+    ///
+    /// Requesting all members requires the [`GUILD_MEMBERS`] intent.
+    ///
+    /// Defaults to false.
+    ///
+    /// [`GUILD_MEMBERS`]: crate::Intents::GUILD_MEMBERS
+    /// [`GuildCreate`]: twilight_model::gateway::payload::GuildCreate
+    /// [command ratelimit]: crate::shard::Shard::command
+    pub const fn chunk_large_guilds(mut self, chunk_large_guilds: bool) -> Self {
+        self.chunk_large_guilds = chunk_large_guilds;
+
+        self
+    }
+
+    /// Set whether to request the presences of the members of large guilds
+    /// requested [automatically].
+    ///
+    /// Requesting presences requires the [`GUILD_PRESENCES`] intent.
+    ///
+    /// Defaults to false.
+    ///
+    /// [`GUILD_PRESENCES`]: crate::Intents::GUILD_PRESENCES
+    /// [automatically]: Self::chunk_large_guilds
+    pub const fn presences(mut self, presences: bool) -> Self {
+        self.presences = presences;
+
+        self
+    }
+}
+
+/// Request guild members and collect the member chunks sent in response.
+///
+/// The chunker has to be passed every event received by the shards it sends
+/// requests over via [`process`], which is cheap for events other than member
+/// chunks. Cloning the chunker is cheap and clones share their state.
+///
+/// # Examples
+///
+/// Request all of the members of a guild:
+///
+/// ```no_run
+/// use futures::StreamExt;
+/// use std::env;
+/// use twilight_gateway::{chunk::MemberChunker, Intents, Shard};
+/// use twilight_model::{gateway::payload::RequestGuildMembers, id::GuildId};
+///
+/// # #[tokio::main] async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let token = env::var("DISCORD_TOKEN")?;
+/// let (shard, mut events) = Shard::new(token, Intents::GUILDS | Intents::GUILD_MEMBERS);
+/// shard.start().await?;
+///
+/// let chunker = MemberChunker::new();
+///
+/// let event_chunker = chunker.clone();
+/// let event_shard = shard.clone();
+/// tokio::spawn(async move {
+///     while let Some(event) = events.next().await {
+///         event_chunker.process(&event_shard, &event);
+///     }
+/// });
+///
+/// let request = RequestGuildMembers::builder(GuildId(1)).query("", None);
+/// let members = chunker.request(&shard, request).await?.await?;
+///
+/// println!("guild has {} members", members.members.len());
+/// # Ok(()) }
+/// ```
+///
+/// [`process`]: Self::process
+#[derive(Clone, Debug)]
+pub struct MemberChunker(Arc<MemberChunkerRef>);
+
+#[derive(Debug)]
+struct MemberChunkerRef {
+    chunk_large_guilds: bool,
+    nonce: AtomicU64,
+    pending: Arc<PendingRequests<String, Chunks, Result<GuildMembers, MemberChunkError>>>,
+    presences: bool,
+    /// Queues of the guilds to automatically request the members of, by shard
+    /// ID.
+    queues: Mutex<HashMap<u64, UnboundedSender<(Shard, GuildId)>>>,
+}
+
+/// Chunks of a request collected so far.
+#[derive(Debug)]
+struct Chunks {
+    members: GuildMembers,
+    /// Indices of the chunks that have been received.
+    received: HashSet<u32>,
+}
+
+impl MemberChunker {
+    /// Create a new member chunker with the default configuration.
+    ///
+    /// Use [`builder`] to automatically request the members of large guilds.
+    ///
+    /// [`builder`]: Self::builder
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
+
+    /// Create a new builder to configure and construct a member chunker.
+    pub const fn builder() -> MemberChunkerBuilder {
+        MemberChunkerBuilder::new()
+    }
+
+    /// Send a request for guild members over a shard, returning a future
+    /// resolving once all of the member chunks have been received.
+    ///
+    /// The request's nonce is replaced by a nonce generated by the chunker.
+    ///
+    /// Discord doesn't respond to requests the shard lacks the intents for,
+    /// in which case the future never resolves, so consider awaiting it with
+    /// a timeout.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CommandError`] if sending the request failed.
+    pub async fn request(
+        &self,
+        shard: &Shard,
+        mut request: RequestGuildMembers,
+    ) -> Result<MemberChunkFuture, CommandError> {
+        let session_id = pending::session_id(shard);
+        let future = self.register(shard.config().shard()[0], session_id, request.d.guild_id);
+        request.d.nonce = Some(future.nonce().to_owned());
+
+        // Dropping the future removes the request if sending it failed.
+        shard.command(&request).await?;
+
+        Ok(future)
+    }
+
+    /// Process an event received by a shard.
+    ///
+    /// Member chunks are collected into their requests, a [`Ready`] event
+    /// fails the requests made during the shard's previous session, and large
+    /// guilds are queued to be requested if [enabled].
+    ///
+    /// # Panics
+    ///
+    /// Panics if large guilds are automatically requested and this isn't
+    /// called within a Tokio runtime.
+    ///
+    /// [`Ready`]: twilight_model::gateway::payload::Ready
+    /// [enabled]: MemberChunkerBuilder::chunk_large_guilds
+    pub fn process(&self, shard: &Shard, event: &Event) {
+        match event {
+            Event::GuildCreate(guild) if self.0.chunk_large_guilds => self.queue(shard, &guild.0),
+            Event::MemberChunk(chunk) => self.chunk(chunk),
+            Event::Ready(ready) => self.invalidate(shard.config().shard()[0], &ready.session_id),
+            _ => {}
+        }
+    }
+
+    /// Register a new request, generating its nonce.
+    fn register(
+        &self,
+        shard_id: u64,
+        session_id: Option<String>,
+        guild_id: GuildId,
+    ) -> MemberChunkFuture {
+        // Nonces are limited to 32 bytes.
+        let nonce = format!("chunk-{:x}", self.0.nonce.fetch_add(1, Ordering::Relaxed));
+
+        let chunks = Chunks {
+            members: GuildMembers {
+                guild_id,
+                members: Vec::new(),
+                not_found: Vec::new(),
+                presences: Vec::new(),
+            },
+            received: HashSet::new(),
+        };
+        let (registration, _) = self.0.pending.register(shard_id, session_id, nonce, chunks);
+
+        MemberChunkFuture(registration)
+    }
+
+    /// Collect a member chunk into its request, completing the request if it
+    /// was the last chunk.
+    fn chunk(&self, chunk: &MemberChunk) {
+        let nonce = match chunk.nonce.as_deref() {
+            Some(nonce) => nonce,
+            None => return,
+        };
+
+        let mut pending = self.0.pending.lock();
+
+        let chunks = match pending.get_mut(nonce) {
+            Some(request) => &mut request.state,
+            None => return,
+        };
+
+        // Ignore chunks that have already been received.
+        if !chunks.received.insert(chunk.chunk_index) {
+            return;
+        }
+
+        chunks.members.members.extend_from_slice(&chunk.members);
+        chunks.members.not_found.extend_from_slice(&chunk.not_found);
+        chunks.members.presences.extend_from_slice(&chunk.presences);
+
+        let chunk_count = usize::try_from(chunk.chunk_count).unwrap_or(usize::MAX);
+
+        if chunks.received.len() < chunk_count {
+            return;
+        }
+
+        if let Some(request) = pending.remove(nonce) {
+            request.resolve_with(|chunks| {
+                tracing::debug!(
+                    chunks = chunk_count,
+                    guild_id = %chunks.members.guild_id,
+                    members = chunks.members.members.len(),
+                    nonce,
+                    "received all member chunks",
+                );
+
+                Ok(chunks.members)
+            });
+        }
+    }
+
+    /// Fail the requests sent over a shard during a session other than the
+    /// current one.
+    fn invalidate(&self, shard_id: u64, session_id: &str) {
+        for request in self.0.pending.invalidate(shard_id, session_id) {
+            request.resolve(Err(MemberChunkError {
+                kind: MemberChunkErrorType::SessionInvalidated,
+                source: None,
+            }));
+        }
+    }
+
+    /// Queue a guild to request its members if it's large and not all of its
+    /// members were sent.
+    fn queue(&self, shard: &Shard, guild: &Guild) {
+        let complete = guild.member_count.map_or(false, |member_count| {
+            u64::try_from(guild.members.len()).map_or(false, |len| len >= member_count)
+        });
+
+        if !guild.large || complete {
+            return;
+        }
+
+        let shard_id = shard.config().shard()[0];
+        let mut queues = self.0.queues.lock().expect("queues poisoned");
+
+        let tx = queues.entry(shard_id).or_insert_with(|| {
+            let (tx, rx) = mpsc::unbounded_channel();
+            tokio::spawn(request_large_guilds(rx, self.0.presences));
+
+            tx
+        });
+
+        if tx.send((shard.clone(), guild.id)).is_err() {
+            tracing::warn!(shard_id, guild_id = %guild.id, "large guild queue closed");
+        }
+    }
+}
+
+impl Default for MemberChunker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Request the members of queued large guilds one at a time, until the
+/// chunker is dropped.
+async fn request_large_guilds(mut rx: UnboundedReceiver<(Shard, GuildId)>, presences: bool) {
+    while let Some((shard, guild_id)) = rx.recv().await {
+        let request = RequestGuildMembers::builder(guild_id)
+            .presences(presences)
+            .query("", None);
+
+        if let Err(source) = shard.command(&request).await {
+            tracing::warn!(
+                guild_id = %guild_id,
+                shard_id = shard.config().shard()[0],
+                "requesting members of large guild failed: {}",
+                source,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        GuildMembers, MemberChunkError, MemberChunkErrorType, MemberChunkFuture, MemberChunker,
+        MemberChunkerBuilder,
+    };
+    use crate::{
+        shard::{CommandErrorType, Shard},
+        Intents,
+    };
+    use static_assertions::assert_impl_all;
+    use std::{error::Error, fmt::Debug, future::Future};
+    use twilight_model::{
+        gateway::{
+            event::Event,
+            payload::{MemberChunk, RequestGuildMembers},
+        },
+        id::{GuildId, UserId},
+    };
+
+    assert_impl_all!(GuildMembers: Clone, Debug, Eq, PartialEq, Send, Sync);
+    assert_impl_all!(MemberChunkError: Error, Send, Sync);
+    assert_impl_all!(MemberChunkErrorType: Debug, Send, Sync);
+    assert_impl_all!(MemberChunkFuture: Debug, Future, Send, Sync);
+    assert_impl_all!(MemberChunker: Clone, Debug, Default, Send, Sync);
+    assert_impl_all!(MemberChunkerBuilder: Debug, Default, Send, Sync);
+
+    fn chunk(nonce: &str, chunk_index: u32, not_found: u64) -> Event {
+        Event::MemberChunk(MemberChunk {
+            chunk_count: 2,
+            chunk_index,
+            guild_id: GuildId(1),
+            members: Vec::new(),
+            nonce: Some(nonce.to_owned()),
+            not_found: vec![UserId(not_found)],
+            presences: Vec::new(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_collect_chunks() {
+        let (shard, _) = Shard::new("token", Intents::empty());
+        let chunker = MemberChunker::new();
+        let future = chunker.register(0, None, GuildId(1));
+        let nonce = future.nonce().to_owned();
+
+        // Chunks may arrive out of order, and chunks of other requests and
+        // duplicate chunks are ignored.
+        chunker.process(&shard, &chunk(&nonce, 1, 2));
+        chunker.process(&shard, &chunk("other", 0, 4));
+        chunker.process(&shard, &chunk(&nonce, 1, 2));
+        chunker.process(&shard, &chunk(&nonce, 0, 3));
+
+        let members = future.await.unwrap();
+        assert_eq!(GuildId(1), members.guild_id);
+        assert_eq!(vec![UserId(2), UserId(3)], members.not_found);
+        assert!(chunker.0.pending.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_nonces_unique() {
+        let chunker = MemberChunker::new();
+        let first = chunker.register(0, None, GuildId(1));
+        let second = chunker.register(0, None, GuildId(1));
+
+        assert_ne!(first.nonce(), second.nonce());
+        assert!(first.nonce().len() <= 32);
+    }
+
+    #[tokio::test]
+    async fn test_invalidate() {
+        let chunker = MemberChunker::new();
+        let previous = chunker.register(0, Some("a".to_owned()), GuildId(1));
+        let current = chunker.register(0, Some("b".to_owned()), GuildId(1));
+        let other_shard = chunker.register(1, Some("a".to_owned()), GuildId(2));
+
+        chunker.invalidate(0, "b");
+
+        assert!(matches!(
+            previous.await.unwrap_err().kind(),
+            MemberChunkErrorType::SessionInvalidated
+        ));
+        assert_eq!(2, chunker.0.pending.lock().len());
+
+        drop(chunker);
+        assert!(matches!(
+            current.await.unwrap_err().kind(),
+            MemberChunkErrorType::Cancelled
+        ));
+        assert!(other_shard.await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_request_session_inactive() {
+        let (shard, _) = Shard::new("token", Intents::empty());
+        let chunker = MemberChunker::new();
+        let request = RequestGuildMembers::builder(GuildId(1)).query("", None);
+
+        let error = chunker.request(&shard, request).await.unwrap_err();
+        assert!(matches!(error.kind(), CommandErrorType::SessionInactive));
+        assert!(chunker.0.pending.lock().is_empty());
+    }
+}
@@ -4,14 +4,23 @@ use super::{
     scheme::ShardScheme,
 };
 use crate::{
-    shard::{LargeThresholdError, ResumeSession, ShardBuilder},
+    shard::{
+        recording::Recorder, Encoding, HealthPolicy, LargeThresholdError, OverflowPolicy, Proxy,
+        ResumeSession, ShardBuilder, TlsConnector, TransportCompression,
+    },
     Event, EventTypeFlags,
 };
 use futures_util::stream::Stream;
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 use twilight_gateway_queue::{LocalQueue, Queue};
 use twilight_http::Client;
-use twilight_model::gateway::{payload::update_presence::UpdatePresencePayload, Intents};
+use twilight_model::{
+    gateway::{
+        payload::{identify::IdentifyProperties, update_presence::UpdatePresencePayload},
+        Intents,
+    },
+    id::GuildId,
+};
 
 /// Builder to configure and construct a [`Cluster`].
 ///
@@ -97,6 +106,28 @@ impl ClusterBuilder {
         Cluster::new_with_config(self.0).await
     }
 
+    /// Set the encoding of the payloads exchanged with the gateway.
+    ///
+    /// Refer to the shard's [`ShardBuilder::encoding`] for more information.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.1 = self.1.encoding(encoding);
+
+        self
+    }
+
+    /// Bound the buffer of events each shard's event stream hasn't received
+    /// yet.
+    ///
+    /// Refer to the shard's [`ShardBuilder::event_buffer`] for more
+    /// information.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn event_buffer(mut self, capacity: usize, policy: OverflowPolicy) -> Self {
+        self.1 = self.1.event_buffer(capacity, policy);
+
+        self
+    }
+
     /// Set the event types to process.
     ///
     /// This is an optimization technique; all events not included in the
@@ -119,6 +150,43 @@ impl ClusterBuilder {
         self
     }
 
+    /// Set a predicate deciding which guilds' events to emit.
+    ///
+    /// Refer to the shard's [`ShardBuilder::guild_filter`] for more
+    /// information.
+    pub fn guild_filter(
+        mut self,
+        predicate: impl Fn(GuildId) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.1 = self.1.guild_filter(predicate);
+
+        self
+    }
+
+    /// Set how long the shards wait for the guilds listed in their ready events
+    /// before considering themselves fully ready.
+    ///
+    /// Refer to the shard's [`ShardBuilder::guild_ready_timeout`] for more
+    /// information.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn guild_ready_timeout(mut self, guild_ready_timeout: Duration) -> Self {
+        self.1 = self.1.guild_ready_timeout(guild_ready_timeout);
+
+        self
+    }
+
+    /// Set the conditions under which the shards' connections are considered
+    /// unhealthy.
+    ///
+    /// Refer to the shard's [`ShardBuilder::health_policy`] for more
+    /// information.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn health_policy(mut self, health_policy: HealthPolicy) -> Self {
+        self.1 = self.1.health_policy(health_policy);
+
+        self
+    }
+
     /// Set the `twilight_http` Client used by the cluster and the shards it
     /// manages.
     ///
@@ -153,6 +221,16 @@ impl ClusterBuilder {
         Ok(self)
     }
 
+    /// Set the properties the shards identify with.
+    ///
+    /// Refer to the shard's [`ShardBuilder::identify_properties`] for more
+    /// information.
+    pub fn identify_properties(mut self, identify_properties: IdentifyProperties) -> Self {
+        self.1 = self.1.identify_properties(identify_properties);
+
+        self
+    }
+
     /// Set the presence to use when identifying with the gateway.
     ///
     /// Refer to the shard's [`ShardBuilder::presence`] for more information.
@@ -162,6 +240,31 @@ impl ClusterBuilder {
         self
     }
 
+    /// Set the proxy each shard connects to the gateway through.
+    ///
+    /// Refer to the shard's [`ShardBuilder::proxy`] for more information.
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.1 = self.1.proxy(proxy);
+
+        self
+    }
+
+    /// Set a function returning the presence each shard sets when
+    /// identifying, given the shard's ID.
+    ///
+    /// This allows the shards to have different presences.
+    ///
+    /// Refer to the shard's [`ShardBuilder::shard_presence`] for more
+    /// information.
+    pub fn shard_presence(
+        mut self,
+        shard_presence: impl Fn(u64) -> Option<UpdatePresencePayload> + Send + Sync + 'static,
+    ) -> Self {
+        self.1 = self.1.shard_presence(shard_presence);
+
+        self
+    }
+
     /// Set the scheme to use for shard managing.
     ///
     /// For example, [`ShardScheme::Auto`] means that the cluster will
@@ -215,6 +318,16 @@ impl ClusterBuilder {
         self
     }
 
+    /// Set the recorder to write the payloads the shards exchange with the
+    /// gateway to.
+    ///
+    /// Refer to the shard's [`ShardBuilder::recorder`] for more information.
+    pub fn recorder(mut self, recorder: Recorder) -> Self {
+        self.1 = self.1.recorder(recorder);
+
+        self
+    }
+
     /// Set the session information to resume shards with.
     ///
     /// This requires having recovered the resume data when shutting down the
@@ -228,6 +341,27 @@ impl ClusterBuilder {
         self.0.resume_sessions = resume_sessions;
         self
     }
+
+    /// Set the TLS connector each shard connects to the gateway with.
+    ///
+    /// Refer to the shard's [`ShardBuilder::tls`] for more information.
+    pub fn tls(mut self, tls: TlsConnector) -> Self {
+        self.1 = self.1.tls(tls);
+
+        self
+    }
+
+    /// Set the compression of the messages each shard receives from the
+    /// gateway.
+    ///
+    /// Refer to the shard's [`ShardBuilder::transport_compression`] for more
+    /// information.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn transport_compression(mut self, transport_compression: TransportCompression) -> Self {
+        self.1 = self.1.transport_compression(transport_compression);
+
+        self
+    }
 }
 
 impl<T: Into<String>> From<(T, Intents)> for ClusterBuilder {
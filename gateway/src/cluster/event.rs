@@ -0,0 +1,644 @@
+//! Stream of events from all of a cluster's shards.
+//!
+//! The stream can swap the set of shards it receives events from while the
+//! cluster is resharding, deduplicating events received by both the old and
+//! new set of shards during the overlap.
+//!
+//! Once every shard of the current set is fully ready, the stream yields an
+//! [`Event::ClusterReady`].
+
+use crate::{shard::Events, EventTypeFlags};
+use futures_util::{
+    future::FutureExt,
+    stream::{SelectAll, Stream, StreamExt},
+};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, VecDeque},
+    fmt::{Result as FmtResult, Write},
+    hash::Hasher,
+    mem,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::{
+    sync::mpsc::UnboundedReceiver,
+    time::{sleep, Sleep},
+};
+use twilight_model::{
+    gateway::event::{
+        shard::{ClusterReady, FullyReady},
+        Event, EventType,
+    },
+    id::GuildId,
+};
+
+/// Minimum period events are deduplicated for after a swap.
+///
+/// The events of the old set of shards are received until their streams end,
+/// which happens once the old shards have been shut down and their buffered
+/// events have been yielded. Deduplication continues until then, and for at
+/// least this period after the swap.
+const OVERLAP_PERIOD: Duration = Duration::from_secs(5);
+
+/// Maximum number of events remembered during the overlap to deduplicate.
+const RECENT_CAPACITY: usize = 1000;
+
+/// Stream of events from a single shard, tagged with the shard's ID.
+pub struct ShardEventStream {
+    events: Events,
+    id: u64,
+}
+
+impl ShardEventStream {
+    pub const fn new(id: u64, events: Events) -> Self {
+        Self { events, id }
+    }
+}
+
+impl Stream for ShardEventStream {
+    type Item = (u64, Event);
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let id = self.id;
+
+        self.events
+            .poll_next_unpin(cx)
+            .map(|event| event.map(|e| (id, e)))
+    }
+}
+
+/// Shards of a generation that are fully ready.
+struct Readiness {
+    /// Whether the cluster ready event was yielded since the last shard became
+    /// fully ready.
+    emitted: bool,
+    ready: HashMap<u64, FullyReady>,
+    shards: Vec<u64>,
+}
+
+impl Readiness {
+    /// Update the readiness of a shard with an event it emitted, returning the
+    /// cluster ready event if it was the last shard to become fully ready.
+    fn process(&mut self, id: u64, event: &Event) -> Option<Event> {
+        match event {
+            // The shard started a new session and loads its guilds again.
+            Event::Ready(_) => {
+                if self.ready.remove(&id).is_some() {
+                    self.emitted = false;
+                }
+
+                None
+            }
+            Event::ShardFullyReady(fully_ready) => {
+                self.ready.insert(id, fully_ready.clone());
+
+                self.complete()
+            }
+            _ => None,
+        }
+    }
+
+    /// Return the cluster ready event if every shard is fully ready and it
+    /// hasn't been yielded yet.
+    fn complete(&mut self) -> Option<Event> {
+        if self.emitted || self.ready.len() < self.shards.len() {
+            return None;
+        }
+
+        self.emitted = true;
+
+        let mut guilds_missing = self
+            .ready
+            .values()
+            .flat_map(|fully_ready| fully_ready.guilds_missing.iter().copied())
+            .collect::<Vec<_>>();
+        guilds_missing.sort_unstable();
+
+        Some(Event::ClusterReady(ClusterReady {
+            guilds_expected: self
+                .ready
+                .values()
+                .map(|fully_ready| fully_ready.guilds_expected)
+                .sum(),
+            guilds_missing,
+            shards: self.shards.clone(),
+        }))
+    }
+}
+
+/// Set of shards the cluster receives events from.
+pub struct Generation {
+    /// Event types to pass through, if the shards receive more event types
+    /// than the cluster was configured with.
+    event_types: Option<EventTypeFlags>,
+    readiness: Readiness,
+    streams: SelectAll<ShardEventStream>,
+}
+
+impl Generation {
+    pub fn new(
+        streams: impl IntoIterator<Item = ShardEventStream>,
+        event_types: Option<EventTypeFlags>,
+    ) -> Self {
+        let streams = streams.into_iter().collect::<Vec<_>>();
+        let mut shards = streams.iter().map(|stream| stream.id).collect::<Vec<_>>();
+        shards.sort_unstable();
+
+        Self {
+            event_types,
+            readiness: Readiness {
+                emitted: false,
+                ready: HashMap::new(),
+                shards,
+            },
+            streams: streams.into_iter().collect(),
+        }
+    }
+
+    /// Mark a shard as fully ready before its events are received, such as
+    /// when it was waited on while resharding.
+    pub fn fully_ready(&mut self, id: u64, fully_ready: FullyReady) {
+        self.readiness.ready.insert(id, fully_ready);
+    }
+
+    fn allows(&self, event: &Event) -> bool {
+        self.event_types.map_or(true, |event_types| {
+            event_types.contains(event.kind().into())
+        })
+    }
+}
+
+/// Key identifying a dispatch event during the overlap, and whether it was
+/// received by the previous generation.
+///
+/// The shards of both generations have their own sessions and sequence
+/// numbers, so events are told apart by their type, guild, and a hash of
+/// their contents.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct RecentEvent {
+    guild_id: Option<GuildId>,
+    hash: u64,
+    kind: EventType,
+    previous: bool,
+}
+
+impl RecentEvent {
+    fn new(previous: bool, event: &Event) -> Self {
+        let mut hasher = DefaultHasher::new();
+        // Events don't implement `Hash`, so their debug representation is
+        // hashed as it's written instead.
+        let _res = write!(HashWriter(&mut hasher), "{:?}", event);
+
+        Self {
+            guild_id: event.guild_id(),
+            hash: hasher.finish(),
+            kind: event.kind(),
+            previous,
+        }
+    }
+}
+
+/// Writer feeding what's written to a hasher.
+struct HashWriter<'a>(&'a mut DefaultHasher);
+
+impl Write for HashWriter<'_> {
+    fn write_str(&mut self, s: &str) -> FmtResult {
+        self.0.write(s.as_bytes());
+
+        Ok(())
+    }
+}
+
+/// Events of the previous generation being received after a swap.
+struct Overlap {
+    deadline: Pin<Box<Sleep>>,
+    previous: Option<Generation>,
+    /// Dispatch events recently yielded, mapped to the ID they were last
+    /// remembered with.
+    recent: HashMap<RecentEvent, u64>,
+    /// Recently yielded dispatch events and their IDs, oldest first.
+    order: VecDeque<(u64, RecentEvent)>,
+    /// ID of the next remembered event.
+    next_id: u64,
+}
+
+impl Overlap {
+    fn new(previous: Generation) -> Self {
+        Self {
+            deadline: Box::pin(sleep(OVERLAP_PERIOD)),
+            previous: Some(previous),
+            recent: HashMap::new(),
+            order: VecDeque::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Whether the event was already received by the other generation,
+    /// remembering it if it wasn't.
+    fn is_duplicate(&mut self, previous: bool, event: &Event) -> bool {
+        if !is_dispatch(event) {
+            return false;
+        }
+
+        let recent = RecentEvent::new(previous, event);
+
+        let other = RecentEvent {
+            previous: !previous,
+            ..recent
+        };
+
+        // Its entry in the eviction order is left behind and ignored once
+        // evicted.
+        if self.recent.remove(&other).is_some() {
+            return true;
+        }
+
+        if self.order.len() == RECENT_CAPACITY {
+            if let Some((id, evicted)) = self.order.pop_front() {
+                // The event may have been remembered again since.
+                if self.recent.get(&evicted) == Some(&id) {
+                    self.recent.remove(&evicted);
+                }
+            }
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.recent.insert(recent, id);
+        self.order.push_back((id, recent));
+
+        false
+    }
+}
+
+/// Stream of events from the shards of a cluster.
+pub struct ClusterEvents {
+    current: Generation,
+    overlap: Option<Overlap>,
+    /// Cluster ready event to yield after the event that completed it.
+    pending: Option<(u64, Event)>,
+    swaps: UnboundedReceiver<Generation>,
+}
+
+impl ClusterEvents {
+    pub const fn new(current: Generation, swaps: UnboundedReceiver<Generation>) -> Self {
+        Self {
+            current,
+            overlap: None,
+            pending: None,
+            swaps,
+        }
+    }
+
+    /// Swap to a new generation of shards, still receiving the events of the
+    /// current one until its streams end.
+    fn swap(&mut self, generation: Generation) {
+        let previous = mem::replace(&mut self.current, generation);
+
+        // The shards of a new generation are usually fully ready already.
+        self.pending = self
+            .current
+            .readiness
+            .complete()
+            .filter(|event| self.current.allows(event))
+            .and_then(|event| Some((*self.current.readiness.shards.first()?, event)));
+
+        self.overlap = Some(Overlap::new(previous));
+    }
+}
+
+impl Stream for ClusterEvents {
+    type Item = (u64, Event);
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        while let Poll::Ready(Some(generation)) = self.swaps.poll_recv(cx) {
+            self.swap(generation);
+        }
+
+        if let Some(item) = self.pending.take() {
+            return Poll::Ready(Some(item));
+        }
+
+        if let Some(overlap) = self.overlap.as_mut() {
+            if overlap.previous.is_none() && overlap.deadline.poll_unpin(cx).is_ready() {
+                self.overlap.take();
+            }
+        }
+
+        // Events of the previous generation were received first, so yield
+        // them before those of the current generation.
+        while let Some(overlap) = self.overlap.as_mut() {
+            let polled = match overlap.previous.as_mut() {
+                Some(previous) => previous
+                    .streams
+                    .poll_next_unpin(cx)
+                    .map(|item| item.map(|(id, event)| (id, previous.allows(&event), event))),
+                None => break,
+            };
+
+            match polled {
+                Poll::Ready(Some((id, allowed, event))) => {
+                    if allowed && !overlap.is_duplicate(true, &event) {
+                        return Poll::Ready(Some((id, event)));
+                    }
+                }
+                Poll::Ready(None) => {
+                    overlap.previous.take();
+                }
+                Poll::Pending => break,
+            }
+        }
+
+        loop {
+            let (id, event) = match self.current.streams.poll_next_unpin(cx) {
+                Poll::Ready(Some(item)) => item,
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            let cluster_ready = self
+                .current
+                .readiness
+                .process(id, &event)
+                .filter(|cluster_ready| self.current.allows(cluster_ready));
+
+            let allowed = self.current.allows(&event)
+                && !self
+                    .overlap
+                    .as_mut()
+                    .map_or(false, |overlap| overlap.is_duplicate(false, &event));
+
+            if allowed {
+                self.pending = cluster_ready.map(|cluster_ready| (id, cluster_ready));
+
+                return Poll::Ready(Some((id, event)));
+            }
+
+            if let Some(cluster_ready) = cluster_ready {
+                return Poll::Ready(Some((id, cluster_ready)));
+            }
+        }
+    }
+}
+
+/// Whether an event was dispatched by Discord, as opposed to being about the
+/// state of a shard's connection.
+const fn is_dispatch(event: &Event) -> bool {
+    !matches!(
+        event,
+        Event::ClusterReady(_)
+            | Event::GatewayHeartbeat(_)
+            | Event::GatewayHeartbeatAck
+            | Event::GatewayHello(_)
+            | Event::GatewayInvalidateSession(_)
+            | Event::GatewayReconnect
+            | Event::ShardConnected(_)
+            | Event::ShardConnecting(_)
+            | Event::ShardDisconnected(_)
+            | Event::ShardFullyReady(_)
+            | Event::ShardIdentifying(_)
+            | Event::ShardReconnecting(_)
+            | Event::ShardPayload(_)
+            | Event::ShardResuming(_)
+            | Event::ShardUnhealthy(_)
+    )
+}
+
+/// Discard the events a shard has received so far.
+pub fn discard_received(events: &mut Events) {
+    while let Some(Some(_)) = events.next().now_or_never() {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ClusterEvents, Generation, Overlap, ShardEventStream, OVERLAP_PERIOD, RECENT_CAPACITY,
+    };
+    use crate::{
+        shard::{
+            channel::{self, Sender},
+            Events,
+        },
+        EventTypeFlags,
+    };
+    use futures_util::{future::FutureExt, stream::StreamExt};
+    use static_assertions::assert_impl_all;
+    use tokio::{sync::mpsc, time};
+    use twilight_model::{
+        gateway::{
+            event::{
+                shard::{ClusterReady, FullyReady},
+                Event,
+            },
+            payload::RoleDelete,
+        },
+        id::{GuildId, RoleId},
+    };
+
+    assert_impl_all!(ClusterEvents: Send, Sync, Unpin);
+
+    fn events() -> (Sender<Event>, Events) {
+        let (tx, rx) = channel::channel(None);
+
+        (tx, Events::new(EventTypeFlags::all(), rx))
+    }
+
+    fn role_delete(role_id: u64) -> Event {
+        Event::RoleDelete(RoleDelete {
+            guild_id: GuildId(1),
+            role_id: RoleId(role_id),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_swap_deduplicates_overlap() {
+        let (old_tx, old_events) = events();
+        let (new_tx, new_events) = events();
+        let (swap_tx, swap_rx) = mpsc::unbounded_channel();
+
+        let mut stream = ClusterEvents::new(
+            Generation::new(vec![ShardEventStream::new(0, old_events)], None),
+            swap_rx,
+        );
+
+        old_tx.send(role_delete(1)).unwrap();
+        assert_eq!(Some((0, role_delete(1))), stream.next().await);
+
+        swap_tx
+            .send(Generation::new(
+                vec![ShardEventStream::new(0, new_events)],
+                None,
+            ))
+            .unwrap();
+
+        // Both generations received the event, so it's only yielded once.
+        new_tx.send(role_delete(2)).unwrap();
+        old_tx.send(role_delete(2)).unwrap();
+        assert_eq!(Some((0, role_delete(2))), stream.next().await);
+
+        // Connection events aren't deduplicated.
+        new_tx.send(Event::GatewayHeartbeatAck).unwrap();
+        old_tx.send(Event::GatewayHeartbeatAck).unwrap();
+        assert_eq!(Some((0, Event::GatewayHeartbeatAck)), stream.next().await);
+        assert_eq!(Some((0, Event::GatewayHeartbeatAck)), stream.next().await);
+
+        new_tx.send(role_delete(3)).unwrap();
+        assert_eq!(Some((0, role_delete(3))), stream.next().await);
+        assert!(stream.next().now_or_never().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_overlap_evicts_oldest() {
+        let mut overlap = Overlap::new(Generation::new(Vec::new(), None));
+
+        assert!(!overlap.is_duplicate(true, &role_delete(0)));
+        assert!(!overlap.is_duplicate(true, &role_delete(1)));
+        // The event is remembered again after its duplicate was received.
+        assert!(overlap.is_duplicate(false, &role_delete(1)));
+        assert!(!overlap.is_duplicate(true, &role_delete(1)));
+
+        for role_id in 2..RECENT_CAPACITY as u64 {
+            assert!(!overlap.is_duplicate(true, &role_delete(role_id)));
+        }
+
+        // The oldest event was forgotten, and remembering another one evicts
+        // the stale entry of the event remembered again, which is kept.
+        assert!(!overlap.is_duplicate(false, &role_delete(0)));
+        assert!(overlap.is_duplicate(false, &role_delete(1)));
+        assert!(overlap.is_duplicate(false, &role_delete(2)));
+    }
+
+    #[tokio::test]
+    async fn test_swap_drains_previous() {
+        let (old_tx, old_events) = events();
+        let (new_tx, new_events) = events();
+        let (swap_tx, swap_rx) = mpsc::unbounded_channel();
+
+        let mut stream = ClusterEvents::new(
+            Generation::new(vec![ShardEventStream::new(0, old_events)], None),
+            swap_rx,
+        );
+
+        old_tx.send(role_delete(1)).unwrap();
+        old_tx.send(role_delete(2)).unwrap();
+        swap_tx
+            .send(Generation::new(
+                vec![ShardEventStream::new(0, new_events)],
+                None,
+            ))
+            .unwrap();
+
+        // The previous generation's buffered events are yielded even after
+        // the overlap period.
+        time::pause();
+        time::advance(OVERLAP_PERIOD * 2).await;
+        assert_eq!(Some((0, role_delete(1))), stream.next().await);
+        assert_eq!(Some((0, role_delete(2))), stream.next().await);
+
+        old_tx.close();
+        new_tx.send(role_delete(3)).unwrap();
+        assert_eq!(Some((0, role_delete(3))), stream.next().await);
+    }
+
+    #[tokio::test]
+    async fn test_generation_filters_event_types() {
+        let (tx, events) = events();
+        let (_swap_tx, swap_rx) = mpsc::unbounded_channel();
+
+        let mut stream = ClusterEvents::new(
+            Generation::new(
+                vec![ShardEventStream::new(3, events)],
+                Some(EventTypeFlags::ROLE_DELETE),
+            ),
+            swap_rx,
+        );
+
+        tx.send(Event::GatewayHeartbeatAck).unwrap();
+        tx.send(role_delete(1)).unwrap();
+        assert_eq!(Some((3, role_delete(1))), stream.next().await);
+    }
+
+    fn fully_ready(shard_id: u64, guilds_missing: Vec<GuildId>) -> Event {
+        Event::ShardFullyReady(FullyReady {
+            guilds_expected: 2,
+            guilds_missing,
+            shard_id,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_cluster_ready() {
+        let (tx_0, events_0) = events();
+        let (tx_1, events_1) = events();
+        let (_swap_tx, swap_rx) = mpsc::unbounded_channel();
+
+        let mut stream = ClusterEvents::new(
+            Generation::new(
+                vec![
+                    ShardEventStream::new(0, events_0),
+                    ShardEventStream::new(1, events_1),
+                ],
+                Some(EventTypeFlags::CLUSTER_READY | EventTypeFlags::ROLE_DELETE),
+            ),
+            swap_rx,
+        );
+
+        tx_0.send(fully_ready(0, Vec::new())).unwrap();
+        tx_0.send(role_delete(1)).unwrap();
+        assert_eq!(Some((0, role_delete(1))), stream.next().await);
+
+        tx_1.send(fully_ready(1, vec![GuildId(5)])).unwrap();
+        assert_eq!(
+            Some((
+                1,
+                Event::ClusterReady(ClusterReady {
+                    guilds_expected: 4,
+                    guilds_missing: vec![GuildId(5)],
+                    shards: vec![0, 1],
+                })
+            )),
+            stream.next().await
+        );
+
+        // Only yielded again once a shard started a new session.
+        tx_1.send(fully_ready(1, Vec::new())).unwrap();
+        tx_1.send(role_delete(2)).unwrap();
+        assert_eq!(Some((1, role_delete(2))), stream.next().await);
+    }
+
+    #[tokio::test]
+    async fn test_swap_cluster_ready() {
+        let (_old_tx, old_events) = events();
+        let (_new_tx, new_events) = events();
+        let (swap_tx, swap_rx) = mpsc::unbounded_channel();
+
+        let mut stream = ClusterEvents::new(
+            Generation::new(vec![ShardEventStream::new(0, old_events)], None),
+            swap_rx,
+        );
+
+        let mut generation = Generation::new(vec![ShardEventStream::new(3, new_events)], None);
+        generation.fully_ready(
+            3,
+            FullyReady {
+                guilds_expected: 1,
+                guilds_missing: Vec::new(),
+                shard_id: 3,
+            },
+        );
+        swap_tx.send(generation).unwrap();
+
+        assert_eq!(
+            Some((
+                3,
+                Event::ClusterReady(ClusterReady {
+                    guilds_expected: 1,
+                    guilds_missing: Vec::new(),
+                    shards: vec![3],
+                })
+            )),
+            stream.next().await
+        );
+    }
+}
@@ -1,21 +1,39 @@
-use super::{builder::ClusterBuilder, config::Config, scheme::ShardScheme};
+use super::{
+    builder::ClusterBuilder,
+    config::Config,
+    event::{self as cluster_event, ClusterEvents, Generation, ShardEventStream},
+    scheme::ShardScheme,
+};
 use crate::{
-    shard::{raw_message::Message, Events, Information, ResumeSession, Shard},
-    Intents,
+    shard::{raw_message::Message, Events, Health, Information, Readiness, ResumeSession, Shard},
+    EventTypeFlags, Intents,
 };
 use futures_util::{
     future,
-    stream::{SelectAll, Stream, StreamExt},
+    stream::{Stream, StreamExt},
 };
 use std::{
     collections::HashMap,
     error::Error,
     fmt::{Display, Formatter, Result as FmtResult},
-    iter::FromIterator,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
 };
+use tokio::sync::mpsc::{self, UnboundedSender};
 use twilight_http::Client as HttpClient;
-use twilight_model::gateway::event::Event;
+use twilight_model::gateway::event::{shard::FullyReady, Event};
+
+/// Event types required to know when every shard is fully ready, to emit
+/// [`Event::ClusterReady`].
+const CLUSTER_READY_EVENT_TYPES: EventTypeFlags = EventTypeFlags::from_bits_truncate(
+    EventTypeFlags::READY.bits() | EventTypeFlags::SHARD_FULLY_READY.bits(),
+);
+
+/// Event types required to know when the shards started while resharding are
+/// ready.
+const RESHARD_EVENT_TYPES: EventTypeFlags = EventTypeFlags::SHARD_FULLY_READY;
 
 /// Sending a command to a shard failed.
 #[derive(Debug)]
@@ -158,6 +176,81 @@ pub enum ClusterSendErrorType {
     },
 }
 
+/// Resharding a cluster failed.
+#[derive(Debug)]
+pub struct ClusterReshardError {
+    kind: ClusterReshardErrorType,
+    source: Option<Box<dyn Error + Send + Sync>>,
+}
+
+impl ClusterReshardError {
+    /// Immutable reference to the type of error that occurred.
+    #[must_use = "retrieving the type has no effect if left unused"]
+    pub const fn kind(&self) -> &ClusterReshardErrorType {
+        &self.kind
+    }
+
+    /// Consume the error, returning the source error if there is any.
+    #[must_use = "consuming the error and retrieving the source has no effect if left unused"]
+    pub fn into_source(self) -> Option<Box<dyn Error + Send + Sync>> {
+        self.source
+    }
+
+    /// Consume the error, returning the owned error type and the source error.
+    #[must_use = "consuming the error into its parts has no effect if left unused"]
+    pub fn into_parts(
+        self,
+    ) -> (
+        ClusterReshardErrorType,
+        Option<Box<dyn Error + Send + Sync>>,
+    ) {
+        (self.kind, self.source)
+    }
+}
+
+impl Display for ClusterReshardError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match &self.kind {
+            ClusterReshardErrorType::AlreadyResharding => {
+                f.write_str("the cluster is already resharding")
+            }
+            ClusterReshardErrorType::RetrievingGatewayInfo => {
+                f.write_str("getting the bot's gateway info failed")
+            }
+            ClusterReshardErrorType::StartingShard { id } => {
+                f.write_fmt(format_args!("starting new shard {} failed", id))
+            }
+        }
+    }
+}
+
+impl Error for ClusterReshardError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| &**source as &(dyn Error + 'static))
+    }
+}
+
+/// Type of [`ClusterReshardError`] that occurred.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ClusterReshardErrorType {
+    /// Another reshard of the cluster is in progress.
+    AlreadyResharding,
+    /// Retrieving the bot's gateway information via the HTTP API failed.
+    ///
+    /// This can occur when resharding with [automatic sharding].
+    ///
+    /// [automatic sharding]: ShardScheme::Auto
+    RetrievingGatewayInfo,
+    /// One of the new shards failed to start or closed before it was ready.
+    StartingShard {
+        /// ID of the shard.
+        id: u64,
+    },
+}
+
 /// Starting a cluster failed.
 #[derive(Debug)]
 pub struct ClusterStartError {
@@ -220,9 +313,39 @@ pub enum ClusterStartErrorType {
 #[derive(Debug)]
 struct ClusterRef {
     config: Config,
-    shard_from: u64,
-    shard_to: u64,
+    /// Number of events dropped by shards that were replaced while resharding.
+    events_dropped: AtomicU64,
+    resharding: AtomicBool,
     shards: Mutex<HashMap<u64, Shard>>,
+    /// Sender of new generations of shards to the event stream.
+    swaps: UnboundedSender<Generation>,
+}
+
+/// Marks a cluster as resharding until dropped.
+struct ReshardGuard<'a>(&'a AtomicBool);
+
+impl Drop for ReshardGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+/// Shuts down the new shards of a reshard unless disarmed, so that they don't
+/// keep running if resharding failed or its future was dropped.
+struct ShutdownGuard(Option<Vec<Shard>>);
+
+impl ShutdownGuard {
+    fn disarm(mut self) {
+        self.0.take();
+    }
+}
+
+impl Drop for ShutdownGuard {
+    fn drop(&mut self) {
+        for shard in self.0.take().into_iter().flatten() {
+            shard.shutdown();
+        }
+    }
 }
 
 /// A manager for multiple shards.
@@ -295,17 +418,47 @@ impl Cluster {
     pub(super) async fn new_with_config(
         mut config: Config,
     ) -> Result<(Self, impl Stream<Item = (u64, Event)>), ClusterStartError> {
-        #[derive(Default)]
-        struct ShardFold {
-            shards: HashMap<u64, Shard>,
-            streams: Vec<(u64, Events)>,
-        }
-
         let scheme = match config.shard_scheme() {
-            ShardScheme::Auto => Self::retrieve_shard_count(&config.http_client).await?,
+            ShardScheme::Auto => Self::retrieve_shard_count(&config.http_client)
+                .await
+                .map_err(|source| ClusterStartError {
+                    kind: ClusterStartErrorType::RetrievingGatewayInfo,
+                    source: Some(Box::new(source)),
+                })?,
             other => other.clone(),
         };
 
+        let mut resume_sessions = std::mem::take(&mut config.resume_sessions);
+        let (event_types, filter) =
+            shard_event_types(config.event_types(), EventTypeFlags::empty());
+        let (shards, streams) =
+            Self::create_shards(&config, &scheme, event_types, &mut resume_sessions);
+
+        let (swaps, swaps_rx) = mpsc::unbounded_channel();
+        let streams = streams
+            .into_iter()
+            .map(|(id, events)| ShardEventStream::new(id, events));
+        let events = ClusterEvents::new(Generation::new(streams, filter), swaps_rx);
+
+        Ok((
+            Self(Arc::new(ClusterRef {
+                config,
+                events_dropped: AtomicU64::new(0),
+                resharding: AtomicBool::new(false),
+                shards: Mutex::new(shards),
+                swaps,
+            })),
+            events,
+        ))
+    }
+
+    /// Create the shards of a shard scheme that isn't automatic.
+    fn create_shards(
+        config: &Config,
+        scheme: &ShardScheme,
+        event_types: EventTypeFlags,
+        resume_sessions: &mut HashMap<u64, ResumeSession>,
+    ) -> (HashMap<u64, Shard>, Vec<(u64, Events)>) {
         let iter = scheme.iter().expect("shard scheme is not auto");
         let total = scheme.total().expect("shard scheme is not auto");
 
@@ -315,53 +468,33 @@ impl Cluster {
             metrics::gauge!("Cluster-Shard-Count", total as f64);
         }
 
-        let ShardFold { shards, streams } = iter.fold(ShardFold::default(), |mut fold, idx| {
+        let mut shards = HashMap::new();
+        let mut streams = Vec::new();
+
+        for idx in iter {
             let mut shard_config = config.shard_config().clone();
+            shard_config.event_types = event_types;
             shard_config.shard = [idx, total];
 
-            if let Some(data) = config.resume_sessions.remove(&idx) {
+            if let Some(data) = resume_sessions.remove(&idx) {
                 shard_config.session_id = Some(data.session_id.into_boxed_str());
                 shard_config.sequence = Some(data.sequence);
             }
 
             let (shard, stream) = Shard::new_with_config(shard_config);
 
-            fold.shards.insert(idx, shard);
-            fold.streams.push((idx, stream));
-
-            fold
-        });
-
-        let combined = streams
-            .into_iter()
-            .map(|(id, stream)| stream.map(move |e| (id, e)));
-
-        #[allow(clippy::from_iter_instead_of_collect)]
-        let select_all = SelectAll::from_iter(combined);
+            shards.insert(idx, shard);
+            streams.push((idx, stream));
+        }
 
-        Ok((
-            Self(Arc::new(ClusterRef {
-                config,
-                shard_from: scheme.from().expect("shard scheme is not auto"),
-                shard_to: scheme.to().expect("shard scheme is not auto"),
-                shards: Mutex::new(shards),
-            })),
-            select_all,
-        ))
+        (shards, streams)
     }
 
     /// Retrieve the recommended number of shards from the HTTP API.
     ///
     /// The returned shard scheme is a [`ShardScheme::Range`].
-    async fn retrieve_shard_count(http: &HttpClient) -> Result<ShardScheme, ClusterStartError> {
-        let gateway = http
-            .gateway()
-            .authed()
-            .await
-            .map_err(|source| ClusterStartError {
-                kind: ClusterStartErrorType::RetrievingGatewayInfo,
-                source: Some(Box::new(source)),
-            })?;
+    async fn retrieve_shard_count(http: &HttpClient) -> Result<ShardScheme, twilight_http::Error> {
+        let gateway = http.gateway().authed().await?;
 
         Ok(ShardScheme::Range {
             from: 0,
@@ -443,12 +576,158 @@ impl Cluster {
     /// # Ok(()) }
     /// ```
     pub async fn up(&self) {
+        let ids = self
+            .0
+            .shards
+            .lock()
+            .expect("shards poisoned")
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+
         future::join_all(
-            (self.0.shard_from..=self.0.shard_to).map(|id| Self::start(Arc::clone(&self.0), id)),
+            ids.into_iter()
+                .map(|id| Self::start(Arc::clone(&self.0), id)),
         )
         .await;
     }
 
+    /// Reshard the cluster without downtime, replacing its shards with a new
+    /// set of shards.
+    ///
+    /// The new shards are started through the configured queue while the
+    /// current shards keep running. Once all of the new shards are ready and
+    /// have received all of their guilds, the cluster's event stream swaps
+    /// to the new shards and the current shards are shut down. Events that are
+    /// received by both sets of shards during the swap are only emitted once.
+    ///
+    /// Use [`ShardScheme::Auto`] to use the number of shards recommended by
+    /// Discord.
+    ///
+    /// # Examples
+    ///
+    /// Reshard the cluster when Discord recommends a different number of
+    /// shards:
+    ///
+    /// ```no_run
+    /// use twilight_gateway::{cluster::{Cluster, ShardScheme}, Intents};
+    /// use std::env;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// let token = env::var("DISCORD_TOKEN")?;
+    /// let (cluster, _) = Cluster::new(token, Intents::GUILDS).await?;
+    /// cluster.up().await;
+    ///
+    /// // some time later..
+    /// cluster.reshard(ShardScheme::Auto).await?;
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ClusterReshardErrorType::AlreadyResharding`] error type if
+    /// the cluster is already being resharded.
+    ///
+    /// Returns a [`ClusterReshardErrorType::RetrievingGatewayInfo`] error type
+    /// if the scheme is automatic and retrieving the recommended number of
+    /// shards failed.
+    ///
+    /// Returns a [`ClusterReshardErrorType::StartingShard`] error type if one
+    /// of the new shards failed to start. The new shards are shut down and the
+    /// current shards are kept, which is also the case if the future is
+    /// dropped before resharding completed.
+    pub async fn reshard(&self, scheme: ShardScheme) -> Result<(), ClusterReshardError> {
+        if self.0.resharding.swap(true, Ordering::AcqRel) {
+            return Err(ClusterReshardError {
+                kind: ClusterReshardErrorType::AlreadyResharding,
+                source: None,
+            });
+        }
+
+        let _guard = ReshardGuard(&self.0.resharding);
+
+        let scheme = match scheme {
+            ShardScheme::Auto => Self::retrieve_shard_count(&self.0.config.http_client)
+                .await
+                .map_err(|source| ClusterReshardError {
+                    kind: ClusterReshardErrorType::RetrievingGatewayInfo,
+                    source: Some(Box::new(source)),
+                })?,
+            other => other,
+        };
+
+        // The new shards need to receive the events that tell when they're
+        // ready.
+        let (event_types, filter) =
+            shard_event_types(self.0.config.event_types(), RESHARD_EVENT_TYPES);
+
+        let (shards, streams) =
+            Self::create_shards(&self.0.config, &scheme, event_types, &mut HashMap::new());
+        let shutdown_guard = ShutdownGuard(Some(shards.values().cloned().collect()));
+
+        let results = future::join_all(streams.into_iter().map(|(id, mut events)| {
+            let shard = shards.get(&id).cloned();
+
+            async move {
+                let shard = shard.expect("shard was created");
+
+                shard.start().await.map_err(|source| ClusterReshardError {
+                    kind: ClusterReshardErrorType::StartingShard { id },
+                    source: Some(Box::new(source)),
+                })?;
+
+                let fully_ready =
+                    wait_until_ready(&mut events)
+                        .await
+                        .ok_or(ClusterReshardError {
+                            kind: ClusterReshardErrorType::StartingShard { id },
+                            source: None,
+                        })?;
+
+                Ok((id, events, fully_ready))
+            }
+        }))
+        .await;
+
+        let ready = results.into_iter().collect::<Result<Vec<_>, _>>()?;
+
+        // The current shards have emitted the events the new shards received
+        // while waiting for the others to be ready.
+        let mut fully_ready = Vec::with_capacity(ready.len());
+        let streams = ready
+            .into_iter()
+            .map(|(id, mut events, event)| {
+                cluster_event::discard_received(&mut events);
+                fully_ready.push((id, event));
+
+                ShardEventStream::new(id, events)
+            })
+            .collect::<Vec<_>>();
+
+        let mut generation = Generation::new(streams, filter);
+
+        for (id, event) in fully_ready {
+            generation.fully_ready(id, event);
+        }
+
+        shutdown_guard.disarm();
+        let old = std::mem::replace(&mut *self.0.shards.lock().expect("shards poisoned"), shards);
+
+        // If the event stream was dropped nobody is receiving events, so
+        // there's nothing to swap.
+        let _res = self.0.swaps.send(generation);
+
+        for shard in old.values() {
+            shard.shutdown();
+            self.0
+                .events_dropped
+                .fetch_add(shard.events_dropped(), Ordering::Relaxed);
+        }
+
+        Ok(())
+    }
+
     /// Bring down the cluster, stopping all of the shards that it's managing.
     pub fn down(&self) {
         for shard in self.0.shards.lock().expect("shards poisoned").values() {
@@ -476,6 +755,28 @@ impl Cluster {
             .collect()
     }
 
+    /// Number of events the shards' event streams have dropped due to their
+    /// buffers being full.
+    ///
+    /// This includes the events dropped by shards that were replaced while
+    /// resharding. It is always zero unless the buffers are bounded with an
+    /// overflow policy that drops events. Refer to
+    /// [`ClusterBuilder::event_buffer`] for more information.
+    ///
+    /// [`ClusterBuilder::event_buffer`]: super::ClusterBuilder::event_buffer
+    pub fn events_dropped(&self) -> u64 {
+        let dropped = self
+            .0
+            .shards
+            .lock()
+            .expect("shards poisoned")
+            .values()
+            .map(Shard::events_dropped)
+            .sum::<u64>();
+
+        self.0.events_dropped.load(Ordering::Relaxed) + dropped
+    }
+
     /// Return a Shard by its ID.
     pub fn shard(&self, id: u64) -> Option<Shard> {
         self.0
@@ -534,6 +835,84 @@ impl Cluster {
             .collect()
     }
 
+    /// Return the health of every shard's connection, keyed by shard ID.
+    ///
+    /// # Examples
+    ///
+    /// Print the shards that haven't received a dispatch event in the last
+    /// minute:
+    ///
+    /// ```no_run
+    /// use twilight_gateway::{Cluster, Intents};
+    /// use std::{env, time::Duration};
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// let (cluster, _) = Cluster::new(env::var("DISCORD_TOKEN")?, Intents::GUILDS).await?;
+    /// cluster.up().await;
+    ///
+    /// for (shard_id, health) in cluster.health() {
+    ///     if health.since_last_dispatch() > Some(Duration::from_secs(60)) {
+    ///         println!(
+    ///             "shard {} is {} and has reconnected {} times",
+    ///             shard_id,
+    ///             health.stage(),
+    ///             health.reconnects(),
+    ///         );
+    ///     }
+    /// }
+    /// # Ok(()) }
+    /// ```
+    pub fn health(&self) -> HashMap<u64, Health> {
+        self.0
+            .shards
+            .lock()
+            .expect("shards poisoned")
+            .iter()
+            .map(|(id, shard)| (*id, shard.health()))
+            .collect()
+    }
+
+    /// Return the progress of all of the shards loading the guilds listed in
+    /// their ready events.
+    ///
+    /// The cluster is fully ready once every shard is. The readiness of each
+    /// shard is included in its [`Information`] returned by [`info`].
+    ///
+    /// # Examples
+    ///
+    /// Print how many guilds the cluster has received so far:
+    ///
+    /// ```no_run
+    /// use twilight_gateway::{Cluster, Intents};
+    /// use std::env;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// let (cluster, _) = Cluster::new(env::var("DISCORD_TOKEN")?, Intents::GUILDS).await?;
+    /// cluster.up().await;
+    ///
+    /// let readiness = cluster.readiness();
+    /// println!(
+    ///     "received {} of {} guilds",
+    ///     readiness.guilds_received(),
+    ///     readiness.guilds_expected(),
+    /// );
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// [`info`]: Self::info
+    pub fn readiness(&self) -> Readiness {
+        Readiness::combine(
+            self.0
+                .shards
+                .lock()
+                .expect("shards poisoned")
+                .values()
+                .map(Shard::readiness),
+        )
+    }
+
     /// Send a command to the specified shard.
     ///
     /// # Errors
@@ -648,11 +1027,50 @@ impl Cluster {
     }
 }
 
+/// Event types the shards receive and the event types the event stream
+/// filters them down to, if they differ.
+///
+/// The shards need to receive the `required` event types and the event types
+/// to know when every shard is fully ready, which are filtered out again by
+/// the event stream if the cluster wasn't configured to receive them.
+fn shard_event_types(
+    configured: EventTypeFlags,
+    required: EventTypeFlags,
+) -> (EventTypeFlags, Option<EventTypeFlags>) {
+    let mut event_types = configured | required;
+
+    if configured.contains(EventTypeFlags::CLUSTER_READY) {
+        event_types |= CLUSTER_READY_EVENT_TYPES;
+    }
+
+    let filter = if event_types == configured {
+        None
+    } else {
+        Some(configured)
+    };
+
+    (event_types, filter)
+}
+
+/// Wait until a newly started shard is fully ready.
+///
+/// Returns `None` if the shard's event stream ended first.
+async fn wait_until_ready(events: &mut Events) -> Option<FullyReady> {
+    while let Some(event) = events.next().await {
+        if let Event::ShardFullyReady(fully_ready) = event {
+            return Some(fully_ready);
+        }
+    }
+
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
-        Cluster, ClusterCommandError, ClusterCommandErrorType, ClusterSendError,
-        ClusterSendErrorType, ClusterStartError, ClusterStartErrorType,
+        Cluster, ClusterCommandError, ClusterCommandErrorType, ClusterReshardError,
+        ClusterReshardErrorType, ClusterSendError, ClusterSendErrorType, ClusterStartError,
+        ClusterStartErrorType,
     };
     use static_assertions::{assert_fields, assert_impl_all};
     use std::{error::Error, fmt::Debug};
@@ -663,6 +1081,9 @@ mod tests {
     assert_impl_all!(ClusterSendErrorType: Debug, Send, Sync);
     assert_fields!(ClusterSendErrorType::ShardNonexistent: id);
     assert_impl_all!(ClusterSendError: Error, Send, Sync);
+    assert_impl_all!(ClusterReshardErrorType: Debug, Send, Sync);
+    assert_fields!(ClusterReshardErrorType::StartingShard: id);
+    assert_impl_all!(ClusterReshardError: Error, Send, Sync);
     assert_impl_all!(ClusterStartErrorType: Debug, Send, Sync);
     assert_impl_all!(ClusterStartError: Error, Send, Sync);
     assert_impl_all!(Cluster: Clone, Debug, Send, Sync);
@@ -87,14 +87,17 @@ pub mod scheme;
 
 mod builder;
 mod config;
+mod event;
 mod r#impl;
+mod resume;
 
 pub use self::{
     builder::ClusterBuilder,
     config::Config,
     r#impl::{
-        Cluster, ClusterCommandError, ClusterCommandErrorType, ClusterStartError,
-        ClusterStartErrorType,
+        Cluster, ClusterCommandError, ClusterCommandErrorType, ClusterReshardError,
+        ClusterReshardErrorType, ClusterStartError, ClusterStartErrorType,
     },
+    resume::{ResumeState, ResumeStateError, ResumeStateErrorType},
     scheme::{ShardScheme, ShardSchemeRangeError, ShardSchemeRangeErrorType},
 };
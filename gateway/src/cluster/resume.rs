@@ -0,0 +1,313 @@
+use crate::shard::ResumeSession;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    fs,
+    io::ErrorKind as IoErrorKind,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Saving or loading a [`ResumeState`] failed.
+#[derive(Debug)]
+pub struct ResumeStateError {
+    kind: ResumeStateErrorType,
+    source: Option<Box<dyn Error + Send + Sync>>,
+}
+
+impl ResumeStateError {
+    /// Immutable reference to the type of error that occurred.
+    #[must_use = "retrieving the type has no effect if left unused"]
+    pub const fn kind(&self) -> &ResumeStateErrorType {
+        &self.kind
+    }
+
+    /// Consume the error, returning the source error if there is any.
+    #[must_use = "consuming the error and retrieving the source has no effect if left unused"]
+    pub fn into_source(self) -> Option<Box<dyn Error + Send + Sync>> {
+        self.source
+    }
+
+    /// Consume the error, returning the owned error type and the source error.
+    #[must_use = "consuming the error into its parts has no effect if left unused"]
+    pub fn into_parts(self) -> (ResumeStateErrorType, Option<Box<dyn Error + Send + Sync>>) {
+        (self.kind, self.source)
+    }
+}
+
+impl Display for ResumeStateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match &self.kind {
+            ResumeStateErrorType::Deserializing => {
+                f.write_str("resume state file is not a valid resume state")
+            }
+            ResumeStateErrorType::Reading => f.write_str("reading the resume state file failed"),
+            ResumeStateErrorType::Serializing => f.write_str("serializing the resume state failed"),
+            ResumeStateErrorType::Writing => f.write_str("writing the resume state file failed"),
+        }
+    }
+}
+
+impl Error for ResumeStateError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| &**source as &(dyn Error + 'static))
+    }
+}
+
+/// Type of [`ResumeStateError`] that occurred.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ResumeStateErrorType {
+    /// Resume state file doesn't contain a valid resume state.
+    Deserializing,
+    /// Reading the resume state file failed.
+    Reading,
+    /// Serializing the resume state failed.
+    Serializing,
+    /// Writing the resume state file failed.
+    Writing,
+}
+
+/// Sessions of a cluster's shards saved to resume them after a restart.
+///
+/// Obtain the sessions by bringing the cluster down with
+/// [`Cluster::down_resumable`], save them to a file before exiting, and load
+/// them when starting again to pass them to
+/// [`ClusterBuilder::resume_sessions`].
+///
+/// Discord only allows resuming a session for a short while after it was
+/// disconnected, so the time the state was saved at is recorded and should
+/// be checked with [`is_stale`] before using the sessions. Shards whose
+/// sessions are rejected anyway identify to create new sessions.
+///
+/// # Examples
+///
+/// Resume the sessions saved by a previous run if they're at most two minutes
+/// old, and save them when shutting down:
+///
+/// ```no_run
+/// use std::{collections::HashMap, env, time::Duration};
+/// use twilight_gateway::{
+///     cluster::{Cluster, ResumeState},
+///     Intents,
+/// };
+///
+/// # #[tokio::main] async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// const PATH: &str = "resume.json";
+///
+/// let sessions = match ResumeState::load(PATH) {
+///     Ok(state) if !state.is_stale(Duration::from_secs(120)) => state.into_sessions(),
+///     _ => HashMap::new(),
+/// };
+///
+/// let token = env::var("DISCORD_TOKEN")?;
+/// let (cluster, _events) = Cluster::builder(token, Intents::GUILDS)
+///     .resume_sessions(sessions)
+///     .build()
+///     .await?;
+/// cluster.up().await;
+///
+/// // Later, when shutting down:
+/// ResumeState::new(cluster.down_resumable()).save(PATH)?;
+/// # Ok(()) }
+/// ```
+///
+/// [`Cluster::down_resumable`]: super::Cluster::down_resumable
+/// [`ClusterBuilder::resume_sessions`]: super::ClusterBuilder::resume_sessions
+/// [`is_stale`]: Self::is_stale
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ResumeState {
+    /// Milliseconds since the Unix epoch when the state was created.
+    saved_at: u64,
+    sessions: HashMap<u64, ResumeSession>,
+}
+
+impl ResumeState {
+    /// Create a resume state from the sessions of a cluster's shards, as of
+    /// now.
+    pub fn new(sessions: HashMap<u64, ResumeSession>) -> Self {
+        // Milliseconds since the epoch fit in 64 bits for the next few hundred
+        // million years.
+        #[allow(clippy::cast_possible_truncation)]
+        let saved_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_millis() as u64);
+
+        Self { saved_at, sessions }
+    }
+
+    /// Load a resume state from a file saved with [`save`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ResumeStateErrorType::Reading`] error type if the file
+    /// couldn't be read, such as if it doesn't exist.
+    ///
+    /// Returns a [`ResumeStateErrorType::Deserializing`] error type if the
+    /// file doesn't contain a resume state.
+    ///
+    /// [`save`]: Self::save
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ResumeStateError> {
+        let bytes = fs::read(path).map_err(|source| ResumeStateError {
+            kind: ResumeStateErrorType::Reading,
+            source: Some(Box::new(source)),
+        })?;
+
+        serde_json::from_slice(&bytes).map_err(|source| ResumeStateError {
+            kind: ResumeStateErrorType::Deserializing,
+            source: Some(Box::new(source)),
+        })
+    }
+
+    /// Save the resume state to a file, replacing it if it exists.
+    ///
+    /// The state is written to a temporary file next to it first, so an
+    /// interrupted save doesn't leave a partially written file behind.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ResumeStateErrorType::Serializing`] error type if the state
+    /// couldn't be serialized.
+    ///
+    /// Returns a [`ResumeStateErrorType::Writing`] error type if the file
+    /// couldn't be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ResumeStateError> {
+        let path = path.as_ref();
+        let bytes = serde_json::to_vec(self).map_err(|source| ResumeStateError {
+            kind: ResumeStateErrorType::Serializing,
+            source: Some(Box::new(source)),
+        })?;
+
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+
+        fs::write(&temporary, bytes)
+            .and_then(|()| fs::rename(&temporary, path))
+            .map_err(|source| ResumeStateError {
+                kind: ResumeStateErrorType::Writing,
+                source: Some(Box::new(source)),
+            })
+    }
+
+    /// Remove a resume state file, doing nothing if it doesn't exist.
+    ///
+    /// This prevents the sessions from being used again once they've been
+    /// loaded.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ResumeStateErrorType::Writing`] error type if the file
+    /// couldn't be removed.
+    pub fn remove(path: impl AsRef<Path>) -> Result<(), ResumeStateError> {
+        match fs::remove_file(path) {
+            Err(source) if source.kind() != IoErrorKind::NotFound => Err(ResumeStateError {
+                kind: ResumeStateErrorType::Writing,
+                source: Some(Box::new(source)),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// How long ago the state was created.
+    ///
+    /// This is zero if the state was created in the future, such as if the
+    /// system clock was changed since.
+    pub fn age(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(self.saved_at())
+            .unwrap_or_default()
+    }
+
+    /// Whether the state was created longer ago than a maximum age, after
+    /// which its sessions are unlikely to be resumable.
+    pub fn is_stale(&self, max_age: Duration) -> bool {
+        self.age() > max_age
+    }
+
+    /// When the state was created.
+    pub fn saved_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(self.saved_at)
+    }
+
+    /// Immutable reference to the sessions, keyed by shard ID.
+    pub const fn sessions(&self) -> &HashMap<u64, ResumeSession> {
+        &self.sessions
+    }
+
+    /// Consume the state, returning the sessions keyed by shard ID.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn into_sessions(self) -> HashMap<u64, ResumeSession> {
+        self.sessions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ResumeState, ResumeStateError, ResumeStateErrorType};
+    use crate::shard::ResumeSession;
+    use serde::{Deserialize, Serialize};
+    use static_assertions::assert_impl_all;
+    use std::{collections::HashMap, env, error::Error, fmt::Debug, fs, time::Duration};
+
+    assert_impl_all!(
+        ResumeState: Clone,
+        Debug,
+        Deserialize<'static>,
+        Send,
+        Serialize,
+        Sync
+    );
+    assert_impl_all!(ResumeStateErrorType: Debug, Send, Sync);
+    assert_impl_all!(ResumeStateError: Error, Send, Sync);
+
+    #[test]
+    fn test_save_load() {
+        let path = env::temp_dir().join(format!("twilight-resume-{}.json", std::process::id()));
+
+        let mut sessions = HashMap::new();
+        sessions.insert(
+            3,
+            ResumeSession {
+                session_id: "session".to_owned(),
+                sequence: 42,
+            },
+        );
+        ResumeState::new(sessions).save(&path).unwrap();
+
+        let state = ResumeState::load(&path).unwrap();
+        assert!(!state.is_stale(Duration::from_secs(60)));
+        assert_eq!(1, state.sessions().len());
+
+        let session = &state.into_sessions()[&3];
+        assert_eq!("session", session.session_id);
+        assert_eq!(42, session.sequence);
+
+        ResumeState::remove(&path).unwrap();
+        ResumeState::remove(&path).unwrap();
+        assert!(matches!(
+            ResumeState::load(&path).unwrap_err().kind(),
+            ResumeStateErrorType::Reading
+        ));
+
+        fs::write(&path, b"{}").unwrap();
+        assert!(matches!(
+            ResumeState::load(&path).unwrap_err().kind(),
+            ResumeStateErrorType::Deserializing
+        ));
+        ResumeState::remove(&path).unwrap();
+    }
+
+    #[test]
+    fn test_stale() {
+        let mut state = ResumeState::new(HashMap::new());
+        assert!(!state.is_stale(Duration::from_secs(60)));
+
+        state.saved_at -= 61_000;
+        assert!(state.is_stale(Duration::from_secs(60)));
+        assert!(state.age() >= Duration::from_secs(61));
+    }
+}
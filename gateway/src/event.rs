@@ -18,6 +18,8 @@ bitflags! {
         const CHANNEL_PINS_UPDATE = 1 << 4;
         /// Channel has been updated.
         const CHANNEL_UPDATE = 1 << 5;
+        /// Every shard of a cluster has become fully ready.
+        const CLUSTER_READY = 1 << 51;
         /// Heartbeat has been created.
         const GATEWAY_HEARTBEAT = 1 << 6;
         /// Heartbeat has been acknowledged.
@@ -105,6 +107,8 @@ bitflags! {
         const SHARD_CONNECTING = 1 << 34;
         /// Shard has disconnected from the gateway.
         const SHARD_DISCONNECTED = 1 << 35;
+        /// Shard has received all of its guilds or timed out waiting for them.
+        const SHARD_FULLY_READY = 1 << 50;
         /// Shard is identifying to create a session with the gateway.
         const SHARD_IDENTIFYING = 1 << 36;
         /// Incoming message has been received from the gateway.
@@ -113,6 +117,8 @@ bitflags! {
         const SHARD_RECONNECTING = 1 << 37;
         /// Shard is resuming a session with the gateway.
         const SHARD_RESUMING = 1 << 38;
+        /// Shard's connection was found to be unhealthy and is being replaced.
+        const SHARD_UNHEALTHY = 1 << 52;
         /// Stage instance was created in a stage channel.
         const STAGE_INSTANCE_CREATE = 1 << 57;
         /// Stage instance was deleted in a stage channel.
@@ -143,6 +149,7 @@ impl From<EventType> for EventTypeFlags {
             EventType::ChannelDelete => EventTypeFlags::CHANNEL_DELETE,
             EventType::ChannelPinsUpdate => EventTypeFlags::CHANNEL_PINS_UPDATE,
             EventType::ChannelUpdate => EventTypeFlags::CHANNEL_UPDATE,
+            EventType::ClusterReady => EventTypeFlags::CLUSTER_READY,
             EventType::GatewayHeartbeat => EventTypeFlags::GATEWAY_HEARTBEAT,
             EventType::GatewayHeartbeatAck => EventTypeFlags::GATEWAY_HEARTBEAT_ACK,
             EventType::GatewayHello => EventTypeFlags::GATEWAY_HELLO,
@@ -182,10 +189,12 @@ impl From<EventType> for EventTypeFlags {
             EventType::ShardConnected => EventTypeFlags::SHARD_CONNECTED,
             EventType::ShardConnecting => EventTypeFlags::SHARD_CONNECTING,
             EventType::ShardDisconnected => EventTypeFlags::SHARD_DISCONNECTED,
+            EventType::ShardFullyReady => EventTypeFlags::SHARD_FULLY_READY,
             EventType::ShardIdentifying => EventTypeFlags::SHARD_IDENTIFYING,
             EventType::ShardReconnecting => EventTypeFlags::SHARD_RECONNECTING,
             EventType::ShardPayload => EventTypeFlags::SHARD_PAYLOAD,
             EventType::ShardResuming => EventTypeFlags::SHARD_RESUMING,
+            EventType::ShardUnhealthy => EventTypeFlags::SHARD_UNHEALTHY,
             EventType::StageInstanceCreate => EventTypeFlags::STAGE_INSTANCE_CREATE,
             EventType::StageInstanceDelete => EventTypeFlags::STAGE_INSTANCE_DELETE,
             EventType::StageInstanceUpdate => EventTypeFlags::STAGE_INSTANCE_UPDATE,
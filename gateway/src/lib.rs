@@ -44,6 +44,13 @@
 //! twilight-gateway = { default-features = false, features = ["rustls", "simd-json"], version = "0.2" }
 //! ```
 //!
+//! #### `etf`
+//!
+//! The `etf` feature enables Erlang's External Term Format as an alternative
+//! encoding of the payloads exchanged with the gateway, which is smaller on the
+//! wire and cheaper to decode than JSON. It's selected per shard via
+//! `ShardBuilder::encoding`. It is not enabled by default.
+//!
 //! ### TLS
 //!
 //! `twilight-gateway` has features to enable [`async-tungstenite`] and
@@ -84,6 +91,13 @@
 //! Enabling **only** `zlib-simd` will make the library use [`zlib-ng`] which is a modern
 //! fork of zlib that is faster and more effective, but it needs `cmake` to compile.
 //!
+//! ### zstd
+//!
+//! The `zstd-stream` feature enables receiving messages compressed with zstd-stream,
+//! which compresses payloads better and decompresses them faster than zlib-stream.
+//! It's selected per shard via `ShardBuilder::transport_compression`. It is not
+//! enabled by default and needs a C compiler to build [`zstd`].
+//!
 //! ### Metrics
 //!
 //! The `metrics` feature provides metrics information via the `metrics` crate.
@@ -91,6 +105,21 @@
 //! their types and gauges about the capacity and efficiency of the inflater of
 //! each shard.
 //!
+//! The following metrics are labelled with the ID of the shard as `shard`:
+//!
+//! - `GatewayDispatch`: counter of received dispatch events, labelled by their
+//!   `event_type`
+//! - `GatewayHeartbeatLatency`: histogram of the time the gateway took to
+//!   acknowledge heartbeats
+//! - `GatewayReconnects` and `GatewayResumes`: counters of new sessions
+//!   created and sessions resumed on a new connection, labelled by the
+//!   `reason`, such as the close code (`Close4009`) or the error that occurred
+//!   receiving from the connection (`EventStreamEnded`)
+//! - `GatewayEventQueueDepth`: gauge of the events waiting to be received from
+//!   the event stream
+//! - `GatewayBytesIn` and `GatewayBytesOut`: counters of the bytes received
+//!   from and sent to the gateway, before decompression
+//!
 //! This is disabled by default.
 //!
 //! [`async-tungstenite`]: https://crates.io/crates/async-tungstenite
@@ -100,6 +129,7 @@
 //! [`simd-json`]: https://crates.io/crates/simd-json
 //! [`twilight-http`]: https://twilight-rs.github.io/twilight/twilight_http/index.html
 //! [`zlib-ng`]: https://github.com/zlib-ng/zlib-ng
+//! [`zstd`]: https://crates.io/crates/zstd
 //! [discord badge]: https://img.shields.io/discord/745809834183753828?color=%237289DA&label=discord%20server&logo=discord&style=for-the-badge
 //! [discord link]: https://discord.gg/7jj8n7D
 //! [docs:discord:sharding]: https://discord.com/developers/docs/topics/gateway#sharding
@@ -123,10 +153,13 @@
 )]
 #![allow(clippy::module_name_repetitions, clippy::must_use_candidate)]
 
+pub mod chunk;
 pub mod cluster;
 pub mod shard;
+pub mod voice;
 
 mod event;
+mod pending;
 
 pub use self::event::EventTypeFlags;
 pub use twilight_model::gateway::Intents;
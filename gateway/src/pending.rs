@@ -0,0 +1,220 @@
+//! Track requests sent over shards whose responses are collected from the
+//! events the shards receive.
+//!
+//! Requests are registered under a key, such as a nonce or guild ID, and
+//! resolved once their responses are received. They're failed when the shard
+//! they were sent over starts a new session, as the responses will then never
+//! be sent, and forgotten when the future awaiting them is dropped.
+
+use crate::shard::Shard;
+use std::{
+    collections::HashMap,
+    future::Future,
+    hash::Hash,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard, Weak,
+    },
+    task::{Context, Poll},
+};
+use tokio::sync::oneshot::{self, Receiver, Sender};
+
+/// ID of the shard's current session, if it has one.
+pub(crate) fn session_id(shard: &Shard) -> Option<String> {
+    shard
+        .info()
+        .ok()
+        .and_then(|info| info.session_id().map(ToOwned::to_owned))
+}
+
+/// Requests awaiting their responses, by key.
+#[derive(Debug)]
+pub(crate) struct PendingRequests<K, S, T> {
+    /// ID of the next registered request.
+    id: AtomicU64,
+    requests: Mutex<HashMap<K, PendingRequest<S, T>>>,
+}
+
+impl<K: Clone + Eq + Hash, S, T> PendingRequests<K, S, T> {
+    pub fn new() -> Self {
+        Self {
+            id: AtomicU64::new(0),
+            requests: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, HashMap<K, PendingRequest<S, T>>> {
+        self.requests.lock().expect("pending requests poisoned")
+    }
+
+    /// Register a request sent over a shard during a session, returning its
+    /// registration and the request it replaced, if any.
+    pub fn register(
+        self: &Arc<Self>,
+        shard_id: u64,
+        session_id: Option<String>,
+        key: K,
+        state: S,
+    ) -> (Registration<K, S, T>, Option<PendingRequest<S, T>>) {
+        let id = self.id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+
+        let previous = self.lock().insert(
+            key.clone(),
+            PendingRequest {
+                id,
+                session_id,
+                shard_id,
+                state,
+                tx,
+            },
+        );
+
+        let registration = Registration {
+            id,
+            key,
+            requests: Arc::downgrade(self),
+            rx,
+        };
+
+        (registration, previous)
+    }
+
+    /// Remove the requests sent over a shard during a session other than its
+    /// current one.
+    pub fn invalidate(&self, shard_id: u64, session_id: &str) -> Vec<PendingRequest<S, T>> {
+        let mut requests = self.lock();
+
+        let invalidated = requests
+            .iter()
+            .filter(|(_, request)| {
+                request.shard_id == shard_id && request.session_id.as_deref() != Some(session_id)
+            })
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+
+        invalidated
+            .iter()
+            .filter_map(|key| requests.remove(key))
+            .collect()
+    }
+}
+
+/// Request awaiting its response.
+#[derive(Debug)]
+pub(crate) struct PendingRequest<S, T> {
+    /// ID telling the request apart from others registered under its key.
+    id: u64,
+    session_id: Option<String>,
+    shard_id: u64,
+    /// State collected from the events received so far.
+    pub state: S,
+    tx: Sender<T>,
+}
+
+impl<S, T> PendingRequest<S, T> {
+    /// Resolve the request with its output.
+    pub fn resolve(self, output: T) {
+        self.resolve_with(|_| output);
+    }
+
+    /// Resolve the request with an output created from its state.
+    pub fn resolve_with(self, f: impl FnOnce(S) -> T) {
+        // The future may have been dropped.
+        let _res = self.tx.send(f(self.state));
+    }
+}
+
+/// Registration of a request, removing the request when dropped.
+#[derive(Debug)]
+pub(crate) struct Registration<K: Clone + Eq + Hash, S, T> {
+    id: u64,
+    key: K,
+    requests: Weak<PendingRequests<K, S, T>>,
+    rx: Receiver<T>,
+}
+
+impl<K: Clone + Eq + Hash, S, T> Registration<K, S, T> {
+    /// Key the request was registered under.
+    pub const fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Poll for the output of the request, or `None` if the requests were
+    /// dropped before it was resolved.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        Pin::new(&mut self.rx).poll(cx).map(Result::ok)
+    }
+
+    /// Remove the request if it's still pending and hasn't been replaced by
+    /// another request registered under its key.
+    pub fn cancel(&self) {
+        let requests = match self.requests.upgrade() {
+            Some(requests) => requests,
+            None => return,
+        };
+
+        let mut requests = requests.lock();
+
+        if requests
+            .get(&self.key)
+            .map_or(false, |request| request.id == self.id)
+        {
+            requests.remove(&self.key);
+        }
+    }
+}
+
+impl<K: Clone + Eq + Hash, S, T> Drop for Registration<K, S, T> {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PendingRequests, Registration};
+    use static_assertions::assert_impl_all;
+    use std::{fmt::Debug, sync::Arc};
+
+    assert_impl_all!(PendingRequests<u64, (), ()>: Debug, Send, Sync);
+    assert_impl_all!(Registration<u64, (), ()>: Debug, Send, Sync);
+
+    #[test]
+    fn test_registration_dropped() {
+        let requests = Arc::new(PendingRequests::<u64, (), ()>::new());
+
+        let (registration, _) = requests.register(0, None, 1, ());
+        let (_other, _) = requests.register(0, None, 2, ());
+        drop(registration);
+        assert_eq!(1, requests.lock().len());
+
+        // Dropping a replaced request's registration keeps the request that
+        // replaced it.
+        let (replaced, _) = requests.register(0, None, 2, ());
+        let (_replacing, previous) = requests.register(0, None, 2, ());
+        assert!(previous.is_some());
+        drop(replaced);
+        assert!(requests.lock().contains_key(&2));
+    }
+
+    #[test]
+    fn test_invalidate() {
+        let requests = Arc::new(PendingRequests::<u64, (), ()>::new());
+        let (_previous, _) = requests.register(0, Some("a".to_owned()), 1, ());
+        let (_current, _) = requests.register(0, Some("b".to_owned()), 2, ());
+        let (_other_shard, _) = requests.register(1, Some("a".to_owned()), 3, ());
+        let (_no_session, _) = requests.register(0, None, 4, ());
+
+        let mut invalidated = requests
+            .invalidate(0, "b")
+            .into_iter()
+            .map(|request| request.id)
+            .collect::<Vec<_>>();
+        invalidated.sort_unstable();
+
+        assert_eq!(vec![0, 3], invalidated);
+        assert_eq!(2, requests.lock().len());
+    }
+}
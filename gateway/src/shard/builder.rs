@@ -1,13 +1,28 @@
-use super::{config::Config, Events, Shard};
+use super::{
+    channel::OverflowPolicy,
+    config::{Config, GuildFilter, ShardPresence},
+    connect::{Proxy, TlsConnector},
+    encoding::Encoding,
+    processor::{HealthPolicy, TransportCompression},
+    recording::Recorder,
+    Events, LazyEvents, Shard,
+};
 use crate::EventTypeFlags;
 use std::{
     error::Error,
     fmt::{Display, Formatter, Result as FmtResult},
     sync::Arc,
+    time::Duration,
 };
 use twilight_gateway_queue::{LocalQueue, Queue};
 use twilight_http::Client as HttpClient;
-use twilight_model::gateway::{payload::update_presence::UpdatePresencePayload, Intents};
+use twilight_model::{
+    gateway::{
+        payload::{identify::IdentifyProperties, update_presence::UpdatePresencePayload},
+        Intents,
+    },
+    id::GuildId,
+};
 
 /// Large threshold configuration is invalid.
 ///
@@ -171,15 +186,26 @@ impl ShardBuilder {
         }
 
         Self(Config {
+            encoding: Encoding::Json,
+            event_buffer: None,
             event_types: EventTypeFlags::default(),
             gateway_url: None,
+            guild_filter: None,
+            guild_ready_timeout: Duration::from_secs(15),
+            health_policy: HealthPolicy::new(),
             http_client: HttpClient::new(token.clone()),
+            identify_properties: None,
             intents,
             large_threshold: 250,
             presence: None,
+            proxy: None,
             queue: Arc::new(Box::new(LocalQueue::new())),
+            recorder: None,
             shard: [0, 1],
+            shard_presence: None,
+            tls: None,
             token: token.into_boxed_str(),
+            transport_compression: TransportCompression::default(),
             session_id: None,
             sequence: None,
         })
@@ -190,6 +216,127 @@ impl ShardBuilder {
         Shard::new_with_config(self.0)
     }
 
+    /// Consume the builder, constructing a shard whose dispatch events are
+    /// deserialized on demand.
+    ///
+    /// Instead of deserializing every dispatch event into an [`Event`], the
+    /// shard peeks at the information needed to route it, such as its
+    /// [sequence], [event type], and [guild ID], and emits it alongside the
+    /// raw payload as a [`RawEvent`]. This is useful for applications that
+    /// forward most events elsewhere, which then don't pay the cost of
+    /// deserializing them.
+    ///
+    /// Events about the shard's connection, and the events the shard has to
+    /// deserialize itself, such as [`Event::Ready`], are emitted already
+    /// deserialized.
+    ///
+    /// # Examples
+    ///
+    /// Forward message payloads without deserializing them:
+    ///
+    /// ```no_run
+    /// use futures::stream::StreamExt;
+    /// use std::env;
+    /// use twilight_gateway::{
+    ///     shard::{LazyEvent, Shard},
+    ///     EventTypeFlags, Intents,
+    /// };
+    ///
+    /// # #[tokio::main] async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let token = env::var("DISCORD_TOKEN")?;
+    /// let (shard, mut events) = Shard::builder(token, Intents::GUILD_MESSAGES)
+    ///     .event_types(EventTypeFlags::MESSAGE_CREATE)
+    ///     .build_lazy();
+    ///
+    /// shard.start().await?;
+    ///
+    /// while let Some(event) = events.next().await {
+    ///     if let LazyEvent::Raw(raw) = event {
+    ///         println!("guild {:?}: {} bytes", raw.guild_id(), raw.bytes().len());
+    ///     }
+    /// }
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// [`Event`]: crate::Event
+    /// [`Event::Ready`]: crate::Event::Ready
+    /// [`RawEvent`]: super::RawEvent
+    /// [event type]: super::RawEvent::kind
+    /// [guild ID]: super::RawEvent::guild_id
+    /// [sequence]: super::RawEvent::sequence
+    pub fn build_lazy(self) -> (Shard, LazyEvents) {
+        Shard::new_lazy_with_config(self.0)
+    }
+
+    /// Set the encoding of the payloads exchanged with the gateway.
+    ///
+    /// [`Encoding::Etf`] is smaller on the wire and cheaper to decode than
+    /// JSON, which adds up for large bots. It requires the `etf` feature.
+    ///
+    /// The encoding is used for all payloads, including the [`RawEvent`]s of
+    /// [lazy shards] and [shard payload] events, and the commands sent via
+    /// [`Shard::command`]. Messages sent via [`Shard::send`] must be encoded
+    /// by the caller.
+    ///
+    /// Default is [`Encoding::Json`].
+    ///
+    /// [`Encoding::Etf`]: super::Encoding
+    /// [`Encoding::Json`]: super::Encoding::Json
+    /// [`RawEvent`]: super::RawEvent
+    /// [`Shard::command`]: super::Shard::command
+    /// [`Shard::send`]: super::Shard::send
+    /// [lazy shards]: Self::build_lazy
+    /// [shard payload]: crate::EventTypeFlags::SHARD_PAYLOAD
+    pub const fn encoding(mut self, encoding: Encoding) -> Self {
+        self.0.encoding = encoding;
+
+        self
+    }
+
+    /// Bound the buffer of events the shard's event stream hasn't received
+    /// yet.
+    ///
+    /// By default the buffer is unbounded, so a consumer that can't keep up
+    /// with the shard, such as during the bursts of [`GuildCreate`] events
+    /// when identifying, lets the buffer grow without limit. Once a bounded
+    /// buffer holds `capacity` events, the [`OverflowPolicy`] decides what
+    /// happens to new events: the shard can stop reading from the gateway
+    /// until the stream catches up, or drop the oldest or the newest event.
+    ///
+    /// The number of dropped events is available via [`Events::dropped`] and,
+    /// with the `metrics` feature, the `GatewayEventsDropped` counter. Dropped
+    /// events still count towards the session's sequence, so the session can
+    /// be resumed as usual.
+    ///
+    /// A capacity of zero is treated as a capacity of one.
+    ///
+    /// Default is an unbounded buffer.
+    ///
+    /// # Examples
+    ///
+    /// Buffer up to 10000 events, dropping the oldest events when full:
+    ///
+    /// ```no_run
+    /// use std::env;
+    /// use twilight_gateway::{shard::OverflowPolicy, Intents, Shard};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let token = env::var("DISCORD_TOKEN")?;
+    /// let (shard, events) = Shard::builder(token, Intents::GUILDS)
+    ///     .event_buffer(10_000, OverflowPolicy::DropOldest)
+    ///     .build();
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// [`Events::dropped`]: super::Events::dropped
+    /// [`GuildCreate`]: crate::Event::GuildCreate
+    /// [`OverflowPolicy`]: super::OverflowPolicy
+    pub const fn event_buffer(mut self, capacity: usize, policy: OverflowPolicy) -> Self {
+        self.0.event_buffer = Some((capacity, policy));
+
+        self
+    }
+
     /// Set the event types to process.
     ///
     /// This is an optimization technique; all events not included in the
@@ -211,6 +358,80 @@ impl ShardBuilder {
         self
     }
 
+    /// Set a predicate deciding which guilds' events to emit.
+    ///
+    /// The predicate is called with the ID of the guild each dispatch event is
+    /// related to. The ID is peeked from the payload before it's deserialized,
+    /// so the events of guilds the predicate returns `false` for are dropped
+    /// without paying the cost of deserializing them. Events that aren't
+    /// related to a guild, such as direct messages, are always emitted.
+    ///
+    /// Dropped events still count towards the session's sequence, so the
+    /// session can be resumed as usual.
+    ///
+    /// Default is no filter.
+    ///
+    /// # Examples
+    ///
+    /// Only emit the events of guilds with an even ID:
+    ///
+    /// ```no_run
+    /// use std::env;
+    /// use twilight_gateway::{Intents, Shard};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let token = env::var("DISCORD_TOKEN")?;
+    /// let (shard, events) = Shard::builder(token, Intents::GUILD_MESSAGES)
+    ///     .guild_filter(|guild_id| guild_id.0 % 2 == 0)
+    ///     .build();
+    /// # Ok(()) }
+    /// ```
+    pub fn guild_filter(
+        mut self,
+        predicate: impl Fn(GuildId) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.0.guild_filter = Some(GuildFilter::new(predicate));
+
+        self
+    }
+
+    /// Set how long to wait for the guilds listed in the ready event before
+    /// considering the shard fully ready.
+    ///
+    /// After identifying, the gateway lists the shard's guilds as unavailable
+    /// in the ready event and then sends a guild create event for each of
+    /// them. Once all of them have been received the shard emits a
+    /// [`Event::ShardFullyReady`]. Guilds that are unavailable due to an outage
+    /// may never arrive, so the event is emitted with the IDs of the missing
+    /// guilds once the timeout elapses.
+    ///
+    /// Default is 15 seconds.
+    ///
+    /// [`Event::ShardFullyReady`]: crate::Event::ShardFullyReady
+    pub const fn guild_ready_timeout(mut self, guild_ready_timeout: Duration) -> Self {
+        self.0.guild_ready_timeout = guild_ready_timeout;
+
+        self
+    }
+
+    /// Set the conditions under which the shard's connection is considered
+    /// unhealthy.
+    ///
+    /// Connections can stop working without being closed, in which case the
+    /// gateway stops acknowledging heartbeats and no more events are received.
+    /// When the policy finds the connection unhealthy the shard emits an
+    /// [`Event::ShardUnhealthy`] and resumes the session on a new connection.
+    ///
+    /// Refer to [`HealthPolicy`] for the default value.
+    ///
+    /// [`Event::ShardUnhealthy`]: crate::Event::ShardUnhealthy
+    /// [`HealthPolicy`]: super::HealthPolicy
+    pub const fn health_policy(mut self, health_policy: HealthPolicy) -> Self {
+        self.0.health_policy = health_policy;
+
+        self
+    }
+
     /// Set the HTTP client to be used by the shard for getting gateway
     /// information.
     ///
@@ -222,6 +443,39 @@ impl ShardBuilder {
         self
     }
 
+    /// Set the properties to identify with.
+    ///
+    /// The gateway uses these to tell what client the bot connects with. For
+    /// example, identifying with a browser of "Discord iOS" shows the bot with
+    /// the mobile status indicator.
+    ///
+    /// Default is a browser and device of "twilight.rs" and the operating
+    /// system the bot is running on.
+    ///
+    /// # Examples
+    ///
+    /// Show the bot as being online on mobile:
+    ///
+    /// ```no_run
+    /// use std::env;
+    /// use twilight_gateway::{Intents, Shard};
+    /// use twilight_model::gateway::payload::identify::IdentifyProperties;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let token = env::var("DISCORD_TOKEN")?;
+    /// let properties = IdentifyProperties::new("Discord iOS", "twilight.rs", "ios", "", "");
+    ///
+    /// let (shard, events) = Shard::builder(token, Intents::empty())
+    ///     .identify_properties(properties)
+    ///     .build();
+    /// # Ok(()) }
+    /// ```
+    pub fn identify_properties(mut self, identify_properties: IdentifyProperties) -> Self {
+        self.0.identify_properties.replace(identify_properties);
+
+        self
+    }
+
     /// Set the maximum number of members in a guild to load the member list.
     ///
     /// Default value is `250`. The minimum value is `50` and the maximum is
@@ -304,6 +558,36 @@ impl ShardBuilder {
         self
     }
 
+    /// Set the proxy to connect to the gateway through.
+    ///
+    /// HTTP proxies supporting CONNECT requests and SOCKS5 proxies are
+    /// supported, optionally with a username and password. The connection to
+    /// the gateway is tunneled through the proxy and still encrypted with TLS.
+    ///
+    /// The default value is no proxy.
+    ///
+    /// # Examples
+    ///
+    /// Connect through an HTTP proxy:
+    ///
+    /// ```no_run
+    /// use std::env;
+    /// use twilight_gateway::{shard::Proxy, Intents, Shard};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let token = env::var("DISCORD_TOKEN")?;
+    ///
+    /// let (shard, events) = Shard::builder(token, Intents::GUILDS)
+    ///     .proxy(Proxy::http("proxy.internal:3128"))
+    ///     .build();
+    /// # Ok(()) }
+    /// ```
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.0.proxy.replace(proxy);
+
+        self
+    }
+
     /// Set the queue to use for queueing shard connections.
     ///
     /// You probably don't need to set this yourself, because the [`Cluster`]
@@ -321,6 +605,25 @@ impl ShardBuilder {
         self
     }
 
+    /// Set the recorder to write the payloads exchanged with the gateway to.
+    ///
+    /// Every payload received from the gateway, after decompression, and every
+    /// payload sent to it is written to the recording along with when it
+    /// happened and the shard's ID. Recordings can be replayed with a
+    /// [`Replayer`] to reproduce bugs.
+    ///
+    /// Refer to the [`recording`] module for more information.
+    ///
+    /// The default value is no recorder.
+    ///
+    /// [`Replayer`]: super::recording::Replayer
+    /// [`recording`]: super::recording
+    pub fn recorder(mut self, recorder: Recorder) -> Self {
+        self.0.recorder.replace(recorder);
+
+        self
+    }
+
     /// Set the shard ID to connect as, and the total number of shards used by
     /// the bot.
     ///
@@ -368,6 +671,109 @@ impl ShardBuilder {
 
         Ok(self)
     }
+
+    /// Set a function returning the presence to set when identifying, given
+    /// the shard's ID.
+    ///
+    /// The function is called every time the shard identifies to create a new
+    /// session, so the presence may change between sessions. Its result
+    /// replaces the presence set with [`presence`]; returning `None`
+    /// identifies without a presence.
+    ///
+    /// Default is no function.
+    ///
+    /// # Examples
+    ///
+    /// Show which shard the bot is connected with in its presence:
+    ///
+    /// ```no_run
+    /// use std::env;
+    /// use twilight_gateway::{Intents, Shard};
+    /// use twilight_model::gateway::{
+    ///     payload::update_presence::UpdatePresencePayload,
+    ///     presence::{ActivityType, MinimalActivity, Status},
+    /// };
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let token = env::var("DISCORD_TOKEN")?;
+    ///
+    /// let (shard, events) = Shard::builder(token, Intents::empty())
+    ///     .shard_presence(|shard_id| {
+    ///         let activity = MinimalActivity {
+    ///             kind: ActivityType::Playing,
+    ///             name: format!("on shard {}", shard_id),
+    ///             url: None,
+    ///         };
+    ///
+    ///         UpdatePresencePayload::new(vec![activity.into()], false, None, Status::Online).ok()
+    ///     })
+    ///     .build();
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// [`presence`]: Self::presence
+    pub fn shard_presence(
+        mut self,
+        shard_presence: impl Fn(u64) -> Option<UpdatePresencePayload> + Send + Sync + 'static,
+    ) -> Self {
+        self.0.shard_presence = Some(ShardPresence::new(shard_presence));
+
+        self
+    }
+
+    /// Set the TLS connector to connect to the gateway with.
+    ///
+    /// This can be used to trust additional root certificates, such as the
+    /// one of a proxy intercepting TLS connections.
+    ///
+    /// The default value is a connector of the enabled TLS feature with its
+    /// default configuration.
+    pub fn tls(mut self, tls: TlsConnector) -> Self {
+        self.0.tls.replace(tls);
+
+        self
+    }
+
+    /// Set the compression of the messages received from the gateway.
+    ///
+    /// [`TransportCompression::ZlibStream`] requires the `compression`
+    /// feature and [`TransportCompression::ZstdStream`] the `zstd-stream`
+    /// feature. zstd-stream compresses payloads better and decompresses them
+    /// faster than zlib-stream.
+    ///
+    /// Default is zlib-stream if the `compression` feature is enabled, and no
+    /// compression otherwise.
+    ///
+    /// # Examples
+    ///
+    /// Receive messages compressed with zstd-stream:
+    ///
+    /// ```no_run
+    /// # #[cfg(feature = "zstd-stream")]
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::env;
+    /// use twilight_gateway::{shard::TransportCompression, Intents, Shard};
+    ///
+    /// let token = env::var("DISCORD_TOKEN")?;
+    ///
+    /// let shard = Shard::builder(token, Intents::empty())
+    ///     .transport_compression(TransportCompression::ZstdStream)
+    ///     .build();
+    /// # Ok(()) }
+    /// # #[cfg(not(feature = "zstd-stream"))]
+    /// # fn main() {}
+    /// ```
+    ///
+    /// [`TransportCompression::ZlibStream`]: super::TransportCompression
+    /// [`TransportCompression::ZstdStream`]: super::TransportCompression
+    pub const fn transport_compression(
+        mut self,
+        transport_compression: TransportCompression,
+    ) -> Self {
+        self.0.transport_compression = transport_compression;
+
+        self
+    }
 }
 
 impl<T: Into<String>> From<(T, Intents)> for ShardBuilder {
@@ -0,0 +1,373 @@
+//! Channel events are sent over from a shard to its event stream.
+//!
+//! The channel is unbounded by default. When bounded via
+//! [`ShardBuilder::event_buffer`], the [`OverflowPolicy`] decides what happens
+//! once the buffer is full.
+//!
+//! [`ShardBuilder::event_buffer`]: super::ShardBuilder::event_buffer
+
+use futures_util::task::AtomicWaker;
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+};
+use tokio::sync::Notify;
+
+/// What to do with events when the buffer of a shard's event stream is full.
+///
+/// Refer to [`ShardBuilder::event_buffer`] for more information.
+///
+/// [`ShardBuilder::event_buffer`]: super::ShardBuilder::event_buffer
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum OverflowPolicy {
+    /// Stop reading payloads from the gateway until the event stream has
+    /// caught up.
+    ///
+    /// No events are dropped. Messages received from the gateway in the
+    /// meantime are kept in their compressed form until the shard resumes
+    /// reading them. Heartbeat acknowledgements are among them, so if the
+    /// event stream isn't polled for longer than the gateway's heartbeat
+    /// interval the connection may be closed, in which case the shard
+    /// resumes it.
+    Backpressure,
+    /// Drop the oldest buffered event to make room for the new event.
+    DropOldest,
+    /// Drop the new event, keeping the buffered events.
+    DropNewest,
+}
+
+/// State shared between the senders and the receiver of a channel.
+#[derive(Debug)]
+struct Shared<T> {
+    buffer: Mutex<VecDeque<T>>,
+    capacity: Option<usize>,
+    /// Whether a sender closed the channel, ending the stream once the
+    /// buffer is empty.
+    closed: AtomicBool,
+    dropped: AtomicU64,
+    policy: OverflowPolicy,
+    /// Notified when an event is received or the receiver is dropped.
+    receiver_notify: Notify,
+    receiver_closed: AtomicBool,
+    receiver_waker: AtomicWaker,
+    senders: AtomicUsize,
+}
+
+impl<T> Shared<T> {
+    fn is_full(&self) -> bool {
+        self.capacity.map_or(false, |capacity| {
+            self.buffer.lock().expect("buffer poisoned").len() >= capacity
+        })
+    }
+}
+
+/// Create a new channel, which is bounded if a capacity and overflow policy
+/// are provided.
+pub fn channel<T>(bound: Option<(usize, OverflowPolicy)>) -> (Sender<T>, Receiver<T>) {
+    let (capacity, policy) = match bound {
+        Some((capacity, policy)) => (Some(capacity.max(1)), policy),
+        None => (None, OverflowPolicy::Backpressure),
+    };
+
+    let shared = Arc::new(Shared {
+        buffer: Mutex::new(VecDeque::new()),
+        capacity,
+        closed: AtomicBool::new(false),
+        dropped: AtomicU64::new(0),
+        policy,
+        receiver_notify: Notify::new(),
+        receiver_closed: AtomicBool::new(false),
+        receiver_waker: AtomicWaker::new(),
+        senders: AtomicUsize::new(1),
+    });
+
+    (
+        Sender {
+            shared: Arc::clone(&shared),
+        },
+        Receiver { shared },
+    )
+}
+
+/// Sending half of a channel.
+#[derive(Debug)]
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Sender<T> {
+    /// Send an item, applying the overflow policy if the buffer is full.
+    ///
+    /// Returns the item back if the receiver has been dropped or the channel
+    /// has been closed.
+    pub fn send(&self, item: T) -> Result<(), T> {
+        if self.shared.receiver_closed.load(Ordering::Acquire)
+            || self.shared.closed.load(Ordering::Acquire)
+        {
+            return Err(item);
+        }
+
+        {
+            let mut buffer = self.shared.buffer.lock().expect("buffer poisoned");
+
+            let full = self
+                .shared
+                .capacity
+                .map_or(false, |capacity| buffer.len() >= capacity);
+
+            if full {
+                match self.shared.policy {
+                    // The processor waits for capacity before reading the
+                    // next payload, so the buffer only overflows by the
+                    // events of a single payload.
+                    OverflowPolicy::Backpressure => {}
+                    OverflowPolicy::DropNewest => {
+                        drop(buffer);
+                        self.record_dropped();
+
+                        return Ok(());
+                    }
+                    OverflowPolicy::DropOldest => {
+                        buffer.pop_front();
+                        self.record_dropped();
+                    }
+                }
+            }
+
+            buffer.push_back(item);
+        }
+
+        self.shared.receiver_waker.wake();
+
+        Ok(())
+    }
+
+    /// Number of items that have been dropped due to the buffer being full.
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Close the channel for all senders.
+    ///
+    /// Items that have already been sent are still received, after which the
+    /// receiver's stream ends even if senders remain.
+    pub fn close(&self) {
+        self.shared.closed.store(true, Ordering::Release);
+        self.shared.receiver_waker.wake();
+    }
+
+    /// Number of items in the buffer.
+    #[cfg(feature = "metrics")]
+    pub fn len(&self) -> usize {
+        self.shared.buffer.lock().expect("buffer poisoned").len()
+    }
+
+    /// Wait until the buffer has capacity for another event.
+    ///
+    /// This only waits if the channel is bounded with the
+    /// [`OverflowPolicy::Backpressure`] policy, and returns immediately if
+    /// the receiver has been dropped.
+    pub async fn ready(&self) {
+        if self.shared.policy != OverflowPolicy::Backpressure {
+            return;
+        }
+
+        loop {
+            // The receiver notifies with a permit, so a notification sent
+            // between the check and awaiting isn't missed.
+            if self.shared.receiver_closed.load(Ordering::Acquire) || !self.shared.is_full() {
+                return;
+            }
+
+            self.shared.receiver_notify.notified().await;
+        }
+    }
+
+    fn record_dropped(&self) {
+        let dropped = self.shared.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+
+        tracing::debug!(dropped, "event buffer full, dropped event");
+
+        #[cfg(feature = "metrics")]
+        metrics::counter!("GatewayEventsDropped", 1);
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::AcqRel);
+
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.shared.receiver_waker.wake();
+        }
+    }
+}
+
+/// Receiving half of a channel.
+#[derive(Debug)]
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Receiver<T> {
+    /// Number of events that have been dropped due to the buffer being full.
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Poll to receive the next item.
+    ///
+    /// Returns `None` once all senders have been dropped or the channel has
+    /// been closed, and the buffer is empty.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        // Register before checking so that an item sent in between wakes
+        // the task.
+        self.shared.receiver_waker.register(cx.waker());
+
+        let item = self
+            .shared
+            .buffer
+            .lock()
+            .expect("buffer poisoned")
+            .pop_front();
+
+        if let Some(item) = item {
+            if self.shared.capacity.is_some() {
+                self.shared.receiver_notify.notify_one();
+            }
+
+            return Poll::Ready(Some(item));
+        }
+
+        if self.shared.senders.load(Ordering::Acquire) == 0
+            || self.shared.closed.load(Ordering::Acquire)
+        {
+            return Poll::Ready(None);
+        }
+
+        Poll::Pending
+    }
+
+    /// Receive the next item.
+    #[cfg(test)]
+    pub async fn recv(&mut self) -> Option<T> {
+        futures_util::future::poll_fn(|cx| self.poll_recv(cx)).await
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.receiver_closed.store(true, Ordering::Release);
+        self.shared.receiver_notify.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{channel, OverflowPolicy, Receiver, Sender};
+    use static_assertions::assert_impl_all;
+    use std::fmt::Debug;
+    use tokio::time::{self, Duration};
+
+    assert_impl_all!(OverflowPolicy: Clone, Copy, Debug, Eq, Send, Sync);
+    assert_impl_all!(Receiver<u8>: Debug, Send, Sync);
+    assert_impl_all!(Sender<u8>: Clone, Debug, Send, Sync);
+
+    #[tokio::test]
+    async fn test_unbounded() {
+        let (tx, mut rx) = channel(None);
+
+        for i in 0..100_u8 {
+            tx.send(i).unwrap();
+        }
+
+        tx.ready().await;
+        drop(tx);
+
+        for i in 0..100_u8 {
+            assert_eq!(Some(i), rx.recv().await);
+        }
+
+        assert!(rx.recv().await.is_none());
+        assert_eq!(0, rx.dropped());
+    }
+
+    #[tokio::test]
+    async fn test_drop_newest() {
+        let (tx, mut rx) = channel(Some((2, OverflowPolicy::DropNewest)));
+
+        for i in 0..5_u8 {
+            tx.send(i).unwrap();
+        }
+
+        assert_eq!(3, rx.dropped());
+        assert_eq!(Some(0), rx.recv().await);
+        assert_eq!(Some(1), rx.recv().await);
+    }
+
+    #[tokio::test]
+    async fn test_drop_oldest() {
+        let (tx, mut rx) = channel(Some((2, OverflowPolicy::DropOldest)));
+
+        for i in 0..5_u8 {
+            tx.send(i).unwrap();
+        }
+
+        assert_eq!(3, rx.dropped());
+        assert_eq!(Some(3), rx.recv().await);
+        assert_eq!(Some(4), rx.recv().await);
+    }
+
+    #[tokio::test]
+    async fn test_backpressure() {
+        let (tx, mut rx) = channel(Some((1, OverflowPolicy::Backpressure)));
+        tx.send(1_u8).unwrap();
+
+        assert!(time::timeout(Duration::from_millis(10), tx.ready())
+            .await
+            .is_err());
+
+        assert_eq!(Some(1), rx.recv().await);
+        time::timeout(Duration::from_millis(10), tx.ready())
+            .await
+            .unwrap();
+        assert_eq!(0, rx.dropped());
+    }
+
+    #[tokio::test]
+    async fn test_receiver_dropped() {
+        let (tx, rx) = channel(Some((1, OverflowPolicy::Backpressure)));
+        tx.send(1_u8).unwrap();
+        drop(rx);
+
+        time::timeout(Duration::from_millis(10), tx.ready())
+            .await
+            .unwrap();
+        assert_eq!(Err(2), tx.send(2));
+    }
+
+    #[tokio::test]
+    async fn test_close() {
+        let (tx, mut rx) = channel(None);
+        let tx_2 = tx.clone();
+        tx.send(1_u8).unwrap();
+        tx_2.close();
+
+        assert_eq!(Err(2), tx.send(2));
+        assert_eq!(Some(1), rx.recv().await);
+        assert!(rx.recv().await.is_none());
+    }
+}
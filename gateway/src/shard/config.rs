@@ -1,8 +1,67 @@
+use super::{
+    channel::OverflowPolicy,
+    connect::{Proxy, TlsConnector},
+    encoding::Encoding,
+    processor::{HealthPolicy, TransportCompression},
+    recording::Recorder,
+};
 use crate::EventTypeFlags;
-use std::sync::Arc;
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    sync::Arc,
+    time::Duration,
+};
 use twilight_gateway_queue::Queue;
 use twilight_http::Client;
-use twilight_model::gateway::{payload::update_presence::UpdatePresencePayload, Intents};
+use twilight_model::{
+    gateway::{
+        payload::{identify::IdentifyProperties, update_presence::UpdatePresencePayload},
+        Intents,
+    },
+    id::GuildId,
+};
+
+/// Predicate deciding which guilds' events are emitted.
+#[derive(Clone)]
+pub(crate) struct GuildFilter(Arc<dyn Fn(GuildId) -> bool + Send + Sync>);
+
+impl GuildFilter {
+    pub fn new(predicate: impl Fn(GuildId) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(predicate))
+    }
+
+    pub fn allows(&self, guild_id: GuildId) -> bool {
+        (self.0)(guild_id)
+    }
+}
+
+impl Debug for GuildFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_tuple("GuildFilter").field(&"<fn>").finish()
+    }
+}
+
+/// Function returning the presence of a shard when it identifies.
+#[derive(Clone)]
+pub(crate) struct ShardPresence(Arc<dyn Fn(u64) -> Option<UpdatePresencePayload> + Send + Sync>);
+
+impl ShardPresence {
+    pub fn new(
+        presence: impl Fn(u64) -> Option<UpdatePresencePayload> + Send + Sync + 'static,
+    ) -> Self {
+        Self(Arc::new(presence))
+    }
+
+    pub fn presence(&self, shard_id: u64) -> Option<UpdatePresencePayload> {
+        (self.0)(shard_id)
+    }
+}
+
+impl Debug for ShardPresence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_tuple("ShardPresence").field(&"<fn>").finish()
+    }
+}
 
 /// The configuration used by the shard to identify with the gateway and
 /// operate.
@@ -12,20 +71,46 @@ use twilight_model::gateway::{payload::update_presence::UpdatePresencePayload, I
 /// [`Shard::builder`]: super::Shard::builder
 #[derive(Clone, Debug)]
 pub struct Config {
+    pub(crate) encoding: Encoding,
+    pub(crate) event_buffer: Option<(usize, OverflowPolicy)>,
     pub(crate) event_types: EventTypeFlags,
     pub(crate) gateway_url: Option<Box<str>>,
+    pub(crate) guild_filter: Option<GuildFilter>,
+    pub(crate) guild_ready_timeout: Duration,
+    pub(crate) health_policy: HealthPolicy,
     pub(crate) http_client: Client,
+    pub(super) identify_properties: Option<IdentifyProperties>,
     pub(super) intents: Intents,
     pub(super) large_threshold: u64,
     pub(super) presence: Option<UpdatePresencePayload>,
+    pub(super) proxy: Option<Proxy>,
     pub(super) queue: Arc<Box<dyn Queue>>,
+    pub(crate) recorder: Option<Recorder>,
     pub(crate) shard: [u64; 2],
+    pub(super) shard_presence: Option<ShardPresence>,
+    pub(super) tls: Option<TlsConnector>,
     pub(super) token: Box<str>,
+    pub(crate) transport_compression: TransportCompression,
     pub(crate) session_id: Option<Box<str>>,
     pub(crate) sequence: Option<u64>,
 }
 
 impl Config {
+    /// Encoding of the payloads exchanged with the gateway.
+    pub const fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Capacity of the event stream's buffer and the policy applied when it's
+    /// full, if the buffer is bounded.
+    ///
+    /// Refer to [`ShardBuilder::event_buffer`] for more information.
+    ///
+    /// [`ShardBuilder::event_buffer`]: super::ShardBuilder::event_buffer
+    pub const fn event_buffer(&self) -> Option<(usize, OverflowPolicy)> {
+        self.event_buffer
+    }
+
     /// Copy of the event type flags.
     pub const fn event_types(&self) -> EventTypeFlags {
         self.event_types
@@ -36,12 +121,55 @@ impl Config {
         self.gateway_url.as_deref()
     }
 
+    /// Whether events of a guild are emitted according to the configured guild
+    /// filter.
+    ///
+    /// This is always `true` if no guild filter is configured.
+    ///
+    /// Refer to [`ShardBuilder::guild_filter`] for more information.
+    ///
+    /// [`ShardBuilder::guild_filter`]: super::ShardBuilder::guild_filter
+    pub fn allows_guild(&self, guild_id: GuildId) -> bool {
+        self.guild_filter
+            .as_ref()
+            .map_or(true, |filter| filter.allows(guild_id))
+    }
+
+    /// How long to wait for the guilds listed in the ready event before
+    /// considering the shard fully ready.
+    ///
+    /// Refer to [`ShardBuilder::guild_ready_timeout`] for more information.
+    ///
+    /// [`ShardBuilder::guild_ready_timeout`]: super::ShardBuilder::guild_ready_timeout
+    pub const fn guild_ready_timeout(&self) -> Duration {
+        self.guild_ready_timeout
+    }
+
+    /// Conditions under which the shard's connection is considered unhealthy.
+    ///
+    /// Refer to [`ShardBuilder::health_policy`] for more information.
+    ///
+    /// [`ShardBuilder::health_policy`]: super::ShardBuilder::health_policy
+    pub const fn health_policy(&self) -> HealthPolicy {
+        self.health_policy
+    }
+
     /// Return an immutable reference to the `twilight_http` client to be used
     /// by the shard.
     pub const fn http_client(&self) -> &Client {
         &self.http_client
     }
 
+    /// Return an immutable reference to the properties to identify with, if
+    /// they were customized.
+    ///
+    /// Refer to [`ShardBuilder::identify_properties`] for more information.
+    ///
+    /// [`ShardBuilder::identify_properties`]: super::ShardBuilder::identify_properties
+    pub const fn identify_properties(&self) -> Option<&IdentifyProperties> {
+        self.identify_properties.as_ref()
+    }
+
     /// Return a copy of the intents that the gateway is using.
     pub const fn intents(&self) -> Intents {
         self.intents
@@ -62,16 +190,61 @@ impl Config {
         self.presence.as_ref()
     }
 
+    /// Return an immutable reference to the proxy to connect to the gateway
+    /// through, if any.
+    ///
+    /// Refer to [`ShardBuilder::proxy`] for more information.
+    ///
+    /// [`ShardBuilder::proxy`]: super::ShardBuilder::proxy
+    pub const fn proxy(&self) -> Option<&Proxy> {
+        self.proxy.as_ref()
+    }
+
+    /// Presence to set when identifying, from the [shard presence] function if
+    /// there is one.
+    ///
+    /// [shard presence]: super::ShardBuilder::shard_presence
+    pub(crate) fn identify_presence(&self) -> Option<UpdatePresencePayload> {
+        self.shard_presence.as_ref().map_or_else(
+            || self.presence.clone(),
+            |shard_presence| shard_presence.presence(self.shard[0]),
+        )
+    }
+
+    /// Recorder of the payloads exchanged with the gateway, if any.
+    ///
+    /// Refer to [`ShardBuilder::recorder`] for more information.
+    ///
+    /// [`ShardBuilder::recorder`]: super::ShardBuilder::recorder
+    pub const fn recorder(&self) -> Option<&Recorder> {
+        self.recorder.as_ref()
+    }
+
     /// The shard's ID and the total number of shards used by the bot.
     pub const fn shard(&self) -> [u64; 2] {
         self.shard
     }
 
+    /// Return an immutable reference to the TLS connector to connect to the
+    /// gateway with, if it was customized.
+    ///
+    /// Refer to [`ShardBuilder::tls`] for more information.
+    ///
+    /// [`ShardBuilder::tls`]: super::ShardBuilder::tls
+    pub const fn tls(&self) -> Option<&TlsConnector> {
+        self.tls.as_ref()
+    }
+
     /// Return an immutable reference to the token used to authenticate with
     /// when identifying with the gateway.
     pub const fn token(&self) -> &str {
         &self.token
     }
+
+    /// Compression of the messages received from the gateway.
+    pub const fn transport_compression(&self) -> TransportCompression {
+        self.transport_compression
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,511 @@
+//! Establishing connections to the gateway, optionally through a proxy.
+
+use super::ShardStream;
+use std::{
+    error::Error,
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+    io::Error as IoError,
+    str,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+use tokio_tungstenite::{tungstenite::protocol::WebSocketConfig, Connector};
+use url::Url;
+
+/// Maximum length of the response to an HTTP CONNECT request.
+const HTTP_RESPONSE_LIMIT: usize = 8192;
+
+/// Connecting through a proxy failed.
+#[derive(Debug)]
+pub struct ProxyError {
+    kind: ProxyErrorType,
+    source: Option<Box<dyn Error + Send + Sync>>,
+}
+
+impl ProxyError {
+    /// Immutable reference to the type of error that occurred.
+    #[must_use = "retrieving the type has no effect if left unused"]
+    pub const fn kind(&self) -> &ProxyErrorType {
+        &self.kind
+    }
+
+    /// Consume the error, returning the source error if there is any.
+    #[must_use = "consuming the error and retrieving the source has no effect if left unused"]
+    pub fn into_source(self) -> Option<Box<dyn Error + Send + Sync>> {
+        self.source
+    }
+
+    /// Consume the error, returning the owned error type and the source error.
+    #[must_use = "consuming the error into its parts has no effect if left unused"]
+    pub fn into_parts(self) -> (ProxyErrorType, Option<Box<dyn Error + Send + Sync>>) {
+        (self.kind, self.source)
+    }
+
+    fn io(source: IoError) -> Self {
+        Self {
+            kind: ProxyErrorType::Io,
+            source: Some(Box::new(source)),
+        }
+    }
+
+    const fn response_invalid() -> Self {
+        Self {
+            kind: ProxyErrorType::ResponseInvalid,
+            source: None,
+        }
+    }
+}
+
+impl Display for ProxyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match &self.kind {
+            ProxyErrorType::AuthenticationFailed => {
+                f.write_str("the proxy rejected the credentials")
+            }
+            ProxyErrorType::AuthenticationUnsupported => {
+                f.write_str("the proxy doesn't support any of the authentication methods")
+            }
+            ProxyErrorType::HostTooLong => {
+                f.write_str("the gateway host is too long to be sent to the proxy")
+            }
+            ProxyErrorType::Io => f.write_str("communicating with the proxy failed"),
+            ProxyErrorType::Refused { code } => f.write_fmt(format_args!(
+                "the proxy refused to connect to the gateway with code {}",
+                code
+            )),
+            ProxyErrorType::ResponseInvalid => f.write_str("the proxy's response is invalid"),
+        }
+    }
+}
+
+impl Error for ProxyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| &**source as &(dyn Error + 'static))
+    }
+}
+
+/// Type of [`ProxyError`] that occurred.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ProxyErrorType {
+    /// Proxy rejected the configured credentials, or requires credentials
+    /// and none were configured.
+    AuthenticationFailed,
+    /// Proxy doesn't support authenticating with a username and password.
+    AuthenticationUnsupported,
+    /// Gateway host is longer than the 255 bytes a SOCKS5 request can hold.
+    HostTooLong,
+    /// Connecting to the proxy or exchanging messages with it failed.
+    Io,
+    /// Proxy refused to connect to the gateway.
+    Refused {
+        /// HTTP status code or SOCKS5 reply code returned by the proxy.
+        code: u16,
+    },
+    /// Proxy sent a response that couldn't be understood.
+    ResponseInvalid,
+}
+
+/// Protocol used to communicate with a [`Proxy`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum ProxyKind {
+    /// Tunnel the connection with an HTTP CONNECT request.
+    Http,
+    /// Tunnel the connection with a SOCKS5 CONNECT command.
+    Socks5,
+}
+
+/// Proxy to connect to the gateway through.
+///
+/// The connection to the gateway is tunneled through the proxy, so it's still
+/// encrypted end to end with TLS.
+///
+/// # Examples
+///
+/// Connect through a SOCKS5 proxy requiring authentication:
+///
+/// ```no_run
+/// use std::env;
+/// use twilight_gateway::{shard::Proxy, Intents, Shard};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let token = env::var("DISCORD_TOKEN")?;
+/// let proxy = Proxy::socks5("proxy.internal:1080").auth("username", "password");
+///
+/// let (shard, events) = Shard::builder(token, Intents::GUILDS)
+///     .proxy(proxy)
+///     .build();
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Eq, PartialEq)]
+pub struct Proxy {
+    address: Box<str>,
+    auth: Option<(Box<str>, Box<str>)>,
+    kind: ProxyKind,
+}
+
+impl Proxy {
+    /// Create a proxy tunneling connections with HTTP CONNECT requests.
+    ///
+    /// The address is the proxy's host and port, such as `proxy.internal:3128`.
+    pub fn http(address: impl Into<String>) -> Self {
+        Self::new(address.into(), ProxyKind::Http)
+    }
+
+    /// Create a SOCKS5 proxy.
+    ///
+    /// The address is the proxy's host and port, such as `proxy.internal:1080`.
+    /// The gateway's hostname is resolved by the proxy.
+    pub fn socks5(address: impl Into<String>) -> Self {
+        Self::new(address.into(), ProxyKind::Socks5)
+    }
+
+    fn new(address: String, kind: ProxyKind) -> Self {
+        Self {
+            address: address.into_boxed_str(),
+            auth: None,
+            kind,
+        }
+    }
+
+    /// Set the username and password to authenticate with.
+    ///
+    /// HTTP proxies receive them with basic authentication.
+    pub fn auth(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.auth = Some((
+            username.into().into_boxed_str(),
+            password.into().into_boxed_str(),
+        ));
+
+        self
+    }
+
+    /// Host and port of the proxy.
+    pub const fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Protocol used to communicate with the proxy.
+    pub const fn kind(&self) -> ProxyKind {
+        self.kind
+    }
+
+    /// Username to authenticate with, if any.
+    pub fn username(&self) -> Option<&str> {
+        self.auth.as_ref().map(|(username, _)| &**username)
+    }
+
+    /// Open a connection to a host tunneled through the proxy.
+    async fn connect(&self, host: &str, port: u16) -> Result<TcpStream, ProxyError> {
+        let mut stream = TcpStream::connect(&*self.address)
+            .await
+            .map_err(ProxyError::io)?;
+
+        match self.kind {
+            ProxyKind::Http => self.http_connect(&mut stream, host, port).await?,
+            ProxyKind::Socks5 => self.socks5_connect(&mut stream, host, port).await?,
+        }
+
+        tracing::debug!(proxy = %self.address, "tunneled connection through proxy");
+
+        Ok(stream)
+    }
+
+    async fn http_connect(
+        &self,
+        stream: &mut TcpStream,
+        host: &str,
+        port: u16,
+    ) -> Result<(), ProxyError> {
+        let mut request = format!(
+            "CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n",
+            host = host,
+            port = port
+        );
+
+        if let Some((username, password)) = &self.auth {
+            let credentials = base64::encode(format!("{}:{}", username, password));
+            request.push_str("Proxy-Authorization: Basic ");
+            request.push_str(&credentials);
+            request.push_str("\r\n");
+        }
+
+        request.push_str("\r\n");
+        stream
+            .write_all(request.as_bytes())
+            .await
+            .map_err(ProxyError::io)?;
+
+        // Read byte by byte so that nothing past the end of the response is
+        // consumed from the tunnel.
+        let mut response = Vec::new();
+
+        while !response.ends_with(b"\r\n\r\n") {
+            if response.len() == HTTP_RESPONSE_LIMIT {
+                return Err(ProxyError::response_invalid());
+            }
+
+            response.push(stream.read_u8().await.map_err(ProxyError::io)?);
+        }
+
+        // The status line looks like `HTTP/1.1 200 Connection established`.
+        let code = str::from_utf8(&response)
+            .ok()
+            .and_then(|response| response.split_whitespace().nth(1))
+            .and_then(|code| code.parse::<u16>().ok())
+            .ok_or_else(ProxyError::response_invalid)?;
+
+        match code {
+            200..=299 => Ok(()),
+            407 => Err(ProxyError {
+                kind: ProxyErrorType::AuthenticationFailed,
+                source: None,
+            }),
+            code => Err(ProxyError {
+                kind: ProxyErrorType::Refused { code },
+                source: None,
+            }),
+        }
+    }
+
+    #[allow(clippy::too_many_lines)]
+    async fn socks5_connect(
+        &self,
+        stream: &mut TcpStream,
+        host: &str,
+        port: u16,
+    ) -> Result<(), ProxyError> {
+        const VERSION: u8 = 5;
+        const NO_AUTHENTICATION: u8 = 0;
+        const USERNAME_PASSWORD: u8 = 2;
+        const NO_ACCEPTABLE_METHODS: u8 = 0xff;
+        const CONNECT: u8 = 1;
+        const DOMAIN_NAME: u8 = 3;
+        const IPV4: u8 = 1;
+        const IPV6: u8 = 4;
+
+        // Offer to authenticate with a username and password if there are
+        // credentials, and without authentication otherwise.
+        let method = if self.auth.is_some() {
+            USERNAME_PASSWORD
+        } else {
+            NO_AUTHENTICATION
+        };
+        stream
+            .write_all(&[VERSION, 1, method])
+            .await
+            .map_err(ProxyError::io)?;
+
+        let mut reply = [0; 2];
+        stream
+            .read_exact(&mut reply)
+            .await
+            .map_err(ProxyError::io)?;
+
+        match reply {
+            [VERSION, NO_ACCEPTABLE_METHODS] => {
+                // Without credentials, the proxy requires authentication.
+                let kind = if self.auth.is_some() {
+                    ProxyErrorType::AuthenticationUnsupported
+                } else {
+                    ProxyErrorType::AuthenticationFailed
+                };
+
+                return Err(ProxyError { kind, source: None });
+            }
+            [VERSION, selected] if selected == method => {}
+            _ => return Err(ProxyError::response_invalid()),
+        }
+
+        if let Some((username, password)) = &self.auth {
+            // RFC 1929 limits the username and password to 255 bytes each.
+            if username.len() > 255 || password.len() > 255 {
+                return Err(ProxyError {
+                    kind: ProxyErrorType::AuthenticationFailed,
+                    source: None,
+                });
+            }
+
+            #[allow(clippy::cast_possible_truncation)]
+            let mut request = vec![1, username.len() as u8];
+            request.extend_from_slice(username.as_bytes());
+            #[allow(clippy::cast_possible_truncation)]
+            request.push(password.len() as u8);
+            request.extend_from_slice(password.as_bytes());
+            stream.write_all(&request).await.map_err(ProxyError::io)?;
+
+            let mut reply = [0; 2];
+            stream
+                .read_exact(&mut reply)
+                .await
+                .map_err(ProxyError::io)?;
+
+            if reply[1] != 0 {
+                return Err(ProxyError {
+                    kind: ProxyErrorType::AuthenticationFailed,
+                    source: None,
+                });
+            }
+        }
+
+        if host.len() > 255 {
+            return Err(ProxyError {
+                kind: ProxyErrorType::HostTooLong,
+                source: None,
+            });
+        }
+
+        #[allow(clippy::cast_possible_truncation)]
+        let mut request = vec![VERSION, CONNECT, 0, DOMAIN_NAME, host.len() as u8];
+        request.extend_from_slice(host.as_bytes());
+        request.extend_from_slice(&port.to_be_bytes());
+        stream.write_all(&request).await.map_err(ProxyError::io)?;
+
+        let mut reply = [0; 4];
+        stream
+            .read_exact(&mut reply)
+            .await
+            .map_err(ProxyError::io)?;
+
+        if reply[0] != VERSION {
+            return Err(ProxyError::response_invalid());
+        }
+
+        if reply[1] != 0 {
+            return Err(ProxyError {
+                kind: ProxyErrorType::Refused {
+                    code: reply[1].into(),
+                },
+                source: None,
+            });
+        }
+
+        // Skip the address the proxy bound to and its port.
+        let address_len = match reply[3] {
+            IPV4 => 4,
+            IPV6 => 16,
+            DOMAIN_NAME => stream.read_u8().await.map_err(ProxyError::io)?.into(),
+            _ => return Err(ProxyError::response_invalid()),
+        };
+        let mut bound = vec![0; address_len + 2];
+        stream
+            .read_exact(&mut bound)
+            .await
+            .map_err(ProxyError::io)?;
+
+        Ok(())
+    }
+}
+
+impl Debug for Proxy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("Proxy")
+            .field("address", &self.address)
+            .field("kind", &self.kind)
+            .field(
+                "auth",
+                &self
+                    .auth
+                    .as_ref()
+                    .map(|(username, _)| (username, "<redacted>")),
+            )
+            .finish()
+    }
+}
+
+/// TLS configuration to connect to the gateway with.
+///
+/// The available variants depend on the enabled TLS features.
+#[derive(Clone)]
+#[non_exhaustive]
+pub enum TlsConnector {
+    /// Connector of the `native` feature's TLS implementation.
+    #[cfg(feature = "native")]
+    NativeTls(native_tls_crate::TlsConnector),
+    /// Configuration of the `rustls-native-roots` and `rustls-webpki-roots`
+    /// features' TLS implementation.
+    #[cfg(any(feature = "rustls-native-roots", feature = "rustls-webpki-roots"))]
+    Rustls(std::sync::Arc<rustls_crate::ClientConfig>),
+}
+
+impl TlsConnector {
+    fn connector(&self) -> Connector {
+        match self {
+            #[cfg(feature = "native")]
+            Self::NativeTls(connector) => Connector::NativeTls(connector.clone()),
+            #[cfg(any(feature = "rustls-native-roots", feature = "rustls-webpki-roots"))]
+            Self::Rustls(config) => Connector::Rustls(std::sync::Arc::clone(config)),
+        }
+    }
+}
+
+impl Debug for TlsConnector {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            #[cfg(feature = "native")]
+            Self::NativeTls(_) => f.debug_tuple("NativeTls").finish(),
+            #[cfg(any(feature = "rustls-native-roots", feature = "rustls-webpki-roots"))]
+            Self::Rustls(_) => f.debug_tuple("Rustls").finish(),
+        }
+    }
+}
+
+/// Connect to the gateway, tunneling the connection through a proxy if one
+/// is configured.
+pub async fn connect(
+    url: &Url,
+    proxy: Option<&Proxy>,
+    tls: Option<&TlsConnector>,
+    config: WebSocketConfig,
+) -> Result<ShardStream, Box<dyn Error + Send + Sync>> {
+    let host = url.host_str().ok_or("gateway url has no host")?;
+    let port = url
+        .port_or_known_default()
+        .ok_or("gateway url has no port")?;
+
+    let socket = if let Some(proxy) = proxy {
+        proxy.connect(host, port).await?
+    } else {
+        TcpStream::connect((host, port)).await?
+    };
+
+    let (stream, _) = tokio_tungstenite::client_async_tls_with_config(
+        url.as_str(),
+        socket,
+        Some(config),
+        tls.map(TlsConnector::connector),
+    )
+    .await?;
+
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Proxy, ProxyError, ProxyErrorType, ProxyKind, TlsConnector};
+    use static_assertions::assert_impl_all;
+    use std::{error::Error, fmt::Debug, hash::Hash};
+
+    assert_impl_all!(Proxy: Clone, Debug, Eq, PartialEq, Send, Sync);
+    assert_impl_all!(ProxyError: Error, Send, Sync);
+    assert_impl_all!(ProxyErrorType: Debug, Send, Sync);
+    assert_impl_all!(ProxyKind: Clone, Copy, Debug, Eq, Hash, PartialEq, Send, Sync);
+    assert_impl_all!(TlsConnector: Clone, Debug, Send, Sync);
+
+    #[test]
+    fn test_debug_hides_password() {
+        let proxy = Proxy::http("localhost:3128").auth("user", "hunter2");
+        assert_eq!(Some("user"), proxy.username());
+        assert_eq!(ProxyKind::Http, proxy.kind());
+        assert_eq!("localhost:3128", proxy.address());
+
+        let debug = format!("{:?}", proxy);
+        assert!(debug.contains("user"));
+        assert!(!debug.contains("hunter2"));
+    }
+}
@@ -1,12 +1,22 @@
-use super::json;
+use super::{
+    channel::{self, OverflowPolicy, Receiver, Sender},
+    config::GuildFilter,
+    encoding::Encoding,
+    lazy::{LazyEvent, RawEvent},
+};
 use crate::{Event, EventTypeFlags};
 use std::{
     convert::TryFrom,
     error::Error,
     fmt::{Display, Formatter, Result as FmtResult},
 };
-use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
-use twilight_model::gateway::event::shard::Payload;
+use twilight_model::{
+    gateway::{
+        event::{shard::Payload, EventType},
+        OpCode,
+    },
+    id::GuildId,
+};
 
 #[derive(Debug)]
 pub struct EmitJsonError {
@@ -56,20 +66,115 @@ pub enum EmitJsonErrorType {
     Parsing,
 }
 
+/// Listener events are sent to.
+#[derive(Clone, Debug)]
+enum Listener {
+    /// Listener of deserialized events.
+    Events(Sender<Event>),
+    /// Listener of dispatch events that are deserialized on demand.
+    Lazy(Sender<LazyEvent>),
+}
+
 /// Emitter over a listener with some useful things on top to abstract common
 /// operations.
 #[derive(Clone, Debug)]
 pub struct Emitter {
+    encoding: Encoding,
     event_types: EventTypeFlags,
-    tx: UnboundedSender<Event>,
+    guild_filter: Option<GuildFilter>,
+    tx: Listener,
 }
 
 impl Emitter {
     /// Create a new emitter for events and bytes.
-    pub fn new(event_types: EventTypeFlags) -> (Self, UnboundedReceiver<Event>) {
-        let (tx, rx) = mpsc::unbounded_channel();
+    ///
+    /// The channel to the listener is bounded if a buffer capacity and
+    /// overflow policy are provided.
+    pub fn new(
+        encoding: Encoding,
+        event_types: EventTypeFlags,
+        guild_filter: Option<GuildFilter>,
+        buffer: Option<(usize, OverflowPolicy)>,
+    ) -> (Self, Receiver<Event>) {
+        let (tx, rx) = channel::channel(buffer);
+
+        (
+            Self {
+                encoding,
+                event_types,
+                guild_filter,
+                tx: Listener::Events(tx),
+            },
+            rx,
+        )
+    }
+
+    /// Create a new emitter that emits dispatch events without deserializing
+    /// them.
+    pub fn new_lazy(
+        encoding: Encoding,
+        event_types: EventTypeFlags,
+        guild_filter: Option<GuildFilter>,
+        buffer: Option<(usize, OverflowPolicy)>,
+    ) -> (Self, Receiver<LazyEvent>) {
+        let (tx, rx) = channel::channel(buffer);
+
+        (
+            Self {
+                encoding,
+                event_types,
+                guild_filter,
+                tx: Listener::Lazy(tx),
+            },
+            rx,
+        )
+    }
+
+    /// Clone of the emitter parsing payloads of another encoding, sending
+    /// events to the same listener.
+    pub fn with_encoding(&self, encoding: Encoding) -> Self {
+        Self {
+            encoding,
+            ..self.clone()
+        }
+    }
 
-        (Self { event_types, tx }, rx)
+    /// Wait until the listener's buffer has capacity for more events.
+    ///
+    /// This only waits if the buffer is bounded with the
+    /// [`OverflowPolicy::Backpressure`] policy.
+    pub async fn ready(&self) {
+        match &self.tx {
+            Listener::Events(tx) => tx.ready().await,
+            Listener::Lazy(tx) => tx.ready().await,
+        }
+    }
+
+    /// Close the listener's channel, ending its stream once the buffered
+    /// events have been received.
+    pub fn close(&self) {
+        match &self.tx {
+            Listener::Events(tx) => tx.close(),
+            Listener::Lazy(tx) => tx.close(),
+        }
+    }
+
+    /// Number of events the listener's channel has dropped due to its buffer
+    /// being full.
+    pub fn dropped(&self) -> u64 {
+        match &self.tx {
+            Listener::Events(tx) => tx.dropped(),
+            Listener::Lazy(tx) => tx.dropped(),
+        }
+    }
+
+    /// Number of events buffered in the listener's channel.
+    #[cfg(feature = "metrics")]
+    pub fn queued(&self) -> usize {
+        match &self.tx {
+            Listener::Events(tx) => tx.len(),
+            Listener::Lazy(tx) => tx.len(),
+        }
     }
 
     /// Whether the configured event types include an individual event type.
@@ -105,23 +210,31 @@ impl Emitter {
         }
     }
 
-    /// Emit a JSON payload that hasn't been deserialized yet, but only if the
+    /// Emit a payload that hasn't been deserialized yet, but only if the
     /// listener wants the event type.
     ///
+    /// Dispatch events of guilds that aren't allowed by the guild filter are
+    /// dropped, and dispatch events are sent to lazy listeners without being
+    /// deserialized.
+    ///
+    /// The guild ID of the event may be passed if the caller already peeked
+    /// at it, sparing another scan of the payload.
+    ///
     /// # Errors
     ///
     /// Returns a [`EmitJsonError::EventTypeUnknown`] error type if the
     /// event type is unknown.
     ///
     /// Returns a [`EmitJsonError::Parsing`] error type if the combination of
-    /// the provided opcode, sequence, event type, and JSON could not be parsed
-    /// into an event.
-    pub fn json(
+    /// the provided opcode, sequence, event type, and payload could not be
+    /// parsed into an event.
+    pub fn payload(
         &self,
         op: u8,
         seq: Option<u64>,
         event_type: Option<&str>,
-        json: &mut str,
+        guild_id: Option<GuildId>,
+        payload: &mut [u8],
     ) -> Result<(), EmitJsonError> {
         let flag = EventTypeFlags::try_from((op, event_type)).map_err(|(op, event_type)| {
             EmitJsonError {
@@ -133,34 +246,81 @@ impl Emitter {
             }
         })?;
 
-        if self.wants(flag) {
-            let gateway_event =
-                json::parse_gateway_event(op, seq, event_type, json).map_err(|source| {
-                    EmitJsonError {
-                        kind: EmitJsonErrorType::Parsing,
-                        source: Some(Box::new(source)),
-                    }
-                })?;
-            self.event(Event::from(gateway_event));
+        if !self.wants(flag) {
+            return Ok(());
+        }
+
+        let dispatch = op == OpCode::Event as u8;
+        let lazy = matches!(self.tx, Listener::Lazy(_));
+
+        // Only peek at the guild ID if it's used, since it requires scanning
+        // the payload.
+        let guild_id = match guild_id {
+            Some(guild_id) => Some(guild_id),
+            None if dispatch && (lazy || self.guild_filter.is_some()) => {
+                self.encoding.peek_guild_id(event_type, payload)
+            }
+            None => None,
+        };
+
+        // The processor has already recorded the sequence of the event, so it
+        // can be dropped without affecting resumes.
+        if let (Some(filter), Some(guild_id)) = (&self.guild_filter, guild_id) {
+            if !filter.allows(guild_id) {
+                return Ok(());
+            }
         }
 
+        if let (Listener::Lazy(tx), true, Some(seq), Some(event_type)) =
+            (&self.tx, dispatch, seq, event_type)
+        {
+            if let Ok(kind) = EventType::try_from(event_type) {
+                let raw = RawEvent::new(
+                    op,
+                    seq,
+                    event_type,
+                    kind,
+                    guild_id,
+                    self.encoding,
+                    payload.to_vec(),
+                );
+                let _res = tx.send(LazyEvent::Raw(raw));
+
+                return Ok(());
+            }
+        }
+
+        let gateway_event = self
+            .encoding
+            .parse_gateway_event(op, seq, event_type, payload)
+            .map_err(|source| EmitJsonError {
+                kind: EmitJsonErrorType::Parsing,
+                source: Some(Box::new(source)),
+            })?;
+        self.event(Event::from(gateway_event));
+
         Ok(())
     }
 
     fn send(&self, event: Event) {
-        let _res = self.tx.send(event);
+        let _res = match &self.tx {
+            Listener::Events(tx) => tx.send(event).map_err(drop),
+            Listener::Lazy(tx) => tx.send(LazyEvent::Deserialized(event)).map_err(drop),
+        };
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Emitter;
+    use super::{Emitter, Encoding, GuildFilter, LazyEvent};
     use crate::{Event, EventTypeFlags};
     use tokio::time::{self, Duration};
+    use twilight_model::id::GuildId;
 
     #[tokio::test]
     async fn test_bytes_send() {
-        let (emitter, mut rx) = Emitter::new(EventTypeFlags::SHARD_PAYLOAD);
+        let (emitter, mut rx) =
+            Emitter::new(Encoding::Json, EventTypeFlags::SHARD_PAYLOAD, None, None);
         emitter.bytes(&[1]);
 
         assert!(rx.recv().await.is_some());
@@ -171,7 +331,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_event_sends_to_rx() {
-        let (emitter, mut rx) = Emitter::new(EventTypeFlags::default());
+        let (emitter, mut rx) = Emitter::new(Encoding::Json, EventTypeFlags::default(), None, None);
         emitter.event(Event::GatewayReconnect);
 
         assert!(rx.recv().await.is_some());
@@ -181,4 +341,85 @@ mod tests {
             .await
             .is_err());
     }
+
+    #[tokio::test]
+    async fn test_json_lazy() {
+        let (emitter, mut rx) = Emitter::new_lazy(
+            Encoding::Json,
+            EventTypeFlags::ROLE_DELETE | EventTypeFlags::GATEWAY_RECONNECT,
+            None,
+            None,
+        );
+        let mut json =
+            br#"{"t":"GUILD_ROLE_DELETE","s":1,"op":0,"d":{"role_id":"2","guild_id":"1"}}"#
+                .to_vec();
+        emitter
+            .payload(0, Some(1), Some("GUILD_ROLE_DELETE"), None, &mut json)
+            .unwrap();
+
+        match rx.recv().await {
+            Some(LazyEvent::Raw(raw)) => {
+                assert_eq!(Some(GuildId(1)), raw.guild_id());
+                assert_eq!(1, raw.sequence());
+                assert!(matches!(raw.into_event(), Ok(Event::RoleDelete(_))));
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        // A guild ID that was already peeked at is used as is.
+        emitter
+            .payload(
+                0,
+                Some(2),
+                Some("GUILD_ROLE_DELETE"),
+                Some(GuildId(3)),
+                &mut json,
+            )
+            .unwrap();
+        assert!(matches!(
+            rx.recv().await,
+            Some(LazyEvent::Raw(raw)) if raw.guild_id() == Some(GuildId(3))
+        ));
+
+        // Events that aren't dispatch events are still deserialized.
+        emitter.event(Event::GatewayReconnect);
+        assert!(matches!(
+            rx.recv().await,
+            Some(LazyEvent::Deserialized(Event::GatewayReconnect))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_json_guild_filter() {
+        let filter = GuildFilter::new(|guild_id| guild_id == GuildId(1));
+        let (emitter, mut rx) = Emitter::new(
+            Encoding::Json,
+            EventTypeFlags::ROLE_DELETE,
+            Some(filter),
+            None,
+        );
+
+        let mut other =
+            br#"{"t":"GUILD_ROLE_DELETE","s":1,"op":0,"d":{"role_id":"2","guild_id":"2"}}"#
+                .to_vec();
+        emitter
+            .payload(0, Some(1), Some("GUILD_ROLE_DELETE"), None, &mut other)
+            .unwrap();
+
+        let mut allowed =
+            br#"{"t":"GUILD_ROLE_DELETE","s":2,"op":0,"d":{"role_id":"2","guild_id":"1"}}"#
+                .to_vec();
+        emitter
+            .payload(0, Some(2), Some("GUILD_ROLE_DELETE"), None, &mut allowed)
+            .unwrap();
+
+        match rx.recv().await {
+            Some(Event::RoleDelete(delete)) => assert_eq!(GuildId(1), delete.guild_id),
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        assert!(time::timeout(Duration::from_millis(10), rx.recv())
+            .await
+            .is_err());
+    }
 }
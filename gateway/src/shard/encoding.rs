@@ -0,0 +1,204 @@
+//! Encodings of the payloads exchanged with the gateway.
+
+#[cfg(feature = "etf")]
+use super::etf;
+use super::json::{self, GatewayEventParsingError};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::error::Error;
+use twilight_model::{
+    gateway::event::{GatewayEvent, GatewayEventDeserializer},
+    id::GuildId,
+};
+
+/// Encoding of the payloads exchanged with the gateway.
+///
+/// Refer to [`ShardBuilder::encoding`] for more information.
+///
+/// [`ShardBuilder::encoding`]: super::ShardBuilder::encoding
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum Encoding {
+    /// Erlang's External Term Format.
+    ///
+    /// Requires the `etf` feature.
+    #[cfg(feature = "etf")]
+    Etf,
+    /// JSON.
+    Json,
+}
+
+impl Encoding {
+    /// Name of the encoding as used in the gateway URL's query.
+    pub const fn name(self) -> &'static str {
+        match self {
+            #[cfg(feature = "etf")]
+            Self::Etf => "etf",
+            Self::Json => "json",
+        }
+    }
+
+    /// Deserialize a value from a payload.
+    pub(crate) fn deserialize<T: DeserializeOwned>(
+        self,
+        payload: &mut [u8],
+    ) -> Result<T, Box<dyn Error + Send + Sync>> {
+        match self {
+            #[cfg(feature = "etf")]
+            Self::Etf => etf::from_slice(payload).map_err(From::from),
+            Self::Json => json::from_slice(payload).map_err(From::from),
+        }
+    }
+
+    /// Serialize a value into a payload.
+    pub(crate) fn serialize<T: Serialize + ?Sized>(
+        self,
+        value: &T,
+    ) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        match self {
+            #[cfg(feature = "etf")]
+            Self::Etf => etf::to_vec(value).map_err(From::from),
+            Self::Json => json::to_vec(value).map_err(From::from),
+        }
+    }
+
+    /// Parse a gateway event from a payload with headers.
+    pub(crate) fn parse_gateway_event(
+        self,
+        op: u8,
+        sequence: Option<u64>,
+        event_type: Option<&str>,
+        payload: &mut [u8],
+    ) -> Result<GatewayEvent, GatewayEventParsingError> {
+        match self {
+            #[cfg(feature = "etf")]
+            Self::Etf => etf::parse_gateway_event(op, sequence, event_type, payload),
+            Self::Json => json::parse_gateway_event(op, sequence, event_type, payload),
+        }
+    }
+
+    /// Peek at the opcode, sequence, and event type of a payload without
+    /// deserializing its data.
+    pub(crate) fn peek_header(self, payload: &[u8]) -> Option<(u8, Option<u64>, Option<String>)> {
+        match self {
+            #[cfg(feature = "etf")]
+            Self::Etf => etf::peek_header(payload),
+            Self::Json => {
+                let json = std::str::from_utf8(payload).ok()?;
+                let (op, seq, event_type) = GatewayEventDeserializer::from_json(json)?.into_parts();
+
+                Some((op, seq, event_type.map(ToOwned::to_owned)))
+            }
+        }
+    }
+
+    /// Peek at the ID of the guild a dispatch payload is related to without
+    /// deserializing the payload.
+    ///
+    /// This is the top-level `guild_id` field of the payload's data, or the
+    /// `id` field for the events whose data is a guild. The payload isn't
+    /// mutated, so it can still be deserialized afterwards.
+    pub(crate) fn peek_guild_id(self, event_type: Option<&str>, payload: &[u8]) -> Option<GuildId> {
+        let is_guild = event_type.map_or(false, |event_type| {
+            matches!(
+                event_type,
+                "GUILD_CREATE" | "GUILD_DELETE" | "GUILD_UPDATE" | "UNAVAILABLE_GUILD"
+            )
+        });
+
+        if is_guild {
+            self.peek::<PeekGuildPayload>(payload)?.d?.id
+        } else {
+            self.peek::<PeekPayload>(payload)?.d?.guild_id
+        }
+    }
+
+    fn peek<'a, T: Deserialize<'a>>(self, payload: &'a [u8]) -> Option<T> {
+        match self {
+            #[cfg(feature = "etf")]
+            Self::Etf => etf::from_slice(payload).ok(),
+            Self::Json => serde_json::from_slice(payload).ok(),
+        }
+    }
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Self::Json
+    }
+}
+
+/// Data of a dispatch payload that only contains the guild ID.
+#[derive(Deserialize)]
+struct PeekData {
+    guild_id: Option<GuildId>,
+}
+
+/// Dispatch payload that only contains its data.
+///
+/// All other fields, and all of the data's other fields, are skipped over
+/// without being deserialized.
+#[derive(Deserialize)]
+struct PeekPayload {
+    d: Option<PeekData>,
+}
+
+/// Data of a dispatch payload whose ID is a guild's ID.
+#[derive(Deserialize)]
+struct PeekGuildData {
+    id: Option<GuildId>,
+}
+
+/// Dispatch payload whose ID is a guild's ID.
+#[derive(Deserialize)]
+struct PeekGuildPayload {
+    d: Option<PeekGuildData>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Encoding;
+    use static_assertions::assert_impl_all;
+    use std::{fmt::Debug, hash::Hash};
+    use twilight_model::id::GuildId;
+
+    assert_impl_all!(Encoding: Clone, Copy, Debug, Default, Eq, Hash, Send, Sync);
+
+    #[test]
+    fn test_peek_guild_id() {
+        let json = br#"{"t":"MESSAGE_CREATE","s":2,"op":0,"d":{"id":"3","member":{"roles":[]},"guild_id":"1"}}"#;
+        assert_eq!(
+            Some(GuildId(1)),
+            Encoding::Json.peek_guild_id(Some("MESSAGE_CREATE"), json)
+        );
+
+        let json = br#"{"t":"GUILD_DELETE","s":2,"op":0,"d":{"unavailable":true,"id":"1"}}"#;
+        assert_eq!(
+            Some(GuildId(1)),
+            Encoding::Json.peek_guild_id(Some("GUILD_DELETE"), json)
+        );
+
+        let json = br#"{"t":"TYPING_START","s":2,"op":0,"d":{"user_id":"3","channel_id":"2"}}"#;
+        assert!(Encoding::Json
+            .peek_guild_id(Some("TYPING_START"), json)
+            .is_none());
+
+        assert!(Encoding::Json
+            .peek_guild_id(Some("MESSAGE_CREATE"), b"{")
+            .is_none());
+    }
+
+    #[cfg(feature = "etf")]
+    #[test]
+    fn test_peek_guild_id_etf() {
+        // `#{d => #{guild_id => 81384788765712384}}`
+        let mut etf = vec![131, 116, 0, 0, 0, 1, 115, 1, b'd', 116, 0, 0, 0, 1, 115, 8];
+        etf.extend_from_slice(b"guild_id");
+        etf.extend_from_slice(&[110, 8, 0]);
+        etf.extend_from_slice(&81_384_788_765_712_384_u64.to_le_bytes());
+
+        assert_eq!(
+            Some(GuildId(81_384_788_765_712_384)),
+            Encoding::Etf.peek_guild_id(Some("MESSAGE_CREATE"), &etf)
+        );
+    }
+}
@@ -0,0 +1,605 @@
+use super::{
+    EtfError, EtfErrorType, ATOM_EXT, ATOM_UTF8_EXT, BINARY_EXT, BIT_BINARY_EXT, FLOAT_EXT,
+    INTEGER_EXT, LARGE_BIG_EXT, LARGE_TUPLE_EXT, LIST_EXT, MAP_EXT, NEW_FLOAT_EXT, NIL_EXT,
+    SMALL_ATOM_EXT, SMALL_ATOM_UTF8_EXT, SMALL_BIG_EXT, SMALL_INTEGER_EXT, SMALL_TUPLE_EXT,
+    STRING_EXT, VERSION,
+};
+use serde::de::{
+    self, DeserializeSeed, EnumAccess, Error as DeError, IntoDeserializer, MapAccess, SeqAccess,
+    VariantAccess, Visitor,
+};
+use std::{borrow::Cow, convert::TryFrom, str};
+
+/// Deserializer of a term.
+///
+/// Use [`from_slice`] to deserialize a value from a complete term.
+///
+/// [`from_slice`]: super::from_slice
+#[derive(Debug)]
+pub struct Deserializer<'de> {
+    input: &'de [u8],
+}
+
+impl<'de> Deserializer<'de> {
+    /// Create a new deserializer of a term, reading its version byte.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`EtfErrorType::VersionInvalid`] error type if the input
+    /// doesn't start with the version byte.
+    pub fn from_slice(input: &'de [u8]) -> Result<Self, EtfError> {
+        let mut deserializer = Self { input };
+
+        match deserializer.u8()? {
+            VERSION => Ok(deserializer),
+            version => Err(EtfError::new(EtfErrorType::VersionInvalid { version })),
+        }
+    }
+
+    /// Ensure that the whole input has been consumed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`EtfErrorType::TrailingBytes`] error type if the input
+    /// contains bytes after the term.
+    pub const fn end(&self) -> Result<(), EtfError> {
+        if self.input.is_empty() {
+            Ok(())
+        } else {
+            Err(EtfError::new(EtfErrorType::TrailingBytes))
+        }
+    }
+
+    #[allow(clippy::missing_const_for_fn)]
+    fn take(&mut self, len: usize) -> Result<&'de [u8], EtfError> {
+        if self.input.len() < len {
+            return Err(EtfError::new(EtfErrorType::EndOfInput));
+        }
+
+        let (taken, rest) = self.input.split_at(len);
+        self.input = rest;
+
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, EtfError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, EtfError> {
+        let bytes = self.take(2)?;
+
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, EtfError> {
+        let bytes = self.take(4)?;
+
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn len(&mut self) -> Result<usize, EtfError> {
+        usize::try_from(self.u32()?).map_err(|_| EtfError::new(EtfErrorType::LengthTooLarge))
+    }
+
+    fn peek_tag(&self) -> Result<u8, EtfError> {
+        self.input
+            .first()
+            .copied()
+            .ok_or_else(|| EtfError::new(EtfErrorType::EndOfInput))
+    }
+
+    /// Read the name of an atom whose tag has already been read.
+    fn atom(&mut self, tag: u8) -> Result<Cow<'de, str>, EtfError> {
+        let len = match tag {
+            ATOM_EXT | ATOM_UTF8_EXT => usize::from(self.u16()?),
+            _ => usize::from(self.u8()?),
+        };
+        let bytes = self.take(len)?;
+
+        match str::from_utf8(bytes) {
+            Ok(name) => Ok(Cow::Borrowed(name)),
+            // Atoms that aren't UTF-8 encoded are Latin-1 encoded, whose
+            // bytes each map to the code point of the same value.
+            Err(_) if matches!(tag, ATOM_EXT | SMALL_ATOM_EXT) => Ok(Cow::Owned(
+                bytes.iter().map(|byte| char::from(*byte)).collect(),
+            )),
+            Err(source) => Err(EtfError {
+                kind: EtfErrorType::Utf8Invalid,
+                source: Some(Box::new(source)),
+            }),
+        }
+    }
+
+    /// Read the value of a big integer whose tag has already been read.
+    fn big(&mut self, tag: u8) -> Result<(bool, u64), EtfError> {
+        let len = if tag == SMALL_BIG_EXT {
+            usize::from(self.u8()?)
+        } else {
+            self.len()?
+        };
+        let negative = self.u8()? != 0;
+        let digits = self.take(len)?;
+
+        // Digits are little-endian, so any digits after the eighth must be
+        // zero for the value to fit into 64 bits.
+        if digits.iter().skip(8).any(|digit| *digit != 0) {
+            return Err(EtfError::new(EtfErrorType::IntegerTooLarge));
+        }
+
+        let value = digits
+            .iter()
+            .take(8)
+            .rev()
+            .fold(0, |value, digit| (value << 8) | u64::from(*digit));
+
+        Ok((negative, value))
+    }
+
+    /// Skip over the next term without deserializing it.
+    fn skip(&mut self) -> Result<(), EtfError> {
+        let tag = self.u8()?;
+
+        match tag {
+            SMALL_INTEGER_EXT => self.take(1).map(drop),
+            INTEGER_EXT => self.take(4).map(drop),
+            NEW_FLOAT_EXT => self.take(8).map(drop),
+            FLOAT_EXT => self.take(31).map(drop),
+            ATOM_EXT | ATOM_UTF8_EXT | STRING_EXT => {
+                let len = self.u16()?;
+
+                self.take(usize::from(len)).map(drop)
+            }
+            SMALL_ATOM_EXT | SMALL_ATOM_UTF8_EXT => {
+                let len = self.u8()?;
+
+                self.take(usize::from(len)).map(drop)
+            }
+            SMALL_TUPLE_EXT => {
+                let arity = self.u8()?;
+
+                (0..arity).try_for_each(|_| self.skip())
+            }
+            LARGE_TUPLE_EXT => {
+                let arity = self.u32()?;
+
+                (0..arity).try_for_each(|_| self.skip())
+            }
+            NIL_EXT => Ok(()),
+            LIST_EXT => {
+                let len = self.u32()?;
+
+                // Skip the elements and the tail.
+                (0..=len).try_for_each(|_| self.skip())
+            }
+            BINARY_EXT => {
+                let len = self.len()?;
+
+                self.take(len).map(drop)
+            }
+            BIT_BINARY_EXT | LARGE_BIG_EXT => {
+                let len = self.len()?;
+
+                // The number of bits in the last byte, or the sign.
+                self.take(len + 1).map(drop)
+            }
+            SMALL_BIG_EXT => {
+                let len = self.u8()?;
+
+                self.take(usize::from(len) + 1).map(drop)
+            }
+            MAP_EXT => {
+                let arity = self.u32()?;
+
+                (0..arity).try_for_each(|_| {
+                    self.skip()?;
+
+                    self.skip()
+                })
+            }
+            tag => Err(EtfError::new(EtfErrorType::TagUnsupported { tag })),
+        }
+    }
+
+    /// Read the tail of a list, which must be an empty list.
+    fn list_tail(&mut self) -> Result<(), EtfError> {
+        if self.u8()? == NIL_EXT {
+            Ok(())
+        } else {
+            Err(EtfError::new(EtfErrorType::ListImproper))
+        }
+    }
+
+    /// Whether the next term is the `nil` atom, consuming it if it is.
+    fn nil(&mut self) -> Result<bool, EtfError> {
+        let tag = self.peek_tag()?;
+
+        if !matches!(
+            tag,
+            ATOM_EXT | ATOM_UTF8_EXT | SMALL_ATOM_EXT | SMALL_ATOM_UTF8_EXT
+        ) {
+            return Ok(false);
+        }
+
+        let mut peek = Self { input: self.input };
+        peek.u8()?;
+
+        if peek.atom(tag)? == "nil" {
+            self.input = peek.input;
+
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = EtfError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let tag = self.u8()?;
+
+        match tag {
+            SMALL_INTEGER_EXT => visitor.visit_u64(u64::from(self.u8()?)),
+            INTEGER_EXT => {
+                let value = i32::from_be_bytes(self.u32()?.to_be_bytes());
+
+                match u64::try_from(value) {
+                    Ok(value) => visitor.visit_u64(value),
+                    Err(_) => visitor.visit_i64(i64::from(value)),
+                }
+            }
+            SMALL_BIG_EXT | LARGE_BIG_EXT => match self.big(tag)? {
+                (false, value) => visitor.visit_u64(value),
+                (true, value) => {
+                    let value = i64::try_from(-i128::from(value))
+                        .map_err(|_| EtfError::new(EtfErrorType::IntegerTooLarge))?;
+
+                    visitor.visit_i64(value)
+                }
+            },
+            NEW_FLOAT_EXT => {
+                let bytes = self.take(8)?;
+                let mut buf = [0; 8];
+                buf.copy_from_slice(bytes);
+
+                visitor.visit_f64(f64::from_be_bytes(buf))
+            }
+            FLOAT_EXT => {
+                let bytes = self.take(31)?;
+                let value = str::from_utf8(bytes)
+                    .ok()
+                    .and_then(|value| value.trim_end_matches('\0').parse().ok())
+                    .ok_or_else(|| {
+                        EtfError::invalid_value(de::Unexpected::Bytes(bytes), &"float")
+                    })?;
+
+                visitor.visit_f64(value)
+            }
+            ATOM_EXT | ATOM_UTF8_EXT | SMALL_ATOM_EXT | SMALL_ATOM_UTF8_EXT => {
+                match self.atom(tag)? {
+                    Cow::Borrowed("nil") => visitor.visit_unit(),
+                    Cow::Borrowed("true") => visitor.visit_bool(true),
+                    Cow::Borrowed("false") => visitor.visit_bool(false),
+                    Cow::Borrowed(name) => visitor.visit_borrowed_str(name),
+                    Cow::Owned(name) => visitor.visit_string(name),
+                }
+            }
+            BINARY_EXT => {
+                let len = self.len()?;
+                let bytes = self.take(len)?;
+
+                match str::from_utf8(bytes) {
+                    Ok(value) => visitor.visit_borrowed_str(value),
+                    Err(_) => visitor.visit_borrowed_bytes(bytes),
+                }
+            }
+            STRING_EXT => {
+                let len = self.u16()?;
+                let bytes = self.take(usize::from(len))?;
+
+                visitor.visit_seq(BytesAccess {
+                    bytes: bytes.iter(),
+                })
+            }
+            NIL_EXT => visitor.visit_seq(ElementsAccess {
+                de: self,
+                remaining: 0,
+            }),
+            LIST_EXT => {
+                let len = self.len()?;
+                let value = visit_elements(&mut *self, len, visitor)?;
+                self.list_tail()?;
+
+                Ok(value)
+            }
+            SMALL_TUPLE_EXT => {
+                let arity = self.u8()?;
+
+                visit_elements(self, usize::from(arity), visitor)
+            }
+            LARGE_TUPLE_EXT => {
+                let arity = self.len()?;
+
+                visit_elements(self, arity, visitor)
+            }
+            MAP_EXT => {
+                let arity = self.len()?;
+                let mut access = ElementsAccess {
+                    de: self,
+                    remaining: arity,
+                };
+                let value = visitor.visit_map(&mut access)?;
+
+                if access.remaining == 0 {
+                    Ok(value)
+                } else {
+                    Err(EtfError::invalid_length(arity, &"fewer elements in map"))
+                }
+            }
+            tag => Err(EtfError::new(EtfErrorType::TagUnsupported { tag })),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.peek_tag()? {
+            BINARY_EXT => {
+                self.u8()?;
+                let len = self.len()?;
+
+                visitor.visit_borrowed_bytes(self.take(len)?)
+            }
+            STRING_EXT => {
+                self.u8()?;
+                let len = self.u16()?;
+
+                visitor.visit_borrowed_bytes(self.take(usize::from(len))?)
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.nil()? {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        // Variants with values are maps of the variant to its value, like
+        // serde_json represents them.
+        if self.peek_tag()? == MAP_EXT {
+            self.u8()?;
+
+            match self.len()? {
+                1 => visitor.visit_enum(VariantEnumAccess { de: self }),
+                arity => Err(EtfError::invalid_length(arity, &"map with a single key")),
+            }
+        } else {
+            visitor.visit_enum(UnitVariantAccess { de: self })
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.skip()?;
+
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+/// Visit a sequence of elements, ensuring that all of them were visited.
+fn visit_elements<'de, V: Visitor<'de>>(
+    de: &mut Deserializer<'de>,
+    len: usize,
+    visitor: V,
+) -> Result<V::Value, EtfError> {
+    let mut access = ElementsAccess { de, remaining: len };
+    let value = visitor.visit_seq(&mut access)?;
+
+    if access.remaining == 0 {
+        Ok(value)
+    } else {
+        Err(EtfError::invalid_length(len, &"fewer elements in sequence"))
+    }
+}
+
+/// Access to the elements of a list, tuple, or map.
+struct ElementsAccess<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    remaining: usize,
+}
+
+impl<'de> SeqAccess<'de> for ElementsAccess<'_, 'de> {
+    type Error = EtfError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        self.remaining -= 1;
+
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'de> MapAccess<'de> for ElementsAccess<'_, 'de> {
+    type Error = EtfError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        self.remaining -= 1;
+
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+/// Access to the bytes of a string, which is a list of small integers.
+struct BytesAccess<'de> {
+    bytes: std::slice::Iter<'de, u8>,
+}
+
+impl<'de> SeqAccess<'de> for BytesAccess<'de> {
+    type Error = EtfError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error> {
+        self.bytes
+            .next()
+            .map(|byte| seed.deserialize(byte.into_deserializer()))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.bytes.len())
+    }
+}
+
+/// Access to a variant represented as a map of the variant to its value.
+struct VariantEnumAccess<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de> EnumAccess<'de> for VariantEnumAccess<'_, 'de> {
+    type Error = EtfError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error> {
+        let variant = seed.deserialize(&mut *self.de)?;
+
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for VariantEnumAccess<'_, 'de> {
+    type Error = EtfError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        de::Deserialize::deserialize(self.de)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Self::Error> {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _: usize, visitor: V) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_any(self.de, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        de::Deserializer::deserialize_any(self.de, visitor)
+    }
+}
+
+/// Access to a unit variant represented as its name.
+struct UnitVariantAccess<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de> EnumAccess<'de> for UnitVariantAccess<'_, 'de> {
+    type Error = EtfError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error> {
+        let variant = seed.deserialize(&mut *self.de)?;
+
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for UnitVariantAccess<'_, 'de> {
+    type Error = EtfError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, _: T) -> Result<T::Value, Self::Error> {
+        Err(EtfError::invalid_type(
+            de::Unexpected::UnitVariant,
+            &"newtype variant",
+        ))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _: usize, _: V) -> Result<V::Value, Self::Error> {
+        Err(EtfError::invalid_type(
+            de::Unexpected::UnitVariant,
+            &"tuple variant",
+        ))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _: &'static [&'static str],
+        _: V,
+    ) -> Result<V::Value, Self::Error> {
+        Err(EtfError::invalid_type(
+            de::Unexpected::UnitVariant,
+            &"struct variant",
+        ))
+    }
+}
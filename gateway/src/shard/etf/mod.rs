@@ -0,0 +1,437 @@
+//! Serializing and deserializing payloads in Erlang's [External Term Format].
+//!
+//! Shards use this format instead of JSON when configured to via
+//! [`ShardBuilder::encoding`]. It's smaller on the wire and cheaper to decode.
+//!
+//! Terms map to serde's data model like their JSON counterparts do:
+//!
+//! - the `nil` atom is `null`, and the `true` and `false` atoms are booleans;
+//! - other atoms and binaries are strings;
+//! - lists and tuples are sequences, and maps are maps;
+//! - integers, including big integers of up to 64 bits, are integers.
+//!
+//! Discord sends snowflakes as integers instead of strings. Twilight's IDs
+//! deserialize from both, so the same models work with either format. When
+//! serializing, strings are encoded as binaries and map keys as binaries,
+//! since Discord rejects payloads with atom keys.
+//!
+//! [External Term Format]: https://www.erlang.org/doc/apps/erts/erl_ext_dist.html
+//! [`ShardBuilder::encoding`]: super::ShardBuilder::encoding
+
+mod de;
+mod ser;
+
+pub use self::{de::Deserializer, ser::Serializer};
+
+use super::json::{GatewayEventParsingError, GatewayEventParsingErrorType};
+use serde::{de::DeserializeSeed, Deserialize, Serialize};
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+};
+use twilight_model::gateway::event::{GatewayEvent, GatewayEventDeserializer};
+
+/// Version byte every term starts with.
+const VERSION: u8 = 131;
+
+const NEW_FLOAT_EXT: u8 = 70;
+const BIT_BINARY_EXT: u8 = 77;
+const SMALL_INTEGER_EXT: u8 = 97;
+const INTEGER_EXT: u8 = 98;
+const FLOAT_EXT: u8 = 99;
+const ATOM_EXT: u8 = 100;
+const SMALL_TUPLE_EXT: u8 = 104;
+const LARGE_TUPLE_EXT: u8 = 105;
+const NIL_EXT: u8 = 106;
+const STRING_EXT: u8 = 107;
+const LIST_EXT: u8 = 108;
+const BINARY_EXT: u8 = 109;
+const SMALL_BIG_EXT: u8 = 110;
+const LARGE_BIG_EXT: u8 = 111;
+const SMALL_ATOM_EXT: u8 = 115;
+const MAP_EXT: u8 = 116;
+const ATOM_UTF8_EXT: u8 = 118;
+const SMALL_ATOM_UTF8_EXT: u8 = 119;
+
+/// Serializing or deserializing a term failed.
+#[derive(Debug)]
+pub struct EtfError {
+    kind: EtfErrorType,
+    source: Option<Box<dyn Error + Send + Sync>>,
+}
+
+impl EtfError {
+    /// Immutable reference to the type of error that occurred.
+    #[must_use = "retrieving the type has no effect if left unused"]
+    pub const fn kind(&self) -> &EtfErrorType {
+        &self.kind
+    }
+
+    /// Consume the error, returning the source error if there is any.
+    #[must_use = "consuming the error and retrieving the source has no effect if left unused"]
+    pub fn into_source(self) -> Option<Box<dyn Error + Send + Sync>> {
+        self.source
+    }
+
+    /// Consume the error, returning the owned error type and the source error.
+    #[must_use = "consuming the error into its parts has no effect if left unused"]
+    pub fn into_parts(self) -> (EtfErrorType, Option<Box<dyn Error + Send + Sync>>) {
+        (self.kind, self.source)
+    }
+
+    const fn new(kind: EtfErrorType) -> Self {
+        Self { kind, source: None }
+    }
+}
+
+impl Display for EtfError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match &self.kind {
+            EtfErrorType::Custom { message } => f.write_str(message),
+            EtfErrorType::EndOfInput => f.write_str("input ended before the term was complete"),
+            EtfErrorType::IntegerTooLarge => f.write_str("integer doesn't fit into 64 bits"),
+            EtfErrorType::LengthTooLarge => {
+                f.write_str("length of a value doesn't fit into 32 bits")
+            }
+            EtfErrorType::ListImproper => f.write_str("list doesn't end with an empty list"),
+            EtfErrorType::TagUnsupported { tag } => {
+                f.write_fmt(format_args!("term tag {} is unsupported", tag))
+            }
+            EtfErrorType::TrailingBytes => f.write_str("input contains bytes after the term"),
+            EtfErrorType::Utf8Invalid => f.write_str("atom isn't valid UTF-8"),
+            EtfErrorType::VersionInvalid { version } => {
+                f.write_fmt(format_args!("version {} is invalid", version))
+            }
+        }
+    }
+}
+
+impl Error for EtfError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| &**source as &(dyn Error + 'static))
+    }
+}
+
+impl serde::de::Error for EtfError {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self::new(EtfErrorType::Custom {
+            message: msg.to_string(),
+        })
+    }
+}
+
+impl serde::ser::Error for EtfError {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self::new(EtfErrorType::Custom {
+            message: msg.to_string(),
+        })
+    }
+}
+
+/// Type of [`EtfError`] that occurred.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum EtfErrorType {
+    /// Serde implementation of a value returned an error.
+    Custom {
+        /// Message of the error.
+        message: String,
+    },
+    /// Input ended before the term was complete.
+    EndOfInput,
+    /// Integer doesn't fit into 64 bits.
+    IntegerTooLarge,
+    /// Length of a string, sequence, or map doesn't fit into 32 bits.
+    LengthTooLarge,
+    /// List ends with a tail other than an empty list.
+    ListImproper,
+    /// Term has a tag that isn't supported, such as a pid or a reference.
+    TagUnsupported {
+        /// Tag of the term.
+        tag: u8,
+    },
+    /// Input contains bytes after the term.
+    TrailingBytes,
+    /// Atom isn't valid UTF-8.
+    Utf8Invalid,
+    /// Input doesn't start with the version byte.
+    VersionInvalid {
+        /// Received version byte.
+        version: u8,
+    },
+}
+
+/// Deserialize a value from a term.
+///
+/// # Errors
+///
+/// Returns an [`EtfErrorType::VersionInvalid`] error type if the input doesn't
+/// start with the version byte.
+///
+/// Returns an [`EtfErrorType::TrailingBytes`] error type if the input contains
+/// bytes after the term.
+///
+/// Returns an error of another type if the term is invalid or doesn't match
+/// the value's structure.
+pub fn from_slice<'a, T: Deserialize<'a>>(bytes: &'a [u8]) -> Result<T, EtfError> {
+    let mut deserializer = Deserializer::from_slice(bytes)?;
+    let value = T::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+
+    Ok(value)
+}
+
+/// Serialize a value into a term.
+///
+/// # Errors
+///
+/// Returns an [`EtfErrorType::LengthTooLarge`] error type if a string,
+/// sequence, or map is too long to be encoded.
+///
+/// Returns an [`EtfErrorType::Custom`] error type if the value's serde
+/// implementation failed.
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, EtfError> {
+    let mut serializer = Serializer::new();
+    value.serialize(&mut serializer)?;
+
+    Ok(serializer.into_inner())
+}
+
+/// Opcode, sequence, and event type of a payload.
+///
+/// The payload's data is skipped over without being deserialized.
+#[derive(Deserialize)]
+struct Header {
+    op: u8,
+    s: Option<u64>,
+    t: Option<String>,
+}
+
+/// Peek at the opcode, sequence, and event type of a payload without
+/// deserializing its data.
+pub(crate) fn peek_header(bytes: &[u8]) -> Option<(u8, Option<u64>, Option<String>)> {
+    let header = from_slice::<Header>(bytes).ok()?;
+
+    Some((header.op, header.s, header.t))
+}
+
+/// Parse a gateway event from a term with headers.
+///
+/// # Errors
+///
+/// Returns a [`GatewayEventParsingErrorType::PayloadInvalid`] error type if
+/// the payload doesn't start with the version byte.
+///
+/// Returns a [`GatewayEventParsingErrorType::Deserializing`] error type if the
+/// payload failed to deserialize.
+pub(crate) fn parse_gateway_event(
+    op: u8,
+    sequence: Option<u64>,
+    event_type: Option<&str>,
+    bytes: &[u8],
+) -> Result<GatewayEvent, GatewayEventParsingError> {
+    let gateway_deserializer = GatewayEventDeserializer::new(op, sequence, event_type);
+    let mut etf_deserializer =
+        Deserializer::from_slice(bytes).map_err(|source| GatewayEventParsingError {
+            kind: GatewayEventParsingErrorType::PayloadInvalid,
+            source: Some(Box::new(source)),
+        })?;
+
+    gateway_deserializer
+        .deserialize(&mut etf_deserializer)
+        .map_err(|source| GatewayEventParsingError {
+            kind: GatewayEventParsingErrorType::Deserializing,
+            source: Some(Box::new(source)),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_slice, parse_gateway_event, peek_header, to_vec, EtfError, EtfErrorType};
+    use serde::{Deserialize, Serialize};
+    use static_assertions::assert_impl_all;
+    use std::{collections::HashMap, error::Error, fmt::Debug};
+    use twilight_model::{
+        gateway::{
+            event::{DispatchEvent, GatewayEvent},
+            payload::{Heartbeat, RequestGuildMembers},
+        },
+        id::{GuildId, RoleId},
+    };
+
+    assert_impl_all!(EtfErrorType: Debug, Send, Sync);
+    assert_impl_all!(EtfError: Error, Send, Sync);
+
+    /// `{op: 0, s: 5, t: 'GUILD_ROLE_DELETE', d: #{role_id => 2, guild_id =>
+    /// 81384788765712384}}` as encoded by Discord, with atom keys and
+    /// snowflakes as integers.
+    fn role_delete() -> Vec<u8> {
+        let mut term = vec![131, 116, 0, 0, 0, 4];
+        term.extend_from_slice(&[115, 1, b'd', 116, 0, 0, 0, 2]);
+        term.extend_from_slice(&[115, 7]);
+        term.extend_from_slice(b"role_id");
+        term.extend_from_slice(&[97, 2]);
+        term.extend_from_slice(&[115, 8]);
+        term.extend_from_slice(b"guild_id");
+        term.extend_from_slice(&[110, 8, 0]);
+        term.extend_from_slice(&81_384_788_765_712_384_u64.to_le_bytes());
+        term.extend_from_slice(&[115, 2, b'o', b'p', 97, 0]);
+        term.extend_from_slice(&[115, 1, b's', 97, 5]);
+        term.extend_from_slice(&[115, 1, b't', 115, 17]);
+        term.extend_from_slice(b"GUILD_ROLE_DELETE");
+
+        term
+    }
+
+    #[test]
+    fn test_peek_header() {
+        assert_eq!(
+            Some((0, Some(5), Some("GUILD_ROLE_DELETE".to_owned()))),
+            peek_header(&role_delete()),
+        );
+
+        // `{op: 11, s: nil, t: nil, d: nil}`
+        let ack = [
+            131, 116, 0, 0, 0, 4, 115, 2, b'o', b'p', 97, 11, 115, 1, b's', 115, 3, b'n', b'i',
+            b'l', 115, 1, b't', 115, 3, b'n', b'i', b'l', 115, 1, b'd', 115, 3, b'n', b'i', b'l',
+        ];
+        assert_eq!(Some((11, None, None)), peek_header(&ack));
+
+        assert!(peek_header(&[131, 97]).is_none());
+    }
+
+    #[test]
+    fn test_parse_gateway_event() {
+        let event =
+            parse_gateway_event(0, Some(5), Some("GUILD_ROLE_DELETE"), &role_delete()).unwrap();
+
+        match event {
+            GatewayEvent::Dispatch(5, event) => match *event {
+                DispatchEvent::RoleDelete(delete) => {
+                    assert_eq!(GuildId(81_384_788_765_712_384), delete.guild_id);
+                    assert_eq!(RoleId(2), delete.role_id);
+                }
+                other => panic!("unexpected event: {:?}", other),
+            },
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        assert!(parse_gateway_event(0, Some(5), Some("GUILD_ROLE_DELETE"), &[130]).is_err());
+    }
+
+    #[test]
+    fn test_heartbeat() {
+        let bytes = to_vec(&Heartbeat::new(300)).unwrap();
+
+        // `#{<<"d">> => 300, <<"op">> => 1}`
+        let expected = [
+            131, 116, 0, 0, 0, 2, 109, 0, 0, 0, 1, b'd', 98, 0, 0, 1, 44, 109, 0, 0, 0, 2, b'o',
+            b'p', 97, 1,
+        ];
+        assert_eq!(&expected[..], bytes.as_slice());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let command = RequestGuildMembers::builder(GuildId(81_384_788_765_712_384))
+            .nonce("nonce")
+            .query("twi", Some(10));
+        let bytes = to_vec(&command).unwrap();
+        let value = from_slice::<serde_json::Value>(&bytes).unwrap();
+
+        assert_eq!(serde_json::to_value(&command).unwrap(), value);
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    enum Kind {
+        Unit,
+        Newtype(i64),
+        Tuple(u8, u8),
+        Struct { value: Option<f64> },
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Values {
+        big: u64,
+        bytes: Vec<u8>,
+        empty: Vec<u8>,
+        flag: bool,
+        kinds: Vec<Kind>,
+        map: HashMap<String, i64>,
+        negative: i64,
+        nothing: Option<String>,
+        text: String,
+        tuple: (u8, String),
+        unit: (),
+    }
+
+    #[test]
+    fn test_values_round_trip() {
+        let mut map = HashMap::new();
+        map.insert("key".to_owned(), i64::MIN);
+
+        let values = Values {
+            big: u64::MAX,
+            bytes: vec![1, 2, 3],
+            empty: Vec::new(),
+            flag: true,
+            kinds: vec![
+                Kind::Unit,
+                Kind::Newtype(-70_000),
+                Kind::Tuple(1, 2),
+                Kind::Struct { value: Some(1.5) },
+                Kind::Struct { value: None },
+            ],
+            map,
+            negative: -5,
+            nothing: None,
+            text: "twilight".to_owned(),
+            tuple: (7, "seven".to_owned()),
+            unit: (),
+        };
+
+        let bytes = to_vec(&values).unwrap();
+        assert_eq!(values, from_slice::<Values>(&bytes).unwrap());
+    }
+
+    #[test]
+    fn test_string_ext() {
+        // `[1, 2, 3]` is encoded as a string by Erlang.
+        let bytes = [131, 107, 0, 3, 1, 2, 3];
+        assert_eq!(vec![1_u8, 2, 3], from_slice::<Vec<u8>>(&bytes).unwrap());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(
+            from_slice::<u8>(&[130, 97, 1]).unwrap_err().kind(),
+            EtfErrorType::VersionInvalid { version: 130 },
+        ));
+        assert!(matches!(
+            from_slice::<u8>(&[131, 97, 1, 0]).unwrap_err().kind(),
+            EtfErrorType::TrailingBytes,
+        ));
+        assert!(matches!(
+            from_slice::<u8>(&[131, 98, 0]).unwrap_err().kind(),
+            EtfErrorType::EndOfInput,
+        ));
+        assert!(matches!(
+            from_slice::<u64>(&[131, 110, 9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1])
+                .unwrap_err()
+                .kind(),
+            EtfErrorType::IntegerTooLarge,
+        ));
+        assert!(matches!(
+            from_slice::<Vec<u8>>(&[131, 108, 0, 0, 0, 1, 97, 1, 97, 2])
+                .unwrap_err()
+                .kind(),
+            EtfErrorType::ListImproper,
+        ));
+        assert!(matches!(
+            from_slice::<u8>(&[131, 103]).unwrap_err().kind(),
+            EtfErrorType::TagUnsupported { tag: 103 },
+        ));
+    }
+}
@@ -0,0 +1,447 @@
+use super::{
+    EtfError, EtfErrorType, BINARY_EXT, INTEGER_EXT, LIST_EXT, MAP_EXT, NEW_FLOAT_EXT, NIL_EXT,
+    SMALL_ATOM_EXT, SMALL_BIG_EXT, SMALL_INTEGER_EXT, VERSION,
+};
+use serde::ser::{self, Serialize};
+use std::convert::TryFrom;
+
+/// Serializer of a term.
+///
+/// Use [`to_vec`] to serialize a value into a term.
+///
+/// [`to_vec`]: super::to_vec
+#[derive(Debug)]
+pub struct Serializer {
+    output: Vec<u8>,
+}
+
+impl Serializer {
+    /// Create a new serializer, writing the version byte.
+    pub fn new() -> Self {
+        Self {
+            output: vec![VERSION],
+        }
+    }
+
+    /// Consume the serializer, returning the serialized term.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn into_inner(self) -> Vec<u8> {
+        self.output
+    }
+
+    fn atom(&mut self, name: &str) {
+        // Only used for the short, ASCII atoms `nil`, `true`, and `false`.
+        self.output.push(SMALL_ATOM_EXT);
+        #[allow(clippy::cast_possible_truncation)]
+        self.output.push(name.len() as u8);
+        self.output.extend_from_slice(name.as_bytes());
+    }
+
+    fn binary(&mut self, bytes: &[u8]) -> Result<(), EtfError> {
+        let len =
+            u32::try_from(bytes.len()).map_err(|_| EtfError::new(EtfErrorType::LengthTooLarge))?;
+
+        self.output.push(BINARY_EXT);
+        self.output.extend_from_slice(&len.to_be_bytes());
+        self.output.extend_from_slice(bytes);
+
+        Ok(())
+    }
+
+    fn unsigned(&mut self, value: u64) {
+        if let Ok(value) = u8::try_from(value) {
+            self.output.push(SMALL_INTEGER_EXT);
+            self.output.push(value);
+        } else if let Ok(value) = i32::try_from(value) {
+            self.output.push(INTEGER_EXT);
+            self.output.extend_from_slice(&value.to_be_bytes());
+        } else {
+            self.big(false, value);
+        }
+    }
+
+    fn signed(&mut self, value: i64) {
+        if let Ok(value) = u64::try_from(value) {
+            self.unsigned(value);
+        } else if let Ok(value) = i32::try_from(value) {
+            self.output.push(INTEGER_EXT);
+            self.output.extend_from_slice(&value.to_be_bytes());
+        } else {
+            // The magnitude of a negative value is its two's complement,
+            // which also holds for `i64::MIN`.
+            #[allow(clippy::cast_sign_loss)]
+            self.big(true, value.wrapping_neg() as u64);
+        }
+    }
+
+    fn big(&mut self, negative: bool, magnitude: u64) {
+        let digits = magnitude.to_le_bytes();
+        let len = digits
+            .iter()
+            .rposition(|digit| *digit != 0)
+            .map_or(0, |i| i + 1);
+
+        // A `u64` has at most 8 digits.
+        self.output.push(SMALL_BIG_EXT);
+        #[allow(clippy::cast_possible_truncation)]
+        self.output.push(len as u8);
+        self.output.push(u8::from(negative));
+        self.output.extend_from_slice(&digits[..len]);
+    }
+
+    /// Start a list or map whose length is written once it's complete.
+    fn compound(&mut self, tag: u8) -> Compound<'_> {
+        self.output.push(tag);
+        let header = self.output.len();
+        self.output.extend_from_slice(&[0; 4]);
+
+        Compound {
+            header,
+            len: 0,
+            ser: self,
+        }
+    }
+
+    /// Start a map of a variant to its value.
+    fn variant(&mut self, variant: &str) -> Result<(), EtfError> {
+        self.output.push(MAP_EXT);
+        self.output.extend_from_slice(&1_u32.to_be_bytes());
+
+        self.binary(variant.as_bytes())
+    }
+}
+
+impl Default for Serializer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut Serializer {
+    type Ok = ();
+    type Error = EtfError;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), EtfError> {
+        self.atom(if v { "true" } else { "false" });
+
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), EtfError> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), EtfError> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), EtfError> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), EtfError> {
+        self.signed(v);
+
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), EtfError> {
+        self.serialize_u64(u64::from(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), EtfError> {
+        self.serialize_u64(u64::from(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), EtfError> {
+        self.serialize_u64(u64::from(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), EtfError> {
+        self.unsigned(v);
+
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), EtfError> {
+        self.serialize_f64(f64::from(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), EtfError> {
+        self.output.push(NEW_FLOAT_EXT);
+        self.output.extend_from_slice(&v.to_be_bytes());
+
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), EtfError> {
+        self.binary(v.encode_utf8(&mut [0; 4]).as_bytes())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), EtfError> {
+        self.binary(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), EtfError> {
+        self.binary(v)
+    }
+
+    fn serialize_none(self) -> Result<(), EtfError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), EtfError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), EtfError> {
+        self.atom("nil");
+
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), EtfError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<(), EtfError> {
+        self.binary(variant.as_bytes())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), EtfError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), EtfError> {
+        self.variant(variant)?;
+
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Compound<'a>, EtfError> {
+        Ok(self.compound(LIST_EXT))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Compound<'a>, EtfError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, len: usize) -> Result<Compound<'a>, EtfError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Compound<'a>, EtfError> {
+        self.variant(variant)?;
+
+        Ok(self.compound(LIST_EXT))
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Compound<'a>, EtfError> {
+        Ok(self.compound(MAP_EXT))
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Compound<'a>, EtfError> {
+        Ok(self.compound(MAP_EXT))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Compound<'a>, EtfError> {
+        self.variant(variant)?;
+
+        Ok(self.compound(MAP_EXT))
+    }
+}
+
+/// List or map being serialized.
+///
+/// The number of elements isn't always known upfront, so it's written into
+/// the header once the list or map is complete.
+#[derive(Debug)]
+pub struct Compound<'a> {
+    /// Position of the length in the output.
+    header: usize,
+    len: u32,
+    ser: &'a mut Serializer,
+}
+
+impl Compound<'_> {
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EtfError> {
+        self.len = self
+            .len
+            .checked_add(1)
+            .ok_or_else(|| EtfError::new(EtfErrorType::LengthTooLarge))?;
+
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end_list(self) {
+        let output = &mut self.ser.output;
+
+        if self.len == 0 {
+            // Empty lists are encoded as `NIL_EXT` without a tail.
+            output.truncate(self.header - 1);
+        } else {
+            output[self.header..self.header + 4].copy_from_slice(&self.len.to_be_bytes());
+        }
+
+        output.push(NIL_EXT);
+    }
+
+    fn end_map(self) {
+        self.ser.output[self.header..self.header + 4].copy_from_slice(&self.len.to_be_bytes());
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = EtfError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EtfError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), EtfError> {
+        self.end_list();
+
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = EtfError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EtfError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), EtfError> {
+        self.end_list();
+
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = EtfError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EtfError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), EtfError> {
+        self.end_list();
+
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = EtfError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EtfError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), EtfError> {
+        self.end_list();
+
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = EtfError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), EtfError> {
+        self.element(key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), EtfError> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), EtfError> {
+        self.end_map();
+
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = EtfError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), EtfError> {
+        self.element(key)?;
+
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), EtfError> {
+        self.end_map();
+
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = EtfError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), EtfError> {
+        self.element(key)?;
+
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<(), EtfError> {
+        self.end_map();
+
+        Ok(())
+    }
+}
@@ -13,13 +13,13 @@
 //! [`EventType`]: ::twilight_model::gateway::event::EventType
 //! [`ShardBuilder::event_types`]: crate::shard::ShardBuilder::event_types
 
+use super::channel::Receiver;
 use crate::EventTypeFlags;
 use futures_util::stream::Stream;
 use std::{
     pin::Pin,
     task::{Context, Poll},
 };
-use tokio::sync::mpsc::UnboundedReceiver;
 use twilight_model::gateway::event::Event;
 
 /// A stream of events from a [`Shard`].
@@ -39,11 +39,11 @@ use twilight_model::gateway::event::Event;
 /// [`futures::stream::Stream`]: https://docs.rs/futures/*/futures/stream/trait.Stream.html
 pub struct Events {
     event_types: EventTypeFlags,
-    rx: UnboundedReceiver<Event>,
+    rx: Receiver<Event>,
 }
 
 impl Events {
-    pub(super) const fn new(event_types: EventTypeFlags, rx: UnboundedReceiver<Event>) -> Self {
+    pub(crate) const fn new(event_types: EventTypeFlags, rx: Receiver<Event>) -> Self {
         Self { event_types, rx }
     }
 
@@ -51,6 +51,18 @@ impl Events {
     pub const fn event_types(&self) -> EventTypeFlags {
         self.event_types
     }
+
+    /// Number of events that have been dropped because the stream's buffer
+    /// was full.
+    ///
+    /// This is always zero unless the buffer is bounded with an overflow
+    /// policy that drops events. Refer to [`ShardBuilder::event_buffer`] for
+    /// more information.
+    ///
+    /// [`ShardBuilder::event_buffer`]: super::ShardBuilder::event_buffer
+    pub fn dropped(&self) -> u64 {
+        self.rx.dropped()
+    }
 }
 
 impl Stream for Events {
@@ -3,13 +3,15 @@ use super::{
     config::Config,
     emitter::Emitter,
     event::Events,
-    json,
-    processor::{ConnectingErrorType, Latency, Session, ShardProcessor},
+    lazy::LazyEvents,
+    processor::{
+        CommandRatelimit, ConnectingErrorType, Health, HealthTracker, Latency, Readiness,
+        ReadinessTracker, Session, ShardProcessor,
+    },
     raw_message::Message,
     stage::Stage,
 };
 use crate::Intents;
-use futures_util::stream::StreamExt;
 use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
 use std::{
@@ -72,7 +74,7 @@ impl Display for CommandError {
             CommandErrorType::Sending => {
                 f.write_str("sending the message over the websocket failed")
             }
-            CommandErrorType::Serializing => f.write_str("serializing the value as json failed"),
+            CommandErrorType::Serializing => f.write_str("serializing the value failed"),
             CommandErrorType::SessionInactive => Display::fmt(&SessionInactiveError, f),
         }
     }
@@ -93,7 +95,7 @@ pub enum CommandErrorType {
     /// Sending the payload over the WebSocket failed. This is indicative of a
     /// shutdown shard.
     Sending,
-    /// Serializing the payload as JSON failed.
+    /// Serializing the payload in the shard's encoding failed.
     Serializing,
     /// Shard's session is inactive because the shard hasn't been started.
     SessionInactive,
@@ -242,6 +244,8 @@ pub enum ShardStartErrorType {
 pub struct Information {
     id: u64,
     latency: Latency,
+    ratelimit: CommandRatelimit,
+    readiness: Readiness,
     session_id: Option<Box<str>>,
     seq: u64,
     stage: Stage,
@@ -261,6 +265,20 @@ impl Information {
         &self.latency
     }
 
+    /// Return the command ratelimit information for the shard.
+    ///
+    /// This includes the number of commands that can be sent right now and
+    /// the time until the next one can be sent.
+    pub const fn ratelimit(&self) -> CommandRatelimit {
+        self.ratelimit
+    }
+
+    /// Return an immutable reference to the progress of the shard loading the
+    /// guilds listed in its ready event.
+    pub const fn readiness(&self) -> &Readiness {
+        &self.readiness
+    }
+
     /// Return an immutable reference to the session ID of the shard.
     pub fn session_id(&self) -> Option<&str> {
         self.session_id.as_deref()
@@ -288,7 +306,7 @@ impl Information {
 }
 
 /// Details to resume a gateway session.
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct ResumeSession {
     /// ID of the session being resumed.
     pub session_id: String,
@@ -300,7 +318,9 @@ pub struct ResumeSession {
 struct ShardRef {
     config: Arc<Config>,
     emitter: Emitter,
+    health: Arc<HealthTracker>,
     processor_handle: OnceCell<JoinHandle<()>>,
+    readiness: Arc<ReadinessTracker>,
     session: OnceCell<WatchReceiver<Arc<Session>>>,
 }
 
@@ -399,19 +419,44 @@ impl Shard {
     }
 
     pub(crate) fn new_with_config(config: Config) -> (Self, Events) {
-        let config = Arc::new(config);
         let event_types = config.event_types();
+        let (emitter, rx) = Emitter::new(
+            config.encoding(),
+            event_types,
+            config.guild_filter.clone(),
+            config.event_buffer(),
+        );
 
-        let (emitter, rx) = Emitter::new(event_types);
+        (
+            Self::with_emitter(config, emitter),
+            Events::new(event_types, rx),
+        )
+    }
 
-        let this = Self(Arc::new(ShardRef {
-            config,
+    pub(crate) fn new_lazy_with_config(config: Config) -> (Self, LazyEvents) {
+        let event_types = config.event_types();
+        let (emitter, rx) = Emitter::new_lazy(
+            config.encoding(),
+            event_types,
+            config.guild_filter.clone(),
+            config.event_buffer(),
+        );
+
+        (
+            Self::with_emitter(config, emitter),
+            LazyEvents::new(event_types, rx),
+        )
+    }
+
+    fn with_emitter(config: Config, emitter: Emitter) -> Self {
+        Self(Arc::new(ShardRef {
+            readiness: Arc::new(ReadinessTracker::new(config.shard()[0])),
+            config: Arc::new(config),
             emitter,
+            health: Arc::new(HealthTracker::default()),
             processor_handle: OnceCell::new(),
             session: OnceCell::new(),
-        }));
-
-        (this, Events::new(event_types, rx))
+        }))
     }
 
     /// Create a builder to configure and construct a shard.
@@ -458,24 +503,25 @@ impl Shard {
 
         let config = Arc::clone(&self.0.config);
         let emitter = self.0.emitter.clone();
-        let (processor, wrx) =
-            ShardProcessor::new(config, url, emitter)
-                .await
-                .map_err(|source| {
-                    let (kind, source) = source.into_parts();
-
-                    let new_kind = match kind {
-                        ConnectingErrorType::Establishing => ShardStartErrorType::Establishing,
-                        ConnectingErrorType::ParsingUrl { url } => {
-                            ShardStartErrorType::ParsingGatewayUrl { url }
-                        }
-                    };
-
-                    ShardStartError {
-                        source,
-                        kind: new_kind,
+        let health = Arc::clone(&self.0.health);
+        let readiness = Arc::clone(&self.0.readiness);
+        let (processor, wrx) = ShardProcessor::new(config, url, emitter, health, readiness)
+            .await
+            .map_err(|source| {
+                let (kind, source) = source.into_parts();
+
+                let new_kind = match kind {
+                    ConnectingErrorType::Establishing => ShardStartErrorType::Establishing,
+                    ConnectingErrorType::ParsingUrl { url } => {
+                        ShardStartErrorType::ParsingGatewayUrl { url }
                     }
-                })?;
+                };
+
+                ShardStartError {
+                    source,
+                    kind: new_kind,
+                }
+            })?;
 
         let handle = tokio::spawn(async move {
             processor.run().await;
@@ -502,14 +548,66 @@ impl Shard {
         Ok(Information {
             id: self.config().shard()[0],
             latency: session.heartbeats.latency(),
+            ratelimit: session.ratelimiter.ratelimit(),
+            readiness: self.0.readiness.readiness(),
             session_id: session.id(),
             seq: session.seq(),
             stage: session.stage(),
         })
     }
 
+    /// Return the health of the shard's connection, such as the time since
+    /// the last dispatch event and how its sessions were started.
+    ///
+    /// Unlike [`info`] this doesn't require the shard's session to be active;
+    /// the stage of a shard that hasn't been started is [`Disconnected`].
+    ///
+    /// [`Disconnected`]: Stage::Disconnected
+    /// [`info`]: Self::info
+    pub fn health(&self) -> Health {
+        let (stage, missed_heartbeat_acks) =
+            self.session().map_or((Stage::Disconnected, 0), |session| {
+                (session.stage(), session.heartbeats.missed())
+            });
+
+        self.0.health.health(stage, missed_heartbeat_acks)
+    }
+
+    /// Return the progress of the shard loading the guilds listed in its ready
+    /// event.
+    ///
+    /// Unlike [`info`] this doesn't require the shard's session to be
+    /// active.
+    ///
+    /// [`info`]: Self::info
+    pub fn readiness(&self) -> Readiness {
+        self.0.readiness.readiness()
+    }
+
+    /// Return the command ratelimit information for the shard's session.
+    ///
+    /// Discord allows 120 payloads to be sent per minute, part of which is
+    /// reserved for heartbeats. Commands sent via [`command`] and [`send`]
+    /// wait for capacity when none is available.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SessionInactiveError`] if the shard's session is inactive.
+    ///
+    /// [`command`]: Self::command
+    /// [`send`]: Self::send
+    pub fn ratelimit(&self) -> Result<CommandRatelimit, SessionInactiveError> {
+        let session = self.session()?;
+
+        Ok(session.ratelimiter.ratelimit())
+    }
+
     /// Send a command over the gateway.
     ///
+    /// Commands are ratelimited to stay within Discord's limit of 120
+    /// payloads per minute, waiting for capacity if needed. Refer to
+    /// [`ratelimit`] for the remaining capacity.
+    ///
     /// # Errors
     ///
     /// Returns a [`CommandErrorType::Sending`] error type if the message could
@@ -517,17 +615,24 @@ impl Shard {
     /// restarting.
     ///
     /// Returns a [`CommandErrorType::Serializing`] error type if the provided
-    /// value failed to serialize into JSON.
+    /// value failed to serialize into the shard's [encoding].
     ///
     /// Returns a [`CommandErrorType::SessionInactive`] error type if the shard
     /// has not been started.
+    ///
+    /// [encoding]: super::ShardBuilder::encoding
+    /// [`ratelimit`]: Self::ratelimit
     pub async fn command(&self, value: &impl serde::Serialize) -> Result<(), CommandError> {
-        let json = json::to_vec(value).map_err(|source| CommandError {
-            source: Some(Box::new(source)),
-            kind: CommandErrorType::Serializing,
-        })?;
-
-        self.send(Message::Binary(json))
+        let payload = self
+            .config()
+            .encoding()
+            .serialize(value)
+            .map_err(|source| CommandError {
+                source: Some(source),
+                kind: CommandErrorType::Serializing,
+            })?;
+
+        self.send(Message::Binary(payload))
             .await
             .map_err(CommandError::from_send)
     }
@@ -586,8 +691,7 @@ impl Shard {
     /// [`shutdown`]: Self::shutdown
     pub async fn send(&self, message: Message) -> Result<(), SendError> {
         if let Ok(session) = self.session() {
-            // Tick ratelimiter.
-            session.ratelimit.lock().await.next().await;
+            session.ratelimiter.acquire().await;
 
             session
                 .tx
@@ -634,11 +738,16 @@ impl Shard {
     /// The shard will cleanly close the connection by sending a normal close
     /// code, causing Discord to show the bot as being offline. The session will
     /// not be resumable.
+    ///
+    /// The shard's event stream ends once the events it has already buffered
+    /// have been received.
     pub fn shutdown(&self) {
         if let Some(processor_handle) = self.0.processor_handle.get() {
             processor_handle.abort();
         }
 
+        self.0.emitter.close();
+
         if let Ok(session) = self.session() {
             // Since we're shutting down now, we don't care if it sends or not.
             let _res = session.close(Some(TungsteniteCloseFrame {
@@ -656,12 +765,17 @@ impl Shard {
     /// will be resumable by using the provided session resume information
     /// to [`ClusterBuilder::resume_sessions`].
     ///
+    /// The shard's event stream ends once the events it has already buffered
+    /// have been received.
+    ///
     /// [`ClusterBuilder::resume_sessions`]: crate::cluster::ClusterBuilder::resume_sessions
     pub fn shutdown_resumable(&self) -> (u64, Option<ResumeSession>) {
         if let Some(processor_handle) = self.0.processor_handle.get() {
             processor_handle.abort();
         }
 
+        self.0.emitter.close();
+
         let shard_id = self.config().shard()[0];
 
         let session = match self.session() {
@@ -687,6 +801,12 @@ impl Shard {
         (shard_id, data)
     }
 
+    /// Number of events the shard's event stream has dropped due to its buffer
+    /// being full.
+    pub(crate) fn events_dropped(&self) -> u64 {
+        self.0.emitter.dropped()
+    }
+
     /// Return a handle to the current session.
     ///
     /// # Errors
@@ -705,13 +825,30 @@ mod tests {
         CommandError, CommandErrorType, Information, ResumeSession, SendError, SendErrorType,
         SessionInactiveError, Shard, ShardStartError, ShardStartErrorType,
     };
+    use serde::{Deserialize, Serialize};
     use static_assertions::{assert_fields, assert_impl_all};
     use std::{error::Error, fmt::Debug};
 
     assert_impl_all!(CommandErrorType: Debug, Send, Sync);
     assert_impl_all!(CommandError: Error, Send, Sync);
-    assert_impl_all!(Information: Clone, Debug, Send, Sync);
-    assert_impl_all!(ResumeSession: Clone, Debug, Send, Sync);
+    assert_impl_all!(
+        Information: Clone,
+        Debug,
+        Deserialize<'static>,
+        Send,
+        Serialize,
+        Sync
+    );
+    assert_impl_all!(
+        ResumeSession: Clone,
+        Debug,
+        Deserialize<'static>,
+        Eq,
+        PartialEq,
+        Send,
+        Serialize,
+        Sync
+    );
     assert_impl_all!(SendErrorType: Debug, Send, Sync);
     assert_impl_all!(SendError: Error, Send, Sync);
     assert_impl_all!(SessionInactiveError: Error, Send, Sync);
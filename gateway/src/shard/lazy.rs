@@ -0,0 +1,338 @@
+//! Events whose payloads are only deserialized when requested.
+//!
+//! Refer to [`ShardBuilder::build_lazy`] for more information.
+//!
+//! [`ShardBuilder::build_lazy`]: super::ShardBuilder::build_lazy
+
+use super::{channel::Receiver, encoding::Encoding};
+use crate::EventTypeFlags;
+use futures_util::stream::Stream;
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    pin::Pin,
+    task::{Context, Poll},
+};
+use twilight_model::{
+    gateway::event::{Event, EventType},
+    id::GuildId,
+};
+
+/// Deserializing a [`RawEvent`] into an [`Event`] failed.
+#[derive(Debug)]
+pub struct DeserializeEventError {
+    kind: DeserializeEventErrorType,
+    source: Option<Box<dyn Error + Send + Sync>>,
+}
+
+impl DeserializeEventError {
+    /// Immutable reference to the type of error that occurred.
+    #[must_use = "retrieving the type has no effect if left unused"]
+    pub const fn kind(&self) -> &DeserializeEventErrorType {
+        &self.kind
+    }
+
+    /// Consume the error, returning the source error if there is any.
+    #[must_use = "consuming the error and retrieving the source has no effect if left unused"]
+    pub fn into_source(self) -> Option<Box<dyn Error + Send + Sync>> {
+        self.source
+    }
+
+    /// Consume the error, returning the owned error type and the source error.
+    #[must_use = "consuming the error into its parts has no effect if left unused"]
+    pub fn into_parts(
+        self,
+    ) -> (
+        DeserializeEventErrorType,
+        Option<Box<dyn Error + Send + Sync>>,
+    ) {
+        (self.kind, self.source)
+    }
+}
+
+impl Display for DeserializeEventError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self.kind {
+            DeserializeEventErrorType::Deserializing => {
+                f.write_str("deserializing the event payload failed")
+            }
+        }
+    }
+}
+
+impl Error for DeserializeEventError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| &**source as &(dyn Error + 'static))
+    }
+}
+
+/// Type of [`DeserializeEventError`] that occurred.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DeserializeEventErrorType {
+    /// The payload isn't a valid payload of its event type.
+    Deserializing,
+}
+
+/// Dispatch event that hasn't been deserialized yet.
+///
+/// The information needed to route the event, such as its type and the ID of
+/// the guild it's related to, is peeked from the payload when it's received.
+/// The payload itself is only deserialized once [`into_event`] is called, so
+/// events that are only forwarded elsewhere never pay the cost.
+///
+/// [`into_event`]: Self::into_event
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RawEvent {
+    encoding: Encoding,
+    event_type: Box<str>,
+    guild_id: Option<GuildId>,
+    kind: EventType,
+    op: u8,
+    payload: Vec<u8>,
+    sequence: u64,
+}
+
+impl RawEvent {
+    pub(crate) fn new(
+        op: u8,
+        sequence: u64,
+        event_type: &str,
+        kind: EventType,
+        guild_id: Option<GuildId>,
+        encoding: Encoding,
+        payload: Vec<u8>,
+    ) -> Self {
+        Self {
+            encoding,
+            event_type: event_type.into(),
+            guild_id,
+            kind,
+            op,
+            payload,
+            sequence,
+        }
+    }
+
+    /// Immutable reference to the raw payload.
+    pub fn bytes(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// Encoding of the raw payload.
+    pub const fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Name of the event type, such as `MESSAGE_CREATE`.
+    pub fn event_type(&self) -> &str {
+        &self.event_type
+    }
+
+    /// ID of the guild the event is related to, if any.
+    pub const fn guild_id(&self) -> Option<GuildId> {
+        self.guild_id
+    }
+
+    /// Type of the event.
+    pub const fn kind(&self) -> EventType {
+        self.kind
+    }
+
+    /// Opcode of the payload.
+    pub const fn op(&self) -> u8 {
+        self.op
+    }
+
+    /// Sequence number of the event.
+    pub const fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Consume the event, returning the raw payload.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.payload
+    }
+
+    /// Consume the event, deserializing its payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DeserializeEventErrorType::Deserializing`] error type if
+    /// the payload isn't a valid payload of its event type.
+    pub fn into_event(self) -> Result<Event, DeserializeEventError> {
+        let Self {
+            encoding,
+            event_type,
+            op,
+            mut payload,
+            sequence,
+            ..
+        } = self;
+
+        encoding
+            .parse_gateway_event(op, Some(sequence), Some(&event_type), &mut payload)
+            .map(Event::from)
+            .map_err(|source| DeserializeEventError {
+                kind: DeserializeEventErrorType::Deserializing,
+                source: Some(Box::new(source)),
+            })
+    }
+}
+
+/// Event emitted by a shard built with [`ShardBuilder::build_lazy`].
+///
+/// [`ShardBuilder::build_lazy`]: super::ShardBuilder::build_lazy
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LazyEvent {
+    /// Event that has already been deserialized.
+    ///
+    /// These are the events about the shard's connection and the events the
+    /// shard has to deserialize to operate, such as [`Event::Ready`].
+    Deserialized(Event),
+    /// Dispatch event that hasn't been deserialized yet.
+    Raw(RawEvent),
+}
+
+impl LazyEvent {
+    /// ID of the guild the event is related to, if any.
+    pub fn guild_id(&self) -> Option<GuildId> {
+        match self {
+            Self::Deserialized(event) => event.guild_id(),
+            Self::Raw(raw) => raw.guild_id(),
+        }
+    }
+
+    /// Type of the event.
+    pub const fn kind(&self) -> EventType {
+        match self {
+            Self::Deserialized(event) => event.kind(),
+            Self::Raw(raw) => raw.kind(),
+        }
+    }
+
+    /// Consume the event, deserializing it if it hasn't been already.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DeserializeEventErrorType::Deserializing`] error type if
+    /// the payload of a raw event isn't a valid payload of its event type.
+    pub fn into_event(self) -> Result<Event, DeserializeEventError> {
+        match self {
+            Self::Deserialized(event) => Ok(event),
+            Self::Raw(raw) => raw.into_event(),
+        }
+    }
+}
+
+impl From<Event> for LazyEvent {
+    fn from(event: Event) -> Self {
+        Self::Deserialized(event)
+    }
+}
+
+/// A stream of lazily deserialized events from a [`Shard`].
+///
+/// Created by [`ShardBuilder::build_lazy`].
+///
+/// [`Shard`]: super::Shard
+/// [`ShardBuilder::build_lazy`]: super::ShardBuilder::build_lazy
+pub struct LazyEvents {
+    event_types: EventTypeFlags,
+    rx: Receiver<LazyEvent>,
+}
+
+impl LazyEvents {
+    pub(crate) const fn new(event_types: EventTypeFlags, rx: Receiver<LazyEvent>) -> Self {
+        Self { event_types, rx }
+    }
+
+    /// Returns the event types that can be passed to this stream.
+    pub const fn event_types(&self) -> EventTypeFlags {
+        self.event_types
+    }
+
+    /// Number of events that have been dropped because the stream's buffer
+    /// was full.
+    ///
+    /// This is always zero unless the buffer is bounded with an overflow
+    /// policy that drops events. Refer to [`ShardBuilder::event_buffer`] for
+    /// more information.
+    ///
+    /// [`ShardBuilder::event_buffer`]: super::ShardBuilder::event_buffer
+    pub fn dropped(&self) -> u64 {
+        self.rx.dropped()
+    }
+}
+
+impl Stream for LazyEvents {
+    type Item = LazyEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DeserializeEventError, Encoding, LazyEvent, LazyEvents, RawEvent};
+    use futures_util::stream::Stream;
+    use static_assertions::assert_impl_all;
+    use std::{error::Error, fmt::Debug};
+    use twilight_model::{
+        gateway::event::{Event, EventType},
+        id::{GuildId, RoleId},
+    };
+
+    assert_impl_all!(DeserializeEventError: Error, Send, Sync);
+    assert_impl_all!(LazyEvent: Clone, Debug, Send, Sync);
+    assert_impl_all!(LazyEvents: Send, Stream, Sync);
+    assert_impl_all!(RawEvent: Clone, Debug, Send, Sync);
+
+    #[test]
+    fn test_raw_event() {
+        let json = r#"{"t":"GUILD_ROLE_DELETE","s":5,"op":0,"d":{"role_id":"2","guild_id":"1"}}"#;
+        let raw = RawEvent::new(
+            0,
+            5,
+            "GUILD_ROLE_DELETE",
+            EventType::RoleDelete,
+            Some(GuildId(1)),
+            Encoding::Json,
+            json.as_bytes().to_vec(),
+        );
+
+        assert_eq!(Some(GuildId(1)), raw.guild_id());
+        assert_eq!(EventType::RoleDelete, raw.kind());
+        assert_eq!(5, raw.sequence());
+        assert_eq!(json.as_bytes(), raw.bytes());
+
+        match raw.into_event().unwrap() {
+            Event::RoleDelete(delete) => {
+                assert_eq!(GuildId(1), delete.guild_id);
+                assert_eq!(RoleId(2), delete.role_id);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_raw_event_invalid() {
+        let json = r#"{"t":"GUILD_ROLE_DELETE","s":5,"op":0,"d":{"guild_id":"1"}}"#;
+        let event = LazyEvent::Raw(RawEvent::new(
+            0,
+            5,
+            "GUILD_ROLE_DELETE",
+            EventType::RoleDelete,
+            Some(GuildId(1)),
+            Encoding::Json,
+            json.as_bytes().to_vec(),
+        ));
+
+        assert_eq!(Some(GuildId(1)), event.guild_id());
+        assert!(event.into_event().is_err());
+    }
+}
@@ -19,24 +19,38 @@
 //! [information about itself]: Shard::info
 //! [new messages]: ::twilight_model::gateway::event::Event::MessageCreate
 
+#[cfg(feature = "etf")]
+pub mod etf;
 pub mod raw_message;
+pub mod recording;
 pub mod stage;
 
 mod builder;
+pub(crate) mod channel;
 mod config;
+mod connect;
 mod emitter;
+mod encoding;
 mod event;
 mod r#impl;
 mod json;
+mod lazy;
 mod processor;
 
 pub use self::{
     builder::{
         LargeThresholdError, LargeThresholdErrorType, ShardBuilder, ShardIdError, ShardIdErrorType,
     },
+    channel::OverflowPolicy,
     config::Config,
+    connect::{Proxy, ProxyError, ProxyErrorType, ProxyKind, TlsConnector},
+    encoding::Encoding,
     event::Events,
-    processor::heartbeat::Latency,
+    lazy::{DeserializeEventError, DeserializeEventErrorType, LazyEvent, LazyEvents, RawEvent},
+    processor::{
+        heartbeat::Latency, CommandRatelimit, Health, HealthPolicy, Readiness, SessionStart,
+        TransportCompression,
+    },
     r#impl::{
         CommandError, CommandErrorType, Information, ResumeSession, SendError, SendErrorType,
         SessionInactiveError, Shard, ShardStartError, ShardStartErrorType,
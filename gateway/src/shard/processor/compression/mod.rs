@@ -1,37 +1,103 @@
 #[cfg(feature = "compression")]
 mod inflater;
+#[cfg(feature = "zstd-stream")]
+mod zstd_stream;
 
 use super::r#impl::ReceivingEventError;
-use std::str;
+
+#[cfg(any(feature = "compression", feature = "zstd-stream"))]
+use super::r#impl::ReceivingEventErrorType;
 
 #[cfg(feature = "compression")]
 use inflater::Inflater;
+#[cfg(feature = "zstd-stream")]
+use zstd_stream::ZstdInflater;
 
-/// Interface for working with buffers variable on the `compression` feature flag.
-#[derive(Debug)]
-pub struct Compression {
-    /// Inflater for use with compression.
+/// Compression of the messages received from the gateway.
+///
+/// Refer to [`ShardBuilder::transport_compression`] for more information.
+///
+/// [`ShardBuilder::transport_compression`]: crate::shard::ShardBuilder::transport_compression
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum TransportCompression {
+    /// Messages aren't compressed.
+    Disabled,
+    /// Messages are compressed as a single zlib stream.
+    ///
+    /// Requires the `compression` feature.
     #[cfg(feature = "compression")]
-    inner: Inflater,
+    ZlibStream,
+    /// Messages are compressed as a single zstd stream.
+    ///
+    /// Requires the `zstd-stream` feature.
+    #[cfg(feature = "zstd-stream")]
+    ZstdStream,
+}
+
+impl TransportCompression {
+    /// Value of the gateway URL's `compress` query parameter, if any.
+    pub const fn name(self) -> Option<&'static str> {
+        match self {
+            Self::Disabled => None,
+            #[cfg(feature = "compression")]
+            Self::ZlibStream => Some("zlib-stream"),
+            #[cfg(feature = "zstd-stream")]
+            Self::ZstdStream => Some("zstd-stream"),
+        }
+    }
+}
+
+impl Default for TransportCompression {
+    /// zlib-stream if the `compression` feature is enabled, otherwise no
+    /// compression.
+    fn default() -> Self {
+        #[cfg(feature = "compression")]
+        {
+            Self::ZlibStream
+        }
+
+        #[cfg(not(feature = "compression"))]
+        Self::Disabled
+    }
+}
+
+/// Buffer of the active transport compression.
+#[derive(Debug)]
+enum Inner {
     /// Buffer for use without compression.
-    #[cfg(not(feature = "compression"))]
-    inner: Vec<u8>,
+    Disabled(Vec<u8>),
+    /// Inflater for use with zlib-stream compression.
+    #[cfg(feature = "compression")]
+    ZlibStream(Inflater),
+    /// Inflater for use with zstd-stream compression.
+    #[cfg(feature = "zstd-stream")]
+    ZstdStream(ZstdInflater),
+}
+
+/// Interface for working with buffers variable on the transport compression.
+#[derive(Debug)]
+pub struct Compression {
+    inner: Inner,
 }
 
 impl Compression {
-    /// Create a new buffer, abstracting over an inflater if the `compression`
-    /// feature is enabled or a simple `Vec` if the feature is disabled.
+    /// Create a new buffer, abstracting over an inflater if compression is
+    /// enabled or a simple `Vec` if it's disabled.
     #[cfg_attr(
-        not(feature = "compression"),
+        not(any(feature = "compression", feature = "zstd-stream")),
         allow(clippy::missing_const_for_fn, unused_variables)
     )]
-    pub fn new(shard_id: [u64; 2]) -> Self {
-        Self {
+    pub fn new(shard_id: [u64; 2], transport_compression: TransportCompression) -> Self {
+        let inner = match transport_compression {
+            TransportCompression::Disabled => Inner::Disabled(Vec::new()),
             #[cfg(feature = "compression")]
-            inner: Inflater::new(shard_id),
-            #[cfg(not(feature = "compression"))]
-            inner: Vec::new(),
-        }
+            TransportCompression::ZlibStream => Inner::ZlibStream(Inflater::new(shard_id)),
+            #[cfg(feature = "zstd-stream")]
+            TransportCompression::ZstdStream => Inner::ZstdStream(ZstdInflater::new(shard_id)),
+        };
+
+        Self { inner }
     }
 
     /// Mutable reference to the internal buffer slice.
@@ -41,14 +107,18 @@ impl Compression {
     ///
     /// When compression is disabled this will mutably reference the standard
     /// buffer.
+    #[cfg_attr(
+        not(any(feature = "compression", feature = "zstd-stream")),
+        allow(clippy::missing_const_for_fn)
+    )]
     pub fn buffer_slice_mut(&mut self) -> &mut [u8] {
-        #[cfg(feature = "compression")]
-        {
-            self.inner.buffer_mut()
+        match &mut self.inner {
+            Inner::Disabled(buffer) => buffer.as_mut_slice(),
+            #[cfg(feature = "compression")]
+            Inner::ZlibStream(inflater) => inflater.buffer_mut(),
+            #[cfg(feature = "zstd-stream")]
+            Inner::ZstdStream(inflater) => inflater.buffer_mut(),
         }
-
-        #[cfg(not(feature = "compression"))]
-        self.inner.as_mut_slice()
     }
 
     /// Immutable reference to the internal buffer slice.
@@ -58,52 +128,43 @@ impl Compression {
     ///
     /// When compression is disabled this will immutably reference the standard
     /// buffer.
+    #[cfg_attr(
+        not(any(feature = "compression", feature = "zstd-stream")),
+        allow(clippy::missing_const_for_fn)
+    )]
     pub fn buffer_slice_ref(&self) -> &[u8] {
-        #[cfg(feature = "compression")]
-        {
-            self.inner.buffer_ref()
+        match &self.inner {
+            Inner::Disabled(buffer) => buffer.as_slice(),
+            #[cfg(feature = "compression")]
+            Inner::ZlibStream(inflater) => inflater.buffer_ref(),
+            #[cfg(feature = "zstd-stream")]
+            Inner::ZstdStream(inflater) => inflater.buffer_ref(),
         }
-
-        #[cfg(not(feature = "compression"))]
-        self.inner.as_slice()
-    }
-
-    /// Mutable reference to the internal buffer slice as a mutable string slice.
-    ///
-    /// # Safety
-    ///
-    /// Ensuring that the internal buffer slice is UTF-8 valid is left to the
-    /// caller to determine.
-    pub unsafe fn buffer_str_mut(&mut self) -> &mut str {
-        // SAFETY: ensuring safety is left to the caller.
-        str::from_utf8_unchecked_mut(self.buffer_slice_mut())
     }
 
     /// Clear the inner buffer.
     pub fn clear(&mut self) {
-        self.inner.clear();
+        match &mut self.inner {
+            Inner::Disabled(buffer) => buffer.clear(),
+            #[cfg(feature = "compression")]
+            Inner::ZlibStream(inflater) => inflater.clear(),
+            #[cfg(feature = "zstd-stream")]
+            Inner::ZstdStream(inflater) => inflater.clear(),
+        }
     }
 
     /// Extend the buffer with bytes from a Binary websocket message.
     ///
-    /// If compression is disabled then this will do nothing.
-    ///
-    /// Returns whether the inner buffer was extended.
-    #[cfg_attr(
-        not(feature = "compression"),
-        allow(clippy::unused_self, unused_variables)
-    )]
-    pub fn extend_binary(&mut self, bytes: &[u8]) -> bool {
-        #[cfg(feature = "compression")]
-        {
-            self.inner.extend(bytes);
-
-            true
+    /// Binary messages are compressed if compression is enabled, and are
+    /// otherwise payloads in a binary encoding such as ETF.
+    pub fn extend_binary(&mut self, bytes: &[u8]) {
+        match &mut self.inner {
+            Inner::Disabled(buffer) => buffer.extend_from_slice(bytes),
+            #[cfg(feature = "compression")]
+            Inner::ZlibStream(inflater) => inflater.extend(bytes),
+            #[cfg(feature = "zstd-stream")]
+            Inner::ZstdStream(inflater) => inflater.extend(bytes),
         }
-
-        #[cfg(not(feature = "compression"))]
-        // Binary payloads are not received when compression is disabled.
-        false
     }
 
     /// Extend the buffer with bytes from a Text websocket message.
@@ -111,79 +172,101 @@ impl Compression {
     /// If compression is enabled then this will do nothing.
     ///
     /// Returns whether the inner buffer was extended.
-    #[cfg_attr(feature = "compression", allow(clippy::unused_self, unused_variables))]
     pub fn extend_text(&mut self, bytes: &[u8]) -> bool {
-        #[cfg(not(feature = "compression"))]
-        {
-            self.inner.extend_from_slice(bytes);
+        match &mut self.inner {
+            Inner::Disabled(buffer) => {
+                buffer.extend_from_slice(bytes);
 
-            true
+                true
+            }
+            // Text payloads are not received when compression is enabled.
+            #[cfg(any(feature = "compression", feature = "zstd-stream"))]
+            _ => false,
         }
-
-        #[cfg(feature = "compression")]
-        // Text payloads are not received when compression is enabled.
-        false
     }
 
-    /// Mutable reference to the inner completed message if compression is
-    /// enabled.
+    /// Mutable reference to the inner completed message.
     ///
     /// If compression is enabled and a message has completed then a mutable
-    /// slice of the buffer is returned.
+    /// slice of the decompressed buffer is returned.
     ///
     /// If compression is enabled and a message has *not* completed then a
     /// successful `None` is returned.
     ///
-    /// If compression is disabled then a successful `None` is returned.
+    /// If compression is disabled then a mutable slice of the standard buffer
+    /// is returned.
     ///
     /// # Errors
     ///
     /// If compression is enabled then this returns a
     /// `ReceivingEventErrorType::Decompressing` error type if decompressing the
     /// message failed.
-    #[cfg_attr(not(feature = "compression"), allow(clippy::unused_self))]
+    #[cfg_attr(
+        not(any(feature = "compression", feature = "zstd-stream")),
+        allow(clippy::missing_const_for_fn, clippy::unnecessary_wraps)
+    )]
     pub fn message_mut(&mut self) -> Result<Option<&mut [u8]>, ReceivingEventError> {
-        #[cfg(feature = "compression")]
-        {
-            use super::r#impl::ReceivingEventErrorType;
-
-            self.inner.msg().map_err(|source| ReceivingEventError {
+        match &mut self.inner {
+            Inner::Disabled(buffer) => Ok(Some(buffer.as_mut_slice())),
+            #[cfg(feature = "compression")]
+            Inner::ZlibStream(inflater) => inflater.msg().map_err(|source| ReceivingEventError {
                 kind: ReceivingEventErrorType::Decompressing,
                 source: Some(Box::new(source)),
-            })
+            }),
+            #[cfg(feature = "zstd-stream")]
+            Inner::ZstdStream(inflater) => inflater.msg().map_err(|source| ReceivingEventError {
+                kind: ReceivingEventErrorType::Decompressing,
+                source: Some(Box::new(source)),
+            }),
         }
-
-        #[cfg(not(feature = "compression"))]
-        Ok(None)
     }
 
     /// Reset the buffer for a new gateway session.
     pub fn reset(&mut self) {
-        #[cfg(feature = "compression")]
-        self.inner.reset();
-
-        #[cfg(not(feature = "compression"))]
-        self.clear();
+        match &mut self.inner {
+            Inner::Disabled(buffer) => buffer.clear(),
+            #[cfg(feature = "compression")]
+            Inner::ZlibStream(inflater) => inflater.reset(),
+            #[cfg(feature = "zstd-stream")]
+            Inner::ZstdStream(inflater) => inflater.reset(),
+        }
     }
 }
 
-/// Add a toggle to a gateway connection URL depending on whether compression is
-/// enabled.
+/// Add a toggle to a gateway connection URL depending on the transport
+/// compression.
 ///
 /// If compression is enabled then the `compress` query parameter is appended
-/// with a value of `zlib-stream`.
-#[cfg_attr(not(feature = "compression"), allow(unused_variables))]
-pub fn add_url_feature(buf: &mut String) {
-    #[cfg(feature = "compression")]
-    buf.push_str("&compress=zlib-stream");
+/// with the name of the compression, such as `zlib-stream`.
+pub fn add_url_feature(buf: &mut String, transport_compression: TransportCompression) {
+    if let Some(name) = transport_compression.name() {
+        buf.push_str("&compress=");
+        buf.push_str(name);
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::{Compression, TransportCompression};
+    use static_assertions::assert_impl_all;
+    use std::{fmt::Debug, hash::Hash};
+
+    assert_impl_all!(
+        TransportCompression: Clone,
+        Copy,
+        Debug,
+        Default,
+        Eq,
+        Hash,
+        PartialEq,
+        Send,
+        Sync
+    );
+
     #[test]
     fn test_add_url_features() {
         let mut buf = String::new();
-        super::add_url_feature(&mut buf);
+        super::add_url_feature(&mut buf, TransportCompression::default());
 
         #[cfg(feature = "compression")]
         {
@@ -192,5 +275,32 @@ mod tests {
 
         #[cfg(not(feature = "compression"))]
         assert!(buf.is_empty());
+
+        let mut buf = String::new();
+        super::add_url_feature(&mut buf, TransportCompression::Disabled);
+        assert!(buf.is_empty());
+
+        #[cfg(feature = "zstd-stream")]
+        {
+            let mut buf = String::new();
+            super::add_url_feature(&mut buf, TransportCompression::ZstdStream);
+            assert_eq!("&compress=zstd-stream", buf);
+        }
+    }
+
+    #[test]
+    fn test_disabled() {
+        let mut compression = Compression::new([0, 1], TransportCompression::Disabled);
+
+        assert!(compression.extend_text(b"{}"));
+        assert_eq!(
+            Some(&b"{}"[..]),
+            compression.message_mut().unwrap().map(|msg| &*msg)
+        );
+
+        // Payloads in a binary encoding are sent as binary messages.
+        compression.clear();
+        compression.extend_binary(&[131, 106]);
+        assert_eq!(&[131, 106], compression.buffer_slice_ref());
     }
 }
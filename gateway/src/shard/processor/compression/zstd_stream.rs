@@ -0,0 +1,293 @@
+use std::{
+    fmt::{Debug, Formatter, Result as FmtResult},
+    io::Error as IoError,
+    mem,
+    time::Instant,
+};
+use zstd::stream::raw::{Decoder, InBuffer, Operation, OutBuffer};
+
+const INTERNAL_BUFFER_SIZE: usize = 32 * 1024;
+
+pub struct ZstdInflater {
+    decoder: Decoder<'static>,
+    compressed: Vec<u8>,
+    internal_buffer: Vec<u8>,
+    buffer: Vec<u8>,
+    last_resize: Instant,
+    shard: [u64; 2],
+    total_in: u64,
+    total_out: u64,
+}
+
+impl ZstdInflater {
+    /// Create a new inflater for a shard.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the zstd decompression context couldn't be allocated.
+    pub fn new(shard: [u64; 2]) -> Self {
+        Self {
+            buffer: Vec::with_capacity(INTERNAL_BUFFER_SIZE),
+            compressed: Vec::new(),
+            decoder: Decoder::new().expect("zstd decompression context allocation failed"),
+            internal_buffer: Vec::with_capacity(INTERNAL_BUFFER_SIZE),
+            last_resize: Instant::now(),
+            shard,
+            total_in: 0,
+            total_out: 0,
+        }
+    }
+
+    /// Return an immutable reference to the buffer.
+    pub fn buffer_ref(&self) -> &[u8] {
+        self.buffer.as_slice()
+    }
+
+    /// Return a mutable reference to the buffer.
+    pub fn buffer_mut(&mut self) -> &mut [u8] {
+        self.buffer.as_mut_slice()
+    }
+
+    /// Extend the internal compressed buffer with bytes.
+    pub fn extend(&mut self, slice: &[u8]) {
+        self.compressed.extend_from_slice(slice);
+    }
+
+    /// Decompress the next message.
+    ///
+    /// Unlike zlib-stream, the gateway flushes the stream at the end of every
+    /// message, so each websocket message can be decompressed on its own.
+    ///
+    /// Returns `None` if no compressed bytes were received.
+    ///
+    /// # Errors
+    ///
+    /// Returns an IO error if the received bytes aren't a valid zstd stream.
+    #[tracing::instrument(level = "trace")]
+    pub fn msg(&mut self) -> Result<Option<&mut [u8]>, IoError> {
+        if self.compressed.is_empty() {
+            return Ok(None);
+        }
+
+        let mut input = InBuffer::around(&self.compressed);
+
+        loop {
+            self.internal_buffer.clear();
+
+            let mut output = OutBuffer::around(&mut self.internal_buffer);
+            self.decoder.run(&mut input, &mut output)?;
+
+            self.buffer.extend_from_slice(&self.internal_buffer[..]);
+
+            // The decoder may hold onto more decompressed bytes if it filled
+            // the internal buffer, even if all of the input was consumed.
+            let not_at_capacity = self.internal_buffer.len() < self.internal_buffer.capacity();
+
+            if not_at_capacity && input.pos() == self.compressed.len() {
+                break;
+            }
+        }
+
+        tracing::trace!(
+            bytes_in = self.compressed.len(),
+            bytes_out = self.buffer.len(),
+            shard_id = self.shard[0],
+            shard_total = self.shard[1],
+            "payload lengths",
+        );
+
+        self.total_in += self.compressed.len() as u64;
+        self.total_out += self.buffer.len() as u64;
+        self.compressed.clear();
+
+        // It doesn't matter if we lose precision for logging.
+        #[allow(clippy::cast_precision_loss)]
+        let saved_percentage = self.total_in as f64 / self.total_out as f64;
+        let saved_percentage_readable = saved_percentage * 100.0;
+
+        let saved_kib = self.total_out.saturating_sub(self.total_in) / 1_024;
+
+        tracing::trace!(
+            saved_kib = saved_kib,
+            saved_percentage = %saved_percentage_readable,
+            shard_id = self.shard[0],
+            shard_total = self.shard[1],
+            total_in = self.total_in,
+            total_out = self.total_out,
+            "data saved",
+        );
+
+        #[cfg(feature = "metrics")]
+        self.inflater_metrics();
+
+        tracing::trace!("capacity: {}", self.buffer.capacity());
+        Ok(Some(&mut self.buffer))
+    }
+
+    /// Clear the buffer and shrink it if the capacity is too large.
+    ///
+    /// The capacity is shrunk to the length if at least 60 seconds have
+    /// passed since the last shrink.
+    #[tracing::instrument(level = "trace")]
+    pub fn clear(&mut self) {
+        self.shrink();
+
+        self.compressed.clear();
+        self.internal_buffer.clear();
+        self.buffer.clear();
+    }
+
+    /// Reset the state of the inflater back to its default state.
+    pub fn reset(&mut self) {
+        let _old_inflater = mem::replace(self, Self::new(self.shard));
+    }
+
+    /// Log metrics about the inflater.
+    #[cfg(feature = "metrics")]
+    #[allow(clippy::cast_precision_loss)]
+    fn inflater_metrics(&self) {
+        metrics::gauge!(
+            format!("Inflater-Capacity-{}", self.shard[0]),
+            self.buffer.capacity() as f64
+        );
+        metrics::gauge!(
+            format!("Inflater-In-{}", self.shard[0]),
+            self.total_in as f64
+        );
+        metrics::gauge!(
+            format!("Inflater-Out-{}", self.shard[0]),
+            self.total_out as f64
+        );
+    }
+
+    /// Shrink the capacity of the compressed buffer and payload buffer if at
+    /// least 60 seconds have passed since the last shrink.
+    fn shrink(&mut self) {
+        if self.last_resize.elapsed().as_secs() < 60 {
+            return;
+        }
+
+        self.compressed.shrink_to_fit();
+        self.buffer.shrink_to_fit();
+
+        tracing::trace!(
+            capacity = self.compressed.capacity(),
+            shard_id = self.shard[0],
+            shard_total = self.shard[1],
+            "compressed capacity",
+        );
+        tracing::trace!(
+            capacity = self.buffer.capacity(),
+            shard_id = self.shard[0],
+            shard_total = self.shard[1],
+            "buffer capacity",
+        );
+
+        self.last_resize = Instant::now();
+    }
+}
+
+impl Debug for ZstdInflater {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("ZstdInflater")
+            .field("decoder", &"<zstd decoder>")
+            .field("compressed", &self.compressed)
+            .field("internal_buffer", &self.internal_buffer)
+            .field("buffer", &self.buffer)
+            .field("last_resize", &self.last_resize)
+            .field("shard", &self.shard)
+            .field("total_in", &self.total_in)
+            .field("total_out", &self.total_out)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ZstdInflater, INTERNAL_BUFFER_SIZE};
+    use static_assertions::assert_impl_all;
+    use std::{fmt::Debug, io::Write};
+    use zstd::stream::write::Encoder;
+
+    assert_impl_all!(ZstdInflater: Debug, Send);
+
+    /// Compress messages into a single stream the way the gateway does,
+    /// flushing at the end of every message.
+    fn compress(messages: &[&[u8]]) -> Vec<Vec<u8>> {
+        let mut encoder = Encoder::new(Vec::new(), 3).unwrap();
+
+        messages
+            .iter()
+            .map(|message| {
+                encoder.write_all(message).unwrap();
+                encoder.flush().unwrap();
+
+                encoder.get_mut().split_off(0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_stream() {
+        let messages: &[&[u8]] = &[
+            br#"{"t":null,"s":null,"op":10,"d":{"heartbeat_interval":41250}}"#,
+            br#"{"t":null,"s":null,"op":11,"d":null}"#,
+            br#"{"t":"RESUMED","s":3,"op":0,"d":{}}"#,
+        ];
+        let mut inflater = ZstdInflater::new([0, 1]);
+
+        for (message, compressed) in messages.iter().zip(compress(messages)) {
+            inflater.clear();
+            inflater.extend(&compressed);
+
+            assert_eq!(Some(*message), inflater.msg().unwrap().map(|msg| &*msg));
+            assert_eq!(*message, inflater.buffer_ref());
+        }
+    }
+
+    #[test]
+    fn test_larger_than_internal_buffer() {
+        let message = (0..=u8::MAX)
+            .cycle()
+            .take(INTERNAL_BUFFER_SIZE * 3)
+            .collect::<Vec<_>>();
+        let compressed = compress(&[&message]).remove(0);
+
+        let mut inflater = ZstdInflater::new([0, 1]);
+        inflater.extend(&compressed);
+
+        assert_eq!(
+            Some(message.as_slice()),
+            inflater.msg().unwrap().map(|msg| &*msg)
+        );
+    }
+
+    #[test]
+    fn test_empty() {
+        let mut inflater = ZstdInflater::new([0, 1]);
+
+        assert!(inflater.msg().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_invalid() {
+        let mut inflater = ZstdInflater::new([0, 1]);
+        inflater.extend(b"not a zstd stream");
+
+        assert!(inflater.msg().is_err());
+    }
+
+    #[test]
+    fn test_reset() {
+        let messages: &[&[u8]] = &[br#"{"op":11,"d":null}"#];
+        let mut inflater = ZstdInflater::new([0, 1]);
+        inflater.extend(&compress(messages)[0]);
+        inflater.msg().unwrap();
+
+        // A new connection starts a new stream.
+        inflater.reset();
+        inflater.extend(&compress(messages)[0]);
+
+        assert_eq!(Some(messages[0]), inflater.msg().unwrap().map(|msg| &*msg));
+    }
+}
@@ -0,0 +1,373 @@
+use super::super::stage::Stage;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use twilight_model::gateway::event::shard::UnhealthyReason;
+
+/// Number of session starts kept in a shard's [`Health`].
+const SESSION_STARTS: usize = 10;
+
+/// How a session of a [`Shard`] was started.
+///
+/// [`Shard`]: crate::shard::Shard
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum SessionStart {
+    /// A new session was created by identifying.
+    Identify,
+    /// An existing session was resumed.
+    Resume,
+}
+
+/// Snapshot of the health of a [`Shard`]'s connection.
+///
+/// This is obtained through [`Shard::health`] or [`Cluster::health`].
+///
+/// [`Cluster::health`]: crate::cluster::Cluster::health
+/// [`Shard`]: crate::shard::Shard
+/// [`Shard::health`]: crate::shard::Shard::health
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Health {
+    identifies: u64,
+    missed_heartbeat_acks: u64,
+    reconnects: u64,
+    resumes: u64,
+    session_starts: VecDeque<SessionStart>,
+    since_last_dispatch: Option<Duration>,
+    stage: Stage,
+}
+
+impl Health {
+    /// Number of sessions created by identifying with the gateway.
+    pub const fn identifies(&self) -> u64 {
+        self.identifies
+    }
+
+    /// Number of heartbeats in a row the gateway hasn't acknowledged on the
+    /// current connection.
+    pub const fn missed_heartbeat_acks(&self) -> u64 {
+        self.missed_heartbeat_acks
+    }
+
+    /// Number of times the connection was replaced by a new one, whether to
+    /// resume the session or to create a new one.
+    pub const fn reconnects(&self) -> u64 {
+        self.reconnects
+    }
+
+    /// Number of sessions resumed.
+    pub const fn resumes(&self) -> u64 {
+        self.resumes
+    }
+
+    /// How the 10 most recent sessions were started.
+    ///
+    /// Index 0 is the oldest.
+    pub const fn session_starts(&self) -> &VecDeque<SessionStart> {
+        &self.session_starts
+    }
+
+    /// Time since the last dispatch event was received.
+    ///
+    /// This is None if no dispatch event has been received yet.
+    pub const fn since_last_dispatch(&self) -> Option<Duration> {
+        self.since_last_dispatch
+    }
+
+    /// Current stage of the shard.
+    pub const fn stage(&self) -> Stage {
+        self.stage
+    }
+}
+
+/// Conditions under which a [`Shard`]'s connection is considered unhealthy.
+///
+/// When a condition is met the shard emits an [`Event::ShardUnhealthy`],
+/// closes the connection, and resumes the session on a new one.
+///
+/// By default a connection is unhealthy once 2 heartbeats in a row weren't
+/// acknowledged, since the connection has most likely stopped working without
+/// being closed.
+///
+/// # Examples
+///
+/// Reconnect a shard that has guilds but hasn't received a dispatch event in
+/// 10 minutes:
+///
+/// ```no_run
+/// use std::{env, time::Duration};
+/// use twilight_gateway::{shard::HealthPolicy, Intents, Shard};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let token = env::var("DISCORD_TOKEN")?;
+/// let policy = HealthPolicy::new().dispatch_timeout(Some(Duration::from_secs(600)));
+///
+/// let (shard, events) = Shard::builder(token, Intents::GUILDS)
+///     .health_policy(policy)
+///     .build();
+/// # Ok(()) }
+/// ```
+///
+/// [`Event::ShardUnhealthy`]: crate::Event::ShardUnhealthy
+/// [`Shard`]: crate::shard::Shard
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct HealthPolicy {
+    dispatch_timeout: Option<Duration>,
+    missed_heartbeat_acks: Option<u64>,
+}
+
+impl HealthPolicy {
+    /// Create the default policy.
+    pub const fn new() -> Self {
+        Self {
+            dispatch_timeout: None,
+            missed_heartbeat_acks: Some(2),
+        }
+    }
+
+    /// Create a policy that never considers a connection unhealthy.
+    pub const fn disabled() -> Self {
+        Self {
+            dispatch_timeout: None,
+            missed_heartbeat_acks: None,
+        }
+    }
+
+    /// Set the time after which a connected shard that has guilds is
+    /// unhealthy if it hasn't received a dispatch event.
+    ///
+    /// Shards without guilds may legitimately not receive events for a long
+    /// time, so they are never considered unhealthy for this reason.
+    ///
+    /// Default is no timeout.
+    pub const fn dispatch_timeout(mut self, dispatch_timeout: Option<Duration>) -> Self {
+        self.dispatch_timeout = dispatch_timeout;
+
+        self
+    }
+
+    /// Set the number of heartbeats in a row the gateway may not acknowledge
+    /// before the connection is unhealthy.
+    ///
+    /// Default is 2.
+    pub const fn missed_heartbeat_acks(mut self, missed_heartbeat_acks: Option<u64>) -> Self {
+        self.missed_heartbeat_acks = missed_heartbeat_acks;
+
+        self
+    }
+
+    /// Reason a shard's connection violates the policy, if it does.
+    pub(crate) fn violation(&self, health: &Health, has_guilds: bool) -> Option<UnhealthyReason> {
+        if self
+            .missed_heartbeat_acks
+            .map_or(false, |max| health.missed_heartbeat_acks >= max)
+        {
+            return Some(UnhealthyReason::HeartbeatAcksMissed);
+        }
+
+        let dispatch_timed_out = match (self.dispatch_timeout, health.since_last_dispatch) {
+            (Some(timeout), Some(elapsed)) => elapsed > timeout,
+            _ => false,
+        };
+
+        if dispatch_timed_out && has_guilds && health.stage == Stage::Connected {
+            return Some(UnhealthyReason::DispatchTimedOut);
+        }
+
+        None
+    }
+}
+
+impl Default for HealthPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    identifies: u64,
+    last_dispatch: Option<Instant>,
+    reconnects: u64,
+    resumes: u64,
+    session_starts: VecDeque<SessionStart>,
+}
+
+/// Tracker of the history of a shard's connections, shared between the shard
+/// and its processor.
+#[derive(Debug, Default)]
+pub struct HealthTracker {
+    state: Mutex<State>,
+}
+
+impl HealthTracker {
+    /// Snapshot of the current health, given the state of the current
+    /// session.
+    pub fn health(&self, stage: Stage, missed_heartbeat_acks: u64) -> Health {
+        let history = self.state.lock().expect("health poisoned");
+
+        Health {
+            identifies: history.identifies,
+            missed_heartbeat_acks,
+            reconnects: history.reconnects,
+            resumes: history.resumes,
+            session_starts: history.session_starts.clone(),
+            since_last_dispatch: history.last_dispatch.map(|instant| instant.elapsed()),
+            stage,
+        }
+    }
+
+    /// Record that a dispatch event was received.
+    pub fn dispatch(&self) {
+        self.state.lock().expect("health poisoned").last_dispatch = Some(Instant::now());
+    }
+
+    /// Record that the connection was replaced by a new one.
+    pub fn reconnect(&self) {
+        self.state.lock().expect("health poisoned").reconnects += 1;
+    }
+
+    /// Record that a session was started.
+    pub fn session_start(&self, start: SessionStart) {
+        let mut state = self.state.lock().expect("health poisoned");
+
+        match start {
+            SessionStart::Identify => state.identifies += 1,
+            SessionStart::Resume => state.resumes += 1,
+        }
+
+        if state.session_starts.len() == SESSION_STARTS {
+            state.session_starts.pop_front();
+        }
+
+        state.session_starts.push_back(start);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Health, HealthPolicy, HealthTracker, SessionStart, SESSION_STARTS};
+    use crate::shard::Stage;
+    use serde::{Deserialize, Serialize};
+    use static_assertions::assert_impl_all;
+    use std::{fmt::Debug, hash::Hash, time::Duration};
+    use twilight_model::gateway::event::shard::UnhealthyReason;
+
+    assert_impl_all!(
+        Health: Clone,
+        Debug,
+        Deserialize<'static>,
+        Eq,
+        PartialEq,
+        Send,
+        Serialize,
+        Sync
+    );
+    assert_impl_all!(
+        HealthPolicy: Clone,
+        Copy,
+        Debug,
+        Default,
+        Eq,
+        PartialEq,
+        Send,
+        Sync
+    );
+    assert_impl_all!(HealthTracker: Debug, Default, Send, Sync);
+    assert_impl_all!(
+        SessionStart: Clone,
+        Copy,
+        Debug,
+        Deserialize<'static>,
+        Eq,
+        Hash,
+        PartialEq,
+        Send,
+        Serialize,
+        Sync
+    );
+
+    #[test]
+    fn test_history() {
+        let tracker = HealthTracker::default();
+        let health = tracker.health(Stage::Disconnected, 0);
+        assert_eq!(0, health.identifies());
+        assert!(health.session_starts().is_empty());
+        assert!(health.since_last_dispatch().is_none());
+
+        tracker.session_start(SessionStart::Identify);
+        tracker.dispatch();
+        tracker.reconnect();
+        tracker.session_start(SessionStart::Resume);
+
+        let health = tracker.health(Stage::Connected, 1);
+        assert_eq!(1, health.identifies());
+        assert_eq!(1, health.missed_heartbeat_acks());
+        assert_eq!(1, health.reconnects());
+        assert_eq!(1, health.resumes());
+        assert_eq!(
+            vec![SessionStart::Identify, SessionStart::Resume],
+            health.session_starts().iter().copied().collect::<Vec<_>>()
+        );
+        assert!(health.since_last_dispatch().is_some());
+        assert_eq!(Stage::Connected, health.stage());
+
+        for _ in 0..SESSION_STARTS {
+            tracker.session_start(SessionStart::Resume);
+        }
+
+        let health = tracker.health(Stage::Connected, 0);
+        assert_eq!(SESSION_STARTS, health.session_starts().len());
+        assert!(health
+            .session_starts()
+            .iter()
+            .all(|start| *start == SessionStart::Resume));
+        assert_eq!(SESSION_STARTS as u64 + 1, health.resumes());
+    }
+
+    #[test]
+    fn test_policy_heartbeat_acks() {
+        let tracker = HealthTracker::default();
+
+        assert!(HealthPolicy::new()
+            .violation(&tracker.health(Stage::Connected, 1), true)
+            .is_none());
+        assert_eq!(
+            Some(UnhealthyReason::HeartbeatAcksMissed),
+            HealthPolicy::new().violation(&tracker.health(Stage::Resuming, 2), false)
+        );
+        assert!(HealthPolicy::disabled()
+            .violation(&tracker.health(Stage::Connected, 100), true)
+            .is_none());
+    }
+
+    #[test]
+    fn test_policy_dispatch_timeout() {
+        let tracker = HealthTracker::default();
+        let policy = HealthPolicy::disabled().dispatch_timeout(Some(Duration::from_secs(0)));
+
+        // No dispatch has been received yet.
+        assert!(policy
+            .violation(&tracker.health(Stage::Connected, 0), true)
+            .is_none());
+
+        tracker.dispatch();
+        std::thread::sleep(Duration::from_millis(5));
+
+        assert_eq!(
+            Some(UnhealthyReason::DispatchTimedOut),
+            policy.violation(&tracker.health(Stage::Connected, 0), true)
+        );
+        assert!(policy
+            .violation(&tracker.health(Stage::Connected, 0), false)
+            .is_none());
+        assert!(policy
+            .violation(&tracker.health(Stage::Resuming, 0), true)
+            .is_none());
+        assert!(HealthPolicy::new()
+            .violation(&tracker.health(Stage::Connected, 0), true)
+            .is_none());
+    }
+}
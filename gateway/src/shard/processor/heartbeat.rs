@@ -1,5 +1,6 @@
 use super::{
-    super::json,
+    super::encoding::Encoding,
+    ratelimiter::CommandRatelimiter,
     session::{SessionSendError, SessionSendErrorType},
 };
 use serde::{Deserialize, Serialize};
@@ -72,6 +73,7 @@ impl Latency {
 
 #[derive(Debug)]
 pub struct Heartbeats {
+    missed: AtomicU64,
     received: Mutex<Option<Instant>>,
     recent: Mutex<VecDeque<u64>>,
     sent: Mutex<Option<Instant>>,
@@ -104,8 +106,14 @@ impl Heartbeats {
         self.received().is_some()
     }
 
+    /// Number of heartbeats in a row that weren't acknowledged.
+    pub fn missed(&self) -> u64 {
+        self.missed.load(Ordering::Relaxed)
+    }
+
     pub fn receive(&self) {
         self.set_received(Instant::now());
+        self.missed.store(0, Ordering::Release);
 
         self.total_iterations.fetch_add(1, Ordering::SeqCst);
 
@@ -165,6 +173,7 @@ impl Heartbeats {
 impl Default for Heartbeats {
     fn default() -> Self {
         Self {
+            missed: AtomicU64::new(0),
             received: Mutex::new(None),
             recent: Mutex::new(VecDeque::with_capacity(5)),
             sent: Mutex::new(None),
@@ -175,22 +184,28 @@ impl Default for Heartbeats {
 }
 
 pub struct Heartbeater {
+    encoding: Encoding,
     heartbeats: Arc<Heartbeats>,
     interval: u64,
+    ratelimiter: Arc<CommandRatelimiter>,
     seq: Arc<AtomicU64>,
     tx: UnboundedSender<TungsteniteMessage>,
 }
 
 impl Heartbeater {
     pub fn new(
+        encoding: Encoding,
         heartbeats: Arc<Heartbeats>,
         interval: u64,
+        ratelimiter: Arc<CommandRatelimiter>,
         seq: Arc<AtomicU64>,
         tx: UnboundedSender<TungsteniteMessage>,
     ) -> Self {
         Self {
+            encoding,
             heartbeats,
             interval,
+            ratelimiter,
             seq,
             tx,
         }
@@ -209,32 +224,26 @@ impl Heartbeater {
     async fn try_run(self) -> Result<(), SessionSendError> {
         let duration = Duration::from_millis(self.interval);
 
-        let mut last = true;
-
         loop {
             tokio::time::sleep(duration).await;
 
-            // Check if a heartbeat acknowledgement was received.
-            //
-            // If so, then check if one was received last time.
-            //
-            // - if so, then mark that we didn't get one this time
-            // - if not, then end the heartbeater because something is off
-            // (connecting closed?)
-            if self.heartbeats.last_acked() {
-                last = true;
-            } else if last {
-                last = false;
-            } else {
-                return Ok(());
+            // Count the previous heartbeat as missed if it wasn't acknowledged.
+            // The shard's processor decides whether the connection is
+            // unhealthy based on its health policy.
+            if self.heartbeats.sent().is_some() && !self.heartbeats.last_acked() {
+                let missed = self.heartbeats.missed.fetch_add(1, Ordering::AcqRel) + 1;
+                tracing::debug!(missed, "heartbeat wasn't acknowledged");
             }
 
             let seq = self.seq.load(Ordering::Acquire);
             let heartbeat = Heartbeat::new(seq);
-            let bytes = json::to_vec(&heartbeat).map_err(|source| SessionSendError {
-                kind: SessionSendErrorType::Serializing,
-                source: Some(Box::new(source)),
-            })?;
+            let bytes = self
+                .encoding
+                .serialize(&heartbeat)
+                .map_err(|source| SessionSendError {
+                    kind: SessionSendErrorType::Serializing,
+                    source: Some(source),
+                })?;
 
             tracing::debug!(seq, "sending heartbeat");
             self.tx
@@ -244,6 +253,7 @@ impl Heartbeater {
                     source: Some(Box::new(source)),
                 })?;
             tracing::debug!(seq, "sent heartbeat");
+            self.ratelimiter.acquire_heartbeat();
             self.heartbeats.send();
         }
     }
@@ -1,12 +1,19 @@
+#[cfg(feature = "etf")]
+use super::super::etf;
 use super::{
     super::{
         config::Config,
+        connect,
         emitter::{EmitJsonErrorType, Emitter},
-        json::{self, GatewayEventParsingError, GatewayEventParsingErrorType},
+        encoding::Encoding,
+        json::{GatewayEventParsingError, GatewayEventParsingErrorType},
+        recording::{Direction, ShardRecorder},
         stage::Stage,
         ShardStream,
     },
     compression::{self, Compression},
+    health::{HealthTracker, SessionStart},
+    readiness::ReadinessTracker,
     session::{Session, SessionSendError, SessionSendErrorType},
     socket_forwarder::SocketForwarder,
 };
@@ -21,9 +28,12 @@ use std::{
     sync::{atomic::Ordering, Arc},
     time::Duration,
 };
-use tokio::sync::{
-    mpsc::UnboundedReceiver,
-    watch::{channel as watch_channel, Receiver as WatchReceiver, Sender as WatchSender},
+use tokio::{
+    sync::{
+        mpsc::UnboundedReceiver,
+        watch::{channel as watch_channel, Receiver as WatchReceiver, Sender as WatchSender},
+    },
+    time::{self, Instant},
 };
 use tokio_tungstenite::tungstenite::{
     protocol::{frame::coding::CloseCode, CloseFrame, WebSocketConfig},
@@ -31,7 +41,10 @@ use tokio_tungstenite::tungstenite::{
 };
 use twilight_model::gateway::{
     event::{
-        shard::{Connected, Connecting, Disconnected, Identifying, Reconnecting, Resuming},
+        shard::{
+            Connected, Connecting, Disconnected, FullyReady, Identifying, Reconnecting, Resuming,
+            Unhealthy, UnhealthyReason,
+        },
         DispatchEvent, Event, GatewayEvent, GatewayEventDeserializer,
     },
     payload::{
@@ -107,7 +120,7 @@ impl Display for ProcessError {
                 "provided event type ({:?})/op ({}) pair is unknown",
                 event_type, op,
             )),
-            ProcessErrorType::ParsingPayload => f.write_str("payload could not be parsed as json"),
+            ProcessErrorType::ParsingPayload => f.write_str("payload could not be parsed"),
             ProcessErrorType::PayloadNotUtf8 { .. } => {
                 f.write_str("the payload from Discord wasn't UTF-8 valid")
             }
@@ -176,6 +189,19 @@ impl ReceivingEventError {
     }
 }
 
+impl ReceivingEventErrorType {
+    /// Name of the error type, used as the reason label of metrics.
+    const fn name(&self) -> &'static str {
+        match self {
+            Self::AuthorizationInvalid { .. } => "AuthorizationInvalid",
+            Self::Decompressing => "Decompressing",
+            Self::EventStreamEnded => "EventStreamEnded",
+            Self::IntentsDisallowed { .. } => "IntentsDisallowed",
+            Self::IntentsInvalid { .. } => "IntentsInvalid",
+        }
+    }
+}
+
 impl Display for ReceivingEventError {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         match &self.kind {
@@ -253,20 +279,33 @@ struct ReadyMinimal {
 pub struct ShardProcessor {
     pub config: Arc<Config>,
     pub emitter: Emitter,
+    pub health: Arc<HealthTracker>,
     pub properties: IdentifyProperties,
+    pub readiness: Arc<ReadinessTracker>,
+    pub recorder: Option<ShardRecorder>,
     pub rx: UnboundedReceiver<Message>,
     pub session: Arc<Session>,
     compression: Compression,
+    /// When the connection is next checked against the health policy.
+    health_check: Instant,
     url: Box<str>,
     resume: Option<(u64, Box<str>)>,
+    /// Shard label of metrics.
+    #[cfg(feature = "metrics")]
+    shard_label: String,
     wtx: WatchSender<Arc<Session>>,
 }
 
 impl ShardProcessor {
+    /// Interval at which the connection is checked against the health policy.
+    const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
     pub async fn new(
         config: Arc<Config>,
         mut url: String,
         emitter: Emitter,
+        health: Arc<HealthTracker>,
+        readiness: Arc<ReadinessTracker>,
     ) -> Result<(Self, WatchReceiver<Arc<Session>>), ConnectingError> {
         //if we got resume info we don't need to wait
         let shard_id = config.shard();
@@ -278,22 +317,29 @@ impl ShardProcessor {
             tracing::debug!("shard {:?} finished queue", config.shard());
         }
 
-        let properties = IdentifyProperties::new("twilight.rs", "twilight.rs", OS, "", "");
+        let properties = config
+            .identify_properties()
+            .cloned()
+            .unwrap_or_else(|| IdentifyProperties::new("twilight.rs", "twilight.rs", OS, "", ""));
 
-        url.push_str("?v=8");
-        compression::add_url_feature(&mut url);
+        url.push_str("?v=8&encoding=");
+        url.push_str(config.encoding().name());
+        compression::add_url_feature(&mut url, config.transport_compression());
 
         emitter.event(Event::ShardConnecting(Connecting {
             gateway: url.clone(),
             shard_id: config.shard()[0],
         }));
-        let stream = Self::connect(&url).await?;
-        let (forwarder, rx, tx) = SocketForwarder::new(stream);
+        let stream = Self::connect(&url, &config).await?;
+        let recorder = config
+            .recorder()
+            .map(|recorder| recorder.shard(shard_id[0], config.encoding()));
+        let (forwarder, rx, tx) = SocketForwarder::new(stream, shard_id[0], recorder.clone());
         tokio::spawn(async move {
             forwarder.run().await;
         });
 
-        let session = Arc::new(Session::new(tx));
+        let session = Arc::new(Session::new(tx, config.encoding()));
         if resumable {
             session.set_id(config.session_id.clone().unwrap());
             session
@@ -304,20 +350,33 @@ impl ShardProcessor {
         let (wtx, wrx) = watch_channel(Arc::clone(&session));
 
         let mut processor = Self {
-            compression: Compression::new(shard_id),
+            compression: Compression::new(shard_id, config.transport_compression()),
             config,
             emitter,
+            health,
+            health_check: Instant::now() + Self::HEALTH_CHECK_INTERVAL,
             properties,
+            readiness,
+            recorder,
             rx,
             session,
             url: url.into_boxed_str(),
             resume: None,
+            #[cfg(feature = "metrics")]
+            shard_label: shard_id[0].to_string(),
             wtx,
         };
 
-        if resumable {
+        // The connection was just opened, so the session can be resumed over
+        // it once the gateway says hello.
+        if let (true, Some(id)) = (resumable, processor.session.id()) {
             tracing::debug!("resuming shard {:?}", shard_id);
-            processor.resume().await;
+            processor.emitter.event(Event::ShardResuming(Resuming {
+                seq: processor.session.seq(),
+                shard_id: shard_id[0],
+            }));
+            processor.session.set_stage(Stage::Resuming);
+            processor.resume = Some((processor.session.seq(), id));
         }
 
         Ok((processor, wrx))
@@ -325,6 +384,11 @@ impl ShardProcessor {
 
     pub async fn run(mut self) {
         loop {
+            // Stop reading payloads while a bounded event stream is full. The
+            // socket forwarder keeps the connection alive in the meantime,
+            // buffering the messages it receives in their compressed form.
+            self.emitter.ready().await;
+
             match self.next_payload().await {
                 Ok(v) => v,
                 Err(source) => {
@@ -335,11 +399,14 @@ impl ShardProcessor {
                     }
 
                     if source.reconnectable() {
+                        #[cfg(feature = "metrics")]
+                        self.record_reconnect(source.kind.name());
+
                         self.reconnect().await;
                     }
 
                     if source.resumable() {
-                        self.resume().await;
+                        self.resume(source.kind.name()).await;
                     }
 
                     continue;
@@ -357,52 +424,73 @@ impl ShardProcessor {
                 if source.fatal() {
                     tracing::debug!("error processing event; reconnecting");
 
+                    #[cfg(feature = "metrics")]
+                    self.record_reconnect("Processing");
+
                     self.reconnect().await;
                 }
             }
+
+            #[cfg(feature = "metrics")]
+            self.record_queue_depth();
         }
     }
 
     #[allow(clippy::too_many_lines)]
     async fn process(&mut self) -> Result<(), ProcessError> {
-        let (op, seq, event_type) = {
-            let buffer = self.compression.buffer_slice_mut();
-            let json = str::from_utf8_mut(buffer).map_err(|source| ProcessError {
-                kind: ProcessErrorType::PayloadNotUtf8,
-                source: Some(Box::new(source)),
-            })?;
+        let encoding = self.config.encoding();
 
-            tracing::trace!(%json, "Received JSON");
+        let (op, seq, event_type) = {
             let emitter = self.emitter.clone();
 
-            let (op, seq, event_type) =
-                if let Some(deserializer) = GatewayEventDeserializer::from_json(json) {
-                    let (op, seq, event_type) = deserializer.into_parts();
+            let header = match encoding {
+                #[cfg(feature = "etf")]
+                Encoding::Etf => etf::peek_header(self.compression.buffer_slice_ref()),
+                Encoding::Json => {
+                    let json =
+                        str::from_utf8(self.compression.buffer_slice_ref()).map_err(|source| {
+                            ProcessError {
+                                kind: ProcessErrorType::PayloadNotUtf8,
+                                source: Some(Box::new(source)),
+                            }
+                        })?;
+
+                    tracing::trace!(%json, "Received JSON");
+
+                    GatewayEventDeserializer::from_json(json).map(|deserializer| {
+                        let (op, seq, event_type) = deserializer.into_parts();
+
+                        // Unfortunately lifetimes and mutability requirements
+                        // conflict here if we return an immutable reference to
+                        // the event type, so we're going to have to take
+                        // ownership of this if we don't want to do anything
+                        // too dangerous. It should be a good trade-off either
+                        // way.
+                        (op, seq, event_type.map(ToOwned::to_owned))
+                    })
+                }
+            };
 
-                    // Unfortunately lifetimes and mutability requirements
-                    // conflict here if we return an immutable reference to the
-                    // event type, so we're going to have to take ownership of
-                    // this if we don't want to do anything too dangerous. It
-                    // should be a good trade-off either way.
-                    (op, seq, event_type.map(ToOwned::to_owned))
-                } else {
-                    tracing::warn!(
-                        json = ?self.compression.buffer_slice_ref(),
-                        shard_id = self.config.shard()[0],
-                        shard_total = self.config.shard()[1],
-                        seq = self.session.seq(),
-                        stage = ?self.session.stage(),
-                        "received payload without opcode",
-                    );
+            let (op, seq, event_type) = if let Some(header) = header {
+                header
+            } else {
+                tracing::warn!(
+                    json = ?self.compression.buffer_slice_ref(),
+                    shard_id = self.config.shard()[0],
+                    shard_total = self.config.shard()[1],
+                    seq = self.session.seq(),
+                    stage = ?self.session.stage(),
+                    "received payload without opcode",
+                );
 
-                    return Err(ProcessError {
-                        kind: ProcessErrorType::ParsingPayload,
-                        source: Some(Box::new(GatewayEventParsingError {
-                            kind: GatewayEventParsingErrorType::PayloadInvalid,
-                            source: None,
-                        })),
-                    });
-                };
+                return Err(ProcessError {
+                    kind: ProcessErrorType::ParsingPayload,
+                    source: Some(Box::new(GatewayEventParsingError {
+                        kind: GatewayEventParsingErrorType::PayloadInvalid,
+                        source: None,
+                    })),
+                });
+            };
 
             // We can do a few little optimisation tricks here. For the
             // "heartbeat ack" and "reconnect" opcodes we can construct
@@ -422,12 +510,17 @@ impl ShardProcessor {
                 } else if op == OpCode::Reconnect as u8 {
                     GatewayEvent::Reconnect
                 } else {
-                    json::parse_gateway_event(op, seq, event_type.as_deref(), json).map_err(
-                        |source| ProcessError {
+                    encoding
+                        .parse_gateway_event(
+                            op,
+                            seq,
+                            event_type.as_deref(),
+                            self.compression.buffer_slice_mut(),
+                        )
+                        .map_err(|source| ProcessError {
                             kind: ProcessErrorType::ParsingPayload,
                             source: Some(Box::new(source)),
-                        },
-                    )?
+                        })?
                 };
 
                 self.process_gateway_event(&gateway_event).await?;
@@ -445,6 +538,11 @@ impl ShardProcessor {
                 source: None,
             })?;
 
+            self.health.dispatch();
+
+            #[cfg(feature = "metrics")]
+            self.record_dispatch(event_type.as_deref());
+
             if event_type.as_deref() == Some("RESUMED") {
                 self.process_resumed(seq);
 
@@ -457,18 +555,25 @@ impl ShardProcessor {
 
                 return Ok(());
             } else if event_type.as_deref() == Some("READY") {
-                let ready = json::from_slice::<ReadyMinimal>(self.compression.buffer_slice_mut())
+                let ready = encoding
+                    .deserialize::<ReadyMinimal>(self.compression.buffer_slice_mut())
                     .map_err(|source| ProcessError {
-                    kind: ProcessErrorType::ParsingPayload,
-                    source: Some(Box::new(GatewayEventParsingError {
-                        kind: GatewayEventParsingErrorType::Deserializing,
-                        source: Some(Box::new(source)),
-                    })),
-                })?;
+                        kind: ProcessErrorType::ParsingPayload,
+                        source: Some(Box::new(GatewayEventParsingError {
+                            kind: GatewayEventParsingErrorType::Deserializing,
+                            source: Some(source),
+                        })),
+                    })?;
 
+                self.session.set_seq(seq);
                 self.process_ready(&ready.d);
+                let fully_ready = self.process_guilds_expected(&ready.d);
                 emitter.event(Event::Ready(Box::new(ready.d)));
 
+                if let Some(fully_ready) = fully_ready {
+                    emitter.event(Event::ShardFullyReady(fully_ready));
+                }
+
                 return Ok(());
             }
 
@@ -477,12 +582,27 @@ impl ShardProcessor {
             (op, seq, event_type)
         };
 
-        // We already know from earlier that the payload is valid UTF-8, so we
-        // can skip having to re-validate here since it hasn't been mutated.
-        let json = unsafe { self.compression.buffer_str_mut() };
+        // Guilds listed in the ready event are received in guild create
+        // events, or guild delete events if they're unavailable. The guild ID
+        // is passed on to the emitter so it doesn't have to peek at it again.
+        let guild_received = if self.readiness.is_loading()
+            && event_type.as_deref().map_or(false, |event_type| {
+                event_type == "GUILD_CREATE" || event_type == "GUILD_DELETE"
+            }) {
+            encoding.peek_guild_id(event_type.as_deref(), self.compression.buffer_slice_ref())
+        } else {
+            None
+        };
 
-        self.emitter
-            .json(op, Some(seq), event_type.as_deref(), json)
+        let result = self
+            .emitter
+            .payload(
+                op,
+                Some(seq),
+                event_type.as_deref(),
+                guild_received,
+                self.compression.buffer_slice_mut(),
+            )
             .map_err(|source| {
                 let (kind, source) = source.into_parts();
 
@@ -497,7 +617,13 @@ impl ShardProcessor {
                     kind: new_kind,
                     source,
                 }
-            })
+            });
+
+        if let Some(fully_ready) = guild_received.and_then(|id| self.readiness.receive(id)) {
+            self.emitter.event(Event::ShardFullyReady(fully_ready));
+        }
+
+        result
     }
 
     fn process_ready(&mut self, ready: &Ready) {
@@ -507,6 +633,7 @@ impl ShardProcessor {
         self.session.set_stage(Stage::Connected);
         self.session
             .set_id(ready.session_id.clone().into_boxed_str());
+        self.health.session_start(SessionStart::Identify);
 
         self.emitter.event(Event::ShardConnected(Connected {
             heartbeat_interval: self.session.heartbeat_interval(),
@@ -514,12 +641,53 @@ impl ShardProcessor {
         }));
     }
 
+    /// Start waiting for the guilds listed in a ready event, emitting a
+    /// [`Event::ShardFullyReady`] once the guild ready timeout elapsed if
+    /// they haven't all been received by then.
+    ///
+    /// Returns the event to emit after the ready event if no guilds are
+    /// expected.
+    fn process_guilds_expected(&self, ready: &Ready) -> Option<FullyReady> {
+        // Guilds are only sent after the ready event with the guilds intent.
+        let guilds = ready
+            .guilds
+            .iter()
+            .map(|guild| guild.id)
+            .filter(|_| self.config.intents().contains(Intents::GUILDS));
+
+        if let Some(fully_ready) = self.readiness.ready(guilds) {
+            return Some(fully_ready);
+        }
+
+        let emitter = self.emitter.clone();
+        let generation = self.readiness.generation();
+        let readiness = Arc::clone(&self.readiness);
+        let timeout = self.config.guild_ready_timeout();
+
+        tokio::spawn(async move {
+            tokio::time::sleep(timeout).await;
+
+            if let Some(fully_ready) = readiness.time_out(generation) {
+                tracing::warn!(
+                    missing = fully_ready.guilds_missing.len(),
+                    shard_id = fully_ready.shard_id,
+                    "guilds did not become available, considering the shard fully ready",
+                );
+
+                emitter.event(Event::ShardFullyReady(fully_ready));
+            }
+        });
+
+        None
+    }
+
     fn process_resumed(&self, seq: u64) {
         #[cfg(feature = "metrics")]
         metrics::counter!("GatewayEvent", 1, "GatewayEvent" => "Dispatch");
 
         self.session.set_seq(seq);
         self.session.set_stage(Stage::Connected);
+        self.health.session_start(SessionStart::Resume);
         self.emitter.event(Event::ShardConnected(Connected {
             heartbeat_interval: self.session.heartbeat_interval(),
             shard_id: self.config.shard()[0],
@@ -547,6 +715,9 @@ impl ShardProcessor {
         metrics::counter!("GatewayEvent", 1, "GatewayEvent" => "HeartbeatAck");
 
         self.session.heartbeats.receive();
+
+        #[cfg(feature = "metrics")]
+        self.record_heartbeat_latency();
     }
 
     async fn process_heartbeat(&mut self, seq: u64) {
@@ -554,12 +725,15 @@ impl ShardProcessor {
         metrics::counter!("GatewayEvent", 1, "GatewayEvent" => "Heartbeat");
 
         if seq > self.session.seq() + 1 {
-            self.resume().await;
+            self.resume("Heartbeat").await;
         }
 
         if let Err(err) = self.session.heartbeat() {
             tracing::warn!("error sending heartbeat; reconnecting: {}", err);
 
+            #[cfg(feature = "metrics")]
+            self.record_reconnect("Sending");
+
             self.reconnect().await;
         }
     }
@@ -612,12 +786,17 @@ impl ShardProcessor {
             metrics::counter!("GatewayEvent", 1, "GatewayEvent" => "InvalidateSessionTrue");
 
             tracing::debug!("got request to resume the session");
-            self.resume().await;
+
+            self.resume("InvalidSession").await;
         } else {
             #[cfg(feature = "metrics")]
             metrics::counter!("GatewayEvent", 1, "GatewayEvent" => "InvalidateSessionFalse");
 
             tracing::debug!("got request to invalidate the session and reconnect");
+
+            #[cfg(feature = "metrics")]
+            self.record_reconnect("InvalidSession");
+
             self.reconnect().await;
         }
     }
@@ -637,7 +816,8 @@ impl ShardProcessor {
                 source: Some(Box::new(source)),
                 kind: ProcessErrorType::SendingClose,
             })?;
-        self.resume().await;
+
+        self.resume("Reconnect").await;
 
         Ok(())
     }
@@ -647,6 +827,9 @@ impl ShardProcessor {
             tracing::warn!("sending message failed: {:?}", source);
 
             if matches!(source.kind(), SessionSendErrorType::Sending { .. }) {
+                #[cfg(feature = "metrics")]
+                self.record_reconnect("Sending");
+
                 self.reconnect().await;
             }
 
@@ -669,9 +852,18 @@ impl ShardProcessor {
         self.compression.clear();
 
         loop {
+            let received =
+                if let Ok(received) = time::timeout_at(self.health_check, self.rx.recv()).await {
+                    received
+                } else {
+                    self.check_health().await;
+
+                    continue;
+                };
+
             // Returns None when the socket forwarder has ended, meaning the
             // connection was dropped.
-            let mut msg = self.rx.recv().await.ok_or(ReceivingEventError {
+            let mut msg = received.ok_or(ReceivingEventError {
                 kind: ReceivingEventErrorType::EventStreamEnded,
                 source: None,
             })?;
@@ -682,6 +874,49 @@ impl ShardProcessor {
         }
     }
 
+    /// Check the connection against the health policy, replacing it by
+    /// resuming the session if it's unhealthy.
+    async fn check_health(&mut self) {
+        self.health_check = Instant::now() + Self::HEALTH_CHECK_INTERVAL;
+
+        let health = self
+            .health
+            .health(self.session.stage(), self.session.heartbeats.missed());
+        let has_guilds = self.readiness.readiness().guilds_expected() > 0;
+
+        let reason =
+            if let Some(reason) = self.config.health_policy().violation(&health, has_guilds) {
+                reason
+            } else {
+                return;
+            };
+
+        tracing::warn!(
+            shard_id = self.config.shard()[0],
+            shard_total = self.config.shard()[1],
+            ?reason,
+            "connection is unhealthy, resuming on a new connection",
+        );
+        self.emitter.event(Event::ShardUnhealthy(Unhealthy {
+            reason,
+            shard_id: self.config.shard()[0],
+        }));
+
+        // Close with a code other than 1000 so that the session stays
+        // resumable.
+        let frame = CloseFrame {
+            code: CloseCode::Restart,
+            reason: Cow::Borrowed("Unhealthy"),
+        };
+        let _res = self.session.close(Some(frame));
+
+        self.resume(match reason {
+            UnhealthyReason::DispatchTimedOut => "DispatchTimedOut",
+            UnhealthyReason::HeartbeatAcksMissed => "HeartbeatAcksMissed",
+        })
+        .await;
+    }
+
     /// Handle a received websocket message, returning whether a decompressed
     /// message buffer is available in the inflater.
     ///
@@ -695,29 +930,37 @@ impl ShardProcessor {
     ///
     /// If a ping or pong are received, then they are ignored.
     ///
-    /// Text messages aren't sent by Discord, so they are left unhandled.
+    /// Text messages are only sent by Discord when compression is disabled and
+    /// the encoding is JSON.
     async fn handle_message<'a>(
         &'a mut self,
         msg: &'a mut Message,
     ) -> Result<bool, ReceivingEventError> {
         match msg {
-            Message::Binary(json) => {
-                let extended = self.compression.extend_binary(json.as_slice());
+            Message::Binary(bytes) => {
+                #[cfg(feature = "metrics")]
+                self.record_bytes_in(bytes.len());
 
-                if extended {
-                    match self.compression.message_mut() {
-                        Ok(Some(bytes)) => self.emitter.bytes(bytes),
-                        Ok(None) => return Ok(false),
-                        Err(source) => {
-                            return Err(ReceivingEventError {
-                                kind: ReceivingEventErrorType::Decompressing,
-                                source: Some(Box::new(source)),
-                            })
+                self.compression.extend_binary(bytes.as_slice());
+
+                match self.compression.message_mut() {
+                    Ok(Some(bytes)) => {
+                        if let Some(recorder) = &self.recorder {
+                            recorder.record(Direction::Inbound, bytes);
                         }
-                    };
-                }
 
-                Ok(extended)
+                        self.emitter.bytes(bytes);
+                    }
+                    Ok(None) => return Ok(false),
+                    Err(source) => {
+                        return Err(ReceivingEventError {
+                            kind: ReceivingEventErrorType::Decompressing,
+                            source: Some(Box::new(source)),
+                        })
+                    }
+                };
+
+                Ok(true)
             }
             Message::Close(close_frame) => {
                 self.handle_close(close_frame.as_ref()).await?;
@@ -725,9 +968,16 @@ impl ShardProcessor {
                 Ok(false)
             }
             Message::Text(json) => {
+                #[cfg(feature = "metrics")]
+                self.record_bytes_in(json.len());
+
                 let extended = self.compression.extend_text(json.as_bytes());
 
                 if extended {
+                    if let Some(recorder) = &self.recorder {
+                        recorder.record(Direction::Inbound, json.as_bytes());
+                    }
+
                     self.emitter.bytes(json.as_bytes());
                 }
 
@@ -782,16 +1032,33 @@ impl ShardProcessor {
                         source: None,
                     });
                 }
+                // The session can't be resumed, so a new one has to be
+                // identified.
+                CloseCode::Library(4007) | CloseCode::Library(4009) => {
+                    tracing::info!(
+                        shard_id = self.config.shard()[0],
+                        "session is invalid, reconnecting",
+                    );
+
+                    #[cfg(feature = "metrics")]
+                    self.record_reconnect(close_reason(Some(close_frame)));
+
+                    self.reconnect().await;
+
+                    return Ok(());
+                }
                 _ => {}
             }
         }
 
-        self.resume().await;
+        self.resume(close_reason(close_frame)).await;
 
         Ok(())
     }
 
-    async fn connect(url: &str) -> Result<ShardStream, ConnectingError> {
+    /// Connect to the gateway, through the configured proxy and with the
+    /// configured TLS connector if there are any.
+    async fn connect(url: &str, config: &Config) -> Result<ShardStream, ConnectingError> {
         let url = Url::parse(url).map_err(|source| ConnectingError {
             kind: ConnectingErrorType::ParsingUrl {
                 url: url.to_owned(),
@@ -804,18 +1071,18 @@ impl ShardProcessor {
         //
         // `accept_unmasked_frames` and `max_send_queue` are set to their
         // defaults.
-        let config = WebSocketConfig {
+        let ws_config = WebSocketConfig {
             accept_unmasked_frames: false,
             max_frame_size: None,
             max_message_size: None,
             max_send_queue: None,
         };
 
-        let (stream, _) = tokio_tungstenite::connect_async_with_config(url, Some(config))
+        let stream = connect::connect(&url, config.proxy(), config.tls(), ws_config)
             .await
             .map_err(|source| ConnectingError {
                 kind: ConnectingErrorType::Establishing,
-                source: Some(Box::new(source)),
+                source: Some(source),
             })?;
 
         tracing::debug!("Shook hands with remote");
@@ -833,7 +1100,7 @@ impl ShardProcessor {
             intents: self.config.intents(),
             properties: self.properties.clone(),
             shard: Some(self.config.shard()),
-            presence: self.config.presence().cloned(),
+            presence: self.config.identify_presence(),
             token: self.config.token().to_owned(),
         });
         self.emitter.event(Event::ShardIdentifying(Identifying {
@@ -848,6 +1115,9 @@ impl ShardProcessor {
     async fn reconnect(&mut self) {
         tracing::info!("reconnection started");
 
+        // The new session lists its guilds in its own ready event.
+        self.readiness.reset();
+
         let mut wait = Duration::from_secs(1);
 
         loop {
@@ -866,7 +1136,7 @@ impl ShardProcessor {
                 shard_id: self.config.shard()[0],
             }));
 
-            let stream = match Self::connect(&self.url).await {
+            let stream = match Self::connect(&self.url, &self.config).await {
                 Ok(s) => s,
                 Err(why) => {
                     tracing::warn!("reconnecting failed: {:?}", why);
@@ -892,7 +1162,12 @@ impl ShardProcessor {
 
     /// Resume a session if possible, defaulting to instantiating a new
     /// connection.
-    async fn resume(&mut self) {
+    ///
+    /// The resume is recorded with the reason once the new connection is
+    /// established, whereas falling back to a new session is recorded as a
+    /// reconnect.
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    async fn resume(&mut self, reason: impl Into<Reason>) {
         tracing::info!("resuming shard {:?}", self.config.shard());
         self.session.set_stage(Stage::Resuming);
         self.session.stop_heartbeater();
@@ -903,6 +1178,10 @@ impl ShardProcessor {
             id
         } else {
             tracing::info!("session id unavailable, reconnecting");
+
+            #[cfg(feature = "metrics")]
+            self.record_reconnect("SessionIdMissing");
+
             self.reconnect().await;
             return;
         };
@@ -918,7 +1197,13 @@ impl ShardProcessor {
                 why,
             );
 
+            #[cfg(feature = "metrics")]
+            self.record_reconnect("ResumeFailed");
+
             self.reconnect().await;
+        } else {
+            #[cfg(feature = "metrics")]
+            self.record_resume(reason);
         }
     }
 
@@ -929,7 +1214,7 @@ impl ShardProcessor {
             shard_id: self.config.shard()[0],
         }));
 
-        let stream = Self::connect(&self.url).await?;
+        let stream = Self::connect(&self.url, &self.config).await?;
 
         self.set_session(stream, Stage::Resuming);
 
@@ -941,12 +1226,14 @@ impl ShardProcessor {
     /// Set the session details and create and run a new socket forwarder for a
     /// new websocket connection.
     fn set_session(&mut self, stream: ShardStream, stage: Stage) {
-        let (forwarder, rx, tx) = SocketForwarder::new(stream);
+        let (forwarder, rx, tx) =
+            SocketForwarder::new(stream, self.config.shard()[0], self.recorder.clone());
 
         tokio::spawn(forwarder.run());
+        self.health.reconnect();
 
         self.rx = rx;
-        self.session = Arc::new(Session::new(tx));
+        self.session = Arc::new(Session::new(tx, self.config.encoding()));
 
         if let Err(why) = self.wtx.send(Arc::clone(&self.session)) {
             tracing::error!("failed to broadcast new session: {:?}", why);
@@ -955,4 +1242,109 @@ impl ShardProcessor {
         self.session.set_stage(stage);
         self.compression.reset();
     }
+
+    /// Record the size of a message received from the gateway, before it's
+    /// decompressed.
+    #[cfg(feature = "metrics")]
+    fn record_bytes_in(&self, len: usize) {
+        let len = len as u64;
+        let shard = self.shard_label.clone();
+
+        metrics::counter!("GatewayBytesIn", len, "shard" => shard);
+    }
+
+    /// Record that a dispatch event was received.
+    #[cfg(feature = "metrics")]
+    fn record_dispatch(&self, event_type: Option<&str>) {
+        use std::convert::TryFrom;
+        use twilight_model::gateway::event::EventType;
+
+        // Known event types have static names, sparing an allocation.
+        let event_type = match event_type {
+            Some(name) => EventType::try_from(name)
+                .ok()
+                .and_then(EventType::name)
+                .map_or_else(|| Cow::Owned(name.to_owned()), Cow::Borrowed),
+            None => Cow::Borrowed(""),
+        };
+        let shard = self.shard_label.clone();
+
+        metrics::counter!("GatewayDispatch", 1, "event_type" => event_type, "shard" => shard);
+    }
+
+    /// Record how long the gateway took to acknowledge the last heartbeat.
+    #[cfg(feature = "metrics")]
+    fn record_heartbeat_latency(&self) {
+        let latency = self.session.heartbeats.latency();
+
+        if let (Some(sent), Some(received)) = (latency.sent(), latency.received()) {
+            let latency = received.saturating_duration_since(sent);
+            let shard = self.shard_label.clone();
+
+            metrics::histogram!("GatewayHeartbeatLatency", latency, "shard" => shard);
+        }
+    }
+
+    /// Record the number of events waiting to be received from the event
+    /// stream.
+    #[cfg(feature = "metrics")]
+    #[allow(clippy::cast_precision_loss)]
+    fn record_queue_depth(&self) {
+        let queued = self.emitter.queued() as f64;
+        let shard = self.shard_label.clone();
+
+        metrics::gauge!("GatewayEventQueueDepth", queued, "shard" => shard);
+    }
+
+    /// Record that a new session is created on a new connection, and why.
+    #[cfg(feature = "metrics")]
+    fn record_reconnect(&self, reason: impl Into<Reason>) {
+        let reason = reason.into().label();
+        let shard = self.shard_label.clone();
+
+        metrics::counter!("GatewayReconnects", 1, "reason" => reason, "shard" => shard);
+    }
+
+    /// Record that the session is resumed on a new connection, and why.
+    #[cfg(feature = "metrics")]
+    fn record_resume(&self, reason: impl Into<Reason>) {
+        let reason = reason.into().label();
+        let shard = self.shard_label.clone();
+
+        metrics::counter!("GatewayResumes", 1, "reason" => reason, "shard" => shard);
+    }
+}
+
+/// Why a connection was replaced, used as the reason label of metrics.
+///
+/// The label is only created when metrics are recorded.
+#[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+#[derive(Clone, Copy, Debug)]
+enum Reason {
+    /// Connection was closed, with the close code if there was one.
+    Closed(Option<u16>),
+    Named(&'static str),
+}
+
+impl Reason {
+    /// Reason label, such as `Close4009` for a closed connection.
+    #[cfg(feature = "metrics")]
+    fn label(self) -> Cow<'static, str> {
+        match self {
+            Self::Closed(Some(code)) => Cow::Owned(format!("Close{}", code)),
+            Self::Closed(None) => Cow::Borrowed("Close"),
+            Self::Named(name) => Cow::Borrowed(name),
+        }
+    }
+}
+
+impl From<&'static str> for Reason {
+    fn from(name: &'static str) -> Self {
+        Self::Named(name)
+    }
+}
+
+/// Reason of a connection replaced after it was closed.
+fn close_reason(close_frame: Option<&CloseFrame<'_>>) -> Reason {
+    Reason::Closed(close_frame.map(|frame| u16::from(frame.code)))
 }
@@ -1,13 +1,19 @@
 pub mod heartbeat;
 
 mod compression;
+mod health;
 mod r#impl;
+mod ratelimiter;
+mod readiness;
 mod session;
 mod socket_forwarder;
-mod throttle;
 
 pub use self::{
+    compression::TransportCompression,
+    health::{Health, HealthPolicy, HealthTracker, SessionStart},
     heartbeat::Latency,
     r#impl::{ConnectingError, ConnectingErrorType, ShardProcessor},
+    ratelimiter::CommandRatelimit,
+    readiness::{Readiness, ReadinessTracker},
     session::Session,
 };
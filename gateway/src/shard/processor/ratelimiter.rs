@@ -0,0 +1,306 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    convert::TryFrom,
+    sync::{
+        atomic::{AtomicU16, Ordering},
+        Mutex as MutexSync,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+
+/// Number of payloads Discord allows to be sent per [`RESET_PERIOD`].
+const COMMANDS_PER_RESET: u16 = 120;
+
+/// Number of heartbeats to reserve capacity for before the heartbeat interval
+/// is known.
+const DEFAULT_HEARTBEAT_RESERVE: u16 = 3;
+
+/// Length of Discord's ratelimit window.
+const RESET_PERIOD: Duration = Duration::from_secs(60);
+
+/// Information about the command ratelimit of a [`Shard`]'s session.
+///
+/// This is obtained through [`Shard::ratelimit`] or [`Information::ratelimit`].
+///
+/// [`Information::ratelimit`]: crate::shard::Information::ratelimit
+/// [`Shard`]: crate::shard::Shard
+/// [`Shard::ratelimit`]: crate::shard::Shard::ratelimit
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CommandRatelimit {
+    available: u16,
+    max: u16,
+    next_available: Duration,
+}
+
+impl CommandRatelimit {
+    /// Number of commands that can be sent right now without waiting.
+    pub const fn available(&self) -> u16 {
+        self.available
+    }
+
+    /// Maximum number of commands that can be sent per minute.
+    ///
+    /// This is Discord's limit of 120 payloads per minute minus the capacity
+    /// reserved for heartbeats.
+    pub const fn max(&self) -> u16 {
+        self.max
+    }
+
+    /// Time until a command can be sent.
+    ///
+    /// This is zero if [`available`] is not zero.
+    ///
+    /// [`available`]: Self::available
+    pub const fn next_available(&self) -> Duration {
+        self.next_available
+    }
+}
+
+/// Sliding window ratelimiter for payloads sent over a session.
+///
+/// Discord allows 120 payloads to be sent per 60 seconds, including
+/// heartbeats. Commands may be sent in bursts up to the limit, while capacity
+/// for the heartbeats expected within a window is reserved so that they're
+/// never delayed.
+#[derive(Debug)]
+pub struct CommandRatelimiter {
+    /// Number of heartbeats capacity is reserved for.
+    heartbeat_reserve: AtomicU16,
+    /// Queue of commands waiting to be sent, so that they're sent in order.
+    queue: Mutex<()>,
+    /// When the payloads within the current window were sent, oldest first.
+    // So long as the lock isn't held across await or panic points this is fine.
+    sent: MutexSync<VecDeque<Instant>>,
+}
+
+impl CommandRatelimiter {
+    pub fn new() -> Self {
+        Self {
+            heartbeat_reserve: AtomicU16::new(DEFAULT_HEARTBEAT_RESERVE),
+            queue: Mutex::new(()),
+            sent: MutexSync::new(VecDeque::with_capacity(COMMANDS_PER_RESET.into())),
+        }
+    }
+
+    /// Set the heartbeat interval of the session in milliseconds, reserving
+    /// capacity for the heartbeats sent within a window.
+    pub fn set_heartbeat_interval(&self, interval: u64) {
+        let reserve = heartbeat_reserve(interval);
+
+        self.heartbeat_reserve.store(reserve, Ordering::Release);
+    }
+
+    /// Wait until a command may be sent and record it as sent.
+    ///
+    /// Commands are let through in the order they called this.
+    pub async fn acquire(&self) {
+        let _queue = self.queue.lock().await;
+
+        loop {
+            let wait = self.try_acquire_at(Instant::now());
+
+            match wait {
+                Some(duration) => tokio::time::sleep(duration).await,
+                None => return,
+            }
+        }
+    }
+
+    /// Record a heartbeat or another session payload, such as an identify, as
+    /// sent.
+    ///
+    /// These are never delayed; their capacity is reserved.
+    pub fn acquire_heartbeat(&self) {
+        self.acquire_heartbeat_at(Instant::now());
+    }
+
+    /// Current state of the ratelimiter.
+    pub fn ratelimit(&self) -> CommandRatelimit {
+        self.ratelimit_at(Instant::now())
+    }
+
+    fn acquire_heartbeat_at(&self, now: Instant) {
+        let mut sent = self.sent();
+        clean(&mut sent, now);
+        sent.push_back(now);
+    }
+
+    fn max(&self) -> u16 {
+        COMMANDS_PER_RESET - self.heartbeat_reserve.load(Ordering::Acquire)
+    }
+
+    fn ratelimit_at(&self, now: Instant) -> CommandRatelimit {
+        let max = self.max();
+        let mut sent = self.sent();
+        clean(&mut sent, now);
+
+        CommandRatelimit {
+            available: max.saturating_sub(u16::try_from(sent.len()).unwrap_or(u16::MAX)),
+            max,
+            next_available: next_available(&sent, max, now),
+        }
+    }
+
+    /// Record a command as sent if there's capacity for it, otherwise return
+    /// how long to wait until there is.
+    fn try_acquire_at(&self, now: Instant) -> Option<Duration> {
+        let max = self.max();
+        let mut sent = self.sent();
+        clean(&mut sent, now);
+
+        if sent.len() < usize::from(max) {
+            sent.push_back(now);
+
+            None
+        } else {
+            Some(next_available(&sent, max, now))
+        }
+    }
+
+    fn sent(&self) -> std::sync::MutexGuard<'_, VecDeque<Instant>> {
+        self.sent.lock().expect("ratelimiter poisoned")
+    }
+}
+
+/// Remove the payloads that were sent before the current window.
+fn clean(sent: &mut VecDeque<Instant>, now: Instant) {
+    while let Some(oldest) = sent.front() {
+        if now.saturating_duration_since(*oldest) < RESET_PERIOD {
+            break;
+        }
+
+        sent.pop_front();
+    }
+}
+
+/// Number of heartbeats to reserve capacity for given a heartbeat interval in
+/// milliseconds.
+///
+/// One more than the number of heartbeats sent within a window is reserved,
+/// since Discord may request additional heartbeats.
+fn heartbeat_reserve(interval: u64) -> u16 {
+    if interval == 0 {
+        return DEFAULT_HEARTBEAT_RESERVE;
+    }
+
+    let window = RESET_PERIOD.as_secs() * 1000;
+    let heartbeats = (window + interval - 1) / interval + 1;
+
+    // Always leave some capacity for commands, even with a tiny interval.
+    u16::try_from(heartbeats)
+        .unwrap_or(u16::MAX)
+        .min(COMMANDS_PER_RESET / 2)
+}
+
+/// Time until a command can be sent, given the payloads sent within the
+/// current window.
+fn next_available(sent: &VecDeque<Instant>, max: u16, now: Instant) -> Duration {
+    let max = usize::from(max);
+
+    if sent.len() < max {
+        return Duration::from_secs(0);
+    }
+
+    // Capacity is freed when the payload that put the window at its limit
+    // leaves it.
+    sent.get(sent.len() - max)
+        .map_or(Duration::from_secs(0), |oldest| {
+            (*oldest + RESET_PERIOD).saturating_duration_since(now)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{heartbeat_reserve, CommandRatelimit, CommandRatelimiter, COMMANDS_PER_RESET};
+    use static_assertions::assert_impl_all;
+    use std::{
+        fmt::Debug,
+        time::{Duration, Instant},
+    };
+
+    assert_impl_all!(CommandRatelimit: Clone, Copy, Debug, Send, Sync);
+    assert_impl_all!(CommandRatelimiter: Debug, Send, Sync);
+
+    #[test]
+    fn test_heartbeat_reserve() {
+        // Discord's usual interval fits two heartbeats per minute.
+        assert_eq!(3, heartbeat_reserve(41_250));
+        assert_eq!(2, heartbeat_reserve(60_000));
+        assert_eq!(60, heartbeat_reserve(1));
+    }
+
+    #[test]
+    fn test_burst_up_to_limit() {
+        let ratelimiter = CommandRatelimiter::new();
+        ratelimiter.set_heartbeat_interval(41_250);
+        let max = COMMANDS_PER_RESET - 3;
+        let start = Instant::now();
+
+        for _ in 0..max {
+            assert!(ratelimiter.try_acquire_at(start).is_none());
+        }
+
+        let ratelimit = ratelimiter.ratelimit_at(start);
+        assert_eq!(0, ratelimit.available());
+        assert_eq!(max, ratelimit.max());
+        assert_eq!(Duration::from_secs(60), ratelimit.next_available());
+
+        let later = start + Duration::from_secs(20);
+        assert_eq!(
+            Some(Duration::from_secs(40)),
+            ratelimiter.try_acquire_at(later)
+        );
+
+        // Once the window passes the whole capacity is available again.
+        let reset = start + Duration::from_secs(60);
+        assert_eq!(max, ratelimiter.ratelimit_at(reset).available());
+        assert!(ratelimiter.try_acquire_at(reset).is_none());
+    }
+
+    #[test]
+    fn test_heartbeats_use_reserve() {
+        let ratelimiter = CommandRatelimiter::new();
+        ratelimiter.set_heartbeat_interval(41_250);
+        let start = Instant::now();
+
+        for _ in 0..COMMANDS_PER_RESET - 3 {
+            assert!(ratelimiter.try_acquire_at(start).is_none());
+        }
+
+        // Heartbeats always go through, using the reserved capacity.
+        ratelimiter.acquire_heartbeat_at(start);
+        ratelimiter.acquire_heartbeat_at(start + Duration::from_secs(30));
+
+        let later = start + Duration::from_secs(30);
+        let ratelimit = ratelimiter.ratelimit_at(later);
+        assert_eq!(0, ratelimit.available());
+        assert_eq!(Duration::from_secs(30), ratelimit.next_available());
+    }
+
+    #[test]
+    fn test_sliding_window() {
+        let ratelimiter = CommandRatelimiter::new();
+        ratelimiter.set_heartbeat_interval(41_250);
+        let max = COMMANDS_PER_RESET - 3;
+        let start = Instant::now();
+
+        assert!(ratelimiter.try_acquire_at(start).is_none());
+
+        for _ in 1..max {
+            assert!(ratelimiter
+                .try_acquire_at(start + Duration::from_secs(30))
+                .is_none());
+        }
+
+        // Only the first command leaves the window after a minute.
+        let reset = start + Duration::from_secs(60);
+        assert_eq!(1, ratelimiter.ratelimit_at(reset).available());
+        assert!(ratelimiter.try_acquire_at(reset).is_none());
+        assert_eq!(
+            Some(Duration::from_secs(30)),
+            ratelimiter.try_acquire_at(reset)
+        );
+    }
+}
@@ -0,0 +1,306 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, sync::Mutex};
+use twilight_model::{gateway::event::shard::FullyReady, id::GuildId};
+
+/// Progress of a [`Shard`] loading the guilds listed in its ready event.
+///
+/// A shard is fully ready once every guild listed in its ready event has been
+/// received in a guild create or guild delete event, or once the
+/// [guild ready timeout] elapsed for the guilds that never arrived.
+///
+/// This is obtained through [`Shard::info`] or [`Cluster::readiness`].
+///
+/// [`Cluster::readiness`]: crate::cluster::Cluster::readiness
+/// [`Shard`]: crate::shard::Shard
+/// [`Shard::info`]: crate::shard::Shard::info
+/// [guild ready timeout]: crate::shard::ShardBuilder::guild_ready_timeout
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Readiness {
+    fully_ready: bool,
+    guilds_expected: u64,
+    guilds_pending: Vec<GuildId>,
+}
+
+impl Readiness {
+    /// Combine the readiness of multiple shards.
+    ///
+    /// The shards are only fully ready if there is at least one and all of
+    /// them are fully ready.
+    pub(crate) fn combine(shards: impl IntoIterator<Item = Self>) -> Self {
+        let mut combined = Self::default();
+        let mut fully_ready = None;
+
+        for shard in shards {
+            combined.guilds_expected += shard.guilds_expected;
+            combined.guilds_pending.extend(shard.guilds_pending);
+            fully_ready = Some(fully_ready.unwrap_or(true) && shard.fully_ready);
+        }
+
+        combined.fully_ready = fully_ready.unwrap_or(false);
+        combined.guilds_pending.sort_unstable();
+
+        combined
+    }
+
+    /// Number of guilds listed in the ready event.
+    pub const fn guilds_expected(&self) -> u64 {
+        self.guilds_expected
+    }
+
+    /// IDs of the guilds listed in the ready event that haven't been received.
+    ///
+    /// Once the shard is fully ready these are the guilds that didn't arrive
+    /// before the timeout elapsed.
+    pub fn guilds_pending(&self) -> &[GuildId] {
+        &self.guilds_pending
+    }
+
+    /// Number of guilds listed in the ready event that have been received.
+    pub fn guilds_received(&self) -> u64 {
+        self.guilds_expected - self.guilds_pending.len() as u64
+    }
+
+    /// Whether all of the guilds have been received, or the timeout for the
+    /// remaining guilds elapsed.
+    pub const fn is_fully_ready(&self) -> bool {
+        self.fully_ready
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    fully_ready: bool,
+    /// Number of ready events received, used to tell apart the timeouts of
+    /// different sessions.
+    generation: u64,
+    guilds_expected: u64,
+    pending: HashSet<GuildId>,
+}
+
+/// Tracker of the guilds a shard's session has yet to receive, shared between
+/// the shard and its processor.
+#[derive(Debug)]
+pub struct ReadinessTracker {
+    shard_id: u64,
+    state: Mutex<State>,
+}
+
+impl ReadinessTracker {
+    pub fn new(shard_id: u64) -> Self {
+        Self {
+            shard_id,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Snapshot of the current readiness.
+    pub fn readiness(&self) -> Readiness {
+        let state = self.state.lock().expect("readiness poisoned");
+
+        let mut guilds_pending = state.pending.iter().copied().collect::<Vec<_>>();
+        guilds_pending.sort_unstable();
+
+        Readiness {
+            fully_ready: state.fully_ready,
+            guilds_expected: state.guilds_expected,
+            guilds_pending,
+        }
+    }
+
+    /// Number of ready events received so far.
+    pub fn generation(&self) -> u64 {
+        self.state.lock().expect("readiness poisoned").generation
+    }
+
+    /// Whether the shard is waiting for guilds to be received.
+    pub fn is_loading(&self) -> bool {
+        let state = self.state.lock().expect("readiness poisoned");
+
+        !state.fully_ready && !state.pending.is_empty()
+    }
+
+    /// Start waiting for the guilds listed in a new ready event.
+    ///
+    /// Returns the event to emit if no guilds are expected.
+    pub fn ready(&self, guilds: impl IntoIterator<Item = GuildId>) -> Option<FullyReady> {
+        let mut state = self.state.lock().expect("readiness poisoned");
+
+        state.generation += 1;
+        state.pending = guilds.into_iter().collect();
+        state.guilds_expected = state.pending.len() as u64;
+        state.fully_ready = false;
+
+        self.complete(&mut state)
+    }
+
+    /// Mark a guild as received.
+    ///
+    /// Returns the event to emit if it was the last guild being waited for.
+    pub fn receive(&self, guild_id: GuildId) -> Option<FullyReady> {
+        let mut state = self.state.lock().expect("readiness poisoned");
+
+        if state.fully_ready || !state.pending.remove(&guild_id) {
+            return None;
+        }
+
+        self.complete(&mut state)
+    }
+
+    /// Reset the readiness when a new session is going to be created.
+    pub fn reset(&self) {
+        let mut state = self.state.lock().expect("readiness poisoned");
+
+        state.generation += 1;
+        state.fully_ready = false;
+    }
+
+    /// Stop waiting for the remaining guilds of the session started by the
+    /// given ready event.
+    ///
+    /// Returns the event to emit if the shard wasn't already fully ready.
+    pub fn time_out(&self, generation: u64) -> Option<FullyReady> {
+        let mut state = self.state.lock().expect("readiness poisoned");
+
+        if state.fully_ready || state.generation != generation {
+            return None;
+        }
+
+        state.fully_ready = true;
+
+        let mut guilds_missing = state.pending.iter().copied().collect::<Vec<_>>();
+        guilds_missing.sort_unstable();
+
+        Some(FullyReady {
+            guilds_expected: state.guilds_expected,
+            guilds_missing,
+            shard_id: self.shard_id,
+        })
+    }
+
+    fn complete(&self, state: &mut State) -> Option<FullyReady> {
+        if !state.pending.is_empty() {
+            return None;
+        }
+
+        state.fully_ready = true;
+
+        Some(FullyReady {
+            guilds_expected: state.guilds_expected,
+            guilds_missing: Vec::new(),
+            shard_id: self.shard_id,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Readiness, ReadinessTracker};
+    use serde::{Deserialize, Serialize};
+    use static_assertions::assert_impl_all;
+    use std::fmt::Debug;
+    use twilight_model::{gateway::event::shard::FullyReady, id::GuildId};
+
+    assert_impl_all!(
+        Readiness: Clone,
+        Debug,
+        Default,
+        Deserialize<'static>,
+        Eq,
+        PartialEq,
+        Send,
+        Serialize,
+        Sync
+    );
+    assert_impl_all!(ReadinessTracker: Debug, Send, Sync);
+
+    #[test]
+    fn test_receive_all() {
+        let tracker = ReadinessTracker::new(3);
+        assert!(tracker.ready(vec![GuildId(1), GuildId(2)]).is_none());
+        assert!(tracker.is_loading());
+
+        assert!(tracker.receive(GuildId(2)).is_none());
+        // Guilds that weren't listed don't count.
+        assert!(tracker.receive(GuildId(4)).is_none());
+
+        let readiness = tracker.readiness();
+        assert!(!readiness.is_fully_ready());
+        assert_eq!(2, readiness.guilds_expected());
+        assert_eq!(1, readiness.guilds_received());
+        assert_eq!(&[GuildId(1)], readiness.guilds_pending());
+
+        assert_eq!(
+            Some(FullyReady {
+                guilds_expected: 2,
+                guilds_missing: Vec::new(),
+                shard_id: 3,
+            }),
+            tracker.receive(GuildId(1))
+        );
+        assert!(!tracker.is_loading());
+        assert!(tracker.readiness().is_fully_ready());
+        assert!(tracker.receive(GuildId(1)).is_none());
+    }
+
+    #[test]
+    fn test_no_guilds() {
+        let tracker = ReadinessTracker::new(0);
+
+        assert_eq!(
+            Some(FullyReady {
+                guilds_expected: 0,
+                guilds_missing: Vec::new(),
+                shard_id: 0,
+            }),
+            tracker.ready(Vec::new())
+        );
+        assert!(tracker.readiness().is_fully_ready());
+    }
+
+    #[test]
+    fn test_time_out() {
+        let tracker = ReadinessTracker::new(0);
+        tracker.ready(vec![GuildId(1), GuildId(2), GuildId(3)]);
+        let generation = tracker.generation();
+        tracker.receive(GuildId(2));
+
+        // Timeouts of previous sessions are ignored.
+        assert!(tracker.time_out(generation - 1).is_none());
+
+        assert_eq!(
+            Some(FullyReady {
+                guilds_expected: 3,
+                guilds_missing: vec![GuildId(1), GuildId(3)],
+                shard_id: 0,
+            }),
+            tracker.time_out(generation)
+        );
+        assert!(tracker.readiness().is_fully_ready());
+        assert!(tracker.time_out(generation).is_none());
+
+        tracker.reset();
+        assert!(!tracker.readiness().is_fully_ready());
+        assert!(tracker.time_out(generation).is_none());
+    }
+
+    #[test]
+    fn test_combine() {
+        let tracker = ReadinessTracker::new(0);
+        tracker.ready(vec![GuildId(3)]);
+        let other = ReadinessTracker::new(1);
+        other.ready(vec![GuildId(1), GuildId(2)]);
+        other.receive(GuildId(2));
+
+        let combined = Readiness::combine(vec![tracker.readiness(), other.readiness()]);
+        assert!(!combined.is_fully_ready());
+        assert_eq!(3, combined.guilds_expected());
+        assert_eq!(1, combined.guilds_received());
+        assert_eq!(&[GuildId(1), GuildId(3)], combined.guilds_pending());
+
+        tracker.receive(GuildId(3));
+        other.time_out(other.generation());
+
+        assert!(Readiness::combine(vec![tracker.readiness(), other.readiness()]).is_fully_ready());
+        assert!(!Readiness::combine(Vec::new()).is_fully_ready());
+    }
+}
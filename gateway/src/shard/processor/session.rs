@@ -1,7 +1,7 @@
 use super::{
-    super::{json, stage::Stage},
+    super::{encoding::Encoding, stage::Stage},
     heartbeat::{Heartbeater, Heartbeats},
-    throttle::Throttle,
+    ratelimiter::CommandRatelimiter,
 };
 use serde::ser::Serialize;
 use std::{
@@ -12,13 +12,9 @@ use std::{
         atomic::{AtomicU64, AtomicU8, Ordering},
         Arc, Mutex as MutexSync,
     },
-    time::Duration,
 };
 use tokio::{
-    sync::{
-        mpsc::{error::SendError, UnboundedSender},
-        Mutex,
-    },
+    sync::mpsc::{error::SendError, UnboundedSender},
     task::JoinHandle,
 };
 use tokio_tungstenite::tungstenite::{protocol::CloseFrame, Message as TungsteniteMessage};
@@ -40,7 +36,7 @@ impl SessionSendError {
 impl Display for SessionSendError {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         match &self.kind {
-            SessionSendErrorType::Serializing => f.write_str("failed to serialize payload as json"),
+            SessionSendErrorType::Serializing => f.write_str("failed to serialize payload"),
             SessionSendErrorType::Sending => f.write_str("failed to send message over websocket"),
         }
     }
@@ -63,35 +59,40 @@ pub enum SessionSendErrorType {
 
 #[derive(Debug)]
 pub struct Session {
+    pub encoding: Encoding,
     // Needs to be Arc so it can be cloned in the `Drop` impl when spawned on
     // the runtime.
     pub heartbeater_handle: Arc<MutexSync<Option<JoinHandle<()>>>>,
     pub heartbeats: Arc<Heartbeats>,
     pub heartbeat_interval: AtomicU64,
     pub id: MutexSync<Option<Box<str>>>,
+    pub ratelimiter: Arc<CommandRatelimiter>,
     pub seq: Arc<AtomicU64>,
     pub stage: AtomicU8,
     pub tx: UnboundedSender<TungsteniteMessage>,
-    pub ratelimit: Mutex<Throttle>,
 }
 
 impl Session {
-    pub fn new(tx: UnboundedSender<TungsteniteMessage>) -> Self {
+    pub fn new(tx: UnboundedSender<TungsteniteMessage>, encoding: Encoding) -> Self {
         Self {
+            encoding,
             heartbeater_handle: Arc::new(MutexSync::new(None)),
             heartbeats: Arc::new(Heartbeats::default()),
             heartbeat_interval: AtomicU64::new(0),
             id: MutexSync::new(None),
+            ratelimiter: Arc::new(CommandRatelimiter::new()),
             seq: Arc::new(AtomicU64::new(0)),
             stage: AtomicU8::new(Stage::default() as u8),
             tx,
-            // 520 instead of 500 to make sure that it can heartbeat.
-            ratelimit: Mutex::new(Throttle::new(Duration::from_millis(520))),
         }
     }
 
     /// Sends a payload as a message over the socket.
     ///
+    /// This is meant for session payloads such as identifies and heartbeats,
+    /// which use the ratelimit capacity reserved for them and are never
+    /// delayed.
+    ///
     /// # Errors
     ///
     /// Returns a [`SessionSendErrorType::Serializing`] error type when there is
@@ -101,10 +102,13 @@ impl Session {
     /// receiving channel has hung up. This will only happen when the shard has
     /// either not started or has already shutdown.
     pub fn send(&self, payload: impl Serialize) -> Result<(), SessionSendError> {
-        let bytes = json::to_vec(&payload).map_err(|source| SessionSendError {
-            kind: SessionSendErrorType::Serializing,
-            source: Some(Box::new(source)),
-        })?;
+        let bytes = self
+            .encoding
+            .serialize(&payload)
+            .map_err(|source| SessionSendError {
+                kind: SessionSendErrorType::Serializing,
+                source: Some(source),
+            })?;
 
         self.tx
             .send(TungsteniteMessage::Binary(bytes))
@@ -113,6 +117,8 @@ impl Session {
                 source: Some(Box::new(source)),
             })?;
 
+        self.ratelimiter.acquire_heartbeat();
+
         Ok(())
     }
 
@@ -130,6 +136,8 @@ impl Session {
     pub fn set_heartbeat_interval(&self, new_heartbeat_interval: u64) {
         self.heartbeat_interval
             .store(new_heartbeat_interval, Ordering::Release);
+        self.ratelimiter
+            .set_heartbeat_interval(new_heartbeat_interval);
     }
 
     /// Returns the current sequence.
@@ -179,8 +187,17 @@ impl Session {
         let interval = self.heartbeat_interval();
         let seq = Arc::clone(&self.seq);
         let heartbeats = Arc::clone(&self.heartbeats);
-
-        let heartbeater = Heartbeater::new(heartbeats, interval, seq, self.tx.clone()).run();
+        let ratelimiter = Arc::clone(&self.ratelimiter);
+
+        let heartbeater = Heartbeater::new(
+            self.encoding,
+            heartbeats,
+            interval,
+            ratelimiter,
+            seq,
+            self.tx.clone(),
+        )
+        .run();
         let handle = tokio::spawn(heartbeater);
 
         if let Some(old) = self
@@ -1,4 +1,7 @@
-use super::super::ShardStream;
+use super::super::{
+    recording::{Direction, ShardRecorder},
+    ShardStream,
+};
 use futures_util::{
     future::{self, Either, FutureExt},
     sink::SinkExt,
@@ -12,7 +15,9 @@ use tokio::{
 use tokio_tungstenite::tungstenite::Message;
 
 pub struct SocketForwarder {
+    recorder: Option<ShardRecorder>,
     rx: UnboundedReceiver<Message>,
+    shard_id: u64,
     pub stream: ShardStream,
     tx: UnboundedSender<Message>,
 }
@@ -22,13 +27,17 @@ impl SocketForwarder {
 
     pub fn new(
         stream: ShardStream,
+        shard_id: u64,
+        recorder: Option<ShardRecorder>,
     ) -> (Self, UnboundedReceiver<Message>, UnboundedSender<Message>) {
         let (to_user, from_forwarder) = mpsc::unbounded_channel();
         let (to_forwarder, from_user) = mpsc::unbounded_channel();
 
         (
             Self {
+                recorder,
                 rx: from_user,
+                shard_id,
                 stream,
                 tx: to_user,
             },
@@ -38,7 +47,7 @@ impl SocketForwarder {
     }
 
     pub async fn run(mut self) {
-        tracing::debug!("starting driving loop");
+        tracing::debug!(shard_id = self.shard_id, "starting driving loop");
 
         loop {
             let timeout = sleep(Self::TIMEOUT).fuse();
@@ -55,8 +64,28 @@ impl SocketForwarder {
                     if let Some(msg) = maybe_msg {
                         tracing::trace!("sending message: {}", msg);
 
+                        if let Some(recorder) = &self.recorder {
+                            match &msg {
+                                Message::Binary(bytes) => {
+                                    recorder.record(Direction::Outbound, bytes);
+                                }
+                                Message::Text(text) => {
+                                    recorder.record(Direction::Outbound, text.as_bytes());
+                                }
+                                _ => {}
+                            }
+                        }
+
+                        #[cfg(feature = "metrics")]
+                        {
+                            let len = msg.len() as u64;
+                            let shard = self.shard_id.to_string();
+
+                            metrics::counter!("GatewayBytesOut", len, "shard" => shard);
+                        }
+
                         if let Err(err) = self.stream.send(msg).await {
-                            tracing::warn!("sending failed: {}", err);
+                            tracing::warn!(shard_id = self.shard_id, "sending failed: {}", err);
                             break;
                         }
                     } else {
@@ -74,7 +103,7 @@ impl SocketForwarder {
                         }
                     }
                     Some(Err(err)) => {
-                        tracing::warn!("socket errored: {}", err);
+                        tracing::warn!(shard_id = self.shard_id, "socket errored: {}", err);
                         break;
                     }
                     None => {
@@ -84,7 +113,7 @@ impl SocketForwarder {
                 },
                 // Timeout future finished first.
                 Either::Right((_, _)) => {
-                    tracing::warn!("socket timed out");
+                    tracing::warn!(shard_id = self.shard_id, "socket timed out");
                     break;
                 }
             };
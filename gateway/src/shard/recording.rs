@@ -0,0 +1,785 @@
+//! Record the payloads exchanged with the gateway and replay them offline.
+//!
+//! A [`Recorder`] given to [`ShardBuilder::recorder`] writes every payload the
+//! shard receives, after decompression, and every payload it sends to a
+//! recording, along with when it happened and the shard's ID. A [`Replayer`]
+//! reads the recording back, either payload by payload or as an [`Events`]
+//! stream produced by parsing the received payloads like a shard does.
+//!
+//! This is useful to reproduce the exact sequence of payloads that led to a
+//! bug, or to test a cache against recorded traffic.
+//!
+//! # Format
+//!
+//! A recording starts with the magic bytes `TWGR` and a format version byte,
+//! followed by the records. Each record is the direction (`0` for received,
+//! `1` for sent), the encoding of the payload (`0` for JSON, `1` for ETF), the
+//! shard ID and the milliseconds since the Unix epoch as little endian
+//! unsigned 64-bit integers, the payload's length as a little endian unsigned
+//! 32-bit integer, and the payload itself. Payloads are at most 64 MiB long.
+//!
+//! The token of sent identify and resume payloads is replaced with
+//! `<redacted>`, so recordings can be shared without leaking the bot's token.
+//!
+//! # Examples
+//!
+//! Record a shard's traffic and replay the events it received:
+//!
+//! ```no_run
+//! use futures::StreamExt;
+//! use std::env;
+//! use twilight_gateway::{
+//!     shard::recording::{Recorder, Replayer},
+//!     EventTypeFlags, Intents, Shard,
+//! };
+//!
+//! # #[tokio::main] async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+//! let recorder = Recorder::create("shard.rec")?;
+//! let token = env::var("DISCORD_TOKEN")?;
+//! let (shard, _events) = Shard::builder(token, Intents::GUILDS)
+//!     .recorder(recorder.clone())
+//!     .build();
+//! shard.start().await?;
+//!
+//! // Later, once the shard has been shut down:
+//! recorder.flush()?;
+//!
+//! let mut events = Replayer::open("shard.rec")?.replay(EventTypeFlags::default())?;
+//!
+//! while let Some(event) = events.next().await {
+//!     println!("replayed: {:?}", event.kind());
+//! }
+//! # Ok(()) }
+//! ```
+//!
+//! [`Events`]: super::Events
+//! [`ShardBuilder::recorder`]: super::ShardBuilder::recorder
+
+use super::{emitter::Emitter, encoding::Encoding, event::Events};
+use crate::EventTypeFlags;
+use std::{
+    borrow::Cow,
+    convert::TryFrom,
+    error::Error,
+    fmt::{Debug, Display, Formatter, Result as FmtResult},
+    fs::File,
+    io::{BufReader, BufWriter, Error as IoError, ErrorKind as IoErrorKind, Read, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use twilight_model::gateway::{
+    payload::{identify::Identify, resume::Resume},
+    OpCode,
+};
+
+/// Bytes every recording starts with.
+const MAGIC: &[u8; 4] = b"TWGR";
+
+/// Version of the recording format.
+const VERSION: u8 = 1;
+
+/// Maximum length of a recorded payload, which is the maximum length of a
+/// message received over the WebSocket.
+const MAX_PAYLOAD_LENGTH: u32 = 64 << 20;
+
+/// Value the token of identify and resume payloads is recorded as.
+const REDACTED: &str = "<redacted>";
+
+/// Reading a recording failed.
+#[derive(Debug)]
+pub struct ReplayError {
+    kind: ReplayErrorType,
+    source: Option<Box<dyn Error + Send + Sync>>,
+}
+
+impl ReplayError {
+    /// Immutable reference to the type of error that occurred.
+    #[must_use = "retrieving the type has no effect if left unused"]
+    pub const fn kind(&self) -> &ReplayErrorType {
+        &self.kind
+    }
+
+    /// Consume the error, returning the source error if there is any.
+    #[must_use = "consuming the error and retrieving the source has no effect if left unused"]
+    pub fn into_source(self) -> Option<Box<dyn Error + Send + Sync>> {
+        self.source
+    }
+
+    /// Consume the error, returning the owned error type and the source error.
+    #[must_use = "consuming the error into its parts has no effect if left unused"]
+    pub fn into_parts(self) -> (ReplayErrorType, Option<Box<dyn Error + Send + Sync>>) {
+        (self.kind, self.source)
+    }
+
+    fn reading(source: IoError) -> Self {
+        Self {
+            kind: ReplayErrorType::Reading,
+            source: Some(Box::new(source)),
+        }
+    }
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match &self.kind {
+            ReplayErrorType::EncodingUnsupported { encoding } => f.write_fmt(format_args!(
+                "payload encoding {} is unknown or its feature isn't enabled",
+                encoding
+            )),
+            ReplayErrorType::FormatInvalid => f.write_str("recording is malformed"),
+            ReplayErrorType::Reading => f.write_str("reading the recording failed"),
+            ReplayErrorType::VersionUnsupported { version } => f.write_fmt(format_args!(
+                "recording format version {} is unsupported",
+                version
+            )),
+        }
+    }
+}
+
+impl Error for ReplayError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| &**source as &(dyn Error + 'static))
+    }
+}
+
+/// Type of [`ReplayError`] that occurred.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ReplayErrorType {
+    /// Encoding of a payload is unknown, or is ETF and the `etf` feature isn't
+    /// enabled.
+    EncodingUnsupported {
+        /// Byte identifying the encoding.
+        encoding: u8,
+    },
+    /// Recording isn't in the recording format or is corrupted.
+    FormatInvalid,
+    /// Reading from the underlying reader failed.
+    Reading,
+    /// Recording was written in a newer version of the recording format.
+    VersionUnsupported {
+        /// Version of the recording's format.
+        version: u8,
+    },
+}
+
+/// Whether a payload was received or sent by the shard.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Direction {
+    /// Payload was received from the gateway.
+    Inbound,
+    /// Payload was sent to the gateway.
+    Outbound,
+}
+
+/// Payload read from a recording.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecordedPayload {
+    bytes: Vec<u8>,
+    direction: Direction,
+    encoding: Encoding,
+    shard_id: u64,
+    timestamp: u64,
+}
+
+impl RecordedPayload {
+    /// Immutable reference to the payload's bytes.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Consume the payload, returning its bytes.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Whether the payload was received or sent.
+    pub const fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Encoding of the payload.
+    pub const fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// ID of the shard that received or sent the payload.
+    pub const fn shard_id(&self) -> u64 {
+        self.shard_id
+    }
+
+    /// When the payload was received or sent.
+    pub fn timestamp(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(self.timestamp)
+    }
+}
+
+/// Writer of the payloads exchanged by shards to a recording.
+///
+/// Clones of the recorder write to the same recording, so one recorder can be
+/// given to all of the shards of a cluster. Records are buffered and written
+/// as payloads are exchanged; failing to write a record is logged and doesn't
+/// affect the shard. The buffer is flushed when the last clone is dropped, or
+/// by calling [`flush`].
+///
+/// [`flush`]: Self::flush
+#[derive(Clone)]
+pub struct Recorder(Arc<Mutex<BufWriter<Box<dyn Write + Send>>>>);
+
+impl Recorder {
+    /// Create a recording file at a path, truncating it if it exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an IO error if the file couldn't be created or written to.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, IoError> {
+        Self::new(File::create(path)?)
+    }
+
+    /// Create a recorder writing a recording to a writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an IO error if writing the start of the recording failed.
+    pub fn new(writer: impl Write + Send + 'static) -> Result<Self, IoError> {
+        let mut writer = BufWriter::new(Box::new(writer) as Box<dyn Write + Send>);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+
+        Ok(Self(Arc::new(Mutex::new(writer))))
+    }
+
+    /// Flush the records buffered so far to the writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an IO error if writing to the writer failed.
+    pub fn flush(&self) -> Result<(), IoError> {
+        self.0.lock().expect("recorder poisoned").flush()
+    }
+
+    /// Recorder of the payloads of a shard.
+    pub(crate) fn shard(&self, shard_id: u64, encoding: Encoding) -> ShardRecorder {
+        ShardRecorder {
+            encoding,
+            recorder: self.clone(),
+            shard_id,
+        }
+    }
+
+    fn write(
+        &self,
+        direction: Direction,
+        encoding: Encoding,
+        shard_id: u64,
+        bytes: &[u8],
+    ) -> Result<(), IoError> {
+        let length = u32::try_from(bytes.len())
+            .ok()
+            .filter(|length| *length <= MAX_PAYLOAD_LENGTH)
+            .ok_or_else(|| IoError::new(IoErrorKind::InvalidInput, "payload too long"))?;
+        // Milliseconds since the epoch fit in 64 bits for the next few hundred
+        // million years.
+        #[allow(clippy::cast_possible_truncation)]
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_millis() as u64);
+
+        let mut writer = self.0.lock().expect("recorder poisoned");
+        writer.write_all(&[direction_id(direction), encoding_id(encoding)])?;
+        writer.write_all(&shard_id.to_le_bytes())?;
+        writer.write_all(&timestamp.to_le_bytes())?;
+        writer.write_all(&length.to_le_bytes())?;
+        writer.write_all(bytes)
+    }
+}
+
+impl Debug for Recorder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_tuple("Recorder").field(&"<writer>").finish()
+    }
+}
+
+/// Recorder bound to a shard's ID and encoding.
+#[derive(Clone, Debug)]
+pub(crate) struct ShardRecorder {
+    encoding: Encoding,
+    recorder: Recorder,
+    shard_id: u64,
+}
+
+impl ShardRecorder {
+    /// Record a payload, logging if writing the record failed.
+    ///
+    /// The token of sent identify and resume payloads is redacted. Payloads
+    /// whose token can't be redacted aren't recorded.
+    pub fn record(&self, direction: Direction, bytes: &[u8]) {
+        let bytes = match direction {
+            Direction::Inbound => Cow::Borrowed(bytes),
+            Direction::Outbound => {
+                if let Some(bytes) = redact(self.encoding, bytes) {
+                    bytes
+                } else {
+                    tracing::warn!(
+                        shard_id = self.shard_id,
+                        "not recording payload whose token couldn't be redacted",
+                    );
+
+                    return;
+                }
+            }
+        };
+
+        if let Err(source) = self
+            .recorder
+            .write(direction, self.encoding, self.shard_id, &bytes)
+        {
+            tracing::warn!(
+                shard_id = self.shard_id,
+                "recording payload failed: {}",
+                source,
+            );
+        }
+    }
+}
+
+/// Reader of a recording written by a [`Recorder`].
+///
+/// The payloads of the recording can be iterated over, or the received ones
+/// can be replayed as an [`Events`] stream with [`replay`].
+///
+/// [`Events`]: super::Events
+/// [`replay`]: Self::replay
+#[derive(Debug)]
+pub struct Replayer<R> {
+    reader: R,
+}
+
+impl Replayer<BufReader<File>> {
+    /// Open a recording file at a path.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ReplayErrorType::Reading`] error type if the file couldn't
+    /// be opened or read.
+    ///
+    /// Returns a [`ReplayErrorType::FormatInvalid`] error type if the file
+    /// isn't a recording.
+    ///
+    /// Returns a [`ReplayErrorType::VersionUnsupported`] error type if the
+    /// recording was written in a newer version of the format.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        let file = File::open(path).map_err(ReplayError::reading)?;
+
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read> Replayer<R> {
+    /// Create a replayer reading a recording from a reader.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ReplayErrorType::Reading`] error type if the reader
+    /// couldn't be read from.
+    ///
+    /// Returns a [`ReplayErrorType::FormatInvalid`] error type if the reader
+    /// doesn't contain a recording.
+    ///
+    /// Returns a [`ReplayErrorType::VersionUnsupported`] error type if the
+    /// recording was written in a newer version of the format.
+    pub fn new(mut reader: R) -> Result<Self, ReplayError> {
+        let mut header = [0; 5];
+        read_exact(&mut reader, &mut header)?;
+
+        if &header[..4] != MAGIC {
+            return Err(ReplayError {
+                kind: ReplayErrorType::FormatInvalid,
+                source: None,
+            });
+        }
+
+        if header[4] != VERSION {
+            return Err(ReplayError {
+                kind: ReplayErrorType::VersionUnsupported { version: header[4] },
+                source: None,
+            });
+        }
+
+        Ok(Self { reader })
+    }
+
+    /// Read the next payload of the recording.
+    ///
+    /// Returns `None` once the end of the recording is reached.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ReplayErrorType::Reading`] error type if the reader
+    /// couldn't be read from.
+    ///
+    /// Returns a [`ReplayErrorType::FormatInvalid`] error type if the record
+    /// is malformed, truncated, or its payload is longer than 64 MiB.
+    ///
+    /// Returns a [`ReplayErrorType::EncodingUnsupported`] error type if the
+    /// payload's encoding isn't supported.
+    pub fn next_payload(&mut self) -> Result<Option<RecordedPayload>, ReplayError> {
+        let mut header = [0; 22];
+
+        // Distinguish the end of the recording from a truncated record.
+        let read = loop {
+            match self.reader.read(&mut header) {
+                Ok(read) => break read,
+                Err(source) if source.kind() == IoErrorKind::Interrupted => {}
+                Err(source) => return Err(ReplayError::reading(source)),
+            }
+        };
+
+        if read == 0 {
+            return Ok(None);
+        }
+
+        read_exact(&mut self.reader, &mut header[read..])?;
+
+        let direction = match header[0] {
+            0 => Direction::Inbound,
+            1 => Direction::Outbound,
+            _ => {
+                return Err(ReplayError {
+                    kind: ReplayErrorType::FormatInvalid,
+                    source: None,
+                })
+            }
+        };
+        let encoding = encoding_from_id(header[1]).ok_or(ReplayError {
+            kind: ReplayErrorType::EncodingUnsupported {
+                encoding: header[1],
+            },
+            source: None,
+        })?;
+        let shard_id = u64::from_le_bytes(<[u8; 8]>::try_from(&header[2..10]).expect("8 bytes"));
+        let timestamp = u64::from_le_bytes(<[u8; 8]>::try_from(&header[10..18]).expect("8 bytes"));
+        let length = u32::from_le_bytes(<[u8; 4]>::try_from(&header[18..22]).expect("4 bytes"));
+
+        if length > MAX_PAYLOAD_LENGTH {
+            return Err(ReplayError {
+                kind: ReplayErrorType::FormatInvalid,
+                source: None,
+            });
+        }
+
+        let mut bytes = vec![0; length as usize];
+        read_exact(&mut self.reader, &mut bytes)?;
+
+        Ok(Some(RecordedPayload {
+            bytes,
+            direction,
+            encoding,
+            shard_id,
+            timestamp,
+        }))
+    }
+
+    /// Replay the received payloads of the recording as a stream of events.
+    ///
+    /// The payloads are parsed into events like a shard parses them, and only
+    /// events of the provided event types are included. Payloads that can't
+    /// be parsed are logged and skipped over.
+    ///
+    /// The whole recording is read before the stream is returned, and the
+    /// stream ends after the last recorded event.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the first payload that couldn't be read. Refer to
+    /// [`next_payload`] for the errors that can occur.
+    ///
+    /// [`next_payload`]: Self::next_payload
+    pub fn replay(mut self, event_types: EventTypeFlags) -> Result<Events, ReplayError> {
+        let (mut emitter, rx) = Emitter::new(Encoding::default(), event_types, None, None);
+        let mut encoding = Encoding::default();
+
+        while let Some(payload) = self.next_payload()? {
+            if payload.direction != Direction::Inbound {
+                continue;
+            }
+
+            if payload.encoding != encoding {
+                encoding = payload.encoding;
+                emitter = emitter.with_encoding(encoding);
+            }
+
+            let mut bytes = payload.bytes;
+            emitter.bytes(&bytes);
+
+            let (op, seq, event_type) = if let Some(header) = encoding.peek_header(&bytes) {
+                header
+            } else {
+                tracing::warn!(
+                    shard_id = payload.shard_id,
+                    "replayed payload without opcode"
+                );
+
+                continue;
+            };
+
+            if let Err(source) = emitter.payload(op, seq, event_type.as_deref(), None, &mut bytes) {
+                tracing::warn!(
+                    shard_id = payload.shard_id,
+                    "replaying payload failed: {}",
+                    source,
+                );
+            }
+        }
+
+        Ok(Events::new(event_types, rx))
+    }
+}
+
+impl<R: Read> Iterator for Replayer<R> {
+    type Item = Result<RecordedPayload, ReplayError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_payload().transpose()
+    }
+}
+
+const fn direction_id(direction: Direction) -> u8 {
+    match direction {
+        Direction::Inbound => 0,
+        Direction::Outbound => 1,
+    }
+}
+
+const fn encoding_id(encoding: Encoding) -> u8 {
+    match encoding {
+        #[cfg(feature = "etf")]
+        Encoding::Etf => 1,
+        Encoding::Json => 0,
+    }
+}
+
+const fn encoding_from_id(id: u8) -> Option<Encoding> {
+    match id {
+        #[cfg(feature = "etf")]
+        1 => Some(Encoding::Etf),
+        0 => Some(Encoding::Json),
+        _ => None,
+    }
+}
+
+/// Replace the token of identify and resume payloads with [`REDACTED`].
+///
+/// Returns `None` if the payload has a token that couldn't be redacted.
+fn redact(encoding: Encoding, bytes: &[u8]) -> Option<Cow<'_, [u8]>> {
+    let op = match encoding.peek_header(bytes) {
+        Some((op, _, _)) => op,
+        None => return Some(Cow::Borrowed(bytes)),
+    };
+
+    let redacted = if op == OpCode::Identify as u8 {
+        let mut identify = encoding.deserialize::<Identify>(&mut bytes.to_vec()).ok()?;
+        identify.d.token = REDACTED.to_owned();
+
+        encoding.serialize(&identify)
+    } else if op == OpCode::Resume as u8 {
+        let mut resume = encoding.deserialize::<Resume>(&mut bytes.to_vec()).ok()?;
+        resume.d.token = REDACTED.to_owned();
+
+        encoding.serialize(&resume)
+    } else {
+        return Some(Cow::Borrowed(bytes));
+    };
+
+    redacted.ok().map(Cow::Owned)
+}
+
+/// Fill a buffer from a reader, treating reaching the end early as a malformed
+/// recording.
+fn read_exact(reader: &mut impl Read, buf: &mut [u8]) -> Result<(), ReplayError> {
+    reader.read_exact(buf).map_err(|source| {
+        if source.kind() == IoErrorKind::UnexpectedEof {
+            ReplayError {
+                kind: ReplayErrorType::FormatInvalid,
+                source: Some(Box::new(source)),
+            }
+        } else {
+            ReplayError::reading(source)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Direction, RecordedPayload, Recorder, ReplayError, ReplayErrorType, Replayer};
+    use crate::{shard::Encoding, Event, EventTypeFlags};
+    use futures_util::stream::StreamExt;
+    use static_assertions::assert_impl_all;
+    use std::{
+        error::Error,
+        fmt::Debug,
+        io::{Result as IoResult, Write},
+        sync::{Arc, Mutex},
+    };
+
+    assert_impl_all!(Direction: Clone, Copy, Debug, Eq, PartialEq, Send, Sync);
+    assert_impl_all!(RecordedPayload: Clone, Debug, Eq, PartialEq, Send, Sync);
+    assert_impl_all!(Recorder: Clone, Debug, Send, Sync);
+    assert_impl_all!(Replayer<&[u8]>: Debug, Iterator, Send, Sync);
+    assert_impl_all!(ReplayErrorType: Debug, Send, Sync);
+    assert_impl_all!(ReplayError: Error, Send, Sync);
+
+    /// Writer into a buffer that can be read after the recorder is dropped.
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> IoResult<()> {
+            Ok(())
+        }
+    }
+
+    fn record(payloads: &[(Direction, &[u8])]) -> Vec<u8> {
+        let buffer = Buffer::default();
+        let recorder = Recorder::new(buffer.clone()).unwrap();
+        let shard = recorder.shard(2, Encoding::Json);
+
+        for (direction, bytes) in payloads {
+            shard.record(*direction, bytes);
+        }
+
+        recorder.flush().unwrap();
+
+        let bytes = buffer.0.lock().unwrap().clone();
+
+        bytes
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let recording = record(&[
+            (Direction::Inbound, br#"{"op":11,"d":null}"#),
+            (Direction::Outbound, br#"{"op":1,"d":null}"#),
+        ]);
+
+        let payloads = Replayer::new(recording.as_slice())
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(2, payloads.len());
+        assert_eq!(Direction::Inbound, payloads[0].direction());
+        assert_eq!(br#"{"op":11,"d":null}"#, payloads[0].bytes());
+        assert_eq!(Encoding::Json, payloads[0].encoding());
+        assert_eq!(2, payloads[0].shard_id());
+        assert!(payloads[0].timestamp() <= payloads[1].timestamp());
+        assert_eq!(Direction::Outbound, payloads[1].direction());
+        assert_eq!(
+            br#"{"op":1,"d":null}"#.to_vec(),
+            payloads[1].clone().into_bytes()
+        );
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert!(matches!(
+            Replayer::new(&b"TWG"[..]).unwrap_err().kind(),
+            ReplayErrorType::FormatInvalid
+        ));
+        assert!(matches!(
+            Replayer::new(&b"TWGR\x02"[..]).unwrap_err().kind(),
+            ReplayErrorType::VersionUnsupported { version: 2 }
+        ));
+
+        let mut recording = record(&[(Direction::Inbound, br#"{"op":11,"d":null}"#)]);
+        recording.pop();
+        let mut replayer = Replayer::new(recording.as_slice()).unwrap();
+
+        assert!(matches!(
+            replayer.next_payload().unwrap_err().kind(),
+            ReplayErrorType::FormatInvalid
+        ));
+
+        // The length of the payload is read from the recording, so it must
+        // not be trusted to allocate.
+        let mut recording = record(&[(Direction::Inbound, br#"{"op":11,"d":null}"#)]);
+        recording[23..27].copy_from_slice(&u32::MAX.to_le_bytes());
+        let mut replayer = Replayer::new(recording.as_slice()).unwrap();
+
+        assert!(matches!(
+            replayer.next_payload().unwrap_err().kind(),
+            ReplayErrorType::FormatInvalid
+        ));
+    }
+
+    #[test]
+    fn test_token_redacted() {
+        let identify = br#"{"op":2,"d":{"token":"Bot secret","properties":{"$os":"linux","$browser":"twilight","$device":"twilight","$referrer":"","$referring_domain":""},"presence":null,"compress":false,"large_threshold":50,"shard":[0,1],"intents":1}}"#;
+        let resume = br#"{"op":6,"d":{"token":"Bot secret","session_id":"session","seq":3}}"#;
+        let recording = record(&[
+            (Direction::Outbound, identify),
+            (Direction::Outbound, resume),
+            (Direction::Outbound, br#"{"op":1,"d":3}"#),
+        ]);
+
+        let payloads = Replayer::new(recording.as_slice())
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(3, payloads.len());
+
+        for payload in &payloads[..2] {
+            let bytes = String::from_utf8_lossy(payload.bytes());
+            assert!(!bytes.contains("secret"));
+            assert!(bytes.contains(r#""token":"<redacted>""#));
+        }
+
+        assert!(String::from_utf8_lossy(payloads[0].bytes()).contains(r#""op":2"#));
+        assert!(String::from_utf8_lossy(payloads[1].bytes()).contains(r#""seq":3"#));
+        assert_eq!(br#"{"op":1,"d":3}"#, payloads[2].bytes());
+    }
+
+    #[tokio::test]
+    async fn test_replay() {
+        let recording = record(&[
+            (
+                Direction::Inbound,
+                br#"{"op":10,"d":{"heartbeat_interval":41250},"s":null,"t":null}"#,
+            ),
+            (Direction::Outbound, br#"{"op":1,"d":null}"#),
+            (
+                Direction::Inbound,
+                br#"{"t":"GUILD_ROLE_DELETE","s":1,"op":0,"d":{"role_id":"2","guild_id":"1"}}"#,
+            ),
+            (Direction::Inbound, b"not a payload"),
+            (
+                Direction::Inbound,
+                br#"{"op":11,"d":null,"s":null,"t":null}"#,
+            ),
+        ]);
+
+        let mut events = Replayer::new(recording.as_slice())
+            .unwrap()
+            .replay(EventTypeFlags::default())
+            .unwrap();
+
+        assert!(matches!(
+            events.next().await,
+            Some(Event::GatewayHello(41250))
+        ));
+        assert!(matches!(events.next().await, Some(Event::RoleDelete(_))));
+        assert!(matches!(
+            events.next().await,
+            Some(Event::GatewayHeartbeatAck)
+        ));
+        assert!(events.next().await.is_none());
+    }
+}
@@ -0,0 +1,661 @@
+//! Join voice channels and collect the information to connect to voice.
+//!
+//! Connecting to voice requires sending an [`UpdateVoiceState`] command over
+//! a shard, after which Discord sends the bot's own [`VoiceStateUpdate`] with
+//! the voice session ID and a [`VoiceServerUpdate`] with the token and
+//! endpoint of the voice server. The [`VoiceConnector`] sends the command and
+//! collects both events as they're [processed], resolving a
+//! [`VoiceConnectionFuture`] with the [`VoiceConnectionInfo`] a voice backend,
+//! such as Lavalink, needs to connect.
+//!
+//! [processed]: VoiceConnector::process
+
+use crate::{
+    pending::{self, PendingRequests, Registration},
+    shard::{CommandError, Shard},
+};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time::{self, Sleep};
+use twilight_model::{
+    gateway::{
+        event::Event,
+        payload::{UpdateVoiceState, VoiceServerUpdate},
+    },
+    id::{ChannelId, GuildId, UserId},
+    voice::VoiceState,
+};
+
+/// Joining a voice channel failed.
+///
+/// Returned by awaiting a [`VoiceConnectionFuture`].
+#[derive(Debug)]
+pub struct VoiceConnectionError {
+    kind: VoiceConnectionErrorType,
+    source: Option<Box<dyn Error + Send + Sync>>,
+}
+
+impl VoiceConnectionError {
+    /// Immutable reference to the type of error that occurred.
+    #[must_use = "retrieving the type has no effect if left unused"]
+    pub const fn kind(&self) -> &VoiceConnectionErrorType {
+        &self.kind
+    }
+
+    /// Consume the error, returning the source error if there is any.
+    #[must_use = "consuming the error and retrieving the source has no effect if left unused"]
+    pub fn into_source(self) -> Option<Box<dyn Error + Send + Sync>> {
+        self.source
+    }
+
+    /// Consume the error, returning the owned error type and the source error.
+    #[must_use = "consuming the error into its parts has no effect if left unused"]
+    pub fn into_parts(
+        self,
+    ) -> (
+        VoiceConnectionErrorType,
+        Option<Box<dyn Error + Send + Sync>>,
+    ) {
+        (self.kind, self.source)
+    }
+}
+
+impl Display for VoiceConnectionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match &self.kind {
+            VoiceConnectionErrorType::Cancelled => {
+                f.write_str("the voice connector was dropped before the channel was joined")
+            }
+            VoiceConnectionErrorType::Disconnected => {
+                f.write_str("the bot was disconnected from voice before the channel was joined")
+            }
+            VoiceConnectionErrorType::SessionInvalidated => {
+                f.write_str("the shard started a new session before the channel was joined")
+            }
+            VoiceConnectionErrorType::Superseded => f.write_str(
+                "another voice state update was sent for the guild before the channel was joined",
+            ),
+            VoiceConnectionErrorType::TimedOut { timeout } => f.write_fmt(format_args!(
+                "the channel wasn't joined within {:.2?}",
+                timeout
+            )),
+        }
+    }
+}
+
+impl Error for VoiceConnectionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| &**source as &(dyn Error + 'static))
+    }
+}
+
+/// Type of [`VoiceConnectionError`] that occurred.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum VoiceConnectionErrorType {
+    /// Voice connector was dropped before the channel was joined.
+    Cancelled,
+    /// Bot's voice state was updated to not be in a channel, such as when it
+    /// lacks the permissions to join the channel or was disconnected by a
+    /// moderator.
+    Disconnected,
+    /// Shard started a new session before the channel was joined.
+    SessionInvalidated,
+    /// Another channel was joined or the guild's voice channel was left
+    /// before the channel was joined.
+    Superseded,
+    /// Discord didn't send both the voice state and voice server updates
+    /// within the configured timeout.
+    TimedOut {
+        /// Configured timeout.
+        timeout: Duration,
+    },
+}
+
+/// Information needed to connect to a guild's voice server.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VoiceConnectionInfo {
+    /// ID of the voice channel the bot is in.
+    pub channel_id: ChannelId,
+    /// Endpoint of the voice server.
+    pub endpoint: String,
+    /// ID of the guild the voice channel is in.
+    pub guild_id: GuildId,
+    /// ID of the bot's voice session.
+    pub session_id: String,
+    /// Token to authenticate with the voice server.
+    pub token: String,
+}
+
+/// Future resolving once both the voice state and voice server updates of a
+/// voice channel join have been received.
+///
+/// Returned by [`VoiceConnector::join`].
+#[derive(Debug)]
+pub struct VoiceConnectionFuture {
+    registration: Registration<GuildId, Join, Result<VoiceConnectionInfo, VoiceConnectionError>>,
+    timeout: Duration,
+    sleep: Pin<Box<Sleep>>,
+}
+
+impl Future for VoiceConnectionFuture {
+    type Output = Result<VoiceConnectionInfo, VoiceConnectionError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Poll::Ready(result) = self.registration.poll_recv(cx) {
+            // The connector was dropped.
+            return Poll::Ready(result.unwrap_or(Err(VoiceConnectionError {
+                kind: VoiceConnectionErrorType::Cancelled,
+                source: None,
+            })));
+        }
+
+        if self.sleep.as_mut().poll(cx).is_pending() {
+            return Poll::Pending;
+        }
+
+        self.registration.cancel();
+
+        Poll::Ready(Err(VoiceConnectionError {
+            kind: VoiceConnectionErrorType::TimedOut {
+                timeout: self.timeout,
+            },
+            source: None,
+        }))
+    }
+}
+
+/// Builder to configure and construct a [`VoiceConnector`].
+#[derive(Debug)]
+#[must_use = "has no effect if not built"]
+pub struct VoiceConnectorBuilder {
+    timeout: Duration,
+    user_id: UserId,
+}
+
+impl VoiceConnectorBuilder {
+    /// Create a new builder to configure and construct a voice connector for
+    /// the bot with the given user ID.
+    pub const fn new(user_id: UserId) -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            user_id,
+        }
+    }
+
+    /// Consume the builder, constructing a voice connector.
+    pub fn build(self) -> VoiceConnector {
+        VoiceConnector(Arc::new(VoiceConnectorRef {
+            connections: Mutex::new(HashMap::new()),
+            pending: Arc::new(PendingRequests::new()),
+            timeout: self.timeout,
+            user_id: self.user_id,
+        }))
+    }
+
+    /// Set the time to wait for both the voice state and voice server updates
+    /// after joining a channel.
+    ///
+    /// Defaults to 10 seconds.
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+
+        self
+    }
+}
+
+/// Join, move between, and leave voice channels, collecting the information
+/// to connect to voice.
+///
+/// Joins complete once the bot's voice state and voice server updates are
+/// [processed], so events received by the shards channels are joined over
+/// must be given to the connector. Clones of the connector track the same
+/// joins and connections, so one can be moved to the task receiving events.
+///
+/// # Examples
+///
+/// Join a voice channel:
+///
+/// ```no_run
+/// use futures::StreamExt;
+/// use std::env;
+/// use twilight_gateway::{voice::VoiceConnector, Intents, Shard};
+/// use twilight_model::{
+///     gateway::payload::UpdateVoiceState,
+///     id::{ChannelId, GuildId, UserId},
+/// };
+///
+/// # #[tokio::main] async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let token = env::var("DISCORD_TOKEN")?;
+/// let (shard, mut events) = Shard::new(token, Intents::GUILD_VOICE_STATES);
+/// shard.start().await?;
+///
+/// let connector = VoiceConnector::new(UserId(1));
+///
+/// let event_connector = connector.clone();
+/// let event_shard = shard.clone();
+/// tokio::spawn(async move {
+///     while let Some(event) = events.next().await {
+///         event_connector.process(&event_shard, &event);
+///     }
+/// });
+///
+/// let request = UpdateVoiceState::new(GuildId(1), ChannelId(2), true, false);
+/// let info = connector.join(&shard, request).await?.await?;
+///
+/// println!("connect to {} with session {}", info.endpoint, info.session_id);
+/// # Ok(()) }
+/// ```
+///
+/// [processed]: Self::process
+#[derive(Clone, Debug)]
+pub struct VoiceConnector(Arc<VoiceConnectorRef>);
+
+#[derive(Debug)]
+struct VoiceConnectorRef {
+    /// Information of the guilds the bot is connected to voice in.
+    connections: Mutex<HashMap<GuildId, VoiceConnectionInfo>>,
+    pending: Arc<PendingRequests<GuildId, Join, Result<VoiceConnectionInfo, VoiceConnectionError>>>,
+    timeout: Duration,
+    user_id: UserId,
+}
+
+/// Voice state and voice server updates of a join received so far.
+#[derive(Debug)]
+struct Join {
+    /// Endpoint and token of the voice server, if received.
+    server: Option<(String, String)>,
+    /// Channel and voice session ID of the bot, if received.
+    state: Option<(ChannelId, String)>,
+}
+
+impl Join {
+    /// Information to connect to voice, if both updates have been received.
+    fn info(&self, guild_id: GuildId) -> Option<VoiceConnectionInfo> {
+        let (endpoint, token) = self.server.clone()?;
+        let (channel_id, session_id) = self.state.clone()?;
+
+        Some(VoiceConnectionInfo {
+            channel_id,
+            endpoint,
+            guild_id,
+            session_id,
+            token,
+        })
+    }
+}
+
+impl VoiceConnector {
+    /// Create a new voice connector with the default configuration for the
+    /// bot with the given user ID.
+    pub fn new(user_id: UserId) -> Self {
+        Self::builder(user_id).build()
+    }
+
+    /// Create a new builder to configure and construct a voice connector for
+    /// the bot with the given user ID.
+    pub const fn builder(user_id: UserId) -> VoiceConnectorBuilder {
+        VoiceConnectorBuilder::new(user_id)
+    }
+
+    /// Information to connect to the voice server of a guild, if the bot is
+    /// connected to voice in it.
+    pub fn connection(&self, guild_id: GuildId) -> Option<VoiceConnectionInfo> {
+        self.0.connections().get(&guild_id).cloned()
+    }
+
+    /// Send a request to join or move to a voice channel over a shard,
+    /// returning a future resolving once both the voice state and voice
+    /// server updates have been received.
+    ///
+    /// Joins still pending for the guild fail with a
+    /// [`VoiceConnectionErrorType::Superseded`] error type. A request without
+    /// a channel fails with a [`VoiceConnectionErrorType::Disconnected`]
+    /// error type once Discord acknowledges it; use [`leave`] to leave a
+    /// channel instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CommandError`] if sending the request failed.
+    ///
+    /// [`leave`]: Self::leave
+    pub async fn join(
+        &self,
+        shard: &Shard,
+        request: UpdateVoiceState,
+    ) -> Result<VoiceConnectionFuture, CommandError> {
+        let session_id = pending::session_id(shard);
+        let future = self.register(shard.config().shard()[0], session_id, request.d.guild_id);
+
+        // Dropping the future removes the join if sending it failed, unless
+        // another join for the guild has superseded it since.
+        shard.command(&request).await?;
+
+        Ok(future)
+    }
+
+    /// Send a request to leave the voice channel of a guild over a shard.
+    ///
+    /// Joins still pending for the guild fail with a
+    /// [`VoiceConnectionErrorType::Superseded`] error type.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`CommandError`] if sending the request failed.
+    pub async fn leave(&self, shard: &Shard, guild_id: GuildId) -> Result<(), CommandError> {
+        let join = self.0.pending.lock().remove(&guild_id);
+
+        if let Some(join) = join {
+            join.resolve(Err(VoiceConnectionError {
+                kind: VoiceConnectionErrorType::Superseded,
+                source: None,
+            }));
+        }
+
+        let request = UpdateVoiceState::new(guild_id, None, false, false);
+
+        shard.command(&request).await
+    }
+
+    /// Process an event received by a shard.
+    ///
+    /// The bot's voice state updates and voice server updates are collected
+    /// into their joins and update the [connections], and a [`Ready`] event
+    /// fails the joins made during the shard's previous session.
+    ///
+    /// [`Ready`]: twilight_model::gateway::payload::Ready
+    /// [connections]: Self::connection
+    pub fn process(&self, shard: &Shard, event: &Event) {
+        match event {
+            Event::Ready(ready) => self.invalidate(shard.config().shard()[0], &ready.session_id),
+            Event::VoiceServerUpdate(update) => self.server_update(update),
+            Event::VoiceStateUpdate(update) if update.0.user_id == self.0.user_id => {
+                self.state_update(&update.0);
+            }
+            _ => {}
+        }
+    }
+
+    /// Register a new join, failing the join still pending for the guild.
+    fn register(
+        &self,
+        shard_id: u64,
+        session_id: Option<String>,
+        guild_id: GuildId,
+    ) -> VoiceConnectionFuture {
+        let join = Join {
+            server: None,
+            state: None,
+        };
+        let (registration, previous) = self
+            .0
+            .pending
+            .register(shard_id, session_id, guild_id, join);
+
+        if let Some(join) = previous {
+            join.resolve(Err(VoiceConnectionError {
+                kind: VoiceConnectionErrorType::Superseded,
+                source: None,
+            }));
+        }
+
+        VoiceConnectionFuture {
+            registration,
+            timeout: self.0.timeout,
+            sleep: Box::pin(time::sleep(self.0.timeout)),
+        }
+    }
+
+    /// Collect the bot's voice state into its guild's join, and update the
+    /// guild's connection.
+    fn state_update(&self, state: &VoiceState) {
+        let guild_id = match state.guild_id {
+            Some(guild_id) => guild_id,
+            None => return,
+        };
+
+        let channel_id = match state.channel_id {
+            Some(channel_id) => channel_id,
+            None => {
+                self.0.connections().remove(&guild_id);
+
+                let join = self.0.pending.lock().remove(&guild_id);
+
+                if let Some(join) = join {
+                    join.resolve(Err(VoiceConnectionError {
+                        kind: VoiceConnectionErrorType::Disconnected,
+                        source: None,
+                    }));
+                }
+
+                return;
+            }
+        };
+
+        if let Some(connection) = self.0.connections().get_mut(&guild_id) {
+            connection.channel_id = channel_id;
+            connection.session_id.clone_from(&state.session_id);
+        }
+
+        if let Some(join) = self.0.pending.lock().get_mut(&guild_id) {
+            join.state.state = Some((channel_id, state.session_id.clone()));
+        }
+
+        self.complete(guild_id);
+    }
+
+    /// Collect the voice server into its guild's join, and update the guild's
+    /// connection.
+    fn server_update(&self, update: &VoiceServerUpdate) {
+        let guild_id = match update.guild_id {
+            Some(guild_id) => guild_id,
+            None => return,
+        };
+
+        // The voice server is unavailable until an update with an endpoint
+        // is sent.
+        let endpoint = match update.endpoint.as_ref() {
+            Some(endpoint) => endpoint,
+            None => return,
+        };
+
+        if let Some(connection) = self.0.connections().get_mut(&guild_id) {
+            connection.endpoint.clone_from(endpoint);
+            connection.token.clone_from(&update.token);
+        }
+
+        if let Some(join) = self.0.pending.lock().get_mut(&guild_id) {
+            join.state.server = Some((endpoint.clone(), update.token.clone()));
+        }
+
+        self.complete(guild_id);
+    }
+
+    /// Complete the join of a guild if both its voice state and voice server
+    /// updates have been received.
+    fn complete(&self, guild_id: GuildId) {
+        let mut pending = self.0.pending.lock();
+
+        let info = match pending
+            .get(&guild_id)
+            .and_then(|join| join.state.info(guild_id))
+        {
+            Some(info) => info,
+            None => return,
+        };
+
+        if let Some(join) = pending.remove(&guild_id) {
+            tracing::debug!(
+                channel_id = %info.channel_id,
+                guild_id = %info.guild_id,
+                "joined voice channel",
+            );
+
+            self.0.connections().insert(guild_id, info.clone());
+
+            join.resolve(Ok(info));
+        }
+    }
+
+    /// Fail the joins sent over a shard during a session other than the
+    /// current one.
+    fn invalidate(&self, shard_id: u64, session_id: &str) {
+        for join in self.0.pending.invalidate(shard_id, session_id) {
+            join.resolve(Err(VoiceConnectionError {
+                kind: VoiceConnectionErrorType::SessionInvalidated,
+                source: None,
+            }));
+        }
+    }
+}
+
+impl VoiceConnectorRef {
+    fn connections(&self) -> MutexGuard<'_, HashMap<GuildId, VoiceConnectionInfo>> {
+        self.connections.lock().expect("connections poisoned")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        VoiceConnectionError, VoiceConnectionErrorType, VoiceConnectionFuture, VoiceConnectionInfo,
+        VoiceConnector, VoiceConnectorBuilder,
+    };
+    use crate::{shard::Shard, Intents};
+    use static_assertions::assert_impl_all;
+    use std::{error::Error, fmt::Debug, future::Future, time::Duration};
+    use twilight_model::{
+        gateway::{
+            event::Event,
+            payload::{VoiceServerUpdate, VoiceStateUpdate},
+        },
+        id::{ChannelId, GuildId, UserId},
+        voice::VoiceState,
+    };
+
+    assert_impl_all!(VoiceConnectionError: Error, Send, Sync);
+    assert_impl_all!(VoiceConnectionErrorType: Debug, Send, Sync);
+    assert_impl_all!(VoiceConnectionFuture: Debug, Future, Send, Sync);
+    assert_impl_all!(VoiceConnectionInfo: Clone, Debug, Eq, PartialEq, Send, Sync);
+    assert_impl_all!(VoiceConnector: Clone, Debug, Send, Sync);
+    assert_impl_all!(VoiceConnectorBuilder: Debug, Send, Sync);
+
+    fn server_update(guild_id: u64) -> Event {
+        Event::VoiceServerUpdate(VoiceServerUpdate {
+            channel_id: None,
+            endpoint: Some("voice.discord.media".to_owned()),
+            guild_id: Some(GuildId(guild_id)),
+            token: "token".to_owned(),
+        })
+    }
+
+    fn state_update(user_id: u64, channel_id: Option<u64>) -> Event {
+        Event::VoiceStateUpdate(Box::new(VoiceStateUpdate(VoiceState {
+            channel_id: channel_id.map(ChannelId),
+            deaf: false,
+            guild_id: Some(GuildId(1)),
+            member: None,
+            mute: false,
+            self_deaf: false,
+            self_mute: false,
+            self_stream: false,
+            session_id: "session".to_owned(),
+            suppress: false,
+            token: None,
+            user_id: UserId(user_id),
+            request_to_speak_timestamp: None,
+        })))
+    }
+
+    #[tokio::test]
+    async fn test_join() {
+        let (shard, _) = Shard::new("token", Intents::empty());
+        let connector = VoiceConnector::new(UserId(1));
+        let future = connector.register(0, None, GuildId(1));
+
+        // The updates may arrive in either order, and voice states of other
+        // users and voice servers of other guilds are ignored.
+        connector.process(&shard, &server_update(1));
+        connector.process(&shard, &state_update(2, Some(3)));
+        connector.process(&shard, &server_update(2));
+        connector.process(&shard, &state_update(1, Some(2)));
+
+        let expected = VoiceConnectionInfo {
+            channel_id: ChannelId(2),
+            endpoint: "voice.discord.media".to_owned(),
+            guild_id: GuildId(1),
+            session_id: "session".to_owned(),
+            token: "token".to_owned(),
+        };
+        assert_eq!(expected, future.await.unwrap());
+        assert_eq!(Some(expected), connector.connection(GuildId(1)));
+        assert!(connector.0.pending.lock().is_empty());
+
+        // Moves are tracked.
+        connector.process(&shard, &state_update(1, Some(4)));
+        assert_eq!(
+            Some(ChannelId(4)),
+            connector.connection(GuildId(1)).map(|info| info.channel_id)
+        );
+
+        connector.process(&shard, &state_update(1, None));
+        assert!(connector.connection(GuildId(1)).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_join_failed() {
+        let (shard, _) = Shard::new("token", Intents::empty());
+        let connector = VoiceConnector::new(UserId(1));
+        let superseded = connector.register(0, None, GuildId(1));
+        let disconnected = connector.register(0, None, GuildId(1));
+
+        connector.process(&shard, &state_update(1, None));
+
+        assert!(matches!(
+            superseded.await.unwrap_err().kind(),
+            VoiceConnectionErrorType::Superseded
+        ));
+        assert!(matches!(
+            disconnected.await.unwrap_err().kind(),
+            VoiceConnectionErrorType::Disconnected
+        ));
+
+        let invalidated = connector.register(0, Some("a".to_owned()), GuildId(1));
+        connector.invalidate(0, "b");
+        assert!(matches!(
+            invalidated.await.unwrap_err().kind(),
+            VoiceConnectionErrorType::SessionInvalidated
+        ));
+
+        let cancelled = connector.register(0, None, GuildId(1));
+        drop(connector);
+        assert!(matches!(
+            cancelled.await.unwrap_err().kind(),
+            VoiceConnectionErrorType::Cancelled
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let connector = VoiceConnector::builder(UserId(1))
+            .timeout(Duration::from_millis(10))
+            .build();
+        let mut future = connector.register(0, None, GuildId(1));
+
+        assert!(matches!(
+            (&mut future).await.unwrap_err().kind(),
+            VoiceConnectionErrorType::TimedOut { .. }
+        ));
+        assert!(connector.0.pending.lock().is_empty());
+    }
+}
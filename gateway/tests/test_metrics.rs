@@ -0,0 +1,124 @@
+#![cfg(feature = "metrics")]
+
+use futures::{future, stream::StreamExt};
+use metrics::{GaugeValue, Key, Recorder, Unit};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time;
+use twilight_gateway::{queue::Queue, Event, Intents, Shard};
+use twilight_gateway_mock::{Command, MockGateway};
+
+/// Queue letting shards identify immediately.
+#[derive(Debug)]
+struct NoopQueue;
+
+impl Queue for NoopQueue {
+    fn request<'a>(&'a self, _: [u64; 2]) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+        Box::pin(future::ready(()))
+    }
+}
+
+/// Recorder keeping the keys of the recorded metrics.
+#[derive(Clone, Default)]
+struct KeyRecorder(Arc<Mutex<Vec<String>>>);
+
+impl KeyRecorder {
+    fn contains(&self, name: &str, label: &str) -> bool {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|key| key.starts_with(&format!("KeyData({},", name)) && key.contains(label))
+    }
+
+    fn push(&self, key: &Key) {
+        self.0.lock().unwrap().push(key.to_string());
+    }
+}
+
+impl Recorder for KeyRecorder {
+    fn register_counter(&self, _: Key, _: Option<Unit>, _: Option<&'static str>) {}
+
+    fn register_gauge(&self, _: Key, _: Option<Unit>, _: Option<&'static str>) {}
+
+    fn register_histogram(&self, _: Key, _: Option<Unit>, _: Option<&'static str>) {}
+
+    fn increment_counter(&self, key: Key, _: u64) {
+        self.push(&key);
+    }
+
+    fn update_gauge(&self, key: Key, _: GaugeValue) {
+        self.push(&key);
+    }
+
+    fn record_histogram(&self, key: Key, _: f64) {
+        self.push(&key);
+    }
+}
+
+#[tokio::test]
+async fn test_metrics() {
+    let recorder = KeyRecorder::default();
+    metrics::set_boxed_recorder(Box::new(recorder.clone())).unwrap();
+
+    let mut gateway = MockGateway::bind().await.unwrap();
+    let (shard, mut events) = Shard::builder("token", Intents::GUILDS)
+        .gateway_url(Some(gateway.url()))
+        .queue(Arc::new(Box::new(NoopQueue)))
+        .shard(3, 4)
+        .unwrap()
+        .build();
+    shard.start().await.unwrap();
+
+    let mut connection = time::timeout(Duration::from_secs(10), gateway.next_connection())
+        .await
+        .unwrap()
+        .unwrap();
+    // Heartbeat quickly to have a heartbeat acknowledged.
+    connection.hello(100).unwrap();
+    assert!(matches!(
+        time::timeout(Duration::from_secs(10), connection.next_command())
+            .await
+            .unwrap(),
+        Some(Command::Identify(_))
+    ));
+    connection.ready("session", Vec::new()).unwrap();
+
+    let ack = async {
+        while let Some(event) = events.next().await {
+            if matches!(event, Event::GatewayHeartbeatAck) {
+                break;
+            }
+        }
+    };
+    time::timeout(Duration::from_secs(10), ack).await.unwrap();
+
+    connection.close(4000, "unknown error");
+    let mut connection = time::timeout(Duration::from_secs(10), gateway.next_connection())
+        .await
+        .unwrap()
+        .unwrap();
+    // The resume is recorded once the new connection is established, before
+    // the shard sends the resume command.
+    connection.hello(41_250).unwrap();
+    assert!(matches!(
+        time::timeout(Duration::from_secs(10), connection.next_command())
+            .await
+            .unwrap(),
+        Some(Command::Resume(_))
+    ));
+
+    assert!(recorder.contains("GatewayDispatch", "event_type = READY"));
+    assert!(recorder.contains("GatewayDispatch", "shard = 3"));
+    assert!(recorder.contains("GatewayHeartbeatLatency", "shard = 3"));
+    assert!(recorder.contains("GatewayResumes", "reason = Close4000"));
+    assert!(recorder.contains("GatewayEventQueueDepth", "shard = 3"));
+    assert!(recorder.contains("GatewayBytesIn", "shard = 3"));
+    assert!(recorder.contains("GatewayBytesOut", "shard = 3"));
+
+    shard.shutdown();
+}
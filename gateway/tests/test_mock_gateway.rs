@@ -0,0 +1,816 @@
+use futures::{
+    future,
+    stream::{Stream, StreamExt},
+};
+use std::{
+    collections::HashMap,
+    future::Future,
+    io::{Result as IoResult, Write},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tokio::time;
+use twilight_gateway::{
+    chunk::MemberChunker,
+    cluster::{Cluster, ResumeState, ShardScheme},
+    queue::Queue,
+    shard::{
+        recording::{Direction, Recorder, Replayer},
+        Events, HealthPolicy, OverflowPolicy, Proxy, ProxyError, ProxyErrorType, ResumeSession,
+        SessionStart, Shard, ShardBuilder, ShardStartErrorType, Stage,
+    },
+    Event, EventTypeFlags, Intents,
+};
+use twilight_gateway_mock::{Command, Connection, MockGateway, MockProxy, TunnelKind};
+use twilight_model::{
+    gateway::{
+        event::shard::UnhealthyReason,
+        payload::{identify::IdentifyProperties, update_presence::UpdatePresencePayload},
+        presence::{ActivityType, MinimalActivity, Status},
+        OpCode,
+    },
+    id::GuildId,
+};
+
+/// Queue letting shards identify immediately.
+#[derive(Debug)]
+struct NoopQueue;
+
+impl Queue for NoopQueue {
+    fn request<'a>(&'a self, _: [u64; 2]) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+        Box::pin(future::ready(()))
+    }
+}
+
+async fn shard(gateway: &mut MockGateway) -> (Shard, Events, Connection) {
+    start(gateway, builder(gateway)).await
+}
+
+fn builder(gateway: &MockGateway) -> ShardBuilder {
+    Shard::builder("token", Intents::GUILDS)
+        .gateway_url(Some(gateway.url()))
+        .queue(Arc::new(Box::new(NoopQueue)))
+}
+
+async fn start(gateway: &mut MockGateway, builder: ShardBuilder) -> (Shard, Events, Connection) {
+    let (shard, events) = builder.build();
+    shard.start().await.unwrap();
+
+    let connection = next_connection(gateway).await;
+
+    (shard, events, connection)
+}
+
+async fn next_connection(gateway: &mut MockGateway) -> Connection {
+    time::timeout(Duration::from_secs(10), gateway.next_connection())
+        .await
+        .expect("shard didn't connect in time")
+        .expect("gateway stopped")
+}
+
+async fn next_command(connection: &mut Connection) -> Command {
+    time::timeout(Duration::from_secs(10), connection.next_command())
+        .await
+        .expect("shard didn't send a command in time")
+        .expect("connection closed")
+}
+
+/// Wait for an event matching the predicate, skipping the others.
+async fn wait_for(
+    events: &mut (impl Stream<Item = Event> + Unpin),
+    predicate: impl Fn(&Event) -> bool,
+) -> Event {
+    let event = async {
+        while let Some(event) = events.next().await {
+            if predicate(&event) {
+                return event;
+            }
+        }
+
+        panic!("event stream ended");
+    };
+
+    time::timeout(Duration::from_secs(10), event)
+        .await
+        .expect("event wasn't received in time")
+}
+
+/// Send a hello, wait for the shard to identify, and start a session.
+async fn identify(connection: &mut Connection, events: &mut (impl Stream<Item = Event> + Unpin)) {
+    connection.hello(41_250).unwrap();
+
+    match next_command(connection).await {
+        Command::Identify(identify) => {
+            assert_eq!(Intents::GUILDS, identify.intents);
+            assert_eq!(Some([0, 1]), identify.shard);
+            assert_eq!("Bot token", identify.token);
+        }
+        other => panic!("expected identify, got {:?}", other),
+    }
+
+    connection.ready("session", Vec::new()).unwrap();
+    wait_for(events, |event| matches!(event, Event::Ready(_))).await;
+}
+
+#[tokio::test]
+async fn test_identify() {
+    let mut gateway = MockGateway::bind().await.unwrap();
+    let (shard, mut events, mut connection) = shard(&mut gateway).await;
+
+    assert!(connection.query().starts_with("v=8&encoding=json"));
+    assert_eq!(cfg!(feature = "compression"), connection.is_compressed());
+
+    identify(&mut connection, &mut events).await;
+    wait_for(
+        &mut events,
+        |event| matches!(event, Event::ShardFullyReady(ready) if ready.guilds_expected == 0),
+    )
+    .await;
+
+    let info = shard.info().unwrap();
+    assert_eq!(Some("session"), info.session_id());
+    assert_eq!(1, info.seq());
+
+    connection
+        .dispatch("RESUMED", &serde_json::json!({}))
+        .unwrap();
+    wait_for(&mut events, |event| matches!(event, Event::Resumed)).await;
+    assert_eq!(2, shard.info().unwrap().seq());
+
+    shard.shutdown();
+}
+
+#[tokio::test]
+async fn test_identify_properties_and_presence() {
+    let identifies = Arc::new(AtomicU64::new(0));
+    let counter = Arc::clone(&identifies);
+
+    let mut gateway = MockGateway::bind().await.unwrap();
+    let builder = builder(&gateway)
+        .identify_properties(IdentifyProperties::new(
+            "Discord iOS",
+            "twilight.rs",
+            "ios",
+            "",
+            "",
+        ))
+        .shard_presence(move |shard_id| {
+            let status = if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                Status::Online
+            } else {
+                Status::Idle
+            };
+            let activity = MinimalActivity {
+                kind: ActivityType::Playing,
+                name: format!("on shard {}", shard_id),
+                url: None,
+            };
+
+            UpdatePresencePayload::new(vec![activity.into()], false, None, status).ok()
+        });
+    let (shard, mut events, mut connection) = start(&mut gateway, builder).await;
+    connection.hello(41_250).unwrap();
+
+    match next_command(&mut connection).await {
+        Command::Identify(identify) => {
+            assert_eq!("Discord iOS", identify.properties.browser);
+            assert_eq!("ios", identify.properties.os);
+
+            let presence = identify.presence.expect("no presence");
+            assert_eq!(Status::Online, presence.status);
+            assert_eq!("on shard 0", presence.activities[0].name);
+        }
+        other => panic!("expected identify, got {:?}", other),
+    }
+
+    connection.ready("session", Vec::new()).unwrap();
+    wait_for(&mut events, |event| matches!(event, Event::Ready(_))).await;
+
+    // A new session gets a new presence.
+    connection.invalid_session(false).unwrap();
+    let mut connection = next_connection(&mut gateway).await;
+    connection.hello(41_250).unwrap();
+
+    match next_command(&mut connection).await {
+        Command::Identify(identify) => {
+            assert_eq!("Discord iOS", identify.properties.browser);
+            assert_eq!(Status::Idle, identify.presence.expect("no presence").status);
+        }
+        other => panic!("expected identify, got {:?}", other),
+    }
+    assert_eq!(2, identifies.load(Ordering::SeqCst));
+
+    shard.shutdown();
+}
+
+#[tokio::test]
+async fn test_http_proxy() {
+    let mut gateway = MockGateway::bind().await.unwrap();
+    let mut proxy = MockProxy::bind().await.unwrap();
+    let builder = builder(&gateway).proxy(Proxy::http(proxy.address()));
+    let (shard, mut events, mut connection) = start(&mut gateway, builder).await;
+
+    let tunnel = proxy.next_tunnel().await.unwrap();
+    assert_eq!(TunnelKind::Http, tunnel.kind());
+    assert_eq!(gateway.addr().to_string(), tunnel.target());
+    assert_eq!(None, tunnel.username());
+
+    identify(&mut connection, &mut events).await;
+
+    shard.shutdown();
+}
+
+#[tokio::test]
+async fn test_socks5_proxy_auth() {
+    let mut gateway = MockGateway::bind().await.unwrap();
+    let mut proxy = MockProxy::bind_with_auth("user", "hunter2").await.unwrap();
+    let builder = builder(&gateway).proxy(Proxy::socks5(proxy.address()).auth("user", "hunter2"));
+    let (shard, mut events, mut connection) = start(&mut gateway, builder).await;
+
+    let tunnel = proxy.next_tunnel().await.unwrap();
+    assert_eq!(TunnelKind::Socks5, tunnel.kind());
+    assert_eq!(gateway.addr().to_string(), tunnel.target());
+    assert_eq!(Some("user"), tunnel.username());
+
+    identify(&mut connection, &mut events).await;
+
+    shard.shutdown();
+}
+
+#[tokio::test]
+async fn test_proxy_auth_rejected() {
+    let gateway = MockGateway::bind().await.unwrap();
+    let proxy = MockProxy::bind_with_auth("user", "hunter2").await.unwrap();
+
+    for proxy in &[
+        Proxy::http(proxy.address()).auth("user", "wrong"),
+        Proxy::socks5(proxy.address()).auth("user", "wrong"),
+    ] {
+        let (shard, _) = builder(&gateway).proxy(proxy.clone()).build();
+        let error = shard.start().await.unwrap_err();
+        assert!(matches!(error.kind(), ShardStartErrorType::Establishing));
+
+        let source = error
+            .into_source()
+            .unwrap()
+            .downcast::<ProxyError>()
+            .unwrap();
+        assert!(matches!(
+            source.kind(),
+            ProxyErrorType::AuthenticationFailed
+        ));
+    }
+}
+
+#[tokio::test]
+async fn test_resume_after_close() {
+    let mut gateway = MockGateway::bind().await.unwrap();
+    let (shard, mut events, mut connection) = shard(&mut gateway).await;
+    identify(&mut connection, &mut events).await;
+    connection.resumed().unwrap();
+    wait_for(&mut events, |event| matches!(event, Event::Resumed)).await;
+
+    connection.close(4000, "unknown error");
+    wait_for(&mut events, |event| {
+        matches!(event, Event::ShardDisconnected(disconnected) if disconnected.code == Some(4000))
+    })
+    .await;
+
+    let mut connection = next_connection(&mut gateway).await;
+    connection.hello(41_250).unwrap();
+
+    match next_command(&mut connection).await {
+        Command::Resume(resume) => {
+            assert_eq!(2, resume.seq);
+            assert_eq!("session", resume.session_id);
+        }
+        other => panic!("expected resume, got {:?}", other),
+    }
+
+    assert_eq!(3, connection.resumed().unwrap());
+    wait_for(&mut events, |event| matches!(event, Event::Resumed)).await;
+
+    shard.shutdown();
+}
+
+#[tokio::test]
+async fn test_reconnect_request() {
+    let mut gateway = MockGateway::bind().await.unwrap();
+    let (shard, mut events, mut connection) = shard(&mut gateway).await;
+    identify(&mut connection, &mut events).await;
+
+    connection.reconnect().unwrap();
+    wait_for(&mut events, |event| {
+        matches!(event, Event::ShardResuming(_))
+    })
+    .await;
+
+    let mut connection = next_connection(&mut gateway).await;
+    connection.hello(41_250).unwrap();
+
+    assert!(matches!(
+        next_command(&mut connection).await,
+        Command::Resume(resume) if resume.seq == 1
+    ));
+
+    shard.shutdown();
+}
+
+#[tokio::test]
+async fn test_invalid_session() {
+    let mut gateway = MockGateway::bind().await.unwrap();
+    let (shard, mut events, mut connection) = shard(&mut gateway).await;
+    identify(&mut connection, &mut events).await;
+
+    connection.invalid_session(false).unwrap();
+    wait_for(&mut events, |event| {
+        matches!(event, Event::ShardReconnecting(_))
+    })
+    .await;
+
+    let mut connection = next_connection(&mut gateway).await;
+    identify(&mut connection, &mut events).await;
+
+    shard.shutdown();
+}
+
+#[tokio::test]
+async fn test_heartbeat() {
+    let mut gateway = MockGateway::bind().await.unwrap();
+    let (shard, mut events, mut connection) = shard(&mut gateway).await;
+    identify(&mut connection, &mut events).await;
+
+    connection.set_heartbeat_ack(false);
+    connection.heartbeat().unwrap();
+
+    assert_eq!(
+        Command::Heartbeat(Some(1)),
+        next_command(&mut connection).await
+    );
+
+    shard.shutdown();
+}
+
+/// Wait for the shard to resume the session on a new connection.
+async fn expect_resume(gateway: &mut MockGateway, seq: u64) -> Connection {
+    let mut connection = next_connection(gateway).await;
+    connection.hello(41_250).unwrap();
+
+    match next_command(&mut connection).await {
+        Command::Resume(resume) => {
+            assert_eq!(seq, resume.seq);
+            assert_eq!("session", resume.session_id);
+        }
+        other => panic!("expected resume, got {:?}", other),
+    }
+
+    connection
+}
+
+#[tokio::test]
+async fn test_unhealthy_heartbeat_acks() {
+    let mut gateway = MockGateway::bind().await.unwrap();
+    let (shard, mut events, mut connection) = shard(&mut gateway).await;
+
+    connection.set_heartbeat_ack(false);
+    connection.hello(100).unwrap();
+
+    loop {
+        match next_command(&mut connection).await {
+            Command::Heartbeat(_) => {}
+            Command::Identify(_) => break,
+            other => panic!("expected identify, got {:?}", other),
+        }
+    }
+
+    connection.ready("session", Vec::new()).unwrap();
+
+    let event = wait_for(&mut events, |event| {
+        matches!(event, Event::ShardUnhealthy(_))
+    })
+    .await;
+    assert!(matches!(
+        event,
+        Event::ShardUnhealthy(unhealthy)
+            if unhealthy.reason == UnhealthyReason::HeartbeatAcksMissed
+    ));
+
+    let mut connection = expect_resume(&mut gateway, 1).await;
+    assert_eq!(2, connection.resumed().unwrap());
+    wait_for(&mut events, |event| matches!(event, Event::Resumed)).await;
+
+    let health = shard.health();
+    assert_eq!(Stage::Connected, health.stage());
+    assert_eq!(0, health.missed_heartbeat_acks());
+    assert_eq!(1, health.reconnects());
+    assert_eq!(
+        vec![SessionStart::Identify, SessionStart::Resume],
+        health.session_starts().iter().copied().collect::<Vec<_>>()
+    );
+
+    shard.shutdown();
+}
+
+#[tokio::test]
+async fn test_unhealthy_dispatch_timeout() {
+    let mut gateway = MockGateway::bind().await.unwrap();
+    let policy = HealthPolicy::new().dispatch_timeout(Some(Duration::from_millis(200)));
+    let builder = builder(&gateway).health_policy(policy);
+    let (shard, mut events, mut connection) = start(&mut gateway, builder).await;
+
+    connection.hello(41_250).unwrap();
+    assert!(matches!(
+        next_command(&mut connection).await,
+        Command::Identify(_)
+    ));
+    connection.ready("session", vec![GuildId(1)]).unwrap();
+    wait_for(&mut events, |event| matches!(event, Event::Ready(_))).await;
+
+    let health = shard.health();
+    assert_eq!(1, health.identifies());
+    assert!(health.since_last_dispatch().is_some());
+
+    let event = wait_for(&mut events, |event| {
+        matches!(event, Event::ShardUnhealthy(_))
+    })
+    .await;
+    assert!(matches!(
+        event,
+        Event::ShardUnhealthy(unhealthy)
+            if unhealthy.reason == UnhealthyReason::DispatchTimedOut
+    ));
+
+    expect_resume(&mut gateway, 1).await;
+
+    shard.shutdown();
+}
+
+/// Writer into a buffer that can be read while the recorder is in use.
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_recording() {
+    let buffer = Buffer::default();
+    let recorder = Recorder::new(buffer.clone()).unwrap();
+
+    let mut gateway = MockGateway::bind().await.unwrap();
+    let builder = builder(&gateway).recorder(recorder.clone());
+    let (shard, mut events, mut connection) = start(&mut gateway, builder).await;
+    identify(&mut connection, &mut events).await;
+    connection.resumed().unwrap();
+    wait_for(&mut events, |event| matches!(event, Event::Resumed)).await;
+    shard.shutdown();
+
+    recorder.flush().unwrap();
+    let recording = buffer.0.lock().unwrap().clone();
+
+    let payloads = Replayer::new(recording.as_slice())
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let directions = payloads
+        .iter()
+        .map(|payload| payload.direction())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            Direction::Inbound,
+            Direction::Outbound,
+            Direction::Inbound,
+            Direction::Inbound
+        ],
+        directions
+    );
+    assert!(payloads.iter().all(|payload| payload.shard_id() == 0));
+    assert!(String::from_utf8_lossy(payloads[1].bytes()).contains(r#""op":2"#));
+    assert!(!String::from_utf8_lossy(payloads[1].bytes()).contains("Bot token"));
+
+    let mut replayed = Replayer::new(recording.as_slice())
+        .unwrap()
+        .replay(EventTypeFlags::default())
+        .unwrap();
+    assert!(matches!(
+        replayed.next().await,
+        Some(Event::GatewayHello(41_250))
+    ));
+    assert!(matches!(replayed.next().await, Some(Event::Ready(_))));
+    assert!(matches!(replayed.next().await, Some(Event::Resumed)));
+    assert!(replayed.next().await.is_none());
+}
+
+/// Start a cluster of one shard resuming a saved session.
+async fn resuming_cluster(
+    gateway: &mut MockGateway,
+) -> (Cluster, impl Stream<Item = Event> + Unpin, Connection) {
+    let path = std::env::temp_dir().join(format!(
+        "twilight-gateway-mock-resume-{}.json",
+        std::process::id()
+    ));
+    let mut sessions = HashMap::new();
+    sessions.insert(
+        0,
+        ResumeSession {
+            session_id: "saved".to_owned(),
+            sequence: 10,
+        },
+    );
+    ResumeState::new(sessions).save(&path).unwrap();
+    let state = ResumeState::load(&path).unwrap();
+    ResumeState::remove(&path).unwrap();
+    assert!(!state.is_stale(Duration::from_secs(60)));
+
+    let (cluster, events) = Cluster::builder("token", Intents::GUILDS)
+        .gateway_url(Some(gateway.url()))
+        .queue(Arc::new(Box::new(NoopQueue)))
+        .resume_sessions(state.into_sessions())
+        .shard_scheme(ShardScheme::Range {
+            from: 0,
+            to: 0,
+            total: 1,
+        })
+        .build()
+        .await
+        .unwrap();
+    cluster.up().await;
+
+    let mut connection = next_connection(gateway).await;
+    connection.hello(41_250).unwrap();
+
+    match next_command(&mut connection).await {
+        Command::Resume(resume) => {
+            assert_eq!(10, resume.seq);
+            assert_eq!("saved", resume.session_id);
+        }
+        other => panic!("expected resume, got {:?}", other),
+    }
+
+    (
+        cluster,
+        Box::pin(events.map(|(_, event)| event)),
+        connection,
+    )
+}
+
+#[tokio::test]
+async fn test_saved_session_resumed() {
+    let mut gateway = MockGateway::bind().await.unwrap();
+    let (cluster, mut events, mut connection) = resuming_cluster(&mut gateway).await;
+
+    assert_eq!(11, connection.resumed().unwrap());
+    wait_for(&mut events, |event| matches!(event, Event::Resumed)).await;
+
+    let resume_sessions = cluster.down_resumable();
+    assert_eq!(
+        Some(&ResumeSession {
+            session_id: "saved".to_owned(),
+            sequence: 11,
+        }),
+        resume_sessions.get(&0)
+    );
+}
+
+#[tokio::test]
+async fn test_saved_session_invalid() {
+    let mut gateway = MockGateway::bind().await.unwrap();
+    let (cluster, mut events, connection) = resuming_cluster(&mut gateway).await;
+
+    connection.invalid_session(false).unwrap();
+    wait_for(&mut events, |event| {
+        matches!(event, Event::GatewayInvalidateSession(false))
+    })
+    .await;
+
+    let mut connection = next_connection(&mut gateway).await;
+    identify(&mut connection, &mut events).await;
+
+    cluster.down();
+}
+
+#[tokio::test]
+async fn test_saved_session_timed_out() {
+    let mut gateway = MockGateway::bind().await.unwrap();
+    let (cluster, mut events, connection) = resuming_cluster(&mut gateway).await;
+
+    connection.close(4009, "session timed out");
+    wait_for(&mut events, |event| {
+        matches!(event, Event::ShardDisconnected(disconnected) if disconnected.code == Some(4009))
+    })
+    .await;
+
+    // The shard must not try to resume the rejected session again.
+    let mut connection = next_connection(&mut gateway).await;
+    identify(&mut connection, &mut events).await;
+
+    cluster.down();
+}
+
+#[tokio::test]
+async fn test_cluster_reshard() {
+    let mut gateway = MockGateway::bind().await.unwrap();
+    let (cluster, events) = Cluster::builder("token", Intents::GUILDS)
+        .gateway_url(Some(gateway.url()))
+        .queue(Arc::new(Box::new(NoopQueue)))
+        .shard_scheme(ShardScheme::Range {
+            from: 0,
+            to: 0,
+            total: 1,
+        })
+        .build()
+        .await
+        .unwrap();
+    let mut events = Box::pin(events.map(|(_, event)| event));
+    cluster.up().await;
+
+    let mut old = next_connection(&mut gateway).await;
+    identify(&mut old, &mut events).await;
+
+    // Received by the old shard right before the swap, but only yielded
+    // after it.
+    old.dispatch(
+        "GUILD_ROLE_DELETE",
+        &serde_json::json!({ "guild_id": "1", "role_id": "2" }),
+    )
+    .unwrap();
+
+    while cluster.shard(0).unwrap().info().unwrap().seq() < 2 {
+        time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let reshard = tokio::spawn({
+        let cluster = cluster.clone();
+
+        async move {
+            cluster
+                .reshard(ShardScheme::Range {
+                    from: 0,
+                    to: 1,
+                    total: 2,
+                })
+                .await
+        }
+    });
+
+    let mut connections = Vec::new();
+
+    for _ in 0..2 {
+        let mut connection = next_connection(&mut gateway).await;
+        connection.hello(41_250).unwrap();
+
+        match next_command(&mut connection).await {
+            Command::Identify(identify) => {
+                let shard = identify.shard.unwrap();
+                assert_eq!(2, shard[1]);
+                connection
+                    .ready(&format!("session-{}", shard[0]), Vec::new())
+                    .unwrap();
+            }
+            other => panic!("expected identify, got {:?}", other),
+        }
+
+        connections.push(connection);
+    }
+
+    time::timeout(Duration::from_secs(10), reshard)
+        .await
+        .expect("cluster didn't reshard in time")
+        .unwrap()
+        .unwrap();
+    assert_eq!(2, cluster.shards().len());
+
+    let mut role_deleted = false;
+    let mut cluster_ready = false;
+
+    while !(role_deleted && cluster_ready) {
+        match wait_for(&mut events, |_| true).await {
+            Event::RoleDelete(_) => role_deleted = true,
+            Event::ClusterReady(ready) if ready.shards == [0, 1] => cluster_ready = true,
+            _ => {}
+        }
+    }
+
+    cluster.down();
+}
+
+#[tokio::test]
+async fn test_cluster_events_dropped() {
+    let mut gateway = MockGateway::bind().await.unwrap();
+    let (cluster, _events) = Cluster::builder("token", Intents::GUILDS)
+        .event_buffer(1, OverflowPolicy::DropNewest)
+        .gateway_url(Some(gateway.url()))
+        .queue(Arc::new(Box::new(NoopQueue)))
+        .shard_scheme(ShardScheme::Range {
+            from: 0,
+            to: 0,
+            total: 1,
+        })
+        .build()
+        .await
+        .unwrap();
+    cluster.up().await;
+    assert_eq!(0, cluster.events_dropped());
+
+    let mut connection = next_connection(&mut gateway).await;
+    connection.hello(41_250).unwrap();
+    assert!(matches!(
+        next_command(&mut connection).await,
+        Command::Identify(_)
+    ));
+    connection.ready("session", Vec::new()).unwrap();
+
+    // The stream isn't polled, so every event after the first is dropped.
+    time::timeout(Duration::from_secs(10), async {
+        while cluster.events_dropped() < 2 {
+            time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("events weren't dropped in time");
+
+    cluster.down();
+}
+
+/// Guild create event data of a large guild with the given number of members,
+/// none of which are sent.
+fn large_guild(id: u64, member_count: u64) -> serde_json::Value {
+    serde_json::json!({
+        "afk_channel_id": null,
+        "afk_timeout": 300,
+        "application_id": null,
+        "banner": null,
+        "default_message_notifications": 0,
+        "description": null,
+        "discovery_splash": null,
+        "emojis": [],
+        "explicit_content_filter": 0,
+        "features": [],
+        "icon": null,
+        "id": id.to_string(),
+        "large": true,
+        "member_count": member_count,
+        "mfa_level": 0,
+        "name": "guild",
+        "nsfw_level": 0,
+        "owner_id": "1",
+        "preferred_locale": "en-US",
+        "roles": [],
+        "rules_channel_id": null,
+        "splash": null,
+        "system_channel_flags": 0,
+        "system_channel_id": null,
+        "vanity_url_code": null,
+        "verification_level": 0,
+    })
+}
+
+#[tokio::test]
+async fn test_chunk_large_guilds() {
+    let mut gateway = MockGateway::bind().await.unwrap();
+    let (shard, mut events, mut connection) = shard(&mut gateway).await;
+    identify(&mut connection, &mut events).await;
+
+    let chunker = MemberChunker::builder().chunk_large_guilds(true).build();
+
+    // Every member of the first guild was sent, so only the second guild's
+    // members are requested.
+    connection
+        .dispatch("GUILD_CREATE", &large_guild(1, 0))
+        .unwrap();
+    connection
+        .dispatch("GUILD_CREATE", &large_guild(2, 500))
+        .unwrap();
+
+    for _ in 0..2 {
+        let event = wait_for(&mut events, |event| matches!(event, Event::GuildCreate(_))).await;
+        chunker.process(&shard, &event);
+    }
+
+    match next_command(&mut connection).await {
+        Command::Other { op, d } => {
+            assert_eq!(OpCode::RequestGuildMembers as u8, op);
+            assert_eq!("2", d["guild_id"]);
+        }
+        other => panic!("expected request guild members, got {:?}", other),
+    }
+
+    assert!(
+        time::timeout(Duration::from_millis(100), connection.next_command())
+            .await
+            .is_err(),
+        "members of a complete guild were requested",
+    );
+}
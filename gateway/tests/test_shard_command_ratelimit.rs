@@ -67,11 +67,15 @@ async fn test_shard_command_ratelimit() {
         Status::DoNotDisturb,
     )
     .unwrap();
+    let available = shard.ratelimit().unwrap().available();
     let now = Instant::now();
     shard.command(&payload).await.unwrap();
-    assert!(now.elapsed() < Duration::from_millis(500));
-    // check that the ~500ms ratelimit has passed
+    // commands can be sent in bursts without waiting
     shard.command(&payload).await.unwrap();
-    assert!(now.elapsed() > Duration::from_millis(500));
+    assert!(now.elapsed() < Duration::from_millis(500));
+
+    let ratelimit = shard.ratelimit().unwrap();
+    assert_eq!(available - 2, ratelimit.available());
+    assert_eq!(Duration::from_secs(0), ratelimit.next_available());
     shard.shutdown();
 }
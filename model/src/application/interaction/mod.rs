@@ -13,7 +13,7 @@ use crate::{
     application::interaction::message_component::MessageComponent,
     channel::Message,
     guild::PartialMember,
-    id::{ApplicationId, ChannelId, GuildId, InteractionId},
+    id::{ApplicationId, ChannelId, GuildId, InteractionId, UserId},
     user::User,
 };
 use serde::{
@@ -48,6 +48,38 @@ impl Interaction {
             Self::MessageComponent(inner) => inner.guild_id,
         }
     }
+
+    /// ID of the channel the interaction was triggered from.
+    ///
+    /// This is `None` for pings.
+    pub const fn channel_id(&self) -> Option<ChannelId> {
+        match self {
+            Self::Ping(_) => None,
+            Self::ApplicationCommand(inner) => Some(inner.channel_id),
+            Self::MessageComponent(inner) => Some(inner.channel_id),
+        }
+    }
+
+    /// ID of the user that triggered the interaction.
+    ///
+    /// This is the member's user when the interaction was triggered in a
+    /// guild, and the user otherwise.
+    pub fn author_id(&self) -> Option<UserId> {
+        match self {
+            Self::Ping(_) => None,
+            Self::ApplicationCommand(inner) => inner
+                .member
+                .as_ref()
+                .and_then(|member| member.user.as_ref())
+                .or(inner.user.as_ref())
+                .map(|user| user.id),
+            Self::MessageComponent(inner) => inner
+                .member
+                .as_ref()
+                .and_then(|member| member.user.as_ref())
+                .map(|user| user.id),
+        }
+    }
 }
 
 impl<'de> Deserialize<'de> for Interaction {
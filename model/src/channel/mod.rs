@@ -119,6 +119,28 @@ impl GuildChannel {
             Self::Stage(stage) => stage.name.as_ref(),
         }
     }
+
+    /// Return the ID of the category the inner guild channel is in, if any.
+    ///
+    /// Categories can't be nested, so this is always `None` for categories.
+    pub const fn parent_id(&self) -> Option<ChannelId> {
+        match self {
+            Self::Category(_) => None,
+            Self::Text(text) => text.parent_id,
+            Self::Voice(voice) => voice.parent_id,
+            Self::Stage(stage) => stage.parent_id,
+        }
+    }
+
+    /// Return the sorting position of the inner guild channel.
+    pub const fn position(&self) -> i64 {
+        match self {
+            Self::Category(category) => category.position,
+            Self::Text(text) => text.position,
+            Self::Voice(voice) => voice.position,
+            Self::Stage(stage) => stage.position,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -1,4 +1,7 @@
-use super::{super::payload::*, Event, EventConversionError, EventType};
+use super::{
+    super::payload::*, channel_guild_id, worker_index, Event, EventConversionError, EventType,
+};
+use crate::id::{ChannelId, GuildId, UserId};
 use serde::{
     de::{Deserialize, DeserializeSeed, Deserializer, Error as DeError, IgnoredAny},
     Serialize,
@@ -115,6 +118,185 @@ impl DispatchEvent {
             Self::WebhooksUpdate(_) => EventType::WebhooksUpdate,
         }
     }
+
+    /// ID of the guild the event is related to, if any.
+    pub fn guild_id(&self) -> Option<GuildId> {
+        match self {
+            Self::BanAdd(v) => Some(v.guild_id),
+            Self::BanRemove(v) => Some(v.guild_id),
+            Self::ChannelCreate(v) => channel_guild_id(&v.0),
+            Self::ChannelDelete(v) => channel_guild_id(&v.0),
+            Self::ChannelPinsUpdate(v) => v.guild_id,
+            Self::ChannelUpdate(v) => channel_guild_id(&v.0),
+            Self::GuildCreate(v) => Some(v.0.id),
+            Self::GuildDelete(v) => Some(v.id),
+            Self::GuildEmojisUpdate(v) => Some(v.guild_id),
+            Self::GuildIntegrationsUpdate(v) => Some(v.guild_id),
+            Self::GuildUpdate(v) => Some(v.0.id),
+            Self::IntegrationCreate(v) => v.0.guild_id,
+            Self::IntegrationDelete(v) => Some(v.guild_id),
+            Self::IntegrationUpdate(v) => v.0.guild_id,
+            Self::InteractionCreate(v) => v.0.guild_id(),
+            Self::InviteCreate(v) => Some(v.guild_id),
+            Self::InviteDelete(v) => Some(v.guild_id),
+            Self::MemberAdd(v) => Some(v.0.guild_id),
+            Self::MemberRemove(v) => Some(v.guild_id),
+            Self::MemberUpdate(v) => Some(v.guild_id),
+            Self::MemberChunk(v) => Some(v.guild_id),
+            Self::MessageCreate(v) => v.0.guild_id,
+            Self::MessageDelete(v) => v.guild_id,
+            Self::MessageDeleteBulk(v) => v.guild_id,
+            Self::MessageUpdate(v) => v.guild_id,
+            Self::PresenceUpdate(v) => Some(v.guild_id),
+            Self::ReactionAdd(v) => v.0.guild_id,
+            Self::ReactionRemove(v) => v.0.guild_id,
+            Self::ReactionRemoveAll(v) => v.guild_id,
+            Self::ReactionRemoveEmoji(v) => Some(v.guild_id),
+            Self::RoleCreate(v) => Some(v.guild_id),
+            Self::RoleDelete(v) => Some(v.guild_id),
+            Self::RoleUpdate(v) => Some(v.guild_id),
+            Self::StageInstanceCreate(v) => Some(v.0.guild_id),
+            Self::StageInstanceDelete(v) => Some(v.0.guild_id),
+            Self::StageInstanceUpdate(v) => Some(v.0.guild_id),
+            Self::TypingStart(v) => v.guild_id,
+            Self::UnavailableGuild(v) => Some(v.id),
+            Self::VoiceServerUpdate(v) => v.guild_id,
+            Self::VoiceStateUpdate(v) => v.0.guild_id,
+            Self::WebhooksUpdate(v) => Some(v.guild_id),
+            Self::GiftCodeUpdate
+            | Self::PresencesReplace
+            | Self::Ready(_)
+            | Self::Resumed
+            | Self::UserUpdate(_) => None,
+        }
+    }
+
+    /// ID of the channel the event is related to, if any.
+    pub fn channel_id(&self) -> Option<ChannelId> {
+        match self {
+            Self::ChannelCreate(v) => Some(v.0.id()),
+            Self::ChannelDelete(v) => Some(v.0.id()),
+            Self::ChannelPinsUpdate(v) => Some(v.channel_id),
+            Self::ChannelUpdate(v) => Some(v.0.id()),
+            Self::InteractionCreate(v) => v.0.channel_id(),
+            Self::InviteCreate(v) => Some(v.channel_id),
+            Self::InviteDelete(v) => Some(v.channel_id),
+            Self::MessageCreate(v) => Some(v.0.channel_id),
+            Self::MessageDelete(v) => Some(v.channel_id),
+            Self::MessageDeleteBulk(v) => Some(v.channel_id),
+            Self::MessageUpdate(v) => Some(v.channel_id),
+            Self::ReactionAdd(v) => Some(v.0.channel_id),
+            Self::ReactionRemove(v) => Some(v.0.channel_id),
+            Self::ReactionRemoveAll(v) => Some(v.channel_id),
+            Self::ReactionRemoveEmoji(v) => Some(v.channel_id),
+            Self::StageInstanceCreate(v) => Some(v.0.channel_id),
+            Self::StageInstanceDelete(v) => Some(v.0.channel_id),
+            Self::StageInstanceUpdate(v) => Some(v.0.channel_id),
+            Self::TypingStart(v) => Some(v.channel_id),
+            Self::VoiceServerUpdate(v) => v.channel_id,
+            Self::VoiceStateUpdate(v) => v.0.channel_id,
+            Self::WebhooksUpdate(v) => Some(v.channel_id),
+            Self::BanAdd(_)
+            | Self::BanRemove(_)
+            | Self::GiftCodeUpdate
+            | Self::GuildCreate(_)
+            | Self::GuildDelete(_)
+            | Self::GuildEmojisUpdate(_)
+            | Self::GuildIntegrationsUpdate(_)
+            | Self::GuildUpdate(_)
+            | Self::IntegrationCreate(_)
+            | Self::IntegrationDelete(_)
+            | Self::IntegrationUpdate(_)
+            | Self::MemberAdd(_)
+            | Self::MemberRemove(_)
+            | Self::MemberUpdate(_)
+            | Self::MemberChunk(_)
+            | Self::PresenceUpdate(_)
+            | Self::PresencesReplace
+            | Self::Ready(_)
+            | Self::Resumed
+            | Self::RoleCreate(_)
+            | Self::RoleDelete(_)
+            | Self::RoleUpdate(_)
+            | Self::UnavailableGuild(_)
+            | Self::UserUpdate(_) => None,
+        }
+    }
+
+    /// ID of the user the event is related to, if any.
+    ///
+    /// This is the user that performed the action, such as a message's author,
+    /// or the user the action was performed on, such as a banned user.
+    pub fn user_id(&self) -> Option<UserId> {
+        match self {
+            Self::BanAdd(v) => Some(v.user.id),
+            Self::BanRemove(v) => Some(v.user.id),
+            Self::IntegrationCreate(v) => v.0.user.as_ref().map(|user| user.id),
+            Self::IntegrationUpdate(v) => v.0.user.as_ref().map(|user| user.id),
+            Self::InteractionCreate(v) => v.0.author_id(),
+            Self::InviteCreate(v) => v.inviter.as_ref().map(|user| user.id),
+            Self::MemberAdd(v) => Some(v.0.user.id),
+            Self::MemberRemove(v) => Some(v.user.id),
+            Self::MemberUpdate(v) => Some(v.user.id),
+            Self::MessageCreate(v) => Some(v.0.author.id),
+            Self::MessageUpdate(v) => v.author.as_ref().map(|user| user.id),
+            Self::PresenceUpdate(v) => Some(v.user.id()),
+            Self::ReactionAdd(v) => Some(v.0.user_id),
+            Self::ReactionRemove(v) => Some(v.0.user_id),
+            Self::Ready(v) => Some(v.user.id),
+            Self::TypingStart(v) => Some(v.user_id),
+            Self::UserUpdate(v) => Some(v.0.id),
+            Self::VoiceStateUpdate(v) => Some(v.0.user_id),
+            Self::ChannelCreate(_)
+            | Self::ChannelDelete(_)
+            | Self::ChannelPinsUpdate(_)
+            | Self::ChannelUpdate(_)
+            | Self::GiftCodeUpdate
+            | Self::GuildCreate(_)
+            | Self::GuildDelete(_)
+            | Self::GuildEmojisUpdate(_)
+            | Self::GuildIntegrationsUpdate(_)
+            | Self::GuildUpdate(_)
+            | Self::IntegrationDelete(_)
+            | Self::InviteDelete(_)
+            | Self::MemberChunk(_)
+            | Self::MessageDelete(_)
+            | Self::MessageDeleteBulk(_)
+            | Self::PresencesReplace
+            | Self::ReactionRemoveAll(_)
+            | Self::ReactionRemoveEmoji(_)
+            | Self::Resumed
+            | Self::RoleCreate(_)
+            | Self::RoleDelete(_)
+            | Self::RoleUpdate(_)
+            | Self::StageInstanceCreate(_)
+            | Self::StageInstanceDelete(_)
+            | Self::StageInstanceUpdate(_)
+            | Self::UnavailableGuild(_)
+            | Self::VoiceServerUpdate(_)
+            | Self::WebhooksUpdate(_) => None,
+        }
+    }
+
+    /// Index of the worker to process the event with, out of a number of
+    /// workers.
+    ///
+    /// The event is routed by its guild ID, falling back to its channel ID and
+    /// then its user ID, so that all events of a guild are processed by the same
+    /// worker. The index is derived with a consistent hash: when the number of
+    /// workers changes only a minimal share of guilds move to another worker.
+    ///
+    /// Returns `None` if the event isn't related to a guild, channel, or user, or
+    /// if the number of workers is zero.
+    pub fn worker_index(&self, workers: u64) -> Option<u64> {
+        let key = self
+            .guild_id()
+            .map(|id| id.0)
+            .or_else(|| self.channel_id().map(|id| id.0))
+            .or_else(|| self.user_id().map(|id| id.0))?;
+
+        worker_index(key, workers)
+    }
 }
 
 impl TryFrom<Event> for DispatchEvent {
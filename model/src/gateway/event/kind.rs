@@ -13,6 +13,7 @@ pub enum EventType {
     ChannelDelete,
     ChannelPinsUpdate,
     ChannelUpdate,
+    ClusterReady,
     GatewayHeartbeat,
     GatewayHeartbeatAck,
     GatewayHello,
@@ -63,10 +64,12 @@ pub enum EventType {
     ShardConnected,
     ShardConnecting,
     ShardDisconnected,
+    ShardFullyReady,
     ShardIdentifying,
     ShardReconnecting,
     ShardPayload,
     ShardResuming,
+    ShardUnhealthy,
     StageInstanceCreate,
     StageInstanceDelete,
     StageInstanceUpdate,
@@ -127,7 +130,8 @@ impl EventType {
             Self::VoiceServerUpdate => Some("VOICE_SERVER_UPDATE"),
             Self::VoiceStateUpdate => Some("VOICE_STATE_UPDATE"),
             Self::WebhooksUpdate => Some("WEBHOOKS_UPDATE"),
-            Self::GatewayHeartbeat
+            Self::ClusterReady
+            | Self::GatewayHeartbeat
             | Self::GatewayHeartbeatAck
             | Self::GatewayHello
             | Self::GatewayInvalidateSession
@@ -135,10 +139,12 @@ impl EventType {
             | Self::ShardConnected
             | Self::ShardConnecting
             | Self::ShardDisconnected
+            | Self::ShardFullyReady
             | Self::ShardIdentifying
             | Self::ShardReconnecting
             | Self::ShardPayload
-            | Self::ShardResuming => None,
+            | Self::ShardResuming
+            | Self::ShardUnhealthy => None,
         }
     }
 }
@@ -222,6 +228,7 @@ mod tests {
         assert_variant(EventType::ChannelDelete, "CHANNEL_DELETE");
         assert_variant(EventType::ChannelPinsUpdate, "CHANNEL_PINS_UPDATE");
         assert_variant(EventType::ChannelUpdate, "CHANNEL_UPDATE");
+        assert_variant(EventType::ClusterReady, "CLUSTER_READY");
         assert_variant(EventType::GatewayHeartbeat, "GATEWAY_HEARTBEAT");
         assert_variant(EventType::GatewayHeartbeatAck, "GATEWAY_HEARTBEAT_ACK");
         assert_variant(EventType::GatewayHello, "GATEWAY_HELLO");
@@ -270,10 +277,12 @@ mod tests {
         assert_variant(EventType::ShardConnected, "SHARD_CONNECTED");
         assert_variant(EventType::ShardConnecting, "SHARD_CONNECTING");
         assert_variant(EventType::ShardDisconnected, "SHARD_DISCONNECTED");
+        assert_variant(EventType::ShardFullyReady, "SHARD_FULLY_READY");
         assert_variant(EventType::ShardIdentifying, "SHARD_IDENTIFYING");
         assert_variant(EventType::ShardPayload, "SHARD_PAYLOAD");
         assert_variant(EventType::ShardReconnecting, "SHARD_RECONNECTING");
         assert_variant(EventType::ShardResuming, "SHARD_RESUMING");
+        assert_variant(EventType::ShardUnhealthy, "SHARD_UNHEALTHY");
         assert_variant(EventType::StageInstanceCreate, "STAGE_INSTANCE_CREATE");
         assert_variant(EventType::StageInstanceDelete, "STAGE_INSTANCE_DELETE");
         assert_variant(EventType::StageInstanceUpdate, "STAGE_INSTANCE_UPDATE");
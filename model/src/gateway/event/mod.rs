@@ -14,6 +14,11 @@ pub use self::{
 
 use self::shard::*;
 use super::payload::*;
+use crate::{
+    channel::Channel,
+    id::{ChannelId, GuildId, UserId},
+};
+use std::convert::TryFrom;
 use std::error::Error;
 use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
 
@@ -35,6 +40,10 @@ pub enum Event {
     ChannelPinsUpdate(ChannelPinsUpdate),
     /// A channel was updated.
     ChannelUpdate(ChannelUpdate),
+    /// Every shard of a cluster is now fully ready.
+    ///
+    /// This is only emitted by clusters.
+    ClusterReady(ClusterReady),
     /// A heartbeat was sent to or received from the gateway.
     GatewayHeartbeat(u64),
     /// A heartbeat acknowledgement was received from the gateway.
@@ -120,6 +129,9 @@ pub enum Event {
     ShardConnecting(Connecting),
     /// A shard is now in a disconnected stage after the connection was closed.
     ShardDisconnected(Disconnected),
+    /// A shard has received all of the guilds listed in its ready event, or
+    /// timed out waiting for the remaining guilds.
+    ShardFullyReady(FullyReady),
     /// A shard is now in a identifying stage after starting a new session.
     ShardIdentifying(Identifying),
     /// A shard is now in a reconnecting stage after a disconnect or session was
@@ -129,6 +141,8 @@ pub enum Event {
     ShardPayload(Payload),
     /// A shard is now in a Resuming stage after a disconnect.
     ShardResuming(Resuming),
+    /// A shard's connection was found to be unhealthy and is being replaced.
+    ShardUnhealthy(Unhealthy),
     /// A stage instance was created in a stage channel.
     StageInstanceCreate(StageInstanceCreate),
     /// A stage instance was deleted in a stage channel.
@@ -158,6 +172,7 @@ impl Event {
             Self::ChannelDelete(_) => EventType::ChannelDelete,
             Self::ChannelPinsUpdate(_) => EventType::ChannelPinsUpdate,
             Self::ChannelUpdate(_) => EventType::ChannelUpdate,
+            Self::ClusterReady(_) => EventType::ClusterReady,
             Self::GatewayHeartbeat(_) => EventType::GatewayHeartbeat,
             Self::GatewayHeartbeatAck => EventType::GatewayHeartbeatAck,
             Self::GatewayHello(_) => EventType::GatewayHello,
@@ -197,10 +212,12 @@ impl Event {
             Self::ShardConnected(_) => EventType::ShardConnected,
             Self::ShardConnecting(_) => EventType::ShardConnecting,
             Self::ShardDisconnected(_) => EventType::ShardDisconnected,
+            Self::ShardFullyReady(_) => EventType::ShardFullyReady,
             Self::ShardIdentifying(_) => EventType::ShardIdentifying,
             Self::ShardReconnecting(_) => EventType::ShardReconnecting,
             Self::ShardPayload(_) => EventType::ShardPayload,
             Self::ShardResuming(_) => EventType::ShardResuming,
+            Self::ShardUnhealthy(_) => EventType::ShardUnhealthy,
             Self::StageInstanceCreate(_) => EventType::StageInstanceCreate,
             Self::StageInstanceDelete(_) => EventType::StageInstanceDelete,
             Self::StageInstanceUpdate(_) => EventType::StageInstanceUpdate,
@@ -212,6 +229,262 @@ impl Event {
             Self::WebhooksUpdate(_) => EventType::WebhooksUpdate,
         }
     }
+
+    /// ID of the guild the event is related to, if any.
+    pub fn guild_id(&self) -> Option<GuildId> {
+        match self {
+            Self::BanAdd(v) => Some(v.guild_id),
+            Self::BanRemove(v) => Some(v.guild_id),
+            Self::ChannelCreate(v) => channel_guild_id(&v.0),
+            Self::ChannelDelete(v) => channel_guild_id(&v.0),
+            Self::ChannelPinsUpdate(v) => v.guild_id,
+            Self::ChannelUpdate(v) => channel_guild_id(&v.0),
+            Self::GuildCreate(v) => Some(v.0.id),
+            Self::GuildDelete(v) => Some(v.id),
+            Self::GuildEmojisUpdate(v) => Some(v.guild_id),
+            Self::GuildIntegrationsUpdate(v) => Some(v.guild_id),
+            Self::GuildUpdate(v) => Some(v.0.id),
+            Self::IntegrationCreate(v) => v.0.guild_id,
+            Self::IntegrationDelete(v) => Some(v.guild_id),
+            Self::IntegrationUpdate(v) => v.0.guild_id,
+            Self::InteractionCreate(v) => v.0.guild_id(),
+            Self::InviteCreate(v) => Some(v.guild_id),
+            Self::InviteDelete(v) => Some(v.guild_id),
+            Self::MemberAdd(v) => Some(v.0.guild_id),
+            Self::MemberRemove(v) => Some(v.guild_id),
+            Self::MemberUpdate(v) => Some(v.guild_id),
+            Self::MemberChunk(v) => Some(v.guild_id),
+            Self::MessageCreate(v) => v.0.guild_id,
+            Self::MessageDelete(v) => v.guild_id,
+            Self::MessageDeleteBulk(v) => v.guild_id,
+            Self::MessageUpdate(v) => v.guild_id,
+            Self::PresenceUpdate(v) => Some(v.guild_id),
+            Self::ReactionAdd(v) => v.0.guild_id,
+            Self::ReactionRemove(v) => v.0.guild_id,
+            Self::ReactionRemoveAll(v) => v.guild_id,
+            Self::ReactionRemoveEmoji(v) => Some(v.guild_id),
+            Self::RoleCreate(v) => Some(v.guild_id),
+            Self::RoleDelete(v) => Some(v.guild_id),
+            Self::RoleUpdate(v) => Some(v.guild_id),
+            Self::StageInstanceCreate(v) => Some(v.0.guild_id),
+            Self::StageInstanceDelete(v) => Some(v.0.guild_id),
+            Self::StageInstanceUpdate(v) => Some(v.0.guild_id),
+            Self::TypingStart(v) => v.guild_id,
+            Self::UnavailableGuild(v) => Some(v.id),
+            Self::VoiceServerUpdate(v) => v.guild_id,
+            Self::VoiceStateUpdate(v) => v.0.guild_id,
+            Self::WebhooksUpdate(v) => Some(v.guild_id),
+            Self::ClusterReady(_)
+            | Self::GatewayHeartbeat(_)
+            | Self::GatewayHeartbeatAck
+            | Self::GatewayHello(_)
+            | Self::GatewayInvalidateSession(_)
+            | Self::GatewayReconnect
+            | Self::GiftCodeUpdate
+            | Self::PresencesReplace
+            | Self::Ready(_)
+            | Self::Resumed
+            | Self::ShardConnected(_)
+            | Self::ShardConnecting(_)
+            | Self::ShardDisconnected(_)
+            | Self::ShardFullyReady(_)
+            | Self::ShardIdentifying(_)
+            | Self::ShardReconnecting(_)
+            | Self::ShardPayload(_)
+            | Self::ShardResuming(_)
+            | Self::ShardUnhealthy(_)
+            | Self::UserUpdate(_) => None,
+        }
+    }
+
+    /// ID of the channel the event is related to, if any.
+    pub fn channel_id(&self) -> Option<ChannelId> {
+        match self {
+            Self::ChannelCreate(v) => Some(v.0.id()),
+            Self::ChannelDelete(v) => Some(v.0.id()),
+            Self::ChannelPinsUpdate(v) => Some(v.channel_id),
+            Self::ChannelUpdate(v) => Some(v.0.id()),
+            Self::InteractionCreate(v) => v.0.channel_id(),
+            Self::InviteCreate(v) => Some(v.channel_id),
+            Self::InviteDelete(v) => Some(v.channel_id),
+            Self::MessageCreate(v) => Some(v.0.channel_id),
+            Self::MessageDelete(v) => Some(v.channel_id),
+            Self::MessageDeleteBulk(v) => Some(v.channel_id),
+            Self::MessageUpdate(v) => Some(v.channel_id),
+            Self::ReactionAdd(v) => Some(v.0.channel_id),
+            Self::ReactionRemove(v) => Some(v.0.channel_id),
+            Self::ReactionRemoveAll(v) => Some(v.channel_id),
+            Self::ReactionRemoveEmoji(v) => Some(v.channel_id),
+            Self::StageInstanceCreate(v) => Some(v.0.channel_id),
+            Self::StageInstanceDelete(v) => Some(v.0.channel_id),
+            Self::StageInstanceUpdate(v) => Some(v.0.channel_id),
+            Self::TypingStart(v) => Some(v.channel_id),
+            Self::VoiceServerUpdate(v) => v.channel_id,
+            Self::VoiceStateUpdate(v) => v.0.channel_id,
+            Self::WebhooksUpdate(v) => Some(v.channel_id),
+            Self::BanAdd(_)
+            | Self::BanRemove(_)
+            | Self::ClusterReady(_)
+            | Self::GatewayHeartbeat(_)
+            | Self::GatewayHeartbeatAck
+            | Self::GatewayHello(_)
+            | Self::GatewayInvalidateSession(_)
+            | Self::GatewayReconnect
+            | Self::GiftCodeUpdate
+            | Self::GuildCreate(_)
+            | Self::GuildDelete(_)
+            | Self::GuildEmojisUpdate(_)
+            | Self::GuildIntegrationsUpdate(_)
+            | Self::GuildUpdate(_)
+            | Self::IntegrationCreate(_)
+            | Self::IntegrationDelete(_)
+            | Self::IntegrationUpdate(_)
+            | Self::MemberAdd(_)
+            | Self::MemberRemove(_)
+            | Self::MemberUpdate(_)
+            | Self::MemberChunk(_)
+            | Self::PresenceUpdate(_)
+            | Self::PresencesReplace
+            | Self::Ready(_)
+            | Self::Resumed
+            | Self::RoleCreate(_)
+            | Self::RoleDelete(_)
+            | Self::RoleUpdate(_)
+            | Self::ShardConnected(_)
+            | Self::ShardConnecting(_)
+            | Self::ShardDisconnected(_)
+            | Self::ShardFullyReady(_)
+            | Self::ShardIdentifying(_)
+            | Self::ShardReconnecting(_)
+            | Self::ShardPayload(_)
+            | Self::ShardResuming(_)
+            | Self::ShardUnhealthy(_)
+            | Self::UnavailableGuild(_)
+            | Self::UserUpdate(_) => None,
+        }
+    }
+
+    /// ID of the user the event is related to, if any.
+    ///
+    /// This is the user that performed the action, such as a message's author,
+    /// or the user the action was performed on, such as a banned user.
+    pub fn user_id(&self) -> Option<UserId> {
+        match self {
+            Self::BanAdd(v) => Some(v.user.id),
+            Self::BanRemove(v) => Some(v.user.id),
+            Self::IntegrationCreate(v) => v.0.user.as_ref().map(|user| user.id),
+            Self::IntegrationUpdate(v) => v.0.user.as_ref().map(|user| user.id),
+            Self::InteractionCreate(v) => v.0.author_id(),
+            Self::InviteCreate(v) => v.inviter.as_ref().map(|user| user.id),
+            Self::MemberAdd(v) => Some(v.0.user.id),
+            Self::MemberRemove(v) => Some(v.user.id),
+            Self::MemberUpdate(v) => Some(v.user.id),
+            Self::MessageCreate(v) => Some(v.0.author.id),
+            Self::MessageUpdate(v) => v.author.as_ref().map(|user| user.id),
+            Self::PresenceUpdate(v) => Some(v.user.id()),
+            Self::ReactionAdd(v) => Some(v.0.user_id),
+            Self::ReactionRemove(v) => Some(v.0.user_id),
+            Self::Ready(v) => Some(v.user.id),
+            Self::TypingStart(v) => Some(v.user_id),
+            Self::UserUpdate(v) => Some(v.0.id),
+            Self::VoiceStateUpdate(v) => Some(v.0.user_id),
+            Self::ChannelCreate(_)
+            | Self::ChannelDelete(_)
+            | Self::ChannelPinsUpdate(_)
+            | Self::ChannelUpdate(_)
+            | Self::ClusterReady(_)
+            | Self::GatewayHeartbeat(_)
+            | Self::GatewayHeartbeatAck
+            | Self::GatewayHello(_)
+            | Self::GatewayInvalidateSession(_)
+            | Self::GatewayReconnect
+            | Self::GiftCodeUpdate
+            | Self::GuildCreate(_)
+            | Self::GuildDelete(_)
+            | Self::GuildEmojisUpdate(_)
+            | Self::GuildIntegrationsUpdate(_)
+            | Self::GuildUpdate(_)
+            | Self::IntegrationDelete(_)
+            | Self::InviteDelete(_)
+            | Self::MemberChunk(_)
+            | Self::MessageDelete(_)
+            | Self::MessageDeleteBulk(_)
+            | Self::PresencesReplace
+            | Self::ReactionRemoveAll(_)
+            | Self::ReactionRemoveEmoji(_)
+            | Self::Resumed
+            | Self::RoleCreate(_)
+            | Self::RoleDelete(_)
+            | Self::RoleUpdate(_)
+            | Self::ShardConnected(_)
+            | Self::ShardConnecting(_)
+            | Self::ShardDisconnected(_)
+            | Self::ShardFullyReady(_)
+            | Self::ShardIdentifying(_)
+            | Self::ShardReconnecting(_)
+            | Self::ShardPayload(_)
+            | Self::ShardResuming(_)
+            | Self::ShardUnhealthy(_)
+            | Self::StageInstanceCreate(_)
+            | Self::StageInstanceDelete(_)
+            | Self::StageInstanceUpdate(_)
+            | Self::UnavailableGuild(_)
+            | Self::VoiceServerUpdate(_)
+            | Self::WebhooksUpdate(_) => None,
+        }
+    }
+
+    /// Index of the worker to process the event with, out of a number of
+    /// workers.
+    ///
+    /// The event is routed by its guild ID, falling back to its channel ID and
+    /// then its user ID, so that all events of a guild are processed by the same
+    /// worker. The index is derived with a consistent hash: when the number of
+    /// workers changes only a minimal share of guilds move to another worker.
+    ///
+    /// Returns `None` if the event isn't related to a guild, channel, or user, or
+    /// if the number of workers is zero.
+    pub fn worker_index(&self, workers: u64) -> Option<u64> {
+        let key = self
+            .guild_id()
+            .map(|id| id.0)
+            .or_else(|| self.channel_id().map(|id| id.0))
+            .or_else(|| self.user_id().map(|id| id.0))?;
+
+        worker_index(key, workers)
+    }
+}
+
+/// ID of the guild a channel is in, if it's a guild channel.
+const fn channel_guild_id(channel: &Channel) -> Option<GuildId> {
+    match channel {
+        Channel::Guild(channel) => channel.guild_id(),
+        Channel::Group(_) | Channel::Private(_) => None,
+    }
+}
+
+/// Map a key to one of a number of workers with Jump Consistent Hash.
+///
+/// Refer to [the paper] for more information.
+///
+/// [the paper]: https://arxiv.org/abs/1406.2294
+fn worker_index(mut key: u64, workers: u64) -> Option<u64> {
+    if workers == 0 {
+        return None;
+    }
+
+    let mut bucket = 0;
+    let mut next = 0;
+
+    while next < workers {
+        bucket = next;
+        key = key.wrapping_mul(2_862_933_555_777_941_757).wrapping_add(1);
+
+        let jump = u128::from(bucket + 1) * (1 << 31) / u128::from((key >> 33) + 1);
+        next = u64::try_from(jump).unwrap_or(u64::MAX);
+    }
+
+    Some(bucket)
 }
 
 impl From<Box<DispatchEvent>> for Event {
@@ -286,10 +559,12 @@ impl From<ShardEvent> for Event {
             ShardEvent::Connected(v) => Self::ShardConnected(v),
             ShardEvent::Connecting(v) => Self::ShardConnecting(v),
             ShardEvent::Disconnected(v) => Self::ShardDisconnected(v),
+            ShardEvent::FullyReady(v) => Self::ShardFullyReady(v),
             ShardEvent::Identifying(v) => Self::ShardIdentifying(v),
             ShardEvent::Payload(v) => Self::ShardPayload(v),
             ShardEvent::Reconnecting(v) => Self::ShardReconnecting(v),
             ShardEvent::Resuming(v) => Self::ShardResuming(v),
+            ShardEvent::Unhealthy(v) => Self::ShardUnhealthy(v),
         }
     }
 }
@@ -324,3 +599,839 @@ impl Display for EventConversionError {
 }
 
 impl Error for EventConversionError {}
+
+#[cfg(test)]
+mod tests {
+    use super::{shard::*, worker_index, DispatchEvent, Event, EventType};
+    use crate::{
+        application::interaction::{
+            application_command::CommandData, ApplicationCommand, Interaction, InteractionType,
+        },
+        channel::{
+            message::MessageType, stage_instance::PrivacyLevel, Channel, ChannelType, GuildChannel,
+            Message, Reaction, ReactionType, StageInstance, TextChannel,
+        },
+        gateway::{
+            payload::{reaction_remove_emoji::PartialEmoji, *},
+            presence::{ClientStatus, Status, UserOrId},
+        },
+        guild::{
+            DefaultMessageNotificationLevel, ExplicitContentFilter, Guild, GuildIntegration,
+            IntegrationAccount, Member, MfaLevel, NSFWLevel, PartialGuild, PartialMember,
+            Permissions, PremiumTier, Role, SystemChannelFlags, VerificationLevel,
+        },
+        id::{
+            ApplicationId, ChannelId, CommandId, GuildId, IntegrationId, InteractionId, MessageId,
+            RoleId, StageId, UserId,
+        },
+        oauth::PartialApplication,
+        user::{CurrentUser, User, UserFlags},
+        voice::VoiceState,
+    };
+    use std::convert::TryFrom;
+
+    const GUILD_ID: GuildId = GuildId(1);
+    const CHANNEL_ID: ChannelId = ChannelId(2);
+    const USER_ID: UserId = UserId(3);
+
+    /// Every event type.
+    const EVENT_TYPES: [EventType; 61] = [
+        EventType::BanAdd,
+        EventType::BanRemove,
+        EventType::ChannelCreate,
+        EventType::ChannelDelete,
+        EventType::ChannelPinsUpdate,
+        EventType::ChannelUpdate,
+        EventType::ClusterReady,
+        EventType::GatewayHeartbeat,
+        EventType::GatewayHeartbeatAck,
+        EventType::GatewayHello,
+        EventType::GatewayInvalidateSession,
+        EventType::GatewayReconnect,
+        EventType::GiftCodeUpdate,
+        EventType::GuildCreate,
+        EventType::GuildDelete,
+        EventType::GuildEmojisUpdate,
+        EventType::GuildIntegrationsUpdate,
+        EventType::GuildUpdate,
+        EventType::IntegrationCreate,
+        EventType::IntegrationDelete,
+        EventType::IntegrationUpdate,
+        EventType::InteractionCreate,
+        EventType::InviteCreate,
+        EventType::InviteDelete,
+        EventType::MemberAdd,
+        EventType::MemberRemove,
+        EventType::MemberUpdate,
+        EventType::MemberChunk,
+        EventType::MessageCreate,
+        EventType::MessageDelete,
+        EventType::MessageDeleteBulk,
+        EventType::MessageUpdate,
+        EventType::PresenceUpdate,
+        EventType::PresencesReplace,
+        EventType::ReactionAdd,
+        EventType::ReactionRemove,
+        EventType::ReactionRemoveAll,
+        EventType::ReactionRemoveEmoji,
+        EventType::Ready,
+        EventType::Resumed,
+        EventType::RoleCreate,
+        EventType::RoleDelete,
+        EventType::RoleUpdate,
+        EventType::ShardConnected,
+        EventType::ShardConnecting,
+        EventType::ShardDisconnected,
+        EventType::ShardFullyReady,
+        EventType::ShardIdentifying,
+        EventType::ShardReconnecting,
+        EventType::ShardPayload,
+        EventType::ShardResuming,
+        EventType::ShardUnhealthy,
+        EventType::StageInstanceCreate,
+        EventType::StageInstanceDelete,
+        EventType::StageInstanceUpdate,
+        EventType::TypingStart,
+        EventType::UnavailableGuild,
+        EventType::UserUpdate,
+        EventType::VoiceServerUpdate,
+        EventType::VoiceStateUpdate,
+        EventType::WebhooksUpdate,
+    ];
+
+    fn user() -> User {
+        User {
+            avatar: None,
+            bot: false,
+            discriminator: "0001".to_owned(),
+            email: None,
+            flags: None,
+            id: USER_ID,
+            locale: None,
+            mfa_enabled: None,
+            name: "user".to_owned(),
+            premium_type: None,
+            public_flags: None,
+            system: None,
+            verified: None,
+        }
+    }
+
+    fn current_user() -> CurrentUser {
+        CurrentUser {
+            avatar: None,
+            bot: true,
+            discriminator: "0001".to_owned(),
+            email: None,
+            flags: None,
+            id: USER_ID,
+            locale: None,
+            mfa_enabled: false,
+            name: "user".to_owned(),
+            premium_type: None,
+            public_flags: None,
+            verified: None,
+        }
+    }
+
+    fn channel() -> Channel {
+        Channel::Guild(GuildChannel::Text(TextChannel {
+            guild_id: Some(GUILD_ID),
+            id: CHANNEL_ID,
+            kind: ChannelType::GuildText,
+            last_message_id: None,
+            last_pin_timestamp: None,
+            name: "channel".to_owned(),
+            nsfw: false,
+            parent_id: None,
+            permission_overwrites: Vec::new(),
+            position: 0,
+            rate_limit_per_user: None,
+            topic: None,
+        }))
+    }
+
+    fn guild() -> Guild {
+        Guild {
+            afk_channel_id: None,
+            afk_timeout: 300,
+            application_id: None,
+            approximate_member_count: None,
+            approximate_presence_count: None,
+            banner: None,
+            channels: Vec::new(),
+            default_message_notifications: DefaultMessageNotificationLevel::Mentions,
+            description: None,
+            discovery_splash: None,
+            emojis: Vec::new(),
+            explicit_content_filter: ExplicitContentFilter::None,
+            features: Vec::new(),
+            icon: None,
+            id: GUILD_ID,
+            joined_at: None,
+            large: false,
+            max_members: None,
+            max_presences: None,
+            max_video_channel_users: None,
+            member_count: None,
+            members: Vec::new(),
+            mfa_level: MfaLevel::None,
+            name: "guild".to_owned(),
+            nsfw_level: NSFWLevel::Default,
+            owner_id: USER_ID,
+            owner: None,
+            permissions: None,
+            preferred_locale: "en-us".to_owned(),
+            premium_subscription_count: None,
+            premium_tier: PremiumTier::None,
+            presences: Vec::new(),
+            roles: Vec::new(),
+            rules_channel_id: None,
+            splash: None,
+            stage_instances: Vec::new(),
+            system_channel_flags: SystemChannelFlags::empty(),
+            system_channel_id: None,
+            unavailable: false,
+            vanity_url_code: None,
+            verification_level: VerificationLevel::None,
+            voice_states: Vec::new(),
+            widget_channel_id: None,
+            widget_enabled: None,
+        }
+    }
+
+    fn partial_guild() -> PartialGuild {
+        PartialGuild {
+            id: GUILD_ID,
+            afk_channel_id: None,
+            afk_timeout: 300,
+            application_id: None,
+            banner: None,
+            default_message_notifications: DefaultMessageNotificationLevel::Mentions,
+            description: None,
+            discovery_splash: None,
+            emojis: Vec::new(),
+            explicit_content_filter: ExplicitContentFilter::None,
+            features: Vec::new(),
+            icon: None,
+            max_members: None,
+            max_presences: None,
+            member_count: None,
+            mfa_level: MfaLevel::None,
+            name: "guild".to_owned(),
+            nsfw_level: NSFWLevel::Default,
+            owner_id: USER_ID,
+            owner: None,
+            permissions: None,
+            preferred_locale: "en-us".to_owned(),
+            premium_subscription_count: None,
+            premium_tier: PremiumTier::None,
+            roles: Vec::new(),
+            rules_channel_id: None,
+            splash: None,
+            system_channel_flags: SystemChannelFlags::empty(),
+            system_channel_id: None,
+            verification_level: VerificationLevel::None,
+            vanity_url_code: None,
+            widget_channel_id: None,
+            widget_enabled: None,
+        }
+    }
+
+    fn integration() -> GuildIntegration {
+        GuildIntegration {
+            account: IntegrationAccount {
+                id: "account".to_owned(),
+                name: "account".to_owned(),
+            },
+            application: None,
+            enable_emoticons: None,
+            enabled: true,
+            expire_behavior: None,
+            expire_grace_period: None,
+            guild_id: Some(GUILD_ID),
+            id: IntegrationId(4),
+            kind: "discord".to_owned(),
+            name: "integration".to_owned(),
+            revoked: None,
+            role_id: None,
+            subscriber_count: None,
+            synced_at: None,
+            syncing: None,
+            user: Some(user()),
+        }
+    }
+
+    fn member() -> Member {
+        Member {
+            deaf: false,
+            guild_id: GUILD_ID,
+            hoisted_role: None,
+            joined_at: None,
+            mute: false,
+            nick: None,
+            pending: false,
+            premium_since: None,
+            roles: Vec::new(),
+            user: user(),
+        }
+    }
+
+    fn message() -> Message {
+        Message {
+            activity: None,
+            application: None,
+            application_id: None,
+            attachments: Vec::new(),
+            author: user(),
+            channel_id: CHANNEL_ID,
+            content: "content".to_owned(),
+            edited_timestamp: None,
+            embeds: Vec::new(),
+            flags: None,
+            guild_id: Some(GUILD_ID),
+            id: MessageId(5),
+            interaction: None,
+            kind: MessageType::Regular,
+            member: None,
+            mention_channels: Vec::new(),
+            mention_everyone: false,
+            mention_roles: Vec::new(),
+            mentions: Vec::new(),
+            pinned: false,
+            reactions: Vec::new(),
+            reference: None,
+            referenced_message: None,
+            stickers: Vec::new(),
+            timestamp: "timestamp".to_owned(),
+            tts: false,
+            webhook_id: None,
+        }
+    }
+
+    fn reaction() -> Reaction {
+        Reaction {
+            channel_id: CHANNEL_ID,
+            emoji: ReactionType::Unicode {
+                name: "🙂".to_owned(),
+            },
+            guild_id: Some(GUILD_ID),
+            member: None,
+            message_id: MessageId(5),
+            user_id: USER_ID,
+        }
+    }
+
+    fn stage_instance() -> StageInstance {
+        StageInstance {
+            channel_id: CHANNEL_ID,
+            discoverable_disabled: false,
+            guild_id: GUILD_ID,
+            id: StageId(6),
+            privacy_level: PrivacyLevel::GuildOnly,
+            topic: "topic".to_owned(),
+        }
+    }
+
+    /// Guild, channel, and user IDs an event is related to.
+    type Ids = (Option<GuildId>, Option<ChannelId>, Option<UserId>);
+
+    const ALL: Ids = (Some(GUILD_ID), Some(CHANNEL_ID), Some(USER_ID));
+    const CHANNEL: Ids = (Some(GUILD_ID), Some(CHANNEL_ID), None);
+    const GUILD: Ids = (Some(GUILD_ID), None, None);
+    const MEMBER: Ids = (Some(GUILD_ID), None, Some(USER_ID));
+    const NONE: Ids = (None, None, None);
+    const USER: Ids = (None, None, Some(USER_ID));
+
+    /// Sample event of a type, along with the guild, channel, and user IDs it
+    /// is expected to be related to.
+    #[allow(clippy::too_many_lines)]
+    fn sample(kind: EventType) -> (Event, Ids) {
+        match kind {
+            EventType::BanAdd => (
+                Event::BanAdd(BanAdd {
+                    guild_id: GUILD_ID,
+                    user: user(),
+                }),
+                MEMBER,
+            ),
+            EventType::BanRemove => (
+                Event::BanRemove(BanRemove {
+                    guild_id: GUILD_ID,
+                    user: user(),
+                }),
+                MEMBER,
+            ),
+            EventType::ChannelCreate => (Event::ChannelCreate(ChannelCreate(channel())), CHANNEL),
+            EventType::ChannelDelete => (Event::ChannelDelete(ChannelDelete(channel())), CHANNEL),
+            EventType::ChannelPinsUpdate => (
+                Event::ChannelPinsUpdate(ChannelPinsUpdate {
+                    channel_id: CHANNEL_ID,
+                    guild_id: Some(GUILD_ID),
+                    last_pin_timestamp: None,
+                }),
+                CHANNEL,
+            ),
+            EventType::ChannelUpdate => (Event::ChannelUpdate(ChannelUpdate(channel())), CHANNEL),
+            EventType::ClusterReady => (
+                Event::ClusterReady(ClusterReady {
+                    guilds_expected: 2,
+                    guilds_missing: vec![GUILD_ID],
+                    shards: vec![0, 1],
+                }),
+                NONE,
+            ),
+            EventType::GatewayHeartbeat => (Event::GatewayHeartbeat(1), NONE),
+            EventType::GatewayHeartbeatAck => (Event::GatewayHeartbeatAck, NONE),
+            EventType::GatewayHello => (Event::GatewayHello(41_250), NONE),
+            EventType::GatewayInvalidateSession => (Event::GatewayInvalidateSession(true), NONE),
+            EventType::GatewayReconnect => (Event::GatewayReconnect, NONE),
+            EventType::GiftCodeUpdate => (Event::GiftCodeUpdate, NONE),
+            EventType::GuildCreate => (Event::GuildCreate(Box::new(GuildCreate(guild()))), GUILD),
+            EventType::GuildDelete => (
+                Event::GuildDelete(Box::new(GuildDelete {
+                    id: GUILD_ID,
+                    unavailable: false,
+                })),
+                GUILD,
+            ),
+            EventType::GuildEmojisUpdate => (
+                Event::GuildEmojisUpdate(GuildEmojisUpdate {
+                    emojis: Vec::new(),
+                    guild_id: GUILD_ID,
+                }),
+                GUILD,
+            ),
+            EventType::GuildIntegrationsUpdate => (
+                Event::GuildIntegrationsUpdate(GuildIntegrationsUpdate { guild_id: GUILD_ID }),
+                GUILD,
+            ),
+            EventType::GuildUpdate => (
+                Event::GuildUpdate(Box::new(GuildUpdate(partial_guild()))),
+                GUILD,
+            ),
+            EventType::IntegrationCreate => (
+                Event::IntegrationCreate(Box::new(IntegrationCreate(integration()))),
+                MEMBER,
+            ),
+            EventType::IntegrationDelete => (
+                Event::IntegrationDelete(IntegrationDelete {
+                    application_id: None,
+                    guild_id: GUILD_ID,
+                    id: IntegrationId(4),
+                }),
+                GUILD,
+            ),
+            EventType::IntegrationUpdate => (
+                Event::IntegrationUpdate(Box::new(IntegrationUpdate(integration()))),
+                MEMBER,
+            ),
+            EventType::InteractionCreate => (
+                Event::InteractionCreate(Box::new(InteractionCreate(
+                    Interaction::ApplicationCommand(Box::new(ApplicationCommand {
+                        application_id: ApplicationId(7),
+                        channel_id: CHANNEL_ID,
+                        data: CommandData {
+                            id: CommandId(8),
+                            ..CommandData::default()
+                        },
+                        guild_id: Some(GUILD_ID),
+                        id: InteractionId(9),
+                        kind: InteractionType::ApplicationCommand,
+                        member: Some(PartialMember {
+                            deaf: false,
+                            joined_at: None,
+                            mute: false,
+                            nick: None,
+                            permissions: Some(Permissions::empty()),
+                            premium_since: None,
+                            roles: Vec::new(),
+                            user: Some(user()),
+                        }),
+                        token: "token".to_owned(),
+                        user: None,
+                    })),
+                ))),
+                ALL,
+            ),
+            EventType::InviteCreate => (
+                Event::InviteCreate(Box::new(InviteCreate {
+                    channel_id: CHANNEL_ID,
+                    code: "twilight".to_owned(),
+                    created_at: "timestamp".to_owned(),
+                    guild_id: GUILD_ID,
+                    inviter: Some(user()),
+                    max_age: 0,
+                    max_uses: 0,
+                    target_user_type: None,
+                    target_user: None,
+                    temporary: false,
+                    uses: 0,
+                })),
+                ALL,
+            ),
+            EventType::InviteDelete => (
+                Event::InviteDelete(InviteDelete {
+                    channel_id: CHANNEL_ID,
+                    code: "twilight".to_owned(),
+                    guild_id: GUILD_ID,
+                }),
+                CHANNEL,
+            ),
+            EventType::MemberAdd => (Event::MemberAdd(Box::new(MemberAdd(member()))), MEMBER),
+            EventType::MemberRemove => (
+                Event::MemberRemove(MemberRemove {
+                    guild_id: GUILD_ID,
+                    user: user(),
+                }),
+                MEMBER,
+            ),
+            EventType::MemberUpdate => (
+                Event::MemberUpdate(Box::new(MemberUpdate {
+                    guild_id: GUILD_ID,
+                    deaf: None,
+                    joined_at: "timestamp".to_owned(),
+                    mute: None,
+                    nick: None,
+                    pending: false,
+                    premium_since: None,
+                    roles: vec![RoleId(10)],
+                    user: user(),
+                })),
+                MEMBER,
+            ),
+            EventType::MemberChunk => (
+                Event::MemberChunk(MemberChunk {
+                    chunk_count: 1,
+                    chunk_index: 0,
+                    guild_id: GUILD_ID,
+                    members: vec![member()],
+                    nonce: None,
+                    not_found: Vec::new(),
+                    presences: Vec::new(),
+                }),
+                GUILD,
+            ),
+            EventType::MessageCreate => (
+                Event::MessageCreate(Box::new(MessageCreate(message()))),
+                ALL,
+            ),
+            EventType::MessageDelete => (
+                Event::MessageDelete(MessageDelete {
+                    channel_id: CHANNEL_ID,
+                    guild_id: Some(GUILD_ID),
+                    id: MessageId(5),
+                }),
+                CHANNEL,
+            ),
+            EventType::MessageDeleteBulk => (
+                Event::MessageDeleteBulk(MessageDeleteBulk {
+                    channel_id: CHANNEL_ID,
+                    guild_id: Some(GUILD_ID),
+                    ids: vec![MessageId(5)],
+                }),
+                CHANNEL,
+            ),
+            EventType::MessageUpdate => (
+                Event::MessageUpdate(Box::new(MessageUpdate {
+                    attachments: None,
+                    author: Some(user()),
+                    channel_id: CHANNEL_ID,
+                    content: None,
+                    edited_timestamp: None,
+                    embeds: None,
+                    guild_id: Some(GUILD_ID),
+                    id: MessageId(5),
+                    kind: None,
+                    mention_everyone: None,
+                    mention_roles: None,
+                    mentions: None,
+                    pinned: None,
+                    timestamp: None,
+                    tts: None,
+                })),
+                ALL,
+            ),
+            EventType::PresenceUpdate => (
+                Event::PresenceUpdate(Box::new(PresenceUpdate {
+                    activities: Vec::new(),
+                    client_status: ClientStatus {
+                        desktop: None,
+                        mobile: None,
+                        web: Some(Status::Online),
+                    },
+                    game: None,
+                    guild_id: GUILD_ID,
+                    status: Status::Online,
+                    user: UserOrId::UserId { id: USER_ID },
+                })),
+                MEMBER,
+            ),
+            EventType::PresencesReplace => (Event::PresencesReplace, NONE),
+            EventType::ReactionAdd => (Event::ReactionAdd(Box::new(ReactionAdd(reaction()))), ALL),
+            EventType::ReactionRemove => (
+                Event::ReactionRemove(Box::new(ReactionRemove(reaction()))),
+                ALL,
+            ),
+            EventType::ReactionRemoveAll => (
+                Event::ReactionRemoveAll(ReactionRemoveAll {
+                    channel_id: CHANNEL_ID,
+                    message_id: MessageId(5),
+                    guild_id: Some(GUILD_ID),
+                }),
+                CHANNEL,
+            ),
+            EventType::ReactionRemoveEmoji => (
+                Event::ReactionRemoveEmoji(ReactionRemoveEmoji {
+                    channel_id: CHANNEL_ID,
+                    emoji: PartialEmoji {
+                        id: None,
+                        name: "🙂".to_owned(),
+                    },
+                    guild_id: GUILD_ID,
+                    message_id: MessageId(5),
+                }),
+                CHANNEL,
+            ),
+            EventType::Ready => (
+                Event::Ready(Box::new(Ready {
+                    application: PartialApplication {
+                        flags: UserFlags::empty(),
+                        id: ApplicationId(7),
+                    },
+                    guilds: vec![crate::guild::UnavailableGuild {
+                        id: GUILD_ID,
+                        unavailable: true,
+                    }],
+                    session_id: "session".to_owned(),
+                    shard: Some([0, 1]),
+                    user: current_user(),
+                    version: 8,
+                })),
+                USER,
+            ),
+            EventType::Resumed => (Event::Resumed, NONE),
+            EventType::RoleCreate => (
+                Event::RoleCreate(RoleCreate {
+                    guild_id: GUILD_ID,
+                    role: role(),
+                }),
+                GUILD,
+            ),
+            EventType::RoleDelete => (
+                Event::RoleDelete(RoleDelete {
+                    guild_id: GUILD_ID,
+                    role_id: RoleId(10),
+                }),
+                GUILD,
+            ),
+            EventType::RoleUpdate => (
+                Event::RoleUpdate(RoleUpdate {
+                    guild_id: GUILD_ID,
+                    role: role(),
+                }),
+                GUILD,
+            ),
+            EventType::ShardConnected => (
+                Event::ShardConnected(Connected {
+                    heartbeat_interval: 41_250,
+                    shard_id: 0,
+                }),
+                NONE,
+            ),
+            EventType::ShardConnecting => (
+                Event::ShardConnecting(Connecting {
+                    gateway: "wss://gateway.discord.gg".to_owned(),
+                    shard_id: 0,
+                }),
+                NONE,
+            ),
+            EventType::ShardDisconnected => (
+                Event::ShardDisconnected(Disconnected {
+                    code: None,
+                    reason: None,
+                    shard_id: 0,
+                }),
+                NONE,
+            ),
+            EventType::ShardFullyReady => (
+                Event::ShardFullyReady(FullyReady {
+                    guilds_expected: 2,
+                    guilds_missing: vec![GUILD_ID],
+                    shard_id: 0,
+                }),
+                NONE,
+            ),
+            EventType::ShardIdentifying => (
+                Event::ShardIdentifying(Identifying {
+                    shard_id: 0,
+                    shard_total: 1,
+                }),
+                NONE,
+            ),
+            EventType::ShardReconnecting => {
+                (Event::ShardReconnecting(Reconnecting { shard_id: 0 }), NONE)
+            }
+            EventType::ShardPayload => (
+                Event::ShardPayload(Payload {
+                    bytes: b"{}".to_vec(),
+                }),
+                NONE,
+            ),
+            EventType::ShardResuming => (
+                Event::ShardResuming(Resuming {
+                    seq: 1,
+                    shard_id: 0,
+                }),
+                NONE,
+            ),
+            EventType::ShardUnhealthy => (
+                Event::ShardUnhealthy(Unhealthy {
+                    reason: UnhealthyReason::HeartbeatAcksMissed,
+                    shard_id: 0,
+                }),
+                NONE,
+            ),
+            EventType::StageInstanceCreate => (
+                Event::StageInstanceCreate(StageInstanceCreate(stage_instance())),
+                CHANNEL,
+            ),
+            EventType::StageInstanceDelete => (
+                Event::StageInstanceDelete(StageInstanceDelete(stage_instance())),
+                CHANNEL,
+            ),
+            EventType::StageInstanceUpdate => (
+                Event::StageInstanceUpdate(StageInstanceUpdate(stage_instance())),
+                CHANNEL,
+            ),
+            EventType::TypingStart => (
+                Event::TypingStart(Box::new(TypingStart {
+                    channel_id: CHANNEL_ID,
+                    guild_id: Some(GUILD_ID),
+                    member: None,
+                    timestamp: 1,
+                    user_id: USER_ID,
+                })),
+                ALL,
+            ),
+            EventType::UnavailableGuild => (
+                Event::UnavailableGuild(UnavailableGuild { id: GUILD_ID }),
+                GUILD,
+            ),
+            EventType::UserUpdate => (Event::UserUpdate(UserUpdate(current_user())), USER),
+            EventType::VoiceServerUpdate => (
+                Event::VoiceServerUpdate(VoiceServerUpdate {
+                    channel_id: Some(CHANNEL_ID),
+                    endpoint: None,
+                    guild_id: Some(GUILD_ID),
+                    token: "token".to_owned(),
+                }),
+                CHANNEL,
+            ),
+            EventType::VoiceStateUpdate => (
+                Event::VoiceStateUpdate(Box::new(VoiceStateUpdate(VoiceState {
+                    channel_id: Some(CHANNEL_ID),
+                    deaf: false,
+                    guild_id: Some(GUILD_ID),
+                    member: None,
+                    mute: false,
+                    self_deaf: false,
+                    self_mute: false,
+                    self_stream: false,
+                    session_id: "session".to_owned(),
+                    suppress: false,
+                    token: None,
+                    user_id: USER_ID,
+                    request_to_speak_timestamp: None,
+                }))),
+                ALL,
+            ),
+            EventType::WebhooksUpdate => (
+                Event::WebhooksUpdate(WebhooksUpdate {
+                    channel_id: CHANNEL_ID,
+                    guild_id: GUILD_ID,
+                }),
+                CHANNEL,
+            ),
+        }
+    }
+
+    fn role() -> Role {
+        Role {
+            color: 0,
+            hoist: false,
+            id: RoleId(10),
+            managed: false,
+            mentionable: false,
+            name: "role".to_owned(),
+            permissions: Permissions::empty(),
+            position: 0,
+            tags: None,
+        }
+    }
+
+    #[test]
+    fn test_related_ids() {
+        for kind in EVENT_TYPES.iter().copied() {
+            let (event, (guild_id, channel_id, user_id)) = sample(kind);
+            assert_eq!(kind, event.kind());
+
+            assert_eq!(guild_id, event.guild_id(), "{:?}", kind);
+            assert_eq!(channel_id, event.channel_id(), "{:?}", kind);
+            assert_eq!(user_id, event.user_id(), "{:?}", kind);
+
+            if let Ok(dispatch) = DispatchEvent::try_from(event.clone()) {
+                assert_eq!(guild_id, dispatch.guild_id(), "{:?}", kind);
+                assert_eq!(channel_id, dispatch.channel_id(), "{:?}", kind);
+                assert_eq!(user_id, dispatch.user_id(), "{:?}", kind);
+                assert_eq!(event.worker_index(16), dispatch.worker_index(16));
+            }
+        }
+    }
+
+    #[test]
+    fn test_worker_index_routing() {
+        let (event, _) = sample(EventType::MessageCreate);
+        let (other, _) = sample(EventType::RoleDelete);
+        let (user_update, _) = sample(EventType::UserUpdate);
+
+        // Events of the same guild are routed to the same worker.
+        assert_eq!(event.worker_index(8), other.worker_index(8));
+        assert_eq!(worker_index(GUILD_ID.0, 8), event.worker_index(8));
+        // Events without a guild fall back to their user.
+        assert_eq!(worker_index(USER_ID.0, 8), user_update.worker_index(8));
+
+        assert!(event.worker_index(0).is_none());
+        assert!(Event::GatewayHeartbeatAck.worker_index(8).is_none());
+    }
+
+    #[test]
+    fn test_worker_index_consistent() {
+        const KEYS: u64 = 10_000;
+
+        let mut counts = [0_u64; 10];
+        let mut moved = 0;
+
+        for key in 0..KEYS {
+            let before = worker_index(key, 9).unwrap();
+            let after = worker_index(key, 10).unwrap();
+            assert!(after < 10);
+            counts[usize::try_from(after).unwrap()] += 1;
+
+            // Keys only ever move to the new worker.
+            if before != after {
+                assert_eq!(9, after);
+                moved += 1;
+            }
+        }
+
+        // Roughly a tenth of the keys move, and keys are spread evenly.
+        assert!(moved > KEYS / 20 && moved < KEYS / 5, "{}", moved);
+        assert!(
+            counts.iter().all(|count| *count > KEYS / 20),
+            "{:?}",
+            counts
+        );
+        assert_eq!(Some(0), worker_index(u64::MAX, 1));
+    }
+}
@@ -1,7 +1,20 @@
 use super::{Event, EventConversionError};
+use crate::id::GuildId;
 use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
 
+/// Indicator that every shard of a cluster is now fully ready.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ClusterReady {
+    /// The number of guilds the shards were expected to load.
+    pub guilds_expected: u64,
+    /// The IDs of the guilds that weren't received before the shards' timeouts
+    /// elapsed.
+    pub guilds_missing: Vec<GuildId>,
+    /// The IDs of the shards in the cluster.
+    pub shards: Vec<u64>,
+}
+
 /// Indicator that a shard is now fully connected.
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct Connected {
@@ -32,6 +45,19 @@ pub struct Disconnected {
     pub shard_id: u64,
 }
 
+/// Indicator that a shard has received all of the guilds listed in its ready
+/// event, or that the timeout for the remaining guilds elapsed.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct FullyReady {
+    /// The number of guilds the shard was expected to load.
+    pub guilds_expected: u64,
+    /// The IDs of the guilds that weren't received before the timeout
+    /// elapsed.
+    pub guilds_missing: Vec<GuildId>,
+    /// The ID of the shard that's now fully ready.
+    pub shard_id: u64,
+}
+
 /// Indicator that a shard is now identifying with the gateway to create a new
 /// session.
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -65,6 +91,28 @@ pub struct Resuming {
     pub shard_id: u64,
 }
 
+/// Indicator that a shard's connection was found to be unhealthy and is being
+/// replaced.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Unhealthy {
+    /// The reason the connection was considered unhealthy.
+    pub reason: UnhealthyReason,
+    /// The ID of the shard with the unhealthy connection.
+    pub shard_id: u64,
+}
+
+/// Reason a shard's connection was considered unhealthy.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnhealthyReason {
+    /// No dispatch event was received for longer than the configured timeout
+    /// while the shard had guilds.
+    DispatchTimedOut,
+    /// The gateway didn't acknowledge the configured number of heartbeats in a
+    /// row.
+    HeartbeatAcksMissed,
+}
+
 /// "Meta" events about a shard's status, not from the gateway.
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[serde(untagged)]
@@ -77,6 +125,8 @@ pub enum ShardEvent {
     Connecting(Connecting),
     /// A shard is now in a Disconnected stage after the connection was closed.
     Disconnected(Disconnected),
+    /// A shard has received all of its guilds or timed out waiting for them.
+    FullyReady(FullyReady),
     /// A shard is now in a Identifying stage after starting a new session.
     Identifying(Identifying),
     /// A payload of bytes came in through the shard's connection.
@@ -86,6 +136,8 @@ pub enum ShardEvent {
     Reconnecting(Reconnecting),
     /// A shard is now in a Resuming stage after a disconnect.
     Resuming(Resuming),
+    /// A shard's connection was found to be unhealthy and is being replaced.
+    Unhealthy(Unhealthy),
 }
 
 impl TryFrom<Event> for ShardEvent {
@@ -96,10 +148,12 @@ impl TryFrom<Event> for ShardEvent {
             Event::ShardConnected(v) => Self::Connected(v),
             Event::ShardConnecting(v) => Self::Connecting(v),
             Event::ShardDisconnected(v) => Self::Disconnected(v),
+            Event::ShardFullyReady(v) => Self::FullyReady(v),
             Event::ShardIdentifying(v) => Self::Identifying(v),
             Event::ShardPayload(v) => Self::Payload(v),
             Event::ShardReconnecting(v) => Self::Reconnecting(v),
             Event::ShardResuming(v) => Self::Resuming(v),
+            Event::ShardUnhealthy(v) => Self::Unhealthy(v),
 
             _ => return Err(EventConversionError::new(event)),
         })
@@ -109,12 +163,45 @@ impl TryFrom<Event> for ShardEvent {
 #[cfg(test)]
 mod tests {
     use super::{
-        Connected, Connecting, Disconnected, Event, Identifying, Payload, Reconnecting, Resuming,
-        ShardEvent,
+        ClusterReady, Connected, Connecting, Disconnected, Event, FullyReady, Identifying, Payload,
+        Reconnecting, Resuming, ShardEvent, Unhealthy, UnhealthyReason,
     };
+    use crate::id::GuildId;
     use serde_test::Token;
     use std::convert::TryInto;
 
+    #[test]
+    fn test_cluster_ready() {
+        let value = ClusterReady {
+            guilds_expected: 3,
+            guilds_missing: vec![GuildId(1)],
+            shards: vec![0, 1],
+        };
+
+        serde_test::assert_tokens(
+            &value,
+            &[
+                Token::Struct {
+                    name: "ClusterReady",
+                    len: 3,
+                },
+                Token::Str("guilds_expected"),
+                Token::U64(3),
+                Token::Str("guilds_missing"),
+                Token::Seq { len: Some(1) },
+                Token::NewtypeStruct { name: "GuildId" },
+                Token::Str("1"),
+                Token::SeqEnd,
+                Token::Str("shards"),
+                Token::Seq { len: Some(2) },
+                Token::U64(0),
+                Token::U64(1),
+                Token::SeqEnd,
+                Token::StructEnd,
+            ],
+        );
+    }
+
     #[test]
     fn test_connected() {
         let value = Connected {
@@ -189,6 +276,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_fully_ready() {
+        let value = FullyReady {
+            guilds_expected: 3,
+            guilds_missing: vec![GuildId(1)],
+            shard_id: 4,
+        };
+
+        serde_test::assert_tokens(
+            &value,
+            &[
+                Token::Struct {
+                    name: "FullyReady",
+                    len: 3,
+                },
+                Token::Str("guilds_expected"),
+                Token::U64(3),
+                Token::Str("guilds_missing"),
+                Token::Seq { len: Some(1) },
+                Token::NewtypeStruct { name: "GuildId" },
+                Token::Str("1"),
+                Token::SeqEnd,
+                Token::Str("shard_id"),
+                Token::U64(4),
+                Token::StructEnd,
+            ],
+        );
+    }
+
     #[test]
     fn test_identifying() {
         let value = Identifying {
@@ -274,6 +390,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_unhealthy() {
+        let value = Unhealthy {
+            reason: UnhealthyReason::HeartbeatAcksMissed,
+            shard_id: 4,
+        };
+
+        serde_test::assert_tokens(
+            &value,
+            &[
+                Token::Struct {
+                    name: "Unhealthy",
+                    len: 2,
+                },
+                Token::Str("reason"),
+                Token::UnitVariant {
+                    name: "UnhealthyReason",
+                    variant: "heartbeat_acks_missed",
+                },
+                Token::Str("shard_id"),
+                Token::U64(4),
+                Token::StructEnd,
+            ],
+        );
+    }
+
     #[test]
     fn test_shard_event_try_from_event() {
         let connected = Event::ShardConnected(Connected {
@@ -304,6 +446,16 @@ mod tests {
             ShardEvent::Disconnected(_)
         ));
 
+        let fully_ready = Event::ShardFullyReady(FullyReady {
+            guilds_expected: 3,
+            guilds_missing: Vec::new(),
+            shard_id: 4,
+        });
+        assert!(matches!(
+            fully_ready.try_into().unwrap(),
+            ShardEvent::FullyReady(_)
+        ));
+
         let identifying = Event::ShardIdentifying(Identifying {
             shard_id: 4,
             shard_total: 7,
@@ -333,5 +485,14 @@ mod tests {
             resuming.try_into().unwrap(),
             ShardEvent::Resuming(_)
         ));
+
+        let unhealthy = Event::ShardUnhealthy(Unhealthy {
+            reason: UnhealthyReason::DispatchTimedOut,
+            shard_id: 4,
+        });
+        assert!(matches!(
+            unhealthy.try_into().unwrap(),
+            ShardEvent::Unhealthy(_)
+        ));
     }
 }
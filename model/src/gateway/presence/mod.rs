@@ -49,6 +49,16 @@ pub enum UserOrId {
     UserId { id: UserId },
 }
 
+impl UserOrId {
+    /// ID of the user.
+    pub const fn id(&self) -> UserId {
+        match self {
+            Self::User(user) => user.id,
+            Self::UserId { id } => *id,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq)]
 pub struct PresenceIntermediary {
     #[serde(default)]
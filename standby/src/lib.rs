@@ -803,6 +803,7 @@ const fn event_guild_id(event: &Event) -> Option<GuildId> {
         Event::ChannelDelete(e) => channel_guild_id(&e.0),
         Event::ChannelPinsUpdate(_) => None,
         Event::ChannelUpdate(e) => channel_guild_id(&e.0),
+        Event::ClusterReady(_) => None,
         Event::GatewayHeartbeatAck => None,
         Event::GatewayHeartbeat(_) => None,
         Event::GatewayHello(_) => None,
@@ -842,10 +843,12 @@ const fn event_guild_id(event: &Event) -> Option<GuildId> {
         Event::ShardConnected(_) => None,
         Event::ShardConnecting(_) => None,
         Event::ShardDisconnected(_) => None,
+        Event::ShardFullyReady(_) => None,
         Event::ShardIdentifying(_) => None,
         Event::ShardPayload(_) => None,
         Event::ShardReconnecting(_) => None,
         Event::ShardResuming(_) => None,
+        Event::ShardUnhealthy(_) => None,
         Event::StageInstanceCreate(e) => Some(e.0.guild_id),
         Event::StageInstanceDelete(e) => Some(e.0.guild_id),
         Event::StageInstanceUpdate(e) => Some(e.0.guild_id),